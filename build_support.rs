@@ -19,11 +19,11 @@ pub fn generate_config_if_needed(dest_path: &std::path::Path) {
     "client_secret": "YOUR_MAL_CLIENT_SECRET"
   },
   "amazon": {
-    "email": "YOUR_AMAZON_EMAIL",
-    "password": "YOUR_AMAZON_PASSWORD"
+    "region": "com"
   },
   "output": {
-    "path": "./export.csv"
+    "path": "./export.csv",
+    "unmatched_report": "./unmatched.json"
   }
 }"#;
 
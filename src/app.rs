@@ -1,29 +1,103 @@
 use std::error::Error;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use crate::config::AppConfig;
+use crate::auth::TokenStore;
+use crate::config::{AppConfig, InputFormat};
 use crate::error::AppError;
 use crate::scraping::Scraper;
-use crate::processor::{CsvGenerator, ProgressTracker};
-use crate::processor::history_processor::{HistoryProcessor, ProcessedItem};
+use crate::scraping::session::SessionStore;
+use crate::processor::{dedup::Deduper, filter::ExportFilter, preview, unmatched_report, watermark, CsvGenerator, ProgressTracker};
+use crate::processor::csv_generator::RowStream;
+use crate::processor::history_processor::{HistoryProcessor, ProcessOptions, ProcessedItem};
 use crate::scraping::models::HistoryItem;
-use crate::metadata::MetadataService;
+use crate::matching::{AnimeDetector, Disambiguator, Matcher, MatchingConfig, Overrides, ReviewQueue, TitleNormalizer};
+use crate::metadata::{AnimeIdMap, CommandProvider, MetadataCache, MetadataService, MockProvider, ServiceType};
+use crate::cli::CliArgs;
+use crate::sync::{self, SyncTarget};
+
+type WatchTime = chrono::DateTime<chrono::Local>;
+
+/// Days of history covered by a quick export
+const QUICK_WINDOW_DAYS: i64 = 30;
+/// Page scrolls needed to cover roughly a month of history
+const QUICK_MAX_SCROLLS: usize = 5;
+/// Provider requests allowed during a quick export
+const QUICK_PROVIDER_BUDGET: usize = 200;
+
+/// Per-run behaviour selected on the command line
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Only export items watched within this many days
+    pub window_days: Option<i64>,
+    /// Only export items watched since the newest one of the previous run
+    pub incremental: bool,
+    /// Maximum page scrolls while loading the history
+    pub max_scrolls: Option<usize>,
+    /// Maximum number of provider requests for the whole run
+    pub provider_budget: Option<usize>,
+    /// History items matched in parallel
+    pub concurrency: Option<usize>,
+    /// Prompt the user to settle ambiguous matches
+    pub interactive: bool,
+    /// Match from the metadata cache and overrides only
+    pub offline: bool,
+    /// Fixtures answering every provider lookup in place of the real APIs
+    pub mock: Option<PathBuf>,
+    /// Saved watch history read instead of scraping Prime Video
+    pub input: Option<PathBuf>,
+    /// Format of `input`
+    pub input_format: InputFormat,
+    /// Netflix profile whose rows are read from `input`
+    pub netflix_profile: Option<String>,
+    /// Print this many rows from each end of the export instead of writing it
+    pub dry_run: Option<usize>,
+}
+
+impl RunOptions {
+    pub fn from_cli(cli_args: &CliArgs) -> Self {
+        let options = if cli_args.quick {
+            Self {
+                window_days: Some(QUICK_WINDOW_DAYS),
+                max_scrolls: Some(QUICK_MAX_SCROLLS),
+                provider_budget: Some(QUICK_PROVIDER_BUDGET),
+                ..Self::default()
+            }
+        } else {
+            Self::default()
+        };
+        Self {
+            concurrency: Some(cli_args.max_concurrent),
+            incremental: cli_args.incremental,
+            interactive: cli_args.interactive,
+            offline: cli_args.offline,
+            mock: cli_args.mock.clone(),
+            input: cli_args.input.clone(),
+            input_format: cli_args.input_format,
+            netflix_profile: cli_args.netflix_profile.clone(),
+            dry_run: cli_args.dry_run.then_some(cli_args.preview_rows),
+            ..options
+        }
+    }
+}
 
 pub struct App {
     config: AppConfig,
+    options: RunOptions,
     progress: Arc<Mutex<ProgressTracker>>,
     scraper: Option<Scraper>,
     generator: CsvGenerator,
 }
 
 impl App {
-    pub fn new_with_config(config: AppConfig) -> Result<Self, AppError> {
+    pub fn new_with_config(config: AppConfig, options: RunOptions) -> Result<Self, AppError> {
         let progress = Arc::new(Mutex::new(ProgressTracker::new()));
         let generator = CsvGenerator::new(config.output.clone());
 
         Ok(Self {
             config,
+            options,
             progress,
             scraper: None,
             generator,
@@ -31,21 +105,120 @@ impl App {
     }
 
     pub async fn run(&mut self) -> Result<(), Box<dyn Error>> {
-        self.initialize_browser().await?;
-        self.login().await?;
-        let items = self.scrape_history().await?;
-        let processed = self.process_items(items).await?;
-        self.generate_output(processed).await?;
+        let (items, newest) = self.history().await?;
+        if self.config.output.path.to_string_lossy().contains("{profile}") {
+            let profile = self.profile().await;
+            self.generator.set_profile(profile);
+        }
+        if let Some(rows) = self.options.dry_run {
+            // Nothing is written, so the next incremental run starts where this one did
+            let processed = self.process_items(items, None).await?;
+            println!();
+            print!("{}", preview::table(&self.generator.prepare(processed)?, rows));
+            return Ok(());
+        }
+        if self.generator.can_stream() {
+            self.stream_output(items).await?;
+        } else {
+            let processed = self.process_items(items, None).await?;
+            self.generate_output(processed).await?;
+        }
+        if self.config.amazon.watchlist {
+            self.export_watchlist().await?;
+        }
+        self.save_watermark(newest);
         Ok(())
     }
 
-    async fn initialize_browser(&mut self) -> Result<(), AppError> {
+    /// Matches the history like `run`, then pushes it to `target` instead of writing a CSV
+    pub async fn sync(&mut self, target: SyncTarget) -> Result<(), Box<dyn Error>> {
+        let (items, newest) = self.history().await?;
+        let processed = self.process_items(items, None).await?;
+        {
+            let mut progress = self.progress.lock().await;
+            progress.start(&format!("Syncing to {}", target.name()));
+        }
+        let report = match target {
+            SyncTarget::Simkl => sync::simkl::push(&self.config, &processed).await?,
+            SyncTarget::Mal => sync::mal::push(&self.config, &processed).await?,
+            SyncTarget::Jellyfin => sync::jellyfin::push(&self.config, &processed).await?,
+            SyncTarget::Plex => sync::plex::push(&self.config, &processed).await?,
+            SyncTarget::Sheets => sync::sheets::push(&self.config, &processed).await?,
+        };
+        {
+            let progress = self.progress.lock().await;
+            progress.complete("Sync complete");
+        }
+        report.print(target.name());
+        self.save_watermark(newest);
+        Ok(())
+    }
+
+    /// The profile the history belongs to: the Netflix profile read from `--input`, or the
+    /// one the Prime Video session is signed in to, when the page shows it
+    async fn profile(&mut self) -> Option<String> {
+        if self.options.input.is_some() {
+            return self.options.netflix_profile.clone();
+        }
+        self.scraper.as_mut()?.active_profile().await
+    }
+
+    /// Reads or scrapes the watch history to export, along with the newest watch date seen
+    async fn history(&mut self) -> Result<(Vec<HistoryItem>, Option<WatchTime>), Box<dyn Error>> {
+        let since = self.incremental_start()?;
+        let items = match self.options.input.clone() {
+            Some(path) => self.load_history(&path)?,
+            None => {
+                self.initialize_browser(since).await?;
+                self.login().await?;
+                self.scrape_history().await?
+            }
+        };
+        let items = self.keep_window(items, since);
+        let newest = items.iter().map(|item| item.watched_at).max();
+        Ok((items, newest))
+    }
+
+    /// Start of an incremental run: the watermark's day, which is read again because Prime
+    /// Video gives no time of day (`--merge` drops the rows exported last time)
+    fn incremental_start(&self) -> Result<Option<WatchTime>, AppError> {
+        if !self.options.incremental {
+            return Ok(None);
+        }
+        let since = watermark::load(&self.config.output.watermark)?;
+        match since {
+            Some(since) => tracing::info!("Incremental run: exporting items watched since {}", since.date_naive()),
+            None => tracing::info!("No watermark at {} yet; exporting the whole history", self.config.output.watermark.display()),
+        }
+        Ok(since)
+    }
+
+    fn save_watermark(&self, newest: Option<WatchTime>) {
+        if let Some(newest) = newest {
+            if let Err(e) = watermark::save(&self.config.output.watermark, newest) {
+                tracing::warn!("Failed to save watermark: {}", e);
+            }
+        }
+    }
+
+    async fn initialize_browser(&mut self, since: Option<WatchTime>) -> Result<(), AppError> {
         {
             let mut progress = self.progress.lock().await;
             progress.start("Initializing browser");
         }
 
-        self.scraper = Some(Scraper::new(self.config.amazon.clone(), true).await?);
+        let mut scraper = Scraper::new(self.config.amazon.clone(), true).await?;
+        if let Some(max_scrolls) = self.options.max_scrolls {
+            scraper.limit_scrolling(max_scrolls);
+        }
+        if let Some(since) = since {
+            scraper.stop_before(since);
+        }
+        if self.config.session.persist {
+            let key_source = self.config.session.key_source()?;
+            scraper.use_session_store(SessionStore::new(self.config.session.path.clone(), key_source));
+        }
+        self.scraper = Some(scraper);
         Ok(())
     }
 
@@ -79,87 +252,303 @@ impl App {
         }
     }
 
-    async fn process_items(&mut self, items: Vec<HistoryItem>) -> Result<Vec<ProcessedItem>, AppError> {
+    /// Reads history items saved as JSON, in place of a browser session
+    fn load_history(&self, path: &std::path::Path) -> Result<Vec<HistoryItem>, AppError> {
+        let items: Vec<HistoryItem> = match self.options.input_format {
+            InputFormat::Json => serde_json::from_reader(std::fs::File::open(path)?)?,
+            InputFormat::Netflix => crate::scraping::netflix::read(path, self.options.netflix_profile.as_deref())?,
+        };
+        tracing::info!("Loaded {} history items from {}", items.len(), path.display());
+        Ok(items)
+    }
+
+    fn keep_window(&self, mut items: Vec<HistoryItem>, since: Option<WatchTime>) -> Vec<HistoryItem> {
+        if let Some(days) = self.options.window_days {
+            let cutoff = chrono::Local::now() - chrono::Duration::days(days);
+            items.retain(|item| item.watched_at >= cutoff);
+            tracing::info!("Keeping {} items watched in the last {} days", items.len(), days);
+        }
+        if let Some(since) = since {
+            items.retain(|item| item.watched_at >= since);
+            tracing::info!("Keeping {} items watched since the last run", items.len());
+        }
+        items
+    }
+
+    /// Builds the metadata service the configuration and run options ask for
+    fn metadata_service(&self) -> Result<MetadataService, AppError> {
+        // Create default rate limits
+        let rate_limits = crate::metadata::RateLimitConfig {
+            simkl: crate::metadata::RateLimit { calls: 1000, per_seconds: 3600 },
+            tmdb: crate::metadata::RateLimit { calls: 1000, per_seconds: 3600 },
+            tvdb: crate::metadata::RateLimit { calls: 1000, per_seconds: 3600 },
+            mal: crate::metadata::RateLimit { calls: 1000, per_seconds: 3600 },
+            trakt: crate::metadata::RateLimit { calls: 1000, per_seconds: 300 },
+            omdb: crate::metadata::RateLimit { calls: 1000, per_seconds: 86400 },
+            tvmaze: crate::metadata::RateLimit { calls: 20, per_seconds: 10 },
+            wikidata: crate::metadata::RateLimit { calls: 60, per_seconds: 60 },
+        };
+
+        // Providers pull (and renew) their tokens from here as they make requests
+        let tokens = Arc::new(TokenStore::load(&self.config)?);
+
+        let mut metadata_service = match &self.options.mock {
+            Some(path) => {
+                tracing::info!("Answering provider lookups from mock fixtures in {}", path.display());
+                MetadataService::mock(self.config.providers.clone(), MockProvider::load(path)?)
+            }
+            None => self.config.custom_providers.iter().filter(|(_, custom)| custom.enabled).fold(
+                MetadataService::new(self.config.providers.clone(), rate_limits, &self.config, tokens),
+                |service, (name, custom)| {
+                    service.with_provider(ServiceType::Custom(name.clone()), Box::new(CommandProvider::new(name, custom.clone())))
+                },
+            ),
+        };
+        metadata_service = metadata_service
+            .with_normalizer(TitleNormalizer::from_config(&self.config.normalization)?)
+            .with_matcher(Matcher::from_config(&self.config.matching));
+        if self.config.matching.detect_anime {
+            let detector = AnimeDetector::new(&self.config.matching.anime_title_patterns)?;
+            metadata_service = metadata_service.with_anime_detector(detector);
+        }
+        if self.config.anime_ids.enabled {
+            let map = AnimeIdMap::load(&self.config.anime_ids.path)?;
+            if map.len() > 0 {
+                tracing::info!("Loaded {} anime ID mappings", map.len());
+                metadata_service = metadata_service.with_anime_ids(map);
+            }
+        }
+        if self.config.matching.detect_language {
+            metadata_service = metadata_service.with_language_detection();
+        }
+        let overrides = Overrides::load(&self.config.matching.overrides_path)?;
+        if overrides.len() > 0 {
+            tracing::info!("Loaded {} title overrides", overrides.len());
+            metadata_service = metadata_service.with_overrides(overrides);
+        }
+        let disambiguator = Disambiguator::load(&self.config.matching.decisions_path, self.options.interactive)?;
+        let decisions = disambiguator.len();
+        if decisions > 0 {
+            tracing::info!("Loaded {} saved match decisions", decisions);
+        }
+        if self.options.interactive || decisions > 0 {
+            metadata_service = metadata_service.with_disambiguator(disambiguator);
+        }
+        if self.config.matching.fetch_details {
+            metadata_service = metadata_service.with_details_pass();
+        }
+        let columns = crate::processor::columns::parse(&self.config.output.columns)?;
+        if !self.config.output.enrichment.is_empty()
+            || self.config.output.auto_status
+            || columns.iter().any(|column| column.uses_enrichment())
+        {
+            metadata_service = metadata_service.with_enrichment();
+        }
+        if self.options.offline {
+            if !self.config.cache.enabled {
+                tracing::warn!("Offline mode with the metadata cache disabled: only overrides can match");
+            }
+            metadata_service = metadata_service.with_offline_mode();
+        }
+        // Mock runs stay deterministic, so neither read nor pollute the real cache
+        if self.config.cache.enabled && self.options.mock.is_none() {
+            match MetadataCache::open(&self.config.cache.path) {
+                Ok(cache) => {
+                    let ttl = chrono::Duration::days(self.config.cache.negative_ttl_days.into());
+                    metadata_service = metadata_service.with_cache(cache.with_negative_ttl(ttl));
+                }
+                Err(e) => tracing::warn!("Metadata cache disabled: {}", e),
+            }
+        }
+        Ok(metadata_service)
+    }
+
+    /// Matches the history and keeps what passes review, dedup and the filter. With a `stream`,
+    /// each title's rows are written out as soon as they are matched and nothing is returned.
+    async fn process_items(
+        &mut self,
+        items: Vec<HistoryItem>,
+        mut stream: Option<&mut RowStream>,
+    ) -> Result<Vec<ProcessedItem>, AppError> {
         {
             let mut progress = self.progress.lock().await;
             progress.start("Processing data");
         }
 
-        // Convert HistoryItem to WatchHistoryItem for processing
-        let watch_items: Vec<crate::models::WatchHistoryItem> = items.into_iter().map(|item| {
-            // Convert scraping MediaType to models MediaType
-            let media_type = match item.media_type {
-                crate::scraping::models::MediaType::Movie => crate::models::MediaType::Movie,
-                crate::scraping::models::MediaType::TvShow { .. } => crate::models::MediaType::Tv,
-            };
-
-            // Extract episode info from scraping MediaType
-            let episode = match item.media_type {
-                crate::scraping::models::MediaType::Movie => None,
-                crate::scraping::models::MediaType::TvShow { season, episode, episode_title } => {
-                    let mut episode_str = String::new();
-                    if let Some(s) = season {
-                        episode_str.push_str(&format!("S{:02}", s));
-                    }
-                    if let Some(e) = episode {
-                        if !episode_str.is_empty() {
-                            episode_str.push_str(&format!("E{:02}", e));
-                        } else {
-                            episode_str.push_str(&format!("E{:02}", e));
-                        }
-                    }
-                    if let Some(title) = episode_title {
-                        if !episode_str.is_empty() {
-                            episode_str.push_str(&format!(" - {}", title));
-                        } else {
-                            episode_str = title;
-                        }
+        // Checked up front so a bad pattern fails before any provider is asked
+        let filter = ExportFilter::from_config(&self.config.output.filter)?;
+        let normalizer = TitleNormalizer::from_config(&self.config.normalization)?;
+        let watch_items = to_watch_items(items, &self.config.ratings);
+        let mut progress_tracker = ProgressTracker::new();
+
+        let mut metadata_service = self.metadata_service()?;
+        let mut process_options =
+            ProcessOptions { episode_rows: self.config.output.episode_rows, ..ProcessOptions::default() };
+        if let Some(concurrency) = self.options.concurrency {
+            process_options.concurrency = concurrency;
+        }
+        if let Some(budget) = self.options.provider_budget {
+            // A quick run trades completeness for speed: one attempt, never abort on misses
+            metadata_service = metadata_service.with_request_budget(budget);
+            process_options.max_attempts = 1;
+            process_options.keep_unmatched = true;
+        }
+        process_options.skip_unmatched = self.config.output.unmatched_csv;
+        let companion = self.config.output.unmatched_csv && self.options.dry_run.is_none();
+        // Kept only to list the watches of titles that end up set aside
+        let watches: Vec<unmatched_report::Watch> =
+            if companion { watch_items.iter().map(Into::into).collect() } else { Vec::new() };
+
+        let mut review = ReviewHold::load(&self.config.matching)?;
+        let mut deduper = Deduper::new(&self.config.output.dedup, &normalizer);
+        let mut duplicates = 0;
+        let mut filtered = 0;
+        let mut exported = Vec::new();
+        let processed = HistoryProcessor::process_each(
+            watch_items,
+            &metadata_service,
+            &mut progress_tracker,
+            &process_options,
+            |batch| {
+                let mut batch = review.take(batch);
+                duplicates += deduper.apply(&mut batch);
+                filtered += filter.apply(&mut batch);
+                match stream.as_deref_mut() {
+                    Some(stream) => stream.write(batch),
+                    None => {
+                        exported.extend(batch);
+                        Ok(())
                     }
-                    Some(episode_str)
                 }
-            };
-
-            crate::models::WatchHistoryItem {
-                simkl_id: None, // Will be filled by metadata service
-                tvdb_id: None,
-                tmdb_id: None,
-                mal_id: None,
-                media_type,
-                title: item.title,
-                year: None, // Could be extracted from watched_at if needed
-                episode,
-                watch_status: crate::models::WatchStatus::Completed,
-                date: item.watched_at.format("%Y-%m-%d").to_string(),
-                rating: None,
-                memo: None,
+            },
+        ).await;
+
+        let metrics = metadata_service.metrics();
+        if !metrics.snapshot().is_empty() {
+            println!();
+            println!("Provider statistics:");
+            print!("{}", metrics.summary_table());
+        }
+
+        // Written even when a miss aborted the run, so the report shows what to override
+        let unmatched = metadata_service.take_unmatched();
+        if !unmatched.is_empty() {
+            let report_path = &self.config.output.unmatched_report;
+            match unmatched_report::write(report_path, &unmatched) {
+                Ok(()) => tracing::warn!(
+                    "{} items could not be matched; see {}",
+                    unmatched.len(),
+                    report_path.display()
+                ),
+                Err(e) => tracing::warn!("Failed to write unmatched report: {}", e),
+            }
+            if companion {
+                let path = unmatched_report::companion_path(&self.generator.path()?);
+                match unmatched_report::write_companion(&path, &self.config.output.csv, &watches, &unmatched) {
+                    Ok(rows) => tracing::warn!("{} unmatched watches set aside in {}", rows, path.display()),
+                    Err(e) => tracing::warn!("Failed to write {}: {}", path.display(), e),
+                }
             }
-        }).collect();
+        }
+        processed?;
+        review.finish()?;
+        if duplicates > 0 {
+            tracing::info!("Dropped {} duplicate listings", duplicates);
+        }
+        if !filter.is_empty() {
+            tracing::info!("Filtered out {} items", filtered);
+        }
 
-        let mut progress_tracker = ProgressTracker::new();
+        {
+            let progress = self.progress.lock().await;
+            progress.complete("Processing complete");
+        }
+        Ok(exported)
+    }
 
-        // Create default rate limits
-        let rate_limits = crate::metadata::RateLimitConfig {
-            simkl: crate::metadata::RateLimit { calls: 1000, per_seconds: 3600 },
-            tmdb: crate::metadata::RateLimit { calls: 1000, per_seconds: 3600 },
-            tvdb: crate::metadata::RateLimit { calls: 1000, per_seconds: 3600 },
-            mal: crate::metadata::RateLimit { calls: 1000, per_seconds: 3600 },
-        };
+    /// Looks up every title of a saved history so the results land in the metadata cache,
+    /// leaving the export itself to a later run that then barely touches the providers
+    pub async fn warm_cache(&mut self, path: &std::path::Path) -> Result<(), AppError> {
+        if !self.config.cache.enabled || self.options.mock.is_some() || self.options.offline {
+            return Err(AppError::ConfigError(config::ConfigError::Message(
+                "Warming the cache needs cache.enabled and the real providers (no --mock or --offline)".to_string(),
+            )));
+        }
+        let watch_items = to_watch_items(self.load_history(path)?, &self.config.ratings);
+        let total = watch_items.len();
+        let metadata_service = self.metadata_service()?;
 
-        let metadata_service = MetadataService::new(
-            vec![], // Empty priority order for now
-            rate_limits,
-            self.config.simkl.clone(),
-            self.config.tmdb.clone(),
-            self.config.tvdb.clone(),
-            self.config.mal.clone(),
+        // Nothing is exported, so a miss is only reported instead of ending the run
+        let mut process_options = ProcessOptions { keep_unmatched: true, ..ProcessOptions::default() };
+        if let Some(concurrency) = self.options.concurrency {
+            process_options.concurrency = concurrency;
+        }
+        HistoryProcessor::process(watch_items, &metadata_service, &mut ProgressTracker::new(), &process_options).await?;
+
+        let metrics = metadata_service.metrics();
+        if !metrics.snapshot().is_empty() {
+            println!();
+            println!("Provider statistics:");
+            print!("{}", metrics.summary_table());
+        }
+        let unmatched = metadata_service.take_unmatched();
+        println!(
+            "✅ Looked up {} history items into {}: {} unmatched",
+            total,
+            self.config.cache.path.display(),
+            unmatched.len()
         );
-        let processed = HistoryProcessor::process(watch_items, &metadata_service, &mut progress_tracker).await?;
+        Ok(())
+    }
+
+    /// Matches the history and writes each title's rows as soon as it is matched, so a long
+    /// run never holds the whole export and a failure keeps the rows written so far
+    async fn stream_output(&mut self, items: Vec<HistoryItem>) -> Result<(), AppError> {
+        let mut stream = self.generator.stream()?;
+        let processed = self.process_items(items, Some(&mut stream)).await;
+        if processed.is_err() {
+            let partials: Vec<String> = stream.partials().iter().map(|path| path.display().to_string()).collect();
+            tracing::warn!(
+                "Run failed; the {} rows written before it stopped are in {}, and the export was left as it was",
+                stream.rows(),
+                partials.join(", ")
+            );
+        }
+        processed?;
+        let rows = stream.rows();
+        stream.finish()?;
+        tracing::info!("Wrote {} rows to {}", rows, self.generator.path()?.display());
+        Ok(())
+    }
 
+    /// Reads the watchlist in the same browser session, matches it and writes it as plan to
+    /// watch next to the export
+    async fn export_watchlist(&mut self) -> Result<(), AppError> {
+        let Some(scraper) = &mut self.scraper else {
+            tracing::warn!("The watchlist can only be read from Prime Video, not from --input; skipping it");
+            return Ok(());
+        };
+        {
+            let mut progress = self.progress.lock().await;
+            progress.start("Scraping watchlist");
+        }
+        let planned = to_planned_items(scraper.scrape_watchlist().await?);
+        let metadata_service = self.metadata_service()?;
+        // A title no provider knows is left off the watchlist rather than failing the export
+        let mut process_options = ProcessOptions { skip_unmatched: true, ..ProcessOptions::default() };
+        if let Some(concurrency) = self.options.concurrency {
+            process_options.concurrency = concurrency;
+        }
+        let processed =
+            HistoryProcessor::process(planned, &metadata_service, &mut ProgressTracker::new(), &process_options).await?;
+        let titles = processed.len();
+        let path = self.generator.generate_watchlist(processed)?;
         {
             let progress = self.progress.lock().await;
-            progress.complete("Processing complete");
+            progress.complete("Watchlist exported");
         }
-        Ok(processed)
+        tracing::info!("Wrote {} watchlist titles to {}", titles, path.display());
+        Ok(())
     }
 
     async fn generate_output(&mut self, items: Vec<ProcessedItem>) -> Result<(), AppError> {
@@ -176,6 +565,141 @@ impl App {
     }
 }
 
+/// Moves matches scoring below `matching.min_score`, and titles the user rejected, out of
+/// the export and into the review queue
+struct ReviewHold<'a> {
+    min_score: f64,
+    path: &'a Path,
+    /// None when nothing can be held: no minimum score and no queue file yet
+    queue: Option<ReviewQueue>,
+    held: usize,
+    queued: usize,
+}
+
+impl<'a> ReviewHold<'a> {
+    fn load(config: &'a MatchingConfig) -> Result<Self, AppError> {
+        let path = config.review_queue_path.as_path();
+        let queue = if config.min_score <= 0.0 && !path.exists() { None } else { Some(ReviewQueue::load(path)?) };
+        Ok(Self { min_score: config.min_score, path, queue, held: 0, queued: 0 })
+    }
+
+    /// The items that go on to the export
+    fn take(&mut self, items: Vec<ProcessedItem>) -> Vec<ProcessedItem> {
+        let Some(queue) = &mut self.queue else {
+            return items;
+        };
+        let mut exported = Vec::with_capacity(items.len());
+        for item in items {
+            let low = item.metadata.score.is_some_and(|score| score < self.min_score);
+            if queue.is_rejected(&item.title, item.media_type) {
+                self.held += 1;
+            } else if low {
+                self.held += 1;
+                if queue.add(&item.title, item.media_type, item.metadata.clone()) {
+                    self.queued += 1;
+                }
+            } else {
+                exported.push(item);
+            }
+        }
+        exported
+    }
+
+    fn finish(self) -> Result<(), AppError> {
+        match self.queue {
+            Some(queue) if self.held > 0 => {
+                queue.save(self.path)?;
+                tracing::warn!(
+                    "Held back {} items ({} newly queued titles); run `review` to go through {}",
+                    self.held,
+                    self.queued,
+                    self.path.display()
+                );
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Converts scraped history items into the rows the metadata lookups work on
+fn to_watch_items(
+    items: Vec<HistoryItem>,
+    ratings: &crate::config::RatingConfig,
+) -> Vec<crate::models::WatchHistoryItem> {
+    items.into_iter().map(|item| {
+        // Convert scraping MediaType to models MediaType
+        let media_type = match item.media_type {
+            crate::scraping::models::MediaType::Movie => crate::models::MediaType::Movie,
+            crate::scraping::models::MediaType::TvShow { .. } => crate::models::MediaType::Tv,
+        };
+
+        // Extract episode info from scraping MediaType
+        let episode = match item.media_type {
+            crate::scraping::models::MediaType::Movie => None,
+            crate::scraping::models::MediaType::TvShow { season, episode, episode_title } => {
+                let mut episode_str = String::new();
+                if let Some(s) = season {
+                    episode_str.push_str(&format!("S{:02}", s));
+                }
+                if let Some(e) = episode {
+                    episode_str.push_str(&format!("E{:02}", e));
+                }
+                if let Some(title) = episode_title {
+                    if !episode_str.is_empty() {
+                        episode_str.push_str(&format!(" - {}", title));
+                    } else {
+                        episode_str = title;
+                    }
+                }
+                Some(episode_str)
+            }
+        };
+
+        crate::models::WatchHistoryItem {
+            simkl_id: None, // Will be filled by metadata service
+            tvdb_id: None,
+            tmdb_id: None,
+            mal_id: None,
+            media_type,
+            title: item.title,
+            year: None, // Could be extracted from watched_at if needed
+            episode,
+            watch_status: crate::models::WatchStatus::Completed,
+            date: item.watched_at.format("%Y-%m-%d").to_string(),
+            rating: ratings.rating(item.rating),
+            memo: None,
+            source_url: item.source_url,
+        }
+    }).collect()
+}
+
+/// Converts watchlist titles into undated, plan-to-watch rows; a show stays one row
+/// whichever seasons are listed
+fn to_planned_items(items: Vec<crate::scraping::models::WatchlistItem>) -> Vec<crate::models::WatchHistoryItem> {
+    items.into_iter().map(|item| {
+        let media_type = match item.media_type {
+            crate::scraping::models::MediaType::Movie => crate::models::MediaType::Movie,
+            crate::scraping::models::MediaType::TvShow { .. } => crate::models::MediaType::Tv,
+        };
+        crate::models::WatchHistoryItem {
+            simkl_id: None,
+            tvdb_id: None,
+            tmdb_id: None,
+            mal_id: None,
+            media_type,
+            title: item.title,
+            year: None,
+            episode: None,
+            watch_status: crate::models::WatchStatus::Planned,
+            date: String::new(),
+            rating: None,
+            memo: None,
+            source_url: item.source_url,
+        }
+    }).collect()
+}
+
 impl AppError {
     pub const BROWSER_NOT_INITIALIZED: AppError = AppError::BrowserError(String::new());
 }
\ No newline at end of file
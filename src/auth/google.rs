@@ -0,0 +1,106 @@
+use reqwest::{Client, Url};
+use tokio::net::TcpListener;
+
+use crate::auth::mal::{random_token, wait_for_code};
+use crate::auth::tokens::OAuthToken;
+use crate::config::GoogleConfig;
+use crate::error::AppError;
+
+const AUTHORIZE_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+/// Read and write access to the user's spreadsheets, nothing else in their Drive
+const SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    /// Only sent by the initial code exchange; refreshes keep using the old one
+    refresh_token: Option<String>,
+    expires_in: i64,
+}
+
+fn check_configured(config: &GoogleConfig) -> Result<(), AppError> {
+    if config.client_id.is_empty() || config.client_secret.is_empty() {
+        return Err(AppError::AuthError(
+            "Set google.client_id and google.client_secret in config (create a Desktop app OAuth client at https://console.cloud.google.com/apis/credentials)".into(),
+        ));
+    }
+    Ok(())
+}
+
+fn redirect_uri(config: &GoogleConfig) -> String {
+    format!("http://127.0.0.1:{}/callback", config.redirect_port)
+}
+
+/// Runs Google's installed-app OAuth flow with PKCE, catching the redirect on a local listener.
+/// Asks for offline access so the token can be refreshed without another login.
+pub async fn authorize(config: &GoogleConfig) -> Result<OAuthToken, AppError> {
+    check_configured(config)?;
+    let verifier = random_token(64);
+    let state = random_token(16);
+    let redirect_uri = redirect_uri(config);
+
+    let listener = TcpListener::bind(("127.0.0.1", config.redirect_port)).await?;
+
+    let mut url = Url::parse(AUTHORIZE_URL).expect("valid Google authorize URL");
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("scope", SCOPE)
+        .append_pair("access_type", "offline")
+        .append_pair("prompt", "consent")
+        .append_pair("code_challenge", &verifier)
+        .append_pair("code_challenge_method", "plain")
+        .append_pair("state", &state)
+        .append_pair("redirect_uri", &redirect_uri);
+
+    println!("🔐 GOOGLE AUTHORIZATION");
+    println!("=======================");
+    println!("Open this URL in your browser and approve access to your spreadsheets:");
+    println!("{}", url);
+    println!("⏳ Waiting for the redirect...");
+
+    let code = wait_for_code(&listener, &state, "Google").await?;
+
+    let response = Client::new()
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("code_verifier", verifier.as_str()),
+        ])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::AuthError(format!("Google token exchange failed: {}", response.status())));
+    }
+    let token: TokenResponse = response.json().await?;
+    Ok(OAuthToken::new(token.access_token, token.refresh_token, Some(token.expires_in)))
+}
+
+/// Exchanges a refresh token for a new access token (Google access tokens last an hour)
+pub async fn refresh(config: &GoogleConfig, refresh_token: &str) -> Result<OAuthToken, AppError> {
+    check_configured(config)?;
+
+    let response = Client::new()
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        return Err(AppError::AuthError(format!("Google token refresh failed: {}", response.status())));
+    }
+    let token: TokenResponse = response.json().await?;
+    let refresh_token = token.refresh_token.unwrap_or_else(|| refresh_token.to_string());
+    Ok(OAuthToken::new(token.access_token, Some(refresh_token), Some(token.expires_in)))
+}
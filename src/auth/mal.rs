@@ -0,0 +1,153 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use reqwest::{Client, Url};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+use crate::auth::tokens::OAuthToken;
+use crate::config::MalConfig;
+use crate::error::AppError;
+
+const AUTHORIZE_URL: &str = "https://myanimelist.net/v1/oauth2/authorize";
+const TOKEN_URL: &str = "https://myanimelist.net/v1/oauth2/token";
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    expires_in: i64,
+}
+
+impl From<TokenResponse> for OAuthToken {
+    fn from(response: TokenResponse) -> Self {
+        OAuthToken::new(response.access_token, Some(response.refresh_token), Some(response.expires_in))
+    }
+}
+
+pub(super) fn random_token(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    rand::thread_rng().fill_bytes(&mut buf);
+    URL_SAFE_NO_PAD.encode(buf)
+}
+
+fn redirect_uri(config: &MalConfig) -> String {
+    format!("http://localhost:{}/callback", config.redirect_port)
+}
+
+/// Runs MAL's OAuth2 PKCE flow, catching the redirect on a local listener.
+/// MAL only supports the `plain` challenge method, so the verifier doubles as the challenge.
+pub async fn authorize(config: &MalConfig) -> Result<OAuthToken, AppError> {
+    let verifier = random_token(64);
+    let state = random_token(16);
+    let redirect_uri = redirect_uri(config);
+
+    let listener = TcpListener::bind(("127.0.0.1", config.redirect_port)).await?;
+
+    let mut url = Url::parse(AUTHORIZE_URL).expect("valid MAL authorize URL");
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("code_challenge", &verifier)
+        .append_pair("code_challenge_method", "plain")
+        .append_pair("state", &state)
+        .append_pair("redirect_uri", &redirect_uri);
+
+    println!("🔐 MYANIMELIST AUTHORIZATION");
+    println!("============================");
+    println!("Open this URL in your browser and approve access:");
+    println!("{}", url);
+    println!("(The app's redirect URL on myanimelist.net must be {})", redirect_uri);
+    println!("⏳ Waiting for the redirect...");
+
+    let code = wait_for_code(&listener, &state, "MAL").await?;
+
+    let response = Client::new()
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("code_verifier", verifier.as_str()),
+        ])
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        Ok(response.json::<TokenResponse>().await?.into())
+    } else {
+        Err(AppError::AuthError(format!("MAL token exchange failed: {}", response.status())))
+    }
+}
+
+/// Accepts connections until the OAuth callback arrives and returns its authorization code
+pub(super) async fn wait_for_code(listener: &TcpListener, expected_state: &str, service: &str) -> Result<String, AppError> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut request_line = String::new();
+        BufReader::new(&mut stream).read_line(&mut request_line).await?;
+
+        // "GET /callback?code=...&state=... HTTP/1.1"
+        let Some(path) = request_line.split_whitespace().nth(1) else {
+            continue;
+        };
+        let Ok(url) = Url::parse(&format!("http://localhost{}", path)) else {
+            continue;
+        };
+        if url.path() != "/callback" {
+            continue;
+        }
+
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+        };
+
+        let (status, body, result) = match (param("code"), param("state"), param("error")) {
+            (Some(code), Some(state), _) if state == expected_state => {
+                ("200 OK", "Authorization complete - you can close this tab.", Ok(code))
+            }
+            (_, _, Some(error)) => (
+                "400 Bad Request",
+                "Authorization was denied.",
+                Err(AppError::AuthError(format!("{} authorization denied: {}", service, error))),
+            ),
+            _ => (
+                "400 Bad Request",
+                "Invalid authorization response.",
+                Err(AppError::AuthError(format!("{} callback state mismatch", service))),
+            ),
+        };
+
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+        return result;
+    }
+}
+
+/// Exchanges a refresh token for a new access token (MAL tokens expire after a month)
+pub async fn refresh(config: &MalConfig, refresh_token: &str) -> Result<OAuthToken, AppError> {
+    let response = Client::new()
+        .post(TOKEN_URL)
+        .form(&[
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+        ])
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        Ok(response.json::<TokenResponse>().await?.into())
+    } else {
+        Err(AppError::AuthError(format!("MAL token refresh failed: {}", response.status())))
+    }
+}
@@ -0,0 +1,17 @@
+pub mod google;
+pub mod mal;
+pub mod simkl;
+pub mod store;
+pub mod tokens;
+pub mod trakt;
+pub mod tvdb;
+
+pub use store::TokenStore;
+pub use tokens::{OAuthToken, TokenFile};
+
+/// Service names used as keys in the token file
+pub const SIMKL: &str = "simkl";
+pub const TRAKT: &str = "trakt";
+pub const MAL: &str = "mal";
+pub const TVDB: &str = "tvdb";
+pub const GOOGLE: &str = "google";
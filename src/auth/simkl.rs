@@ -0,0 +1,79 @@
+use reqwest::Client;
+use std::time::Duration;
+
+use crate::auth::tokens::OAuthToken;
+use crate::config::SimklConfig;
+use crate::error::AppError;
+
+const PIN_URL: &str = "https://api.simkl.com/oauth/pin";
+
+#[derive(serde::Deserialize)]
+struct PinResponse {
+    user_code: String,
+    verification_url: String,
+    #[serde(default = "default_expires_in")]
+    expires_in: u64,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_expires_in() -> u64 {
+    900
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+#[derive(serde::Deserialize)]
+struct PinStatus {
+    result: String,
+    access_token: Option<String>,
+}
+
+/// Runs Simkl's PIN (device) flow: shows a code to enter on simkl.com and polls until approved
+pub async fn authorize(config: &SimklConfig) -> Result<OAuthToken, AppError> {
+    let client = Client::new();
+
+    let response = client
+        .get(PIN_URL)
+        .query(&[("client_id", config.client_id.as_str())])
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(AppError::AuthError(format!(
+            "Simkl PIN request failed: {}",
+            response.status()
+        )));
+    }
+    let pin: PinResponse = response.json().await?;
+
+    println!("🔐 SIMKL AUTHORIZATION");
+    println!("======================");
+    println!("1. Open {}", pin.verification_url);
+    println!("2. Enter the code: {}", pin.user_code);
+    println!("⏳ Waiting for approval...");
+
+    let status_url = format!("{}/{}", PIN_URL, pin.user_code);
+    let attempts = pin.expires_in / pin.interval.max(1);
+    for _ in 0..attempts {
+        tokio::time::sleep(Duration::from_secs(pin.interval)).await;
+
+        let response = client
+            .get(&status_url)
+            .query(&[("client_id", config.client_id.as_str())])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            continue;
+        }
+
+        let status: PinStatus = response.json().await?;
+        if let ("OK", Some(token)) = (status.result.as_str(), status.access_token) {
+            // Simkl access tokens do not expire and have no refresh token
+            return Ok(OAuthToken::new(token, None, None));
+        }
+    }
+
+    Err(AppError::AuthError("Simkl authorization timed out".into()))
+}
@@ -0,0 +1,265 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+
+use crate::auth::{self, OAuthToken, TokenFile};
+use crate::config::{AppConfig, GoogleConfig, MalConfig, TraktConfig, TvdbConfig};
+use crate::error::AppError;
+
+/// Shared access to the token file that renews expired tokens on demand.
+/// Providers ask it for a token per request instead of tracking lifetimes themselves.
+pub struct TokenStore {
+    file: Mutex<TokenFile>,
+    /// One lock per service, held while its token is renewed; the file lock never is,
+    /// so a renewal only holds up requests to the same service
+    renewals: Mutex<HashMap<String, Arc<Mutex<()>>>>,
+    /// Services whose renewal failed this run; they are not asked again until the next login
+    failed: Mutex<HashSet<String>>,
+    renewer: Box<dyn Renewer>,
+}
+
+/// Obtains new tokens from the providers; tests swap in a fake
+#[async_trait]
+trait Renewer: Send + Sync {
+    /// A new token for `service`, exchanged for `refresh_token` where the service needs one.
+    /// `None` for services that cannot be renewed.
+    async fn renew(&self, service: &str, refresh_token: Option<&str>) -> Result<Option<OAuthToken>, AppError>;
+}
+
+struct ProviderRenewer {
+    mal: MalConfig,
+    trakt: TraktConfig,
+    tvdb: TvdbConfig,
+    google: GoogleConfig,
+}
+
+#[async_trait]
+impl Renewer for ProviderRenewer {
+    async fn renew(&self, service: &str, refresh_token: Option<&str>) -> Result<Option<OAuthToken>, AppError> {
+        match (service, refresh_token) {
+            // TVDB has no user login, a fresh JWT is always one API call away
+            (auth::TVDB, _) => auth::tvdb::login(&self.tvdb).await.map(Some),
+            (auth::MAL, Some(refresh_token)) => Ok(Some(auth::mal::refresh(&self.mal, refresh_token).await?)),
+            (auth::TRAKT, Some(refresh_token)) => Ok(Some(auth::trakt::refresh(&self.trakt, refresh_token).await?)),
+            (auth::GOOGLE, Some(refresh_token)) => Ok(Some(auth::google::refresh(&self.google, refresh_token).await?)),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl TokenStore {
+    pub fn new(file: TokenFile, mal: MalConfig, trakt: TraktConfig, tvdb: TvdbConfig, google: GoogleConfig) -> Self {
+        Self::with_renewer(file, Box::new(ProviderRenewer { mal, trakt, tvdb, google }))
+    }
+
+    fn with_renewer(file: TokenFile, renewer: Box<dyn Renewer>) -> Self {
+        Self {
+            file: Mutex::new(file),
+            renewals: Mutex::new(HashMap::new()),
+            failed: Mutex::new(HashSet::new()),
+            renewer,
+        }
+    }
+
+    /// Store without a backing file or provider credentials, for client tests
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self::new(
+            TokenFile::default(),
+            MalConfig::default(),
+            TraktConfig::default(),
+            TvdbConfig::default(),
+            GoogleConfig::default(),
+        )
+    }
+
+    pub fn load(config: &AppConfig) -> Result<Self, AppError> {
+        Ok(Self::new(
+            TokenFile::load(&config.auth.tokens_path)?,
+            config.mal.clone(),
+            config.trakt.clone(),
+            config.tvdb.clone(),
+            config.google.clone(),
+        ))
+    }
+
+    /// Returns a usable access token for `service`, refreshing it first when it has expired.
+    /// `None` means the user has not logged in (or the refresh failed) and the caller should
+    /// fall back to unauthenticated requests.
+    pub async fn access_token(&self, service: &str) -> Result<Option<String>, AppError> {
+        if let Some(token) = self.current(service).await {
+            return Ok(Some(token));
+        }
+        if self.failed.lock().await.contains(service) {
+            return Ok(None);
+        }
+
+        let renewal = self.renewal_lock(service).await;
+        let _renewing = renewal.lock().await;
+        // Another request may have renewed the token, or failed to, while this one waited
+        if let Some(token) = self.current(service).await {
+            return Ok(Some(token));
+        }
+        if self.failed.lock().await.contains(service) {
+            return Ok(None);
+        }
+
+        let refresh_token = self.file.lock().await.get(service).and_then(|t| t.refresh_token.clone());
+        match self.renewer.renew(service, refresh_token.as_deref()).await {
+            Ok(Some(token)) => {
+                tracing::debug!("Renewed {} token", service);
+                let access_token = token.access_token.clone();
+                self.file.lock().await.set(service, token)?;
+                Ok(Some(access_token))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                self.failed.lock().await.insert(service.to_string());
+                if service == auth::TVDB {
+                    return Err(e);
+                }
+                tracing::warn!("Could not refresh {} token, run `login {}` again: {}", service, service, e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// The stored token for `service` while it has not expired
+    async fn current(&self, service: &str) -> Option<String> {
+        let file = self.file.lock().await;
+        file.get(service).filter(|t| !t.is_expired()).map(|t| t.access_token.clone())
+    }
+
+    async fn renewal_lock(&self, service: &str) -> Arc<Mutex<()>> {
+        self.renewals.lock().await.entry(service.to_string()).or_default().clone()
+    }
+
+    /// Refreshes a stored token ahead of its expiry, returning whether a new token was saved
+    pub async fn refresh(&self, service: &str) -> Result<bool, AppError> {
+        let renewal = self.renewal_lock(service).await;
+        let _renewing = renewal.lock().await;
+        let refresh_token = self.file.lock().await.get(service).and_then(|t| t.refresh_token.clone());
+        let Some(refresh_token) = refresh_token else {
+            return Ok(false);
+        };
+        match self.renewer.renew(service, Some(&refresh_token)).await {
+            Ok(Some(token)) => {
+                self.file.lock().await.set(service, token)?;
+                self.failed.lock().await.remove(service);
+                Ok(true)
+            }
+            Ok(None) => Ok(false),
+            Err(e) => {
+                tracing::debug!("Refreshing {} token failed: {}", service, e);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Marks a token rejected by the API as expired so the next request renews it
+    pub async fn invalidate(&self, service: &str) -> Result<(), AppError> {
+        let mut file = self.file.lock().await;
+        if let Some(mut token) = file.get(service).cloned() {
+            token.expires_at = Some(chrono::Utc::now());
+            file.set(service, token)?;
+        }
+        Ok(())
+    }
+
+    pub async fn set(&self, service: &str, token: OAuthToken) -> Result<(), AppError> {
+        self.file.lock().await.set(service, token)?;
+        self.failed.lock().await.remove(service);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Hands out numbered tokens, or fails, and counts how often it was asked
+    struct FakeRenewer {
+        calls: Arc<AtomicUsize>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl Renewer for FakeRenewer {
+        async fn renew(&self, _service: &str, refresh_token: Option<&str>) -> Result<Option<OAuthToken>, AppError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            if self.fail {
+                return Err(AppError::AuthError("refresh token revoked".into()));
+            }
+            let Some(refresh_token) = refresh_token else {
+                return Ok(None);
+            };
+            Ok(Some(OAuthToken::new(format!("renewed-{}", call), Some(refresh_token.to_string()), Some(3600))))
+        }
+    }
+
+    fn store(dir: &tempfile::TempDir, fail: bool) -> (TokenStore, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let file = TokenFile::load(&dir.path().join("tokens.json")).unwrap();
+        let store = TokenStore::with_renewer(file, Box::new(FakeRenewer { calls: calls.clone(), fail }));
+        (store, calls)
+    }
+
+    fn expired() -> OAuthToken {
+        OAuthToken::new("stale".to_string(), Some("refresh".to_string()), Some(0))
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_renewed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let (store, calls) = store(&dir, false);
+        store.set(auth::MAL, expired()).await.unwrap();
+
+        let (first, second) = tokio::join!(store.access_token(auth::MAL), store.access_token(auth::MAL));
+        assert_eq!(first.unwrap().as_deref(), Some("renewed-1"));
+        assert_eq!(second.unwrap().as_deref(), Some("renewed-1"));
+        assert_eq!(store.access_token(auth::MAL).await.unwrap().as_deref(), Some("renewed-1"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // The renewed token is written to the token file
+        let saved = TokenFile::load(&dir.path().join("tokens.json")).unwrap();
+        assert_eq!(saved.get(auth::MAL).unwrap().access_token, "renewed-1");
+    }
+
+    #[tokio::test]
+    async fn test_failed_renewal_is_remembered_until_login() {
+        let dir = tempfile::tempdir().unwrap();
+        let (store, calls) = store(&dir, true);
+        store.set(auth::TRAKT, expired()).await.unwrap();
+
+        assert!(store.access_token(auth::TRAKT).await.unwrap().is_none());
+        assert!(store.access_token(auth::TRAKT).await.unwrap().is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A new login clears the failure, so the next expiry is renewed again
+        store.set(auth::TRAKT, expired()).await.unwrap();
+        assert!(store.access_token(auth::TRAKT).await.unwrap().is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_invalidated_token_is_renewed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (store, calls) = store(&dir, false);
+        store.set(auth::MAL, OAuthToken::new("valid".to_string(), Some("refresh".to_string()), Some(3600))).await.unwrap();
+        assert_eq!(store.access_token(auth::MAL).await.unwrap().as_deref(), Some("valid"));
+
+        store.invalidate(auth::MAL).await.unwrap();
+        assert_eq!(store.access_token(auth::MAL).await.unwrap().as_deref(), Some("renewed-1"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_missing_token_is_not_renewed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (store, _) = store(&dir, false);
+        assert!(store.access_token(auth::MAL).await.unwrap().is_none());
+    }
+}
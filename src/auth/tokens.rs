@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+
+/// An OAuth access token as persisted between runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthToken {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// None for tokens that never expire (Simkl)
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl OAuthToken {
+    pub fn new(access_token: String, refresh_token: Option<String>, expires_in: Option<i64>) -> Self {
+        Self {
+            access_token,
+            refresh_token,
+            expires_at: expires_in.map(|secs| Utc::now() + chrono::Duration::seconds(secs)),
+        }
+    }
+
+    /// True once the token is within a minute of expiring, so in-flight requests don't race it
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now() + chrono::Duration::seconds(60))
+    }
+}
+
+/// JSON file holding one token per service name
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct TokenFile {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(flatten)]
+    tokens: BTreeMap<String, OAuthToken>,
+}
+
+impl TokenFile {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let mut file = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(path)?)?
+        } else {
+            TokenFile::default()
+        };
+        file.path = path.to_path_buf();
+        Ok(file)
+    }
+
+    pub fn get(&self, service: &str) -> Option<&OAuthToken> {
+        self.tokens.get(service)
+    }
+
+    pub fn set(&mut self, service: &str, token: OAuthToken) -> Result<(), AppError> {
+        self.tokens.insert(service.to_string(), token);
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), AppError> {
+        std::fs::write(&self.path, serde_json::to_string_pretty(self)?)?;
+        // Tokens grant account access, keep them private to the user
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_file_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokens.json");
+
+        let mut file = TokenFile::load(&path).unwrap();
+        assert!(file.get("simkl").is_none());
+        file.set("simkl", OAuthToken::new("abc".to_string(), None, None)).unwrap();
+
+        let reloaded = TokenFile::load(&path).unwrap();
+        let token = reloaded.get("simkl").unwrap();
+        assert_eq!(token.access_token, "abc");
+        assert!(token.expires_at.is_none());
+    }
+
+    #[test]
+    fn test_token_expiry_margin() {
+        assert!(!OAuthToken::new("a".to_string(), None, None).is_expired());
+        assert!(!OAuthToken::new("a".to_string(), None, Some(3600)).is_expired());
+        assert!(OAuthToken::new("a".to_string(), None, Some(30)).is_expired());
+    }
+}
@@ -0,0 +1,117 @@
+use reqwest::{Client, StatusCode};
+use std::time::Duration;
+
+use crate::auth::tokens::OAuthToken;
+use crate::config::TraktConfig;
+use crate::error::AppError;
+
+const API_URL: &str = "https://api.trakt.tv";
+const REDIRECT_URI: &str = "urn:ietf:wg:oauth:2.0:oob";
+
+#[derive(serde::Deserialize)]
+struct DeviceCode {
+    device_code: String,
+    user_code: String,
+    verification_url: String,
+    expires_in: u64,
+    interval: u64,
+}
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+    expires_in: i64,
+}
+
+impl From<TokenResponse> for OAuthToken {
+    fn from(response: TokenResponse) -> Self {
+        OAuthToken::new(response.access_token, Some(response.refresh_token), Some(response.expires_in))
+    }
+}
+
+fn check_configured(config: &TraktConfig) -> Result<(), AppError> {
+    if config.client_id.is_empty() || config.client_secret.is_empty() {
+        return Err(AppError::AuthError(
+            "Set trakt.client_id and trakt.client_secret in config (create an app at https://trakt.tv/oauth/applications)".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Runs Trakt's device-code flow and returns an access token with its refresh token
+pub async fn authorize(config: &TraktConfig) -> Result<OAuthToken, AppError> {
+    check_configured(config)?;
+    let client = Client::new();
+
+    let response = client
+        .post(format!("{}/oauth/device/code", API_URL))
+        .json(&serde_json::json!({ "client_id": config.client_id }))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(AppError::AuthError(format!(
+            "Trakt device code request failed: {}",
+            response.status()
+        )));
+    }
+    let device: DeviceCode = response.json().await?;
+
+    println!("🔐 TRAKT AUTHORIZATION");
+    println!("======================");
+    println!("1. Open {}", device.verification_url);
+    println!("2. Enter the code: {}", device.user_code);
+    println!("⏳ Waiting for approval...");
+
+    let mut interval = device.interval.max(1);
+    let deadline = std::time::Instant::now() + Duration::from_secs(device.expires_in);
+    while std::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+
+        let response = client
+            .post(format!("{}/oauth/device/token", API_URL))
+            .json(&serde_json::json!({
+                "code": device.device_code,
+                "client_id": config.client_id,
+                "client_secret": config.client_secret,
+            }))
+            .send()
+            .await?;
+
+        match response.status() {
+            StatusCode::OK => return Ok(response.json::<TokenResponse>().await?.into()),
+            // Pending approval
+            StatusCode::BAD_REQUEST => continue,
+            // Polling too fast
+            StatusCode::TOO_MANY_REQUESTS => interval += 1,
+            StatusCode::GONE => return Err(AppError::AuthError("Trakt code expired".into())),
+            StatusCode::IM_A_TEAPOT => return Err(AppError::AuthError("Trakt authorization denied".into())),
+            status => return Err(AppError::AuthError(format!("Trakt authorization failed: {}", status))),
+        }
+    }
+
+    Err(AppError::AuthError("Trakt authorization timed out".into()))
+}
+
+/// Exchanges a refresh token for a new access token (Trakt tokens expire after three months)
+pub async fn refresh(config: &TraktConfig, refresh_token: &str) -> Result<OAuthToken, AppError> {
+    check_configured(config)?;
+
+    let response = Client::new()
+        .post(format!("{}/oauth/token", API_URL))
+        .json(&serde_json::json!({
+            "refresh_token": refresh_token,
+            "client_id": config.client_id,
+            "client_secret": config.client_secret,
+            "redirect_uri": REDIRECT_URI,
+            "grant_type": "refresh_token",
+        }))
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        Ok(response.json::<TokenResponse>().await?.into())
+    } else {
+        Err(AppError::AuthError(format!("Trakt token refresh failed: {}", response.status())))
+    }
+}
@@ -0,0 +1,29 @@
+use reqwest::Client;
+
+use crate::auth::tokens::OAuthToken;
+use crate::config::TvdbConfig;
+use crate::error::AppError;
+
+/// TVDB v3 JWTs are valid for 24 hours
+const TOKEN_LIFETIME_SECS: i64 = 24 * 60 * 60;
+
+#[derive(serde::Deserialize)]
+struct LoginResponse {
+    token: String,
+}
+
+/// Exchanges the API key for a JWT; there is no user step, so this doubles as refresh
+pub async fn login(config: &TvdbConfig) -> Result<OAuthToken, AppError> {
+    let response = Client::new()
+        .post("https://api.thetvdb.com/login")
+        .json(&serde_json::json!({ "apikey": config.api_key }))
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        let login: LoginResponse = response.json().await?;
+        Ok(OAuthToken::new(login.token, None, Some(TOKEN_LIFETIME_SECS)))
+    } else {
+        Err(AppError::AuthError(format!("TVDB authentication failed: {}", response.status())))
+    }
+}
@@ -1,6 +1,9 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 
+use crate::config::{Delimiter, InputFormat, OnlyKind, OutputFormat, QuoteStyle, SortKey, SortOrder, SplitPeriod, TimeOfDay, Timezone, WatchPrecision};
+use crate::metadata::ServiceType;
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct CliArgs {
@@ -8,10 +11,94 @@ pub struct CliArgs {
     #[arg(short, long, value_name = "FILE")]
     pub config: Option<PathBuf>,
 
-    /// Output CSV file path
+    /// Output CSV file path; may hold {date}, {profile}, {year} and {month}
     #[arg(short, long, value_name = "FILE")]
     pub output: Option<PathBuf>,
 
+    /// Import format of the export file
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub format: Option<OutputFormat>,
+
+    /// Write movies and shows to separate files instead of one mixed CSV
+    #[arg(long)]
+    pub split: bool,
+
+    /// Write one file per year or month of watches
+    #[arg(long, value_enum, value_name = "PERIOD")]
+    pub split_by: Option<SplitPeriod>,
+
+    /// Write a row for every watched episode instead of one per show
+    #[arg(long)]
+    pub episode_rows: bool,
+
+    /// Mark shows completed or watching by comparing the episodes watched with those aired
+    #[arg(long)]
+    pub auto_status: bool,
+
+    /// Also export the Prime Video watchlist as plan to watch, in <output>-watchlist.csv
+    #[arg(long, conflicts_with = "input")]
+    pub watchlist: bool,
+
+    /// Add only new rows to an existing export file instead of overwriting it
+    #[arg(long)]
+    pub merge: bool,
+
+    /// Sort the exported rows by watch date, title or release year
+    #[arg(long, value_enum, value_name = "KEY")]
+    pub sort: Option<SortKey>,
+
+    /// Direction of --sort
+    #[arg(long, value_enum, value_name = "ORDER", requires = "sort")]
+    pub sort_order: Option<SortOrder>,
+
+    /// Export only movies or only shows
+    #[arg(long, value_enum, value_name = "KIND")]
+    pub only: Option<OnlyKind>,
+
+    /// Export only items watched on or after this day (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub since: Option<chrono::NaiveDate>,
+
+    /// Export only items watched on or before this day (YYYY-MM-DD)
+    #[arg(long, value_name = "DATE")]
+    pub until: Option<chrono::NaiveDate>,
+
+    /// Export only titles matching this regular expression
+    #[arg(long, value_name = "REGEX")]
+    pub title_regex: Option<String>,
+
+    /// Field delimiter of the CSV formats
+    #[arg(long, value_enum, value_name = "DELIMITER")]
+    pub delimiter: Option<Delimiter>,
+
+    /// Which CSV fields are quoted
+    #[arg(long, value_enum, value_name = "STYLE")]
+    pub quote: Option<QuoteStyle>,
+
+    /// Start CSV files with a UTF-8 byte order mark, so Excel reads them as UTF-8
+    #[arg(long)]
+    pub bom: bool,
+
+    /// Zone the watch dates are in when writing watched_at: system, utc or an offset like +02:00
+    #[arg(long, value_name = "ZONE")]
+    pub timezone: Option<Timezone>,
+
+    /// Write watched_at as a full timestamp or just the day
+    #[arg(long, value_enum, value_name = "PRECISION")]
+    pub watched_at_precision: Option<WatchPrecision>,
+
+    /// Time of day given to every watch in a full watched_at timestamp (HH:MM)
+    #[arg(long, value_name = "TIME")]
+    pub watch_time: Option<TimeOfDay>,
+
+    /// Match the history and print a preview of the export instead of writing it
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Rows shown from each end of the export by --dry-run
+    #[arg(long, value_name = "N", default_value = "10", requires = "dry_run")]
+    pub preview_rows: usize,
+
     /// Log level (trace, debug, info, warn, error)
     #[arg(short = 'L', long, value_name = "LEVEL", default_value = "info")]
     pub log_level: String,
@@ -20,13 +107,140 @@ pub struct CliArgs {
     #[arg(long)]
     pub headless: bool,
 
-    /// Maximum number of concurrent requests
-    #[arg(long, default_value = "5")]
+    /// Maximum number of history items matched concurrently
+    #[arg(long, default_value = "4")]
     pub max_concurrent: usize,
 
     /// Timeout for browser operations (in seconds)
     #[arg(long, default_value = "30")]
     pub browser_timeout: u64,
+
+    /// Quick export: only the last 30 days, with a small provider request budget
+    #[arg(long)]
+    pub quick: bool,
+
+    /// Only scrape, match and export items watched since the previous run
+    #[arg(long)]
+    pub incremental: bool,
+
+    /// Providers to try for movies, in order (e.g. tmdb,simkl)
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub movie_providers: Option<Vec<ServiceType>>,
+
+    /// Providers to try for shows, in order (e.g. simkl,tmdb,tvdb)
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub show_providers: Option<Vec<ServiceType>>,
+
+    /// Ask which candidate is right when several match a title almost equally well
+    #[arg(short, long)]
+    pub interactive: bool,
+
+    /// Match only from the metadata cache and overrides file, without contacting any provider
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Answer every provider lookup from a JSON array of canned results instead of the real APIs
+    #[arg(long, value_name = "FILE")]
+    pub mock: Option<PathBuf>,
+
+    /// Read the watch history from a JSON file (as saved by a previous scrape) instead of Prime Video
+    #[arg(long, value_name = "FILE")]
+    pub input: Option<PathBuf>,
+
+    /// What --input is: saved Prime Video history or a Netflix viewing history CSV
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "json", requires = "input")]
+    pub input_format: InputFormat,
+
+    /// Import only this profile's rows of a Netflix ViewingActivity.csv
+    #[arg(long, value_name = "NAME", requires = "input")]
+    pub netflix_profile: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Check whether the saved Prime Video session is still valid
+    CheckLogin,
+    /// Authorize this app with a service account
+    Login {
+        #[command(subcommand)]
+        service: LoginCommand,
+    },
+    /// Manage secrets stored in the OS keyring
+    Credentials {
+        #[command(subcommand)]
+        action: CredentialsCommand,
+    },
+    /// Approve, correct or reject low-confidence matches held back from the export
+    Review,
+    /// Download TMDB's daily movie and show ID exports for the offline `tmdb_export` provider
+    FetchTmdbExports,
+    /// Download Fribb's anime-lists, which maps anime between MAL, AniList, AniDB, TVDB and TMDB IDs
+    FetchAnimeIds,
+    /// Match the watch history and add it straight to a service's history instead of writing a CSV
+    Sync {
+        #[command(subcommand)]
+        service: SyncCommand,
+    },
+    /// Manage the metadata cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum LoginCommand {
+    /// Authorize with Simkl using the PIN/device flow
+    Simkl,
+    /// Authorize with Trakt using the device-code flow
+    Trakt,
+    /// Authorize with MyAnimeList using OAuth2 PKCE
+    Mal,
+    /// Authorize access to Google Sheets using OAuth2 PKCE
+    Google,
+}
+
+#[derive(Subcommand)]
+pub enum CredentialsCommand {
+    /// Store or update the Amazon email and password in the OS keyring
+    SetAmazon,
+    /// Remove the Amazon email and password from the OS keyring
+    ClearAmazon,
+    /// Store or update a secret, e.g. `simkl.client_secret`, for config values written as `keyring:<name>`
+    Set {
+        name: String,
+    },
+    /// Remove a secret stored with `credentials set`
+    Clear {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SyncCommand {
+    /// Add the history to Simkl through its sync API, using the token from `login simkl`
+    Simkl,
+    /// Update the MyAnimeList entries of matched anime, using the token from `login mal`
+    Mal,
+    /// Mark matched movies and episodes as played on the Jellyfin server set in config
+    Jellyfin,
+    /// Mark matched movies and episodes as watched on the Plex server set in config
+    Plex,
+    /// Append the rows not yet in the Google Sheet set in config, using the token from `login google`
+    Sheets,
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Look up every title of a saved history so a later export is answered from the cache
+    Warm {
+        /// History saved as JSON, in the format the top-level `--input` reads
+        #[arg(long, value_name = "FILE")]
+        input: PathBuf,
+    },
 }
 
 impl Default for CliArgs {
@@ -34,10 +248,42 @@ impl Default for CliArgs {
         Self {
             config: None,
             output: None,
+            format: None,
+            split: false,
+            split_by: None,
+            episode_rows: false,
+            auto_status: false,
+            watchlist: false,
+            merge: false,
+            sort: None,
+            sort_order: None,
+            only: None,
+            since: None,
+            until: None,
+            title_regex: None,
+            delimiter: None,
+            quote: None,
+            bom: false,
+            timezone: None,
+            watched_at_precision: None,
+            watch_time: None,
+            dry_run: false,
+            preview_rows: 10,
             log_level: "info".to_string(),
             headless: true,
-            max_concurrent: 5,
+            max_concurrent: 4,
             browser_timeout: 30,
+            quick: false,
+            incremental: false,
+            movie_providers: None,
+            show_providers: None,
+            interactive: false,
+            offline: false,
+            mock: None,
+            input: None,
+            input_format: InputFormat::Json,
+            netflix_profile: None,
+            command: None,
         }
     }
 }
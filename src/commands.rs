@@ -0,0 +1,142 @@
+use std::error::Error;
+
+use crate::app::{App, RunOptions};
+use crate::auth::{self, TokenStore};
+use crate::cli::{CacheCommand, CliArgs, Command, CredentialsCommand, LoginCommand, SyncCommand};
+use crate::config::AppConfig;
+use crate::credentials;
+use crate::matching::ReviewQueue;
+use crate::metadata::{anime_ids, tmdb_export};
+use crate::scraping::session::SessionStore;
+use crate::scraping::Scraper;
+use crate::sync::SyncTarget;
+
+/// Runs a subcommand instead of the default export pipeline
+pub async fn run(command: &Command, cli_args: &CliArgs) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::CheckLogin => check_login(cli_args).await?,
+        Command::Login { service } => login(service, cli_args).await?,
+        Command::Credentials { action } => match action {
+            CredentialsCommand::SetAmazon => credentials::prompt_and_store_amazon_credentials()?,
+            CredentialsCommand::ClearAmazon => {
+                credentials::clear_amazon_credentials()?;
+                println!("✅ Amazon credentials removed from the OS keyring");
+            }
+            CredentialsCommand::Set { name } => credentials::prompt_and_store_secret(name)?,
+            CredentialsCommand::Clear { name } => {
+                credentials::delete_secret(name)?;
+                println!("✅ '{}' removed from the OS keyring", name);
+            }
+        },
+        Command::Review => review(cli_args)?,
+        Command::FetchTmdbExports => {
+            let config = AppConfig::load_with_cli_args(cli_args)?;
+            tmdb_export::download(&config.tmdb_export).await?;
+            println!("   They are indexed on the next run that uses the tmdb_export provider.");
+        }
+        Command::FetchAnimeIds => {
+            let config = AppConfig::load_with_cli_args(cli_args)?;
+            anime_ids::download(&config.anime_ids).await?;
+        }
+        Command::Sync { service } => {
+            let target = match service {
+                SyncCommand::Simkl => SyncTarget::Simkl,
+                SyncCommand::Mal => SyncTarget::Mal,
+                SyncCommand::Jellyfin => SyncTarget::Jellyfin,
+                SyncCommand::Plex => SyncTarget::Plex,
+                SyncCommand::Sheets => SyncTarget::Sheets,
+            };
+            let config = AppConfig::load_with_cli_args(cli_args)?;
+            App::new_with_config(config, RunOptions::from_cli(cli_args))?.sync(target).await?;
+        }
+        Command::Cache { action } => match action {
+            CacheCommand::Warm { input } => {
+                let config = AppConfig::load_with_cli_args(cli_args)?;
+                let mut app = App::new_with_config(config, RunOptions::from_cli(cli_args))?;
+                app.warm_cache(input).await?;
+            }
+        },
+    }
+    Ok(())
+}
+
+async fn check_login(cli_args: &CliArgs) -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load_with_cli_args(cli_args)?;
+    let key_source = config.session.key_source()?;
+
+    let mut scraper = Scraper::new(config.amazon.clone(), cli_args.headless).await?;
+    scraper.use_session_store(SessionStore::new(config.session.path.clone(), key_source));
+    let status = scraper.check_session().await;
+    scraper.shutdown().await?;
+    let status = status?;
+
+    if !status.saved {
+        println!("⚠️  No saved session found at {}", config.session.path.display());
+        println!("   Run the exporter once and log in to create one.");
+    } else if status.valid {
+        println!("✅ Saved session is valid");
+        match status.profile {
+            Some(profile) => println!("   Active profile: {}", profile),
+            None => println!("   Active profile: (could not be determined)"),
+        }
+    } else {
+        println!("❌ Saved session has expired - the next run will ask you to log in again");
+    }
+    if let Some(url) = status.url {
+        println!("   Current URL: {}", url);
+    }
+    Ok(())
+}
+
+fn review(cli_args: &CliArgs) -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load_with_cli_args(cli_args)?;
+    let queue_path = &config.matching.review_queue_path;
+    let mut queue = ReviewQueue::load(queue_path)?;
+    if queue.pending() == 0 {
+        println!("✅ Nothing to review in {}", queue_path.display());
+        return Ok(());
+    }
+
+    let summary = queue.review(&config.matching.overrides_path)?;
+    queue.save(queue_path)?;
+    println!();
+    println!(
+        "✅ {} approved (saved to {}), {} rejected, {} still pending",
+        summary.approved,
+        config.matching.overrides_path.display(),
+        summary.rejected,
+        summary.pending
+    );
+    if summary.approved > 0 {
+        println!("   Run the export again to include the approved titles.");
+    }
+    Ok(())
+}
+
+async fn login(service: &LoginCommand, cli_args: &CliArgs) -> Result<(), Box<dyn Error>> {
+    let config = AppConfig::load_with_cli_args(cli_args)?;
+    let tokens = TokenStore::load(&config)?;
+
+    let name = match service {
+        LoginCommand::Simkl => auth::SIMKL,
+        LoginCommand::Trakt => auth::TRAKT,
+        LoginCommand::Mal => auth::MAL,
+        LoginCommand::Google => auth::GOOGLE,
+    };
+
+    // Renew silently when a refresh token is still on file
+    if tokens.refresh(name).await? {
+        println!("✅ Refreshed {} token in {}", name, config.auth.tokens_path.display());
+        return Ok(());
+    }
+
+    let token = match service {
+        LoginCommand::Simkl => auth::simkl::authorize(&config.simkl).await?,
+        LoginCommand::Trakt => auth::trakt::authorize(&config.trakt).await?,
+        LoginCommand::Mal => auth::mal::authorize(&config.mal).await?,
+        LoginCommand::Google => auth::google::authorize(&config.google).await?,
+    };
+    tokens.set(name, token).await?;
+    println!("✅ Authorized {} - token saved to {}", name, config.auth.tokens_path.display());
+    Ok(())
+}
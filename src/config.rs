@@ -1,16 +1,79 @@
 use serde::{Deserialize, Serialize};
 use config::Config;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use validator::Validate;
 
+use crate::credentials::CredentialSource;
+use crate::paths::{self, AppDirs};
+use crate::scraping::models::PrimeRating;
+
 #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
 pub struct AppConfig {
     pub simkl: SimklConfig,
     pub tmdb: TmdbConfig,
+    /// Optional; leaving the section out switches TVDB off
+    #[serde(default)]
     pub tvdb: TvdbConfig,
+    /// Optional; leaving the section out switches MyAnimeList off
+    #[serde(default)]
     pub mal: MalConfig,
+    /// Only needed to pick a non-US marketplace; login itself happens in the browser
+    #[serde(default)]
+    #[validate]
     pub amazon: AmazonConfig,
     pub output: OutputConfig,
+    #[serde(default)]
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub trakt: TraktConfig,
+    /// Only needed for `sync jellyfin`
+    #[serde(default)]
+    pub jellyfin: JellyfinConfig,
+    /// Only needed for `sync plex`
+    #[serde(default)]
+    pub plex: PlexConfig,
+    /// Only needed for `login google`
+    #[serde(default)]
+    pub google: GoogleConfig,
+    /// Only needed for `sync sheets`
+    #[serde(default)]
+    pub sheets: SheetsConfig,
+    #[serde(default)]
+    pub omdb: OmdbConfig,
+    #[serde(default)]
+    pub tvmaze: KeylessProviderConfig,
+    #[serde(default)]
+    pub wikidata: KeylessProviderConfig,
+    #[serde(default)]
+    pub imdb: ImdbDatasetConfig,
+    #[serde(default)]
+    pub tmdb_export: TmdbExportConfig,
+    #[serde(default)]
+    pub anime_ids: AnimeIdsConfig,
+    /// External commands acting as metadata providers, keyed by the name used in `providers`
+    #[serde(default)]
+    pub custom_providers: BTreeMap<String, CustomProviderConfig>,
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+    #[serde(default)]
+    #[validate]
+    pub ratings: RatingConfig,
+    /// Order in which metadata providers are tried for movies and for shows
+    #[serde(default)]
+    pub providers: crate::metadata::ProviderPriority,
+    /// Rules that clean scraped titles before they are searched
+    #[serde(default)]
+    pub normalization: crate::matching::NormalizationConfig,
+    /// How provider candidates are scored against scraped titles
+    #[serde(default)]
+    pub matching: crate::matching::MatchingConfig,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
@@ -19,18 +82,223 @@ pub struct SimklConfig {
     pub client_id: String,
     #[validate(length(min = 1, message = "Client secret cannot be empty"))]
     pub client_secret: String,
+    /// Set to false to leave the provider out of every chain
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// A program answering provider requests: one JSON request on stdin, JSON results on stdout
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CustomProviderConfig {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Seconds a single request may take before the command is killed
+    #[serde(default = "default_custom_provider_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_custom_provider_timeout() -> u64 {
+    30
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
 pub struct TmdbConfig {
     #[validate(length(min = 1, message = "Access token cannot be empty"))]
     pub access_token: String,
+    /// ISO 639-1 language, optionally with region (e.g. "de-DE"), for localized titles
+    #[serde(default)]
+    pub language: Option<String>,
+    /// ISO 3166-1 country (e.g. "DE") used to prefer local release titles and dates for movies
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
 pub struct TvdbConfig {
     #[validate(length(min = 1, message = "API key cannot be empty"))]
     pub api_key: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// A missing section has no key to use, so the provider starts switched off
+impl Default for TvdbConfig {
+    fn default() -> Self {
+        Self { api_key: String::new(), enabled: false }
+    }
+}
+
+/// Optional Trakt application credentials (https://trakt.tv/oauth/applications)
+#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+#[serde(default)]
+pub struct TraktConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub enabled: bool,
+}
+
+impl Default for TraktConfig {
+    fn default() -> Self {
+        Self { client_id: String::new(), client_secret: String::new(), enabled: true }
+    }
+}
+
+/// Jellyfin server to mark watched items on (Dashboard → API Keys)
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JellyfinConfig {
+    /// Base URL, e.g. `http://localhost:8096`
+    pub url: String,
+    pub api_key: String,
+    /// Name of the user whose played state is updated; may be left out on a single-user server
+    pub user: String,
+}
+
+/// Plex Media Server to mark watched items on
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PlexConfig {
+    /// Base URL, e.g. `http://localhost:32400`
+    pub url: String,
+    /// The `X-Plex-Token` of the account whose watched state is updated
+    pub token: String,
+}
+
+/// Google OAuth client used by `login google` (a "Desktop app" client from the Cloud Console)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GoogleConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    /// Local port for the `login google` redirect
+    pub redirect_port: u16,
+}
+
+impl Default for GoogleConfig {
+    fn default() -> Self {
+        Self { client_id: String::new(), client_secret: String::new(), redirect_port: 8766 }
+    }
+}
+
+/// Google Sheet that `sync sheets` appends the history to
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SheetsConfig {
+    /// The long ID in the sheet's URL, between `/d/` and `/edit`
+    pub spreadsheet_id: String,
+    /// Name of the tab rows are added to
+    pub sheet: String,
+    /// Columns of each row, as in `output.columns`
+    pub columns: Vec<crate::processor::columns::ColumnSpec>,
+}
+
+impl Default for SheetsConfig {
+    fn default() -> Self {
+        use crate::processor::columns::ColumnSpec;
+        let columns = ["watched_date", "type", "title", "year", "episode", "rating", "imdb_id", "tmdb_id", "prime_url"];
+        Self {
+            spreadsheet_id: String::new(),
+            sheet: "Sheet1".to_string(),
+            columns: columns.into_iter().map(|name| ColumnSpec::Field(name.to_string())).collect(),
+        }
+    }
+}
+
+/// Optional OMDb API key (https://www.omdbapi.com/apikey.aspx)
+#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+#[serde(default)]
+pub struct OmdbConfig {
+    pub api_key: String,
+    pub enabled: bool,
+}
+
+impl Default for OmdbConfig {
+    fn default() -> Self {
+        Self { api_key: String::new(), enabled: true }
+    }
+}
+
+/// Local index over IMDB's `title.basics` dataset (https://datasets.imdbws.com/)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ImdbDatasetConfig {
+    /// Downloaded `title.basics.tsv.gz`, or the unpacked `.tsv`
+    pub dataset_path: PathBuf,
+    /// SQLite index built from the dataset on first use, and again whenever the dataset is newer
+    pub index_path: PathBuf,
+    pub enabled: bool,
+}
+
+impl Default for ImdbDatasetConfig {
+    fn default() -> Self {
+        Self {
+            dataset_path: PathBuf::from("./title.basics.tsv.gz"),
+            index_path: PathBuf::from("./imdb-index.sqlite"),
+            enabled: true,
+        }
+    }
+}
+
+/// Local index over TMDB's daily ID exports (https://developer.themoviedb.org/docs/daily-id-exports)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TmdbExportConfig {
+    /// `movie_ids_*.json.gz` as downloaded by `fetch-tmdb-exports`
+    pub movies_path: PathBuf,
+    /// `tv_series_ids_*.json.gz` as downloaded by `fetch-tmdb-exports`
+    pub shows_path: PathBuf,
+    /// SQLite index rebuilt whenever an export file is newer
+    pub index_path: PathBuf,
+    pub enabled: bool,
+}
+
+impl Default for TmdbExportConfig {
+    fn default() -> Self {
+        Self {
+            movies_path: PathBuf::from("./tmdb-movie-ids.json.gz"),
+            shows_path: PathBuf::from("./tmdb-tv-ids.json.gz"),
+            index_path: PathBuf::from("./tmdb-index.sqlite"),
+            enabled: true,
+        }
+    }
+}
+
+/// Local anime ID mapping file, either Fribb's anime-lists (https://github.com/Fribb/anime-lists)
+/// or manami's anime-offline-database (https://github.com/manami-project/anime-offline-database)
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnimeIdsConfig {
+    /// `anime-list-full.json` as downloaded by `fetch-anime-ids`, or `anime-offline-database.json`
+    pub path: PathBuf,
+    pub enabled: bool,
+}
+
+impl Default for AnimeIdsConfig {
+    fn default() -> Self {
+        Self { path: PathBuf::from("./anime-list-full.json"), enabled: true }
+    }
+}
+
+/// Switch for a provider that needs no credentials
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KeylessProviderConfig {
+    pub enabled: bool,
+}
+
+impl Default for KeylessProviderConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
@@ -39,31 +307,730 @@ pub struct MalConfig {
     pub client_id: String,
     #[validate(length(min = 1, message = "Client secret cannot be empty"))]
     pub client_secret: String,
+    /// Local port for the `login mal` redirect (app redirect URL: http://localhost:<port>/callback)
+    #[serde(default = "default_mal_redirect_port")]
+    pub redirect_port: u16,
+    /// Also match titles MAL rates as adult (`nsfw: black`); they are dropped otherwise
+    #[serde(default)]
+    pub include_nsfw: bool,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for MalConfig {
+    fn default() -> Self {
+        Self {
+            client_id: String::new(),
+            client_secret: String::new(),
+            redirect_port: default_mal_redirect_port(),
+            include_nsfw: false,
+            enabled: false,
+        }
+    }
+}
+
+fn default_mal_redirect_port() -> u16 {
+    8765
 }
 
 
 #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
 pub struct AmazonConfig {
-    #[validate(email(message = "Invalid email format (optional for manual login)"))]
+    /// Only needed for automated login; checked when set
+    #[validate(custom = "optional_email")]
+    #[serde(default)]
     pub email: String,
-    #[validate(length(min = 1, message = "Password cannot be empty (optional for manual login)"))]
+    #[serde(default)]
     pub password: String,
+    /// Read email/password from the OS keyring instead of this file
+    #[serde(default)]
+    pub credential_source: CredentialSource,
+    /// Amazon marketplace domain suffix (com, co.uk, de, it, co.jp, ...)
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// Also scrape the Prime Video watchlist and export it as plan to watch, in
+    /// `<export>-watchlist.csv`
+    #[serde(default)]
+    pub watchlist: bool,
+}
+
+/// An empty email is fine, since login usually happens in the browser
+fn optional_email(email: &str) -> Result<(), validator::ValidationError> {
+    if email.is_empty() || validator::validate_email(email) {
+        Ok(())
+    } else {
+        let mut error = validator::ValidationError::new("email");
+        error.message = Some("Invalid email format (optional for manual login)".into());
+        Err(error)
+    }
+}
+
+fn default_region() -> String {
+    "com".to_string()
+}
+
+impl Default for AmazonConfig {
+    fn default() -> Self {
+        Self {
+            email: String::new(),
+            password: String::new(),
+            credential_source: CredentialSource::default(),
+            region: default_region(),
+            watchlist: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
 pub struct OutputConfig {
+    /// May hold `{date}`, `{profile}`, `{year}` and `{month}` placeholders (see
+    /// [`crate::processor::output_path`])
+    pub path: PathBuf,
+    /// Report of items no provider could match; CSV if the name ends in `.csv`, JSON otherwise
+    #[serde(default = "default_unmatched_report")]
+    pub unmatched_report: PathBuf,
+    /// Set unmatched titles aside in `<export>.unmatched.csv`, one row per watch, and carry on;
+    /// when false, a title no provider matches stops the run
+    #[serde(default = "default_enabled")]
+    pub unmatched_csv: bool,
+    /// Newest watch date of the last export, read by `--incremental`
+    #[serde(default = "default_watermark")]
+    pub watermark: PathBuf,
+    /// Extra columns appended after Memo (genres, runtime, poster_url, overview); each one
+    /// costs a details request per title, so none are fetched unless listed
+    #[serde(default)]
+    pub enrichment: Vec<crate::metadata::EnrichmentField>,
+    /// Which service's import the export file is laid out for
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// Write movies and shows to separate `<name>-movies.csv` and `<name>-shows.csv` files
+    /// (Simkl format only); the movies file has no LastEpWatched, Season or Episode columns
+    #[serde(default)]
+    pub split: bool,
+    /// One file per year or month of watches, named by the path's `{year}`/`{month}`
+    /// placeholders or with the period appended
+    #[serde(default)]
+    pub split_by: Option<SplitPeriod>,
+    /// One row per watched episode with the show's IDs, rather than one row per show with
+    /// the last episode watched
+    #[serde(default)]
+    pub episode_rows: bool,
+    /// Mark a show completed once every episode aired so far is in the history, and watching
+    /// otherwise; looks up each show's episode count, a details request per title
+    #[serde(default)]
+    pub auto_status: bool,
+    /// Append to an existing CSV export only the rows it does not have yet, instead of replacing it
+    #[serde(default)]
+    pub merge: bool,
+    /// Custom column set and order for the CSV formats, replacing their built-in layout; each
+    /// entry is a field name or `{ header, template }` (see [`crate::processor::columns`])
+    #[serde(default)]
+    pub columns: Vec<crate::processor::columns::ColumnSpec>,
+    /// Rewatch handling per format, e.g. `{ "simkl": "plays" }`; formats not listed write one row per watch
+    #[serde(default)]
+    pub rewatches: BTreeMap<OutputFormat, RewatchMode>,
+    /// Rules for dropping repeated listings of one watch (HD and UHD editions), applied in order
+    /// before anything is written; an empty list keeps every row
+    #[serde(default = "crate::processor::dedup::default_rules")]
+    pub dedup: Vec<crate::processor::dedup::DedupRule>,
+    /// Order of the exported rows; Prime Video's own (newest first) when unset
+    #[serde(default)]
+    pub sort: Option<SortKey>,
+    #[serde(default)]
+    pub sort_order: SortOrder,
+    /// Which matched rows make it into the export (or sync); everything when left empty
+    #[serde(default)]
+    pub filter: FilterConfig,
+    /// Delimiter, quoting and BOM of the CSV formats
+    #[serde(default)]
+    pub csv: CsvDialect,
+    /// Timestamped copies of an export kept before it is overwritten or merged into; 0 keeps none
+    #[serde(default = "default_backups")]
+    pub backups: usize,
+    /// How `watched_at` timestamps are written
+    #[serde(default)]
+    pub watched_at: WatchedAtConfig,
+}
+
+impl OutputConfig {
+    pub fn rewatch_mode(&self) -> RewatchMode {
+        self.rewatches.get(&self.format).copied().unwrap_or_default()
+    }
+}
+
+/// Layout of the export file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Simkl's CSV import: one row per title with the last episode watched
+    #[default]
+    Simkl,
+    /// Trakt's CSV import: one row per movie or episode with IMDB, TMDB and TVDB IDs and `watched_at`
+    Trakt,
+    /// A JSON array of every item with its full match: all IDs, score, original title, enrichment
+    Json,
+    /// The same objects as `json`, one per line
+    Ndjson,
+    /// An Excel workbook with a Movies and a Shows sheet and real date cells
+    Xlsx,
+    /// A CSV for Serializd: one row per show season watched, movies left out
+    Serializd,
+    /// Kodi's `videodb.xml` library export with play counts and last play dates
+    Kodi,
+    /// A SQLite database with a titles and a watches table, for querying with SQL
+    Sqlite,
+    /// A readable Markdown report of the history grouped by month
+    Markdown,
+    /// The same report as a standalone HTML page
+    Html,
+    /// The JSON layout of a Simkl backup, for Simkl's restore instead of the CSV importer
+    #[serde(rename = "simkl_backup")]
+    #[value(name = "simkl-backup")]
+    SimklBackup,
+    /// A Parquet file with typed columns for DuckDB, pandas or Polars (needs the `parquet` feature)
+    Parquet,
+    /// An iCalendar (`.ics`) file with an all-day event per watch, for calendar apps
+    Ics,
+}
+
+/// What `--input` reads
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum InputFormat {
+    /// A JSON array of history items, as saved by a previous scrape
+    #[default]
+    Json,
+    /// Netflix's `ViewingActivity.csv` or `NetflixViewingHistory.csv`
+    Netflix,
+}
+
+/// How repeat watches of the same movie or episode are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RewatchMode {
+    /// One row per watch, each with its own date
+    #[default]
+    Rows,
+    /// One row per title with the latest date and a Plays count
+    Plays,
+}
+
+/// Span of watches each file holds when the export is split by period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitPeriod {
+    Year,
+    Month,
+}
+
+/// What the exported rows are sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// Watch date, then season and episode
+    #[value(name = "watched_at")]
+    WatchedAt,
+    /// Title, ignoring case, then watch date
+    Title,
+    /// Release year, then title; rows without a year come last
+    Year,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// UTF-8 byte order mark
+pub const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// How the CSV formats lay out their files; the defaults suit Simkl and Trakt, while Excel in
+/// many European locales wants semicolons and a BOM
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CsvDialect {
+    #[serde(default)]
+    pub delimiter: Delimiter,
+    #[serde(default)]
+    pub quote: QuoteStyle,
+    /// Start the file with a UTF-8 byte order mark, which Excel needs to read it as UTF-8
+    #[serde(default)]
+    pub bom: bool,
+}
+
+impl CsvDialect {
+    pub fn writer(&self) -> csv::WriterBuilder {
+        let mut builder = csv::WriterBuilder::new();
+        builder.delimiter(self.delimiter.byte()).quote_style(match self.quote {
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+        });
+        builder
+    }
+
+    pub fn reader(&self) -> csv::ReaderBuilder {
+        let mut builder = csv::ReaderBuilder::new();
+        builder.delimiter(self.delimiter.byte());
+        builder
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Delimiter {
+    #[default]
+    Comma,
+    Semicolon,
+    Tab,
+}
+
+impl Delimiter {
+    pub fn byte(self) -> u8 {
+        match self {
+            Self::Comma => b',',
+            Self::Semicolon => b';',
+            Self::Tab => b'\t',
+        }
+    }
+}
+
+/// Which fields get quotes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteStyle {
+    /// Only fields holding a delimiter, quote or line break
+    #[default]
+    Necessary,
+    Always,
+    /// Every field that is not a number
+    #[value(name = "non_numeric")]
+    NonNumeric,
+    /// No field, even if that makes the row ambiguous
+    Never,
+}
+
+/// How `watched_at` timestamps are built from the days Prime Video shows
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct WatchedAtConfig {
+    /// Zone the watch dates are in, which is the account's; the system zone by default
+    #[serde(default)]
+    pub timezone: Timezone,
+    /// Full timestamps, or just the day for targets that take one
+    #[serde(default)]
+    pub precision: WatchPrecision,
+    /// Time of day given to every watch in a full timestamp; midnight by default
+    #[serde(default)]
+    pub time: TimeOfDay,
+}
+
+impl WatchedAtConfig {
+    /// The configured time of `date` in the configured zone, as an RFC 3339 timestamp carrying
+    /// its offset, so an importer showing it in that zone lands on the same day; just `date`
+    /// with date precision. A date that does not parse is written at midnight UTC.
+    pub fn timestamp(&self, date: &str) -> String {
+        match (chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d"), self.precision) {
+            (Ok(day), WatchPrecision::Date) => day.to_string(),
+            (Ok(day), WatchPrecision::Datetime) => self.timezone.localize(day.and_time(self.time.0)),
+            (Err(_), _) => format!("{}T00:00:00Z", date),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchPrecision {
+    /// `2024-03-01`
+    Date,
+    /// `2024-03-01T00:00:00+01:00`
+    #[default]
+    Datetime,
+}
+
+/// A time of day written `HH:MM` or `HH:MM:SS`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(pub chrono::NaiveTime);
+
+impl std::str::FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        chrono::NaiveTime::parse_from_str(s.trim(), "%H:%M:%S")
+            .or_else(|_| chrono::NaiveTime::parse_from_str(s.trim(), "%H:%M"))
+            .map(Self)
+            .map_err(|_| format!("invalid time of day '{}'; expected HH:MM or HH:MM:SS", s))
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.0.format("%H:%M:%S").to_string()
+    }
+}
+
+/// A zone to read watch dates in: `system`, `utc` or a fixed offset such as `+02:00`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Timezone {
+    /// The zone of the machine running the export, with its daylight saving time
+    #[default]
+    System,
+    Utc,
+    Offset(chrono::FixedOffset),
+}
+
+impl Timezone {
+    fn localize(self, time: chrono::NaiveDateTime) -> String {
+        use chrono::{SecondsFormat, TimeZone};
+        let local = match self {
+            Self::System => chrono::Local.from_local_datetime(&time).earliest().map(|local| local.fixed_offset()),
+            Self::Utc => Some(time.and_utc().fixed_offset()),
+            Self::Offset(offset) => offset.from_local_datetime(&time).single(),
+        };
+        // Midnight only goes missing where a clock change skips it; UTC is the fallback then
+        local.unwrap_or_else(|| time.and_utc().fixed_offset()).to_rfc3339_opts(SecondsFormat::Secs, true)
+    }
+}
+
+impl std::str::FromStr for Timezone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "system" | "local" => Ok(Self::System),
+            "utc" | "z" => Ok(Self::Utc),
+            offset => offset.parse().map(Self::Offset).map_err(|_| {
+                format!("unknown timezone '{}'; expected `system`, `utc` or an offset such as `+02:00`", s)
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for Timezone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::System => write!(f, "system"),
+            Self::Utc => write!(f, "utc"),
+            Self::Offset(offset) => write!(f, "{}", offset),
+        }
+    }
+}
+
+impl TryFrom<String> for Timezone {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Timezone> for String {
+    fn from(timezone: Timezone) -> Self {
+        timezone.to_string()
+    }
+}
+
+/// Narrows one scraped session down to a targeted export, e.g. only the movies of 2024
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FilterConfig {
+    #[serde(default)]
+    pub only: Option<OnlyKind>,
+    /// First day to keep, inclusive
+    #[serde(default)]
+    pub since: Option<chrono::NaiveDate>,
+    /// Last day to keep, inclusive
+    #[serde(default)]
+    pub until: Option<chrono::NaiveDate>,
+    /// Keep only titles (as listed on Prime Video) this regex matches
+    #[serde(default)]
+    pub title_regex: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OnlyKind {
+    Movies,
+    Shows,
+}
+
+fn default_backups() -> usize {
+    3
+}
+
+fn default_unmatched_report() -> PathBuf {
+    PathBuf::from("./unmatched.json")
+}
+
+fn default_watermark() -> PathBuf {
+    PathBuf::from("./watermark.json")
+}
+
+/// Environment variable holding the passphrase for `"encryption": "passphrase"`
+pub const SESSION_PASSPHRASE_ENV: &str = "PV2SIMKL_SESSION_PASSPHRASE";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionEncryption {
+    /// Random key kept in the OS keyring
+    #[default]
+    Keyring,
+    /// Key derived from the passphrase in PV2SIMKL_SESSION_PASSPHRASE
+    Passphrase,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+#[serde(default)]
+pub struct SessionConfig {
+    /// Save the Amazon session cookies after login and reuse them on the next run
+    pub persist: bool,
+    pub path: PathBuf,
+    pub encryption: SessionEncryption,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            persist: true,
+            path: PathBuf::from("./session.bin"),
+            encryption: SessionEncryption::Keyring,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// File where OAuth tokens from the `login` commands are stored
+    pub tokens_path: PathBuf,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            tokens_path: PathBuf::from("./tokens.json"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Reuse provider search results from earlier runs
+    pub enabled: bool,
+    /// SQLite file holding cached provider responses
     pub path: PathBuf,
+    /// Days a search that found nothing is remembered before the provider is asked again
+    pub negative_ttl_days: u32,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: PathBuf::from("./metadata-cache.sqlite"),
+            negative_ttl_days: 30,
+        }
+    }
+}
+
+/// Backoff for provider requests failing with 429, 5xx or network errors
+#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+#[serde(default)]
+pub struct RetryConfig {
+    /// Total tries per request, including the first
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Random spread applied to each delay, as a fraction (0.2 = +/-20%)
+    pub jitter: f64,
+    /// Longest wait taken from a provider's `Retry-After` or rate-limit reset header
+    pub max_retry_after_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+            jitter: 0.2,
+            max_retry_after_ms: 120_000,
+        }
+    }
+}
+
+/// What a Prime Video thumbs up or down becomes in the exported rating, on Simkl's 1-10 scale
+#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+#[serde(default)]
+pub struct RatingConfig {
+    #[validate(range(min = 1, max = 10, message = "Ratings go from 1 to 10"))]
+    pub like: u8,
+    #[validate(range(min = 1, max = 10, message = "Ratings go from 1 to 10"))]
+    pub dislike: u8,
+    /// Rating for titles without a thumbs; left empty unless set
+    #[validate(range(min = 1, max = 10, message = "Ratings go from 1 to 10"))]
+    pub default: Option<u8>,
+}
+
+impl Default for RatingConfig {
+    fn default() -> Self {
+        Self { like: 8, dislike: 3, default: None }
+    }
+}
+
+impl RatingConfig {
+    pub fn rating(&self, prime: Option<PrimeRating>) -> Option<u8> {
+        match prime {
+            Some(PrimeRating::Like) => Some(self.like),
+            Some(PrimeRating::Dislike) => Some(self.dislike),
+            None => self.default,
+        }
+    }
+}
+
+/// How long a provider request may take before it fails, so a stalled provider can't hold up matching
+#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    /// Longest wait for a connection to be established
+    pub connect_secs: u64,
+    /// Longest wait for a whole request, from sending it to reading the last byte of the response
+    pub read_secs: u64,
+    /// Overrides for single providers, keyed by the name used in `providers`
+    pub providers: BTreeMap<String, ProviderTimeouts>,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            connect_secs: 10,
+            read_secs: 30,
+            providers: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ProviderTimeouts {
+    pub connect_secs: Option<u64>,
+    pub read_secs: Option<u64>,
+}
+
+impl TimeoutConfig {
+    /// Connect and read timeouts for `provider`, falling back to the shared ones
+    pub fn for_provider(&self, provider: &str) -> (std::time::Duration, std::time::Duration) {
+        let own = self.providers.get(provider).cloned().unwrap_or_default();
+        (
+            std::time::Duration::from_secs(own.connect_secs.unwrap_or(self.connect_secs)),
+            std::time::Duration::from_secs(own.read_secs.unwrap_or(self.read_secs)),
+        )
+    }
+}
+
+impl SessionConfig {
+    pub fn key_source(&self) -> Result<crate::crypto::KeySource, crate::error::AppError> {
+        match self.encryption {
+            SessionEncryption::Keyring => Ok(crate::crypto::KeySource::OsKeyring),
+            SessionEncryption::Passphrase => std::env::var(SESSION_PASSPHRASE_ENV)
+                .map(crate::crypto::KeySource::Passphrase)
+                .map_err(|_| crate::error::AppError::CryptoError(format!(
+                    "Session encryption is set to passphrase but {} is not set",
+                    SESSION_PASSPHRASE_ENV
+                ))),
+        }
+    }
+}
+
+/// Prefix of the environment variables that override config values, e.g.
+/// `PV2SIMKL_SIMKL__CLIENT_ID` for `simkl.client_id`
+pub const ENV_PREFIX: &str = "PV2SIMKL";
+
+/// Keys holding a list, given in the environment as comma-separated values
+const ENV_LIST_KEYS: &[&str] = &[
+    "providers.movies",
+    "providers.shows",
+    "providers.anime",
+    "output.enrichment",
+    "matching.anime_title_patterns",
+];
+
+/// Config values from `PV2SIMKL_*` variables, with `__` between the levels of a key.
+/// Values are passed on as strings, which serde reads as booleans or numbers where the
+/// field is one, so a secret such as `0123` keeps its leading zero.
+#[derive(Debug, Clone)]
+struct Environment {
+    variables: Vec<(String, String)>,
+}
+
+impl Environment {
+    fn from_process() -> Self {
+        Self { variables: std::env::vars().collect() }
+    }
+}
+
+impl config::Source for Environment {
+    fn clone_into_box(&self) -> Box<dyn config::Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<config::Map<String, config::Value>, config::ConfigError> {
+        let origin = "the environment".to_string();
+        let prefix = format!("{}_", ENV_PREFIX.to_lowercase());
+        let mut values = config::Map::new();
+        for (name, value) in &self.variables {
+            let name = name.to_lowercase();
+            let Some(key) = name.strip_prefix(&prefix) else {
+                continue;
+            };
+            let key = key.replace("__", ".");
+            let kind = if ENV_LIST_KEYS.contains(&key.as_str()) {
+                config::ValueKind::Array(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(|item| config::Value::new(Some(&origin), item.to_string()))
+                        .collect(),
+                )
+            } else {
+                config::ValueKind::String(value.clone())
+            };
+            values.insert(key, config::Value::new(Some(&origin), kind));
+        }
+        Ok(values)
+    }
 }
 
 impl AppConfig {
     pub fn load_with_cli_args(cli_args: &crate::cli::CliArgs) -> Result<Self, Box<dyn std::error::Error>> {
-        // Get the executable's directory
+        // Earlier versions kept config.json next to the executable; it moves to the
+        // platform config directory on first use, unless this run names its own config
         let exe_path = std::env::current_exe()?;
         let exe_dir = exe_path.parent().unwrap_or_else(|| std::path::Path::new("."));
-        let config_path = exe_dir.join("config.json");
+        let dirs = AppDirs::locate().unwrap_or_else(|| AppDirs::beside(exe_dir));
+        let config_path = dirs.config_file();
+        if cli_args.config.is_none() {
+            if let Err(e) = paths::migrate(&exe_dir.join("config.json"), &config_path) {
+                tracing::warn!("Could not move config.json to {}: {}", config_path.display(), e);
+            }
+        }
 
-        // Create default config if it doesn't exist
-        if !config_path.exists() {
+        // Create default config if it doesn't exist, unless the whole configuration comes
+        // from elsewhere
+        let from_env = std::env::vars().any(|(key, _)| key.starts_with(&format!("{}_", ENV_PREFIX)));
+        if !config_path.exists() && cli_args.config.is_none() && !from_env {
             Self::create_default_config(&config_path)?;
             println!("Created default config file at: {}", config_path.display());
             println!("Please edit the config file with your API keys and credentials before running the application.");
@@ -78,26 +1045,172 @@ impl AppConfig {
             builder = builder.add_source(config::File::with_name(cli_config_path.to_str().unwrap()));
         }
 
+        // Environment variables win over both files, so secrets need not be written to disk
+        builder = builder.add_source(Environment::from_process());
+
         // Override specific values from CLI args
         if let Some(output_path) = &cli_args.output {
             builder = builder.set_override("output.path", output_path.to_str().unwrap())?;
         }
 
         let config = builder.build()?;
-        let app_config: AppConfig = config.try_deserialize()?;
+        let mut app_config: AppConfig = config.try_deserialize()?;
+        app_config.place_files(&dirs);
+        app_config.resolve_secrets()?;
+
+        if let Some(movies) = &cli_args.movie_providers {
+            app_config.providers.movies = movies.clone();
+        }
+        if let Some(shows) = &cli_args.show_providers {
+            app_config.providers.shows = shows.clone();
+        }
+        if let Some(format) = cli_args.format {
+            app_config.output.format = format;
+        }
+        if cli_args.split {
+            app_config.output.split = true;
+        }
+        if let Some(period) = cli_args.split_by {
+            app_config.output.split_by = Some(period);
+        }
+        if cli_args.episode_rows {
+            app_config.output.episode_rows = true;
+        }
+        if cli_args.auto_status {
+            app_config.output.auto_status = true;
+        }
+        if cli_args.watchlist {
+            app_config.amazon.watchlist = true;
+        }
+        if cli_args.merge {
+            app_config.output.merge = true;
+        }
+        if let Some(sort) = cli_args.sort {
+            app_config.output.sort = Some(sort);
+        }
+        if let Some(order) = cli_args.sort_order {
+            app_config.output.sort_order = order;
+        }
+        if let Some(only) = cli_args.only {
+            app_config.output.filter.only = Some(only);
+        }
+        if let Some(since) = cli_args.since {
+            app_config.output.filter.since = Some(since);
+        }
+        if let Some(until) = cli_args.until {
+            app_config.output.filter.until = Some(until);
+        }
+        if let Some(pattern) = &cli_args.title_regex {
+            app_config.output.filter.title_regex = Some(pattern.clone());
+        }
+        if let Some(delimiter) = cli_args.delimiter {
+            app_config.output.csv.delimiter = delimiter;
+        }
+        if let Some(quote) = cli_args.quote {
+            app_config.output.csv.quote = quote;
+        }
+        if cli_args.bom {
+            app_config.output.csv.bom = true;
+        }
+        if let Some(timezone) = cli_args.timezone {
+            app_config.output.watched_at.timezone = timezone;
+        }
+        if let Some(precision) = cli_args.watched_at_precision {
+            app_config.output.watched_at.precision = precision;
+        }
+        if let Some(time) = cli_args.watch_time {
+            app_config.output.watched_at.time = time;
+        }
 
         // Validate the configuration
         app_config.validate().map_err(|e: validator::ValidationErrors| -> Box<dyn std::error::Error> {
             Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("Configuration validation failed: {}", e)))
         })?;
+        app_config.check_provider_names()?;
 
         Ok(app_config)
     }
 
+    /// Puts relative state and cache paths under the platform directories, and moves files
+    /// that earlier versions left at those paths in the working directory
+    fn place_files(&mut self, dirs: &AppDirs) {
+        fn place(path: &mut PathBuf, dir: &std::path::Path) {
+            let placed = paths::rebase(dir, path);
+            // Whatever writes the file later can count on its directory being there
+            let moved = std::fs::create_dir_all(dir).and_then(|_| paths::migrate(path, &placed));
+            if let Err(e) = moved {
+                tracing::warn!("Could not move {} to {}: {}", path.display(), placed.display(), e);
+            }
+            *path = placed;
+        }
+
+        place(&mut self.matching.overrides_path, &dirs.config);
+        place(&mut self.session.path, &dirs.data);
+        place(&mut self.auth.tokens_path, &dirs.data);
+        place(&mut self.output.watermark, &dirs.data);
+        place(&mut self.matching.review_queue_path, &dirs.data);
+        place(&mut self.matching.decisions_path, &dirs.data);
+        place(&mut self.cache.path, &dirs.cache);
+        place(&mut self.imdb.dataset_path, &dirs.cache);
+        place(&mut self.imdb.index_path, &dirs.cache);
+        place(&mut self.tmdb_export.movies_path, &dirs.cache);
+        place(&mut self.tmdb_export.shows_path, &dirs.cache);
+        place(&mut self.tmdb_export.index_path, &dirs.cache);
+        place(&mut self.anime_ids.path, &dirs.cache);
+    }
+
+    /// Reads the provider secrets written as `keyring:<name>` from the OS keyring
+    fn resolve_secrets(&mut self) -> Result<(), crate::error::AppError> {
+        use crate::credentials::resolve_secret;
+
+        resolve_secret(&mut self.simkl.client_id)?;
+        resolve_secret(&mut self.simkl.client_secret)?;
+        resolve_secret(&mut self.tmdb.access_token)?;
+        resolve_secret(&mut self.tvdb.api_key)?;
+        resolve_secret(&mut self.mal.client_id)?;
+        resolve_secret(&mut self.mal.client_secret)?;
+        Ok(())
+    }
+
     pub fn validate(&self) -> Result<(), validator::ValidationErrors> {
         validator::Validate::validate(self)
     }
 
+    /// Whether `service` may be queried, per its `enabled` switch
+    pub fn is_enabled(&self, service: &crate::metadata::ServiceType) -> bool {
+        use crate::metadata::ServiceType;
+
+        match service {
+            ServiceType::Simkl => self.simkl.enabled,
+            ServiceType::Tmdb => self.tmdb.enabled,
+            ServiceType::Tvdb => self.tvdb.enabled,
+            ServiceType::Mal => self.mal.enabled,
+            ServiceType::Trakt => self.trakt.enabled,
+            ServiceType::Omdb => self.omdb.enabled,
+            ServiceType::Tvmaze => self.tvmaze.enabled,
+            ServiceType::Wikidata => self.wikidata.enabled,
+            ServiceType::Imdb => self.imdb.enabled,
+            ServiceType::TmdbExport => self.tmdb_export.enabled,
+            ServiceType::Custom(name) => self.custom_providers.get(name).is_some_and(|custom| custom.enabled),
+        }
+    }
+
+    /// Rejects provider chains naming a provider that is neither built in nor in `custom_providers`
+    pub fn check_provider_names(&self) -> Result<(), String> {
+        let chains = [&self.providers.movies, &self.providers.shows, &self.providers.anime];
+        for service in chains.into_iter().flatten() {
+            if let crate::metadata::ServiceType::Custom(name) = service {
+                if !self.custom_providers.contains_key(name) {
+                    return Err(format!(
+                        "unknown provider '{}' (expected simkl, tmdb, tvdb, mal, trakt, omdb, tvmaze, wikidata, imdb, tmdb_export or a name from custom_providers)",
+                        name
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn create_default_config(config_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
         let default_config = r#"{
   "simkl": {
@@ -108,22 +1221,298 @@ impl AppConfig {
     "access_token": "YOUR_TMDB_ACCESS_TOKEN"
   },
   "tvdb": {
-    "api_key": "YOUR_TVDB_API_KEY"
+    "api_key": "YOUR_TVDB_API_KEY",
+    "enabled": true
   },
   "mal": {
     "client_id": "YOUR_MAL_CLIENT_ID",
-    "client_secret": "YOUR_MAL_CLIENT_SECRET"
+    "client_secret": "YOUR_MAL_CLIENT_SECRET",
+    "redirect_port": 8765,
+    "enabled": true
   },
   "amazon": {
-    "email": "YOUR_AMAZON_EMAIL (optional for manual login)",
-    "password": "YOUR_AMAZON_PASSWORD (optional for manual login)"
+    "region": "com"
   },
   "output": {
-    "path": "./export.csv"
+    "path": "./export.csv",
+    "unmatched_report": "./unmatched.json",
+    "watermark": "./watermark.json",
+    "format": "simkl"
+  },
+  "session": {
+    "persist": true,
+    "path": "./session.bin",
+    "encryption": "keyring"
+  },
+  "cache": {
+    "enabled": true,
+    "path": "./metadata-cache.sqlite",
+    "negative_ttl_days": 30
+  },
+  "providers": {
+    "movies": ["tmdb", "simkl", "wikidata"],
+    "shows": ["simkl", "tmdb", "tvdb", "mal", "tvmaze", "wikidata"],
+    "anime": ["mal", "simkl", "tmdb"]
+  },
+  "matching": {
+    "year_tolerance": 1,
+    "overrides_path": "./overrides.toml",
+    "detect_anime": true,
+    "detect_language": true,
+    "fetch_details": false,
+    "id_conflicts": "keep_first",
+    "min_score": 0.0,
+    "review_queue_path": "./review-queue.json",
+    "decisions_path": "./decisions.json"
   }
 }"#;
 
+        if let Some(parent) = config_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
         std::fs::write(config_path, default_config)?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amazon_section_is_optional() {
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token" },
+            "tvdb": { "api_key": "key" },
+            "mal": { "client_id": "id", "client_secret": "secret" },
+            "output": { "path": "./export.csv" }
+        }"#;
+
+        let mut config: AppConfig = serde_json::from_str(json).unwrap();
+        assert!(config.amazon.email.is_empty());
+        assert!(config.amazon.password.is_empty());
+        assert_eq!(config.amazon.region, "com");
+        assert!(config.validate().is_ok());
+
+        config.amazon.email = "not an email".to_string();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_output_columns_from_config() {
+        use crate::processor::columns::ColumnSpec;
+
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token" },
+            "tvdb": { "api_key": "key" },
+            "mal": { "client_id": "id", "client_secret": "secret" },
+            "output": {
+                "path": "./export.csv",
+                "columns": ["imdb_id", { "header": "Name", "template": "{title} ({year})" }]
+            }
+        }"#;
+        let config: AppConfig = config::Config::builder()
+            .add_source(config::File::from_str(json, config::FileFormat::Json))
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(
+            config.output.columns,
+            [
+                ColumnSpec::Field("imdb_id".to_string()),
+                ColumnSpec::Template { header: "Name".to_string(), template: "{title} ({year})".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_environment_overrides_nested_values() {
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token" },
+            "tvdb": { "api_key": "key" },
+            "mal": { "client_id": "id", "client_secret": "secret" },
+            "output": { "path": "./export.csv" }
+        }"#;
+        let variables = [
+            ("PV2SIMKL_SIMKL__CLIENT_ID", "from-env"),
+            ("PV2SIMKL_TVDB__API_KEY", "0123456789"),
+            ("PV2SIMKL_OUTPUT__EPISODE_ROWS", "true"),
+            ("PV2SIMKL_OUTPUT__BACKUPS", "7"),
+            ("PV2SIMKL_PROVIDERS__MOVIES", "imdb, tmdb"),
+            ("OTHER_SIMKL__CLIENT_SECRET", "ignored"),
+        ];
+        let environment = Environment {
+            variables: variables.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect(),
+        };
+        let config: AppConfig = config::Config::builder()
+            .add_source(config::File::from_str(json, config::FileFormat::Json))
+            .add_source(environment)
+            .build()
+            .unwrap()
+            .try_deserialize()
+            .unwrap();
+        assert_eq!(config.simkl.client_id, "from-env");
+        assert_eq!(config.simkl.client_secret, "secret");
+        assert_eq!(config.tvdb.api_key, "0123456789");
+        assert!(config.output.episode_rows);
+        assert_eq!(config.output.backups, 7);
+        assert_eq!(config.providers.movies, vec![crate::metadata::ServiceType::Imdb, crate::metadata::ServiceType::Tmdb]);
+    }
+
+    #[test]
+    fn test_provider_priority_from_config() {
+        use crate::metadata::ServiceType;
+
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token" },
+            "tvdb": { "api_key": "key" },
+            "mal": { "client_id": "id", "client_secret": "secret" },
+            "output": { "path": "./export.csv" },
+            "providers": { "shows": ["tvdb", "simkl"] }
+        }"#;
+
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.providers.shows, vec![ServiceType::Tvdb, ServiceType::Simkl]);
+        assert_eq!(config.providers.movies, vec![ServiceType::Tmdb, ServiceType::Simkl, ServiceType::Wikidata]);
+        assert_eq!(" TMDB ".parse::<ServiceType>(), Ok(ServiceType::Tmdb));
+        assert!("tm db".parse::<ServiceType>().is_err());
+        assert!(config.check_provider_names().is_ok());
+    }
+
+    #[test]
+    fn test_custom_providers_must_be_declared() {
+        use crate::metadata::ServiceType;
+
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token" },
+            "tvdb": { "api_key": "key" },
+            "mal": { "client_id": "id", "client_secret": "secret" },
+            "output": { "path": "./export.csv" },
+            "providers": { "movies": ["tmdb", "Letterboxd"] }
+        }"#;
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.providers.movies[1], ServiceType::Custom("letterboxd".to_string()));
+        assert!(config.check_provider_names().unwrap_err().contains("'letterboxd'"));
+
+        let json = json.replace(
+            r#""output": {"#,
+            r#""custom_providers": { "letterboxd": { "command": "./letterboxd.py" } }, "output": {"#,
+        );
+        let config: AppConfig = serde_json::from_str(&json).unwrap();
+        assert!(config.check_provider_names().is_ok());
+        assert_eq!(config.custom_providers["letterboxd"].timeout_secs, 30);
+        assert!(config.is_enabled(&ServiceType::Custom("letterboxd".to_string())));
+    }
+
+    #[test]
+    fn test_provider_timeouts_fall_back_to_shared_ones() {
+        use std::time::Duration;
+
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token" },
+            "output": { "path": "./export.csv" },
+            "timeouts": { "read_secs": 20, "providers": { "wikidata": { "read_secs": 60 } } }
+        }"#;
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.timeouts.for_provider("tmdb"), (Duration::from_secs(10), Duration::from_secs(20)));
+        assert_eq!(config.timeouts.for_provider("wikidata"), (Duration::from_secs(10), Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_ratings_map_thumbs_to_scale() {
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token" },
+            "output": { "path": "./export.csv" },
+            "ratings": { "like": 9, "default": 5 }
+        }"#;
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.ratings.rating(Some(PrimeRating::Like)), Some(9));
+        assert_eq!(config.ratings.rating(Some(PrimeRating::Dislike)), Some(3));
+        assert_eq!(config.ratings.rating(None), Some(5));
+        assert!(config.validate().is_ok());
+
+        let config: AppConfig = serde_json::from_str(&json.replace("\"like\": 9", "\"like\": 11")).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_provider_switches() {
+        use crate::metadata::ServiceType;
+
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token", "enabled": false },
+            "mal": { "client_id": "id", "client_secret": "secret" },
+            "output": { "path": "./export.csv" },
+            "wikidata": { "enabled": false }
+        }"#;
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert!(config.is_enabled(&ServiceType::Simkl));
+        assert!(!config.is_enabled(&ServiceType::Tmdb));
+        // No section, so no key: off until configured
+        assert!(!config.is_enabled(&ServiceType::Tvdb));
+        assert!(config.is_enabled(&ServiceType::Mal));
+        assert!(config.is_enabled(&ServiceType::Trakt));
+        assert!(config.is_enabled(&ServiceType::Tvmaze));
+        assert!(!config.is_enabled(&ServiceType::Wikidata));
+        assert!(!config.is_enabled(&ServiceType::Custom("letterboxd".to_string())));
+    }
+
+    #[test]
+    fn test_enrichment_columns_are_opt_in() {
+        use crate::metadata::EnrichmentField;
+
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token" },
+            "tvdb": { "api_key": "key" },
+            "mal": { "client_id": "id", "client_secret": "secret" },
+            "output": { "path": "./export.csv" }
+        }"#;
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert!(config.output.enrichment.is_empty());
+
+        let json = json.replace(r#""path": "./export.csv""#, r#""path": "./export.csv", "enrichment": ["genres", "poster_url"]"#);
+        let config: AppConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(config.output.enrichment, vec![EnrichmentField::Genres, EnrichmentField::PosterUrl]);
+    }
+
+    #[test]
+    fn test_watched_at_carries_the_configured_offset() {
+        let json = r#"{
+            "simkl": { "client_id": "id", "client_secret": "secret" },
+            "tmdb": { "access_token": "token" },
+            "output": { "path": "./export.csv", "watched_at": { "timezone": "-05:00" } }
+        }"#;
+        let config: AppConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.output.watched_at.timestamp("2024-03-01"), "2024-03-01T00:00:00-05:00");
+        assert_eq!(config.output.watched_at.timestamp("March 1"), "March 1T00:00:00Z");
+
+        let utc = WatchedAtConfig { timezone: "UTC".parse().unwrap(), ..Default::default() };
+        assert_eq!(utc.timestamp("2024-03-01"), "2024-03-01T00:00:00Z");
+        assert_eq!("system".parse::<Timezone>(), Ok(Timezone::System));
+        assert!("Europe/Rome".parse::<Timezone>().unwrap_err().contains("+02:00"));
+    }
+
+    #[test]
+    fn test_watched_at_precision_and_time_of_day() {
+        let timezone = Timezone::Utc;
+        let evening = WatchedAtConfig { timezone, time: "20:30".parse().unwrap(), ..Default::default() };
+        assert_eq!(evening.timestamp("2024-03-01"), "2024-03-01T20:30:00Z");
+        let day = WatchedAtConfig { timezone, precision: WatchPrecision::Date, ..evening };
+        assert_eq!(day.timestamp("2024-03-01"), "2024-03-01");
+
+        let config: WatchedAtConfig = serde_json::from_str(r#"{ "precision": "date", "time": "21:15:30" }"#).unwrap();
+        assert_eq!(config.precision, WatchPrecision::Date);
+        assert_eq!(String::from(config.time), "21:15:30");
+        assert!(serde_json::from_str::<WatchedAtConfig>(r#"{ "time": "9pm" }"#).is_err());
+    }
+}
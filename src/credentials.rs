@@ -0,0 +1,177 @@
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+
+use crate::config::AmazonConfig;
+use crate::error::AppError;
+
+/// Service name under which all secrets are stored in the OS keyring
+pub const KEYRING_SERVICE: &str = "primevideo-to-simkl-exporter";
+
+const AMAZON_EMAIL_ENTRY: &str = "amazon.email";
+const AMAZON_PASSWORD_ENTRY: &str = "amazon.password";
+
+/// A config value of `keyring:<name>` is read from the keyring entry `<name>`
+pub const KEYRING_REFERENCE_PREFIX: &str = "keyring:";
+
+/// Where secrets referenced by the config are read from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialSource {
+    /// Plaintext values in config.json
+    #[default]
+    Config,
+    /// Entries stored in the OS keyring (Keychain, Credential Manager, kernel keyring)
+    Keyring,
+}
+
+fn entry(name: &str) -> Result<Entry, AppError> {
+    Ok(Entry::new(KEYRING_SERVICE, name)?)
+}
+
+/// Reads a secret from the keyring; None when there is no such entry
+pub fn find_secret(name: &str) -> Result<Option<String>, AppError> {
+    match entry(name)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Reads a secret from the keyring, mapping a missing entry to a helpful error
+pub fn get_secret(name: &str) -> Result<String, AppError> {
+    match find_secret(name)? {
+        Some(secret) => Ok(secret),
+        None => {
+            let command = match name {
+                AMAZON_EMAIL_ENTRY | AMAZON_PASSWORD_ENTRY => "credentials set-amazon".to_string(),
+                _ => format!("credentials set {}", name),
+            };
+            Err(AppError::AuthError(format!("No keyring entry '{}' found. Run `{}` to store it.", name, command)))
+        }
+    }
+}
+
+pub fn set_secret(name: &str, secret: &str) -> Result<(), AppError> {
+    entry(name)?.set_password(secret)?;
+    Ok(())
+}
+
+/// Deletes a secret, treating an already-missing entry as success
+pub fn delete_secret(name: &str) -> Result<(), AppError> {
+    match entry(name)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The keyring entry a config value refers to, e.g. `simkl.client_secret` for
+/// `keyring:simkl.client_secret`; None for a plaintext value
+pub fn keyring_reference(value: &str) -> Option<&str> {
+    value.strip_prefix(KEYRING_REFERENCE_PREFIX).map(str::trim)
+}
+
+/// Replaces a `keyring:<name>` value with the secret stored under `<name>`; plaintext
+/// values are left as they are
+pub fn resolve_secret(value: &mut String) -> Result<(), AppError> {
+    match keyring_reference(value) {
+        Some("") => Err(AppError::AuthError(format!(
+            "'{}' names no keyring entry; write it as {}<name>",
+            value, KEYRING_REFERENCE_PREFIX
+        ))),
+        Some(name) => {
+            *value = get_secret(name)?;
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Interactively prompts for a secret and stores it under `name`, for config values
+/// written as `keyring:<name>`
+pub fn prompt_and_store_secret(name: &str) -> Result<(), AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::AuthError("Keyring entry name cannot be empty".into()));
+    }
+    let secret = rpassword::prompt_password(format!("Value for '{}': ", name))?;
+    if secret.is_empty() {
+        return Err(AppError::AuthError("Secret cannot be empty".into()));
+    }
+
+    set_secret(name, &secret)?;
+    println!("✅ '{}' stored in the OS keyring", name);
+    println!("   Use \"{}{}\" as the value in config.json to read it from there.", KEYRING_REFERENCE_PREFIX, name);
+    Ok(())
+}
+
+pub fn store_amazon_credentials(email: &str, password: &str) -> Result<(), AppError> {
+    set_secret(AMAZON_EMAIL_ENTRY, email)?;
+    set_secret(AMAZON_PASSWORD_ENTRY, password)
+}
+
+pub fn clear_amazon_credentials() -> Result<(), AppError> {
+    delete_secret(AMAZON_EMAIL_ENTRY)?;
+    delete_secret(AMAZON_PASSWORD_ENTRY)
+}
+
+/// Resolves the Amazon email and password from the configured credential source
+pub fn resolve_amazon_credentials(config: &AmazonConfig) -> Result<(String, String), AppError> {
+    match config.credential_source {
+        CredentialSource::Config => {
+            if config.email.is_empty() || config.password.is_empty() {
+                return Err(AppError::AuthError(
+                    "Amazon email and password must be set in config for automated login".into(),
+                ));
+            }
+            Ok((config.email.clone(), config.password.clone()))
+        }
+        CredentialSource::Keyring => {
+            // An email in config takes precedence so only the password needs to be stored
+            let email = if config.email.is_empty() {
+                get_secret(AMAZON_EMAIL_ENTRY)?
+            } else {
+                config.email.clone()
+            };
+            let password = get_secret(AMAZON_PASSWORD_ENTRY)?;
+            Ok((email, password))
+        }
+    }
+}
+
+/// Interactively prompts for the Amazon credentials and stores them in the keyring
+pub fn prompt_and_store_amazon_credentials() -> Result<(), AppError> {
+    print!("Amazon email: ");
+    std::io::Write::flush(&mut std::io::stdout())?;
+    let mut email = String::new();
+    std::io::stdin().read_line(&mut email)?;
+    let email = email.trim();
+
+    let password = rpassword::prompt_password("Amazon password: ")?;
+    if email.is_empty() || password.is_empty() {
+        return Err(AppError::AuthError("Email and password cannot be empty".into()));
+    }
+
+    store_amazon_credentials(email, &password)?;
+    println!("✅ Amazon credentials stored in the OS keyring");
+    println!("   Set \"credential_source\": \"keyring\" in the amazon section of config.json to use them.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyring_references_in_config_values() {
+        assert_eq!(keyring_reference("keyring:simkl.client_secret"), Some("simkl.client_secret"));
+        assert_eq!(keyring_reference("keyring: tmdb.access_token "), Some("tmdb.access_token"));
+        assert_eq!(keyring_reference("0123456789abcdef"), None);
+
+        // Plaintext values never touch the keyring
+        let mut plain = "0123456789abcdef".to_string();
+        resolve_secret(&mut plain).unwrap();
+        assert_eq!(plain, "0123456789abcdef");
+
+        // A reference without a name is a mistake, not a secret
+        assert!(resolve_secret(&mut "keyring: ".to_string()).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use rand::RngCore;
+
+use crate::credentials;
+use crate::error::AppError;
+
+const MAGIC: &[u8; 4] = b"PVS1";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+const HEADER_LEN: usize = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+
+/// Keyring entry holding the random key used when no passphrase is configured
+const OS_KEY_ENTRY: &str = "session.key";
+
+/// How the encryption key for sealed files is obtained
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    /// Derived from a user passphrase with Argon2id
+    Passphrase(String),
+    /// Random key generated once and kept in the OS keyring
+    OsKeyring,
+}
+
+impl KeySource {
+    fn tag(&self) -> u8 {
+        match self {
+            KeySource::Passphrase(_) => 1,
+            KeySource::OsKeyring => 2,
+        }
+    }
+
+    fn derive_key(&self, salt: &[u8]) -> Result<[u8; KEY_LEN], AppError> {
+        let mut key = [0u8; KEY_LEN];
+        match self {
+            KeySource::Passphrase(passphrase) => {
+                Argon2::default()
+                    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+                    .map_err(|e| AppError::CryptoError(format!("Key derivation failed: {}", e)))?;
+            }
+            KeySource::OsKeyring => {
+                // Only a missing entry gets a fresh key; replacing the key on any other
+                // keyring error would leave every sealed session unreadable
+                let encoded = match credentials::find_secret(OS_KEY_ENTRY)? {
+                    Some(encoded) => encoded,
+                    None => {
+                        let mut fresh = [0u8; KEY_LEN];
+                        rand::thread_rng().fill_bytes(&mut fresh);
+                        let encoded = STANDARD.encode(fresh);
+                        credentials::set_secret(OS_KEY_ENTRY, &encoded)?;
+                        encoded
+                    }
+                };
+                let decoded = STANDARD
+                    .decode(encoded)
+                    .map_err(|e| AppError::CryptoError(format!("Corrupt keyring key: {}", e)))?;
+                if decoded.len() != KEY_LEN {
+                    return Err(AppError::CryptoError("Corrupt keyring key: wrong length".into()));
+                }
+                key.copy_from_slice(&decoded);
+            }
+        }
+        Ok(key)
+    }
+}
+
+/// Encrypts `plaintext` into a self-describing blob (magic, key source, salt, nonce, ciphertext)
+pub fn seal(plaintext: &[u8], key_source: &KeySource) -> Result<Vec<u8>, AppError> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let key = key_source.derive_key(&salt)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), plaintext)
+        .map_err(|_| AppError::CryptoError("Encryption failed".into()))?;
+
+    let mut sealed = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.push(key_source.tag());
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypts a blob produced by [`seal`], failing if it was sealed with a different key source
+pub fn unseal(sealed: &[u8], key_source: &KeySource) -> Result<Vec<u8>, AppError> {
+    if sealed.len() < HEADER_LEN || &sealed[..MAGIC.len()] != MAGIC {
+        return Err(AppError::CryptoError("Not a sealed file".into()));
+    }
+    if sealed[MAGIC.len()] != key_source.tag() {
+        return Err(AppError::CryptoError(
+            "File was sealed with a different key source".into(),
+        ));
+    }
+
+    let salt = &sealed[MAGIC.len() + 1..MAGIC.len() + 1 + SALT_LEN];
+    let nonce = &sealed[MAGIC.len() + 1 + SALT_LEN..HEADER_LEN];
+    let key = key_source.derive_key(salt)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(&key));
+    cipher
+        .decrypt(XNonce::from_slice(nonce), &sealed[HEADER_LEN..])
+        .map_err(|_| AppError::CryptoError("Decryption failed (wrong passphrase or corrupt file)".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passphrase_roundtrip() {
+        let key_source = KeySource::Passphrase("correct horse".to_string());
+        let sealed = seal(b"session cookies", &key_source).unwrap();
+
+        assert_ne!(&sealed[HEADER_LEN..], b"session cookies");
+        assert_eq!(unseal(&sealed, &key_source).unwrap(), b"session cookies");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_fails() {
+        let sealed = seal(b"secret", &KeySource::Passphrase("one".to_string())).unwrap();
+        let result = unseal(&sealed, &KeySource::Passphrase("two".to_string()));
+
+        assert!(matches!(result, Err(AppError::CryptoError(_))));
+    }
+
+    #[test]
+    fn test_rejects_unsealed_data() {
+        let result = unseal(b"{\"plain\": true}", &KeySource::Passphrase("x".to_string()));
+        assert!(result.is_err());
+    }
+}
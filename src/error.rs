@@ -1,6 +1,7 @@
 use thiserror::Error;
 
 #[derive(Debug, Error)]
+#[allow(clippy::enum_variant_names)]
 pub enum AppError {
     #[error("Configuration error: {0}")]
     ConfigError(#[from] config::ConfigError),
@@ -11,12 +12,25 @@ pub enum AppError {
     #[error("CSV error: {0}")]
     CsvError(#[from] csv::Error),
 
+    #[error("Spreadsheet error: {0}")]
+    XlsxError(#[from] rust_xlsxwriter::XlsxError),
+
+    #[cfg(feature = "parquet")]
+    #[error("Parquet error: {0}")]
+    ParquetError(#[from] parquet::errors::ParquetError),
+
     #[error("HTTP request error: {0}")]
     RequestError(#[from] reqwest::Error),
 
     #[error("JSON parsing error: {0}")]
     JsonError(#[from] serde_json::Error),
 
+    #[error("Cache database error: {0}")]
+    CacheError(#[from] rusqlite::Error),
+
+    #[error("Keyring error: {0}")]
+    KeyringError(#[from] keyring::Error),
+
     #[error("Semaphore acquisition error: {0}")]
     SemaphoreError(#[from] tokio::sync::AcquireError),
 
@@ -26,9 +40,15 @@ pub enum AppError {
     #[error("Authentication failed: {0}")]
     AuthError(String),
 
+    #[error("Unexpected {provider} response: {detail}")]
+    ResponseError { provider: String, detail: String },
+
     #[error("Metadata lookup failed: {0}")]
     MetadataError(String),
 
+    #[error("Encryption error: {0}")]
+    CryptoError(String),
+
     #[error("Parsing error: {0}")]
     ParseError(String),
 }
@@ -39,6 +59,13 @@ impl From<std::num::ParseIntError> for AppError {
     }
 }
 
+#[cfg(feature = "parquet")]
+impl From<arrow::error::ArrowError> for AppError {
+    fn from(err: arrow::error::ArrowError) -> Self {
+        AppError::ParquetError(err.into())
+    }
+}
+
 impl From<tokio::task::JoinError> for AppError {
     fn from(err: tokio::task::JoinError) -> Self {
         AppError::MetadataError(format!("Task join error: {}", err))
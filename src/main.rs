@@ -1,16 +1,26 @@
+// Provider clients share state through locks (see `auth::TokenStore`), never through raw pointers
+#![forbid(unsafe_code)]
+
 use std::error::Error;
 
 mod app;
+mod auth;
 mod cli;
+mod commands;
 mod config;
+mod credentials;
+mod crypto;
 mod error;
+mod matching;
 mod metadata;
 mod models;
+mod paths;
 mod scraping;
 mod processor;
 mod shutdown;
+mod sync;
 
-use app::App;
+use app::{App, RunOptions};
 use cli::CliArgs;
 
 #[tokio::main]
@@ -35,6 +45,11 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .with_max_level(log_level)
         .init();
 
+    // Subcommands run standalone and skip the export pipeline
+    if let Some(command) = &cli_args.command {
+        return commands::run(command, &cli_args).await;
+    }
+
     tracing::info!("Starting Prime Video to Simkl exporter");
 
     // Setup shutdown handling
@@ -44,7 +59,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let config = config::AppConfig::load_with_cli_args(&cli_args)?;
 
     // Create the application
-    let mut app = App::new_with_config(config)?;
+    let mut app = App::new_with_config(config, RunOptions::from_cli(&cli_args))?;
 
     // Run the application with shutdown handling
     tokio::select! {
@@ -53,7 +68,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 Ok(()) => tracing::info!("Application completed successfully"),
                 Err(e) => {
                     tracing::error!("Application error: {}", e);
-                    return Err(e.into());
+                    return Err(e);
                 }
             }
         }
@@ -0,0 +1,69 @@
+use regex::Regex;
+
+use crate::error::AppError;
+
+/// Title shapes that are almost always anime on Prime Video
+const BUILTIN_PATTERNS: &[&str] = &[
+    // Japanese script anywhere in the title
+    r"[\p{Hiragana}\p{Katakana}]",
+    // Romanized Japanese: "Shingeki no Kyojin", "Boku no Hero Academia"
+    r"(?i)\b\w+ no (?:kyojin|hero|yaiba|kanojo|tenshi|kuni|sekai|tame|mori|hana|uta)\b",
+    r"(?i)\b(?:shippuden|shippuuden|gekijouban|gekijoban|isekai|shounen|shonen|shoujo|shojo|seinen)\b",
+    // Honorifics and Prime's own anime labels
+    r"(?i)\w+-(?:san|kun|chan|sama|senpai|sensei)\b",
+    r"(?i)[\[(](?:anime|ova|ona|simulcast)[\])]",
+    r"(?i)\b(?:ova|ona)\s*\d*$",
+];
+
+/// Guesses from the title alone whether an item is anime, so it can be routed to anime providers
+pub struct AnimeDetector {
+    patterns: Vec<Regex>,
+}
+
+impl AnimeDetector {
+    pub fn new(extra_patterns: &[String]) -> Result<Self, AppError> {
+        let patterns = BUILTIN_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .chain(extra_patterns.iter().cloned())
+            .map(|pattern| {
+                Regex::new(&pattern).map_err(|e| {
+                    AppError::ConfigError(config::ConfigError::Message(format!(
+                        "Invalid anime title pattern '{}': {}",
+                        pattern, e
+                    )))
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { patterns })
+    }
+
+    pub fn is_anime_title(&self, title: &str) -> bool {
+        self.patterns.iter().any(|pattern| pattern.is_match(title))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_anime_titles() {
+        let detector = AnimeDetector::new(&[]).unwrap();
+        assert!(detector.is_anime_title("Shingeki no Kyojin"));
+        assert!(detector.is_anime_title("Naruto Shippuden"));
+        assert!(detector.is_anime_title("Kaguya-sama: Love is War"));
+        assert!(detector.is_anime_title("進撃の巨人"));
+        assert!(detector.is_anime_title("Vinland Saga (Simulcast)"));
+        assert!(!detector.is_anime_title("The Boys"));
+        assert!(!detector.is_anime_title("Casino Royale"));
+        assert!(!detector.is_anime_title("Night of the Living Dead"));
+    }
+
+    #[test]
+    fn test_extra_patterns() {
+        let detector = AnimeDetector::new(&["(?i)^frieren".to_string()]).unwrap();
+        assert!(detector.is_anime_title("Frieren: Beyond Journey's End"));
+        assert!(AnimeDetector::new(&["(".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::error::AppError;
+use crate::metadata::{MediaIds, MetadataResult};
+use crate::models::MediaType;
+
+/// Most candidates listed in one prompt
+const MAX_CHOICES: usize = 5;
+
+/// What the user typed at the prompt
+#[derive(Debug, PartialEq)]
+enum Choice {
+    Pick(usize),
+    NoneOfThese,
+}
+
+/// Parses a 1-based selection; Enter takes the top candidate and `0`/`s` rejects them all
+fn parse_choice(input: &str, count: usize) -> Option<Choice> {
+    match input.trim().to_lowercase().as_str() {
+        "" => Some(Choice::Pick(0)),
+        "0" | "s" | "skip" => Some(Choice::NoneOfThese),
+        other => match other.parse::<usize>() {
+            Ok(n) if (1..=count).contains(&n) => Some(Choice::Pick(n - 1)),
+            _ => None,
+        },
+    }
+}
+
+pub(super) fn describe_ids(ids: &MediaIds) -> String {
+    [
+        ("simkl", &ids.simkl),
+        ("tmdb", &ids.tmdb),
+        ("tvdb", &ids.tvdb),
+        ("imdb", &ids.imdb),
+        ("mal", &ids.mal),
+        ("anilist", &ids.anilist),
+        ("anidb", &ids.anidb),
+    ]
+        .iter()
+        .filter_map(|(name, id)| id.as_ref().map(|id| format!("{}:{}", name, id)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// How an ambiguous match was settled
+#[derive(Debug, Clone)]
+pub enum Decision {
+    Pick(Box<MetadataResult>),
+    NoneOfThese,
+}
+
+/// One answer as saved in the decisions file
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedDecision {
+    provider: String,
+    /// Title as scraped from Prime Video
+    title: String,
+    media_type: MediaType,
+    /// The picked candidate; absent when the user rejected them all
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    choice: Option<MetadataResult>,
+}
+
+type Key = (String, String, MediaType);
+
+fn key(provider: &str, title: &str, media_type: MediaType) -> Key {
+    (provider.to_string(), title.trim().to_lowercase(), media_type)
+}
+
+/// Settles ambiguous matches with the user's answers. Answers are saved to a decisions file
+/// when one is given, so later runs reuse them without asking again, even non-interactive ones.
+#[derive(Default)]
+pub struct Disambiguator {
+    /// Answers keyed by provider, title and media type
+    choices: Mutex<HashMap<Key, SavedDecision>>,
+    path: Option<PathBuf>,
+    /// Prompt for titles without a saved answer, instead of leaving them to the scores
+    interactive: bool,
+}
+
+impl Disambiguator {
+    /// Reuses the answers saved at `path` and, when `interactive`, prompts for new ones and
+    /// saves them there too; a missing file simply means no saved answers
+    pub fn load(path: &Path, interactive: bool) -> Result<Self, AppError> {
+        let saved: Vec<SavedDecision> = if path.exists() {
+            serde_json::from_reader(std::fs::File::open(path)?).map_err(|e| {
+                AppError::ConfigError(config::ConfigError::Message(format!(
+                    "Invalid decisions file {}: {}",
+                    path.display(),
+                    e
+                )))
+            })?
+        } else {
+            Vec::new()
+        };
+        let choices = saved
+            .into_iter()
+            .map(|decision| (key(&decision.provider, &decision.title, decision.media_type), decision))
+            .collect();
+        Ok(Self { choices: Mutex::new(choices), path: Some(path.to_path_buf()), interactive })
+    }
+
+    /// Number of saved answers
+    pub fn len(&self) -> usize {
+        self.choices.try_lock().map_or(0, |choices| choices.len())
+    }
+
+    fn save(&self, choices: &HashMap<Key, SavedDecision>) -> Result<(), AppError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut saved: Vec<&SavedDecision> = choices.values().collect();
+        saved.sort_by(|a, b| (&a.title, &a.provider).cmp(&(&b.title, &b.provider)));
+        std::fs::write(path, serde_json::to_string_pretty(&saved)?)?;
+        Ok(())
+    }
+
+    /// Returns how the user settled `ranked`, either from a saved answer or by prompting,
+    /// or None when there is no saved answer and this run may not prompt.
+    /// Prompts are serialized so concurrent lookups never interleave on the terminal.
+    pub async fn choose(
+        &self,
+        provider: &str,
+        title: &str,
+        year: Option<i32>,
+        media_type: MediaType,
+        ranked: &[MetadataResult],
+    ) -> Result<Option<Decision>, AppError> {
+        let key = key(provider, title, media_type);
+        let mut choices = self.choices.lock().await;
+        if let Some(saved) = choices.get(&key) {
+            return Ok(Some(match &saved.choice {
+                Some(choice) => Decision::Pick(Box::new(choice.clone())),
+                None => Decision::NoneOfThese,
+            }));
+        }
+        if !self.interactive {
+            return Ok(None);
+        }
+
+        let shown = &ranked[..ranked.len().min(MAX_CHOICES)];
+        println!();
+        match year {
+            Some(year) => println!("🔎 Several {} matches for '{}' ({}):", provider, title, year),
+            None => println!("🔎 Several {} matches for '{}':", provider, title),
+        }
+        for (i, candidate) in shown.iter().enumerate() {
+            println!(
+                "  {}. {} ({}) [{}] score {:.2}",
+                i + 1,
+                candidate.title,
+                candidate.year.as_deref().unwrap_or("?"),
+                describe_ids(&candidate.ids),
+                candidate.score.unwrap_or_default()
+            );
+        }
+        println!("  0. None of these");
+
+        let choice = loop {
+            print!("Pick 1-{} (Enter = 1): ", shown.len());
+            std::io::stdout().flush()?;
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+            match parse_choice(&input, shown.len()) {
+                Some(choice) => break choice,
+                None => println!("Please enter a number between 0 and {}.", shown.len()),
+            }
+        };
+
+        let picked = match choice {
+            Choice::Pick(index) => Some(shown[index].clone()),
+            Choice::NoneOfThese => None,
+        };
+        let decision = match &picked {
+            Some(candidate) => Decision::Pick(Box::new(candidate.clone())),
+            None => Decision::NoneOfThese,
+        };
+        let saved = SavedDecision { provider: provider.to_string(), title: title.trim().to_string(), media_type, choice: picked };
+        choices.insert(key, saved);
+        if let Err(e) = self.save(&choices) {
+            tracing::warn!("Could not save the answer for '{}': {}", title, e);
+        }
+        Ok(Some(decision))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("\n", 3), Some(Choice::Pick(0)));
+        assert_eq!(parse_choice(" 2 \n", 3), Some(Choice::Pick(1)));
+        assert_eq!(parse_choice("0", 3), Some(Choice::NoneOfThese));
+        assert_eq!(parse_choice("S", 3), Some(Choice::NoneOfThese));
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("two", 3), None);
+    }
+
+    #[test]
+    fn test_describe_ids_skips_missing() {
+        let ids = MediaIds {
+            simkl: Some("1".to_string()),
+            tmdb: None,
+            tvdb: Some("3".to_string()),
+            imdb: None,
+            mal: None,
+            anilist: None,
+            anidb: Some("7".to_string()),
+        };
+        assert_eq!(describe_ids(&ids), "simkl:1 tvdb:3 anidb:7");
+    }
+
+    #[tokio::test]
+    async fn test_saved_decisions_answer_later_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("decisions.json");
+        let dune = MetadataResult {
+            ids: MediaIds { tmdb: Some("438631".to_string()), ..Default::default() },
+            title: "Dune".to_string(),
+            year: Some("2021".to_string()),
+            media_type: MediaType::Movie,
+            score: Some(0.9),
+            ..Default::default()
+        };
+        let first_run = Disambiguator::load(&path, false).unwrap();
+        let saved = [
+            ("Dune", Some(dune.clone())),
+            ("The Office", None),
+        ];
+        let choices: HashMap<Key, SavedDecision> = saved
+            .into_iter()
+            .map(|(title, choice)| {
+                let decision = SavedDecision { provider: "TMDB".to_string(), title: title.to_string(), media_type: MediaType::Movie, choice };
+                (key("TMDB", title, MediaType::Movie), decision)
+            })
+            .collect();
+        first_run.save(&choices).unwrap();
+
+        let next_run = Disambiguator::load(&path, false).unwrap();
+        assert_eq!(next_run.len(), 2);
+        let ranked = [dune.clone()];
+        let picked = next_run.choose("TMDB", " dune ", None, MediaType::Movie, &ranked).await.unwrap();
+        assert!(matches!(picked, Some(Decision::Pick(choice)) if choice.ids.tmdb.as_deref() == Some("438631")));
+        let rejected = next_run.choose("TMDB", "The Office", None, MediaType::Movie, &ranked).await.unwrap();
+        assert!(matches!(rejected, Some(Decision::NoneOfThese)));
+        // Never asked before, and this run may not prompt
+        assert!(next_run.choose("TMDB", "Dune", None, MediaType::Tv, &ranked).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,79 @@
+/// Writing systems that point at one search language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Kana,
+    Han,
+    Hangul,
+    Cyrillic,
+    Greek,
+    Arabic,
+    Hebrew,
+    Thai,
+    Devanagari,
+}
+
+fn script_of(c: char) -> Option<Script> {
+    Some(match c {
+        '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => Script::Kana,
+        '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' => Script::Han,
+        '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' => Script::Hangul,
+        '\u{0400}'..='\u{04FF}' => Script::Cyrillic,
+        '\u{0370}'..='\u{03FF}' => Script::Greek,
+        '\u{0600}'..='\u{06FF}' => Script::Arabic,
+        '\u{0590}'..='\u{05FF}' => Script::Hebrew,
+        '\u{0E00}'..='\u{0E7F}' => Script::Thai,
+        '\u{0900}'..='\u{097F}' => Script::Devanagari,
+        _ => return None,
+    })
+}
+
+/// Guesses the ISO 639-1 language of `title` from its script, so providers can be searched
+/// in it; None for Latin-script titles, which keep the configured language.
+///
+/// Kana marks Japanese even among kanji, and Ukrainian-only letters mark Ukrainian over
+/// Russian. Otherwise the most frequent non-Latin script decides, so "進撃の巨人 Season 2"
+/// still counts as Japanese.
+pub fn detect_language(title: &str) -> Option<&'static str> {
+    let mut counts: Vec<(Script, usize)> = Vec::new();
+    for script in title.chars().filter_map(script_of) {
+        match counts.iter_mut().find(|(s, _)| *s == script) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((script, 1)),
+        }
+    }
+    let has = |script: Script| counts.iter().any(|(s, _)| *s == script);
+    if has(Script::Kana) {
+        return Some("ja");
+    }
+    let (script, _) = counts.iter().copied().max_by_key(|(_, count)| *count)?;
+    Some(match script {
+        Script::Kana => "ja",
+        Script::Han => "zh",
+        Script::Hangul => "ko",
+        Script::Cyrillic if title.chars().any(|c| matches!(c, 'ґ' | 'є' | 'і' | 'ї' | 'Ґ' | 'Є' | 'І' | 'Ї')) => "uk",
+        Script::Cyrillic => "ru",
+        Script::Greek => "el",
+        Script::Arabic => "ar",
+        Script::Hebrew => "he",
+        Script::Thai => "th",
+        Script::Devanagari => "hi",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_follows_script() {
+        assert_eq!(detect_language("The Boys"), None);
+        assert_eq!(detect_language("Amélie"), None);
+        assert_eq!(detect_language("進撃の巨人 Season 2"), Some("ja"));
+        assert_eq!(detect_language("鬼滅の刃"), Some("ja"));
+        assert_eq!(detect_language("霸王别姬"), Some("zh"));
+        assert_eq!(detect_language("오징어 게임"), Some("ko"));
+        assert_eq!(detect_language("Мастер и Маргарита"), Some("ru"));
+        assert_eq!(detect_language("Кіборги"), Some("uk"));
+        assert_eq!(detect_language("Η Εύα"), Some("el"));
+    }
+}
@@ -0,0 +1,357 @@
+mod anime;
+mod disambiguate;
+mod language;
+mod normalize;
+mod overrides;
+mod review;
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+pub use anime::AnimeDetector;
+pub use disambiguate::{Decision, Disambiguator};
+pub use language::detect_language;
+pub use normalize::{NormalizationConfig, TitleNormalizer};
+pub use overrides::Overrides;
+pub use review::ReviewQueue;
+
+use crate::metadata::{IdConflictPolicy, MetadataResult};
+use crate::models::MediaType;
+
+/// Score at which a provider's best candidate is accepted without asking later providers
+pub const CONFIDENT_SCORE: f64 = 0.85;
+
+/// Candidates whose scores are this close to the best one are treated as equally likely
+const AMBIGUITY_MARGIN: f64 = 0.05;
+
+/// Vote count at which a candidate gets full popularity credit; fewer votes earn a log-scaled share
+const POPULAR_VOTES: f64 = 10_000.0;
+
+/// Lowercases and reduces a title to space-separated alphanumeric words
+pub fn simplify(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn edit_similarity(a: &str, b: &str) -> f64 {
+    strsim::normalized_levenshtein(a, b)
+}
+
+/// Compares the shared words plus each side's leftovers, so extra or reordered words
+/// ("The Office (US)" vs "Office, The") cost little
+fn token_set_similarity(a: &str, b: &str) -> f64 {
+    let a_tokens: BTreeSet<&str> = a.split_whitespace().collect();
+    let b_tokens: BTreeSet<&str> = b.split_whitespace().collect();
+    if a_tokens.is_empty() || b_tokens.is_empty() {
+        return 0.0;
+    }
+
+    let join = |tokens: Vec<&str>| tokens.join(" ");
+    let common = join(a_tokens.intersection(&b_tokens).copied().collect());
+    let only_a = join(a_tokens.difference(&b_tokens).copied().collect());
+    let only_b = join(b_tokens.difference(&a_tokens).copied().collect());
+
+    let with = |rest: &str| format!("{} {}", common, rest).trim().to_string();
+    let (full_a, full_b) = (with(&only_a), with(&only_b));
+
+    let mut best = edit_similarity(&full_a, &full_b);
+    if !common.is_empty() {
+        best = best
+            .max(edit_similarity(&common, &full_a))
+            .max(edit_similarity(&common, &full_b));
+    }
+    best
+}
+
+/// Similarity of two titles in 0.0..=1.0
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (simplify(a), simplify(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    edit_similarity(&a, &b).max(token_set_similarity(&a, &b))
+}
+
+/// Extracts the year from values like "2021" or "2021-10-22"
+pub fn parse_year(value: &str) -> Option<i32> {
+    value.get(..4).and_then(|y| y.parse().ok())
+}
+
+/// The year Prime Video sometimes appends to a title, as in "Dune (2021)" or "Dune [2021]"
+pub fn year_in_title(title: &str) -> Option<String> {
+    let (_, rest) = title.trim_end().rsplit_once(['(', '['])?;
+    let year = rest.strip_suffix([')', ']'])?;
+    let plausible = year.len() == 4 && year.starts_with(['1', '2']) && year.bytes().all(|b| b.is_ascii_digit());
+    plausible.then(|| year.to_string())
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MatchingConfig {
+    /// Years a candidate may be off by and still earn partial year credit
+    pub year_tolerance: u32,
+    /// TOML file mapping Prime Video titles to fixed IDs, consulted before any provider
+    pub overrides_path: PathBuf,
+    /// Route titles that look like anime to the anime provider chain and add MAL IDs to anime matches
+    pub detect_anime: bool,
+    /// Extra regexes marking a title as anime, on top of the built-in heuristics
+    pub anime_title_patterns: Vec<String>,
+    /// Search titles written in a non-Latin script (Japanese, Korean, Cyrillic, ...) in the
+    /// language that script suggests instead of the configured one
+    pub detect_language: bool,
+    /// Fetch each match's details from the providers it has IDs for, to pick up external
+    /// IDs that search results leave out; costs one request per provider
+    pub fetch_details: bool,
+    /// How to settle providers disagreeing on an ID for the same title
+    pub id_conflicts: IdConflictPolicy,
+    /// Matches scoring below this are held back from the export for review; 0 exports everything
+    pub min_score: f64,
+    /// Where held-back matches wait for `review`
+    pub review_queue_path: PathBuf,
+    /// Answers given to `--interactive` prompts, reused on later runs
+    pub decisions_path: PathBuf,
+    /// How much each signal counts towards a candidate's score
+    pub weights: RankingWeights,
+}
+
+/// Relative weights of the signals a candidate is scored on. The score is their weighted
+/// average, leaving out signals that are unknown for the candidate (a missing year, or a
+/// provider that reports no votes), so an exact match still scores 1.0.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RankingWeights {
+    /// Similarity of the scraped title to the candidate's localized or original title
+    pub title: f64,
+    /// Agreement of the release years, within `year_tolerance`
+    pub year: f64,
+    /// Whether the candidate is the movie or show that was searched for
+    pub media_type: f64,
+    /// How many users rated or listed the candidate on the provider
+    pub popularity: f64,
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            title: 0.85,
+            year: 0.15,
+            media_type: 0.15,
+            popularity: 0.05,
+        }
+    }
+}
+
+impl Default for MatchingConfig {
+    fn default() -> Self {
+        Self {
+            year_tolerance: 1,
+            overrides_path: PathBuf::from("./overrides.toml"),
+            detect_anime: true,
+            anime_title_patterns: Vec::new(),
+            detect_language: true,
+            fetch_details: false,
+            id_conflicts: IdConflictPolicy::default(),
+            min_score: 0.0,
+            review_queue_path: PathBuf::from("./review-queue.json"),
+            decisions_path: PathBuf::from("./decisions.json"),
+            weights: RankingWeights::default(),
+        }
+    }
+}
+
+/// Scores provider results against scraped titles
+#[derive(Debug, Clone, Default)]
+pub struct Matcher {
+    config: MatchingConfig,
+}
+
+impl Matcher {
+    pub fn from_config(config: &MatchingConfig) -> Self {
+        Self { config: config.clone() }
+    }
+
+    pub fn id_conflict_policy(&self) -> IdConflictPolicy {
+        self.config.id_conflicts
+    }
+
+    /// Whether year filtering at the provider may hide a candidate this matcher would accept
+    pub fn tolerates_year_drift(&self) -> bool {
+        self.config.year_tolerance > 0
+    }
+
+    /// Agreement between two years, or None when either is unknown. Credit falls off
+    /// linearly and reaches zero just past the tolerance.
+    fn year_similarity(&self, query: Option<i32>, candidate: Option<i32>) -> Option<f64> {
+        let (query, candidate) = (query?, candidate?);
+        let diff = query.abs_diff(candidate);
+        let tolerance = self.config.year_tolerance;
+        Some(if diff > tolerance {
+            0.0
+        } else {
+            1.0 - diff as f64 / (tolerance + 1) as f64
+        })
+    }
+
+    /// Log-scaled share of full popularity credit, or None when the provider reports no votes
+    fn popularity(votes: Option<u64>) -> Option<f64> {
+        votes.map(|votes| ((votes as f64).ln_1p() / POPULAR_VOTES.ln_1p()).min(1.0))
+    }
+
+    /// Scores a provider result against the scraped title, year and media type: the weighted
+    /// average of title similarity (crediting whichever of its localized and original titles
+    /// is closer), year agreement, media-type agreement and popularity
+    pub fn score(&self, title: &str, year: Option<i32>, media_type: MediaType, candidate: &MetadataResult) -> f64 {
+        let weights = &self.config.weights;
+        let title_score = std::iter::once(&candidate.title)
+            .chain(&candidate.original_title)
+            .map(|candidate_title| title_similarity(title, candidate_title))
+            .fold(0.0, f64::max);
+        let candidate_year = candidate.year.as_deref().and_then(parse_year);
+        let type_score = if candidate.media_type == media_type { 1.0 } else { 0.0 };
+
+        let signals = [
+            (weights.title, Some(title_score)),
+            (weights.year, self.year_similarity(year, candidate_year)),
+            (weights.media_type, Some(type_score)),
+            (weights.popularity, Self::popularity(candidate.votes)),
+        ];
+        let (total, weight) = signals
+            .iter()
+            .filter_map(|&(weight, score)| score.map(|score| (weight.max(0.0) * score, weight.max(0.0))))
+            .fold((0.0, 0.0), |(total, sum), (value, weight)| (total + value, sum + weight));
+        if weight > 0.0 { total / weight } else { title_score }
+    }
+
+    /// Scores every candidate and sorts them best first
+    pub fn rank(&self, title: &str, year: Option<i32>, media_type: MediaType, candidates: Vec<MetadataResult>) -> Vec<MetadataResult> {
+        let mut ranked: Vec<MetadataResult> = candidates
+            .into_iter()
+            .map(|mut candidate| {
+                candidate.score = Some(self.score(title, year, media_type, &candidate));
+                candidate
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.score.unwrap_or_default().total_cmp(&a.score.unwrap_or_default()));
+        ranked
+    }
+
+    /// Whether the runner-up of a ranked list scores too close to the winner to pick automatically
+    pub fn is_ambiguous(ranked: &[MetadataResult]) -> bool {
+        match ranked {
+            [best, runner_up, ..] => {
+                best.score.unwrap_or_default() - runner_up.score.unwrap_or_default() <= AMBIGUITY_MARGIN
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(title: &str, year: Option<&str>) -> MetadataResult {
+        MetadataResult {
+            title: title.to_string(),
+            year: year.map(String::from),
+            media_type: MediaType::Movie,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_score_credits_original_title() {
+        let matcher = Matcher::default();
+        let mut frieren = candidate("Frieren: Beyond Journey's End", Some("2023"));
+        let localized_only = matcher.score("Sousou no Frieren", Some(2023), MediaType::Movie, &frieren);
+        frieren.original_title = Some("Sousou no Frieren".to_string());
+        assert!(localized_only < CONFIDENT_SCORE);
+        assert_eq!(matcher.score("Sousou no Frieren", Some(2023), MediaType::Movie, &frieren), 1.0);
+        assert_eq!(matcher.score("Frieren: Beyond Journey's End", Some(2023), MediaType::Movie, &frieren), 1.0);
+    }
+
+    #[test]
+    fn test_title_similarity_ignores_case_punctuation_and_order() {
+        assert_eq!(title_similarity("The Office", "the office!"), 1.0);
+        assert!(title_similarity("Office, The", "The Office") > 0.95);
+        assert!(title_similarity("Dune", "Dune: Part Two") > title_similarity("Dune", "Dunkirk"));
+        assert!(title_similarity("Reacher", "The Boys") < 0.5);
+    }
+
+    #[test]
+    fn test_rank_prefers_title_then_year() {
+        let candidates = vec![
+            candidate("Dune: Part Two", Some("2024")),
+            candidate("Dune", Some("1984")),
+            candidate("Dune", Some("2021")),
+        ];
+
+        let best = Matcher::default().rank("Dune", Some(2021), MediaType::Movie, candidates).remove(0);
+        assert_eq!(best.year.as_deref(), Some("2021"));
+        assert_eq!(best.score, Some(1.0));
+    }
+
+    #[test]
+    fn test_ambiguity_between_close_candidates() {
+        let matcher = Matcher::default();
+        let remakes = matcher.rank("Dune", None, MediaType::Movie, vec![candidate("Dune", Some("1984")), candidate("Dune", Some("2021"))]);
+        assert!(Matcher::is_ambiguous(&remakes));
+
+        let clear = matcher.rank("Dune", None, MediaType::Movie, vec![candidate("Dunkirk", None), candidate("Dune", None)]);
+        assert_eq!(clear[0].title, "Dune");
+        assert!(!Matcher::is_ambiguous(&clear));
+        assert!(!Matcher::is_ambiguous(&clear[..1]));
+    }
+
+    #[test]
+    fn test_rank_weighs_media_type_and_popularity() {
+        let matcher = Matcher::default();
+        let mut series = candidate("Fargo", Some("2014"));
+        series.media_type = MediaType::Tv;
+        let ranked = matcher.rank("Fargo", None, MediaType::Movie, vec![series, candidate("Fargo", Some("1996"))]);
+        assert_eq!(ranked[0].year.as_deref(), Some("1996"));
+        assert_eq!(ranked[0].score, Some(1.0));
+
+        // Equal titles and no year to go on: the better known remake comes first
+        let mut remake = candidate("Dune", Some("2021"));
+        remake.votes = Some(12_000);
+        let mut original = candidate("Dune", Some("1984"));
+        original.votes = Some(3_000);
+        let ranked = matcher.rank("Dune", None, MediaType::Movie, vec![original.clone(), remake]);
+        assert_eq!(ranked[0].year.as_deref(), Some("2021"));
+
+        let unweighted = Matcher::from_config(&MatchingConfig {
+            weights: RankingWeights { popularity: 0.0, ..Default::default() },
+            ..Default::default()
+        });
+        assert_eq!(unweighted.score("Dune", None, MediaType::Movie, &original), 1.0);
+    }
+
+    #[test]
+    fn test_year_tolerance() {
+        let strict = Matcher::from_config(&MatchingConfig { year_tolerance: 0, ..Default::default() });
+        let lenient = Matcher::from_config(&MatchingConfig { year_tolerance: 2, ..Default::default() });
+        let off_by_one = candidate("Reacher", Some("2021"));
+
+        assert!(strict.score("Reacher", Some(2022), MediaType::Movie, &off_by_one) < lenient.score("Reacher", Some(2022), MediaType::Movie, &off_by_one));
+        assert_eq!(strict.year_similarity(Some(2022), Some(2021)), Some(0.0));
+        assert!(lenient.year_similarity(Some(2022), Some(2020)).unwrap() > 0.0);
+        assert_eq!(lenient.year_similarity(Some(2022), Some(2019)), Some(0.0));
+        assert_eq!(lenient.year_similarity(None, Some(2019)), None);
+    }
+
+    #[test]
+    fn test_parse_year_from_dates() {
+        assert_eq!(parse_year("2021-10-22"), Some(2021));
+        assert_eq!(parse_year("1999"), Some(1999));
+        assert_eq!(parse_year("n/a"), None);
+    }
+}
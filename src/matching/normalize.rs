@@ -0,0 +1,137 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+/// Prime Video decorations that never appear in provider titles
+const BUILTIN_RULES: &[&str] = &[
+    // Video quality tags: "[Ultra HD]", "(4K UHD)", "[HDR]"
+    r"(?i)\s*[\[(](?:ultra\s*hd|4k(?:\s*uhd)?|uhd|hdr(?:10)?|dolby\s*vision|hd|sd)[\])]",
+    // Edition suffixes: "– Director's Cut", "(Extended Edition)"
+    r"(?i)\s*[-–—:]\s*(?:director'?s\s+cut|extended(?:\s+(?:edition|cut|version))?|theatrical\s+cut|unrated(?:\s+cut)?|special\s+edition|remastered)\s*$",
+    r"(?i)\s*[\[(](?:director'?s\s+cut|extended(?:\s+(?:edition|cut|version))?|theatrical\s+cut|unrated(?:\s+cut)?|special\s+edition|remastered)[\])]",
+    // Language and audio tags: "(Dubbed)", "[OV]", "[dt./OV]", "(English Subtitles)"
+    r"(?i)\s*[\[(](?:[a-z]+\s+)?(?:dubbed|subbed|subtitled|subtitles|ov|omu|dt\.?(?:\s*/\s*ov)?|original\s+version|ita|eng|deu|fra|esp)[\])]",
+    // Season suffixes: "Season 2", ": Staffel 3", "(Temporada 1)", "- S2"
+    r"(?i)\s*[\[(](?:season|staffel|stagione|saison|temporada|seizoen)\s*\d+[\])]",
+    r"(?i)\s*[-–—:,]?\s*(?:season|staffel|stagione|saison|temporada|seizoen)\s*\d+\s*$",
+    r"(?i)\s*[-–—:]\s*s\d{1,2}\s*$",
+    // A trailing year: "Dune (2021)"; it is read separately as the year to match
+    r"\s*[\[(][12]\d{3}[\])]\s*$",
+];
+
+/// A user-defined rewrite applied to titles before searching
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NormalizationRule {
+    pub pattern: String,
+    /// Replacement text; `$1` etc. refer to capture groups
+    #[serde(default)]
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NormalizationConfig {
+    /// Strip quality, edition, language and season decorations
+    pub builtin_rules: bool,
+    /// Extra rules, applied after the built-ins in order
+    pub rules: Vec<NormalizationRule>,
+}
+
+impl Default for NormalizationConfig {
+    fn default() -> Self {
+        Self {
+            builtin_rules: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Cleans scraped titles so they look like the titles providers index
+pub struct TitleNormalizer {
+    rules: Vec<(Regex, String)>,
+}
+
+impl TitleNormalizer {
+    pub fn from_config(config: &NormalizationConfig) -> Result<Self, AppError> {
+        let builtins: &[&str] = if config.builtin_rules { BUILTIN_RULES } else { &[] };
+        let builtins = builtins.iter().map(|pattern| (pattern.to_string(), String::new()));
+        let custom = config
+            .rules
+            .iter()
+            .map(|rule| (rule.pattern.clone(), rule.replacement.clone()));
+
+        let rules = builtins
+            .chain(custom)
+            .map(|(pattern, replacement)| {
+                Regex::new(&pattern)
+                    .map(|regex| (regex, replacement))
+                    .map_err(|e| {
+                        AppError::ConfigError(config::ConfigError::Message(format!(
+                            "Invalid title normalization rule '{}': {}",
+                            pattern, e
+                        )))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn normalize(&self, title: &str) -> String {
+        let mut normalized = title.to_string();
+        for (regex, replacement) in &self.rules {
+            normalized = regex.replace_all(&normalized, replacement.as_str()).into_owned();
+        }
+        let normalized = normalized.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        // Never hand providers an empty query because a rule ate the whole title
+        if normalized.is_empty() {
+            title.trim().to_string()
+        } else {
+            normalized
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builtin() -> TitleNormalizer {
+        TitleNormalizer::from_config(&NormalizationConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_rules_strip_prime_decorations() {
+        let normalizer = builtin();
+        assert_eq!(normalizer.normalize("The Grand Tour [Ultra HD]"), "The Grand Tour");
+        assert_eq!(normalizer.normalize("Dune (4K UHD)"), "Dune");
+        assert_eq!(normalizer.normalize("Blade Runner – Director's Cut"), "Blade Runner");
+        assert_eq!(normalizer.normalize("Parasite (Subtitled)"), "Parasite");
+        assert_eq!(normalizer.normalize("Babylon Berlin [dt./OV]"), "Babylon Berlin");
+        assert_eq!(normalizer.normalize("The Boys - Season 3"), "The Boys");
+        assert_eq!(normalizer.normalize("Reacher: Staffel 2"), "Reacher");
+        assert_eq!(normalizer.normalize("Dune (2021)"), "Dune");
+        assert_eq!(normalizer.normalize("1917"), "1917");
+        assert_eq!(normalizer.normalize("Fleabag"), "Fleabag");
+    }
+
+    #[test]
+    fn test_custom_rules_and_invalid_patterns() {
+        let config = NormalizationConfig {
+            builtin_rules: false,
+            rules: vec![NormalizationRule {
+                pattern: r"^Tom Clancy's\s+".to_string(),
+                replacement: String::new(),
+            }],
+        };
+        let normalizer = TitleNormalizer::from_config(&config).unwrap();
+        assert_eq!(normalizer.normalize("Tom Clancy's Jack Ryan [HD]"), "Jack Ryan [HD]");
+
+        let invalid = NormalizationConfig {
+            builtin_rules: false,
+            rules: vec![NormalizationRule { pattern: "(".to_string(), replacement: String::new() }],
+        };
+        assert!(TitleNormalizer::from_config(&invalid).is_err());
+    }
+}
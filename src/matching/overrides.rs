@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::metadata::{MediaIds, MetadataResult};
+use crate::models::MediaType;
+
+/// IDs a user pinned to one Prime Video title
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct TitleOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simkl: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tmdb: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tvdb: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imdb: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anilist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anidb: Option<String>,
+    /// Title to export instead of the scraped one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<String>,
+    /// Only apply to movies or to shows; both when unset
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<MediaType>,
+}
+
+impl TitleOverride {
+    /// Pins `ids` for titles of `media_type` only
+    pub fn pinning(ids: MediaIds, year: Option<String>, media_type: MediaType) -> Self {
+        Self {
+            simkl: ids.simkl,
+            tmdb: ids.tmdb,
+            tvdb: ids.tvdb,
+            imdb: ids.imdb,
+            mal: ids.mal,
+            anilist: ids.anilist,
+            anidb: ids.anidb,
+            title: None,
+            year,
+            media_type: Some(media_type),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct OverridesFile {
+    #[serde(default)]
+    titles: HashMap<String, TitleOverride>,
+}
+
+/// User-maintained title to ID mappings that win over any provider search
+#[derive(Debug, Default)]
+pub struct Overrides {
+    titles: HashMap<String, TitleOverride>,
+}
+
+impl Overrides {
+    /// Loads `path`; a missing file simply means no overrides
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents).map_err(|e| {
+            AppError::ConfigError(config::ConfigError::Message(format!(
+                "Invalid overrides file {}: {}",
+                path.display(),
+                e
+            )))
+        })
+    }
+
+    fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        let file: OverridesFile = toml::from_str(contents)?;
+        let titles = file
+            .titles
+            .into_iter()
+            .map(|(title, entry)| (title.trim().to_lowercase(), entry))
+            .collect();
+        Ok(Self { titles })
+    }
+
+    /// Adds an entry for `title` to the file at `path`, creating it if needed, and leaves
+    /// any existing entries and comments untouched
+    pub fn append(path: &Path, title: &str, entry: &TitleOverride) -> Result<(), AppError> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            AppError::ConfigError(config::ConfigError::Message(format!("Cannot write override for '{}': {}", title, e)))
+        };
+        let body = toml::to_string(entry).map_err(|e| invalid(&e))?;
+        // JSON string escapes are valid in TOML basic strings, which is what a quoted key is
+        let key = serde_json::to_string(title)?;
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+        write!(file, "\n[titles.{}]\n{}", key, body)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.titles.len()
+    }
+
+    /// Builds the pinned result for `title`, if the user mapped it
+    pub fn find(&self, title: &str, media_type: MediaType, year: Option<&str>) -> Option<MetadataResult> {
+        let entry = self.titles.get(&title.trim().to_lowercase())?;
+        if entry.media_type.is_some_and(|only| only != media_type) {
+            return None;
+        }
+        Some(MetadataResult {
+            ids: MediaIds {
+                simkl: entry.simkl.clone(),
+                tmdb: entry.tmdb.clone(),
+                tvdb: entry.tvdb.clone(),
+                imdb: entry.imdb.clone(),
+                mal: entry.mal.clone(),
+                anilist: entry.anilist.clone(),
+                anidb: entry.anidb.clone(),
+            },
+            title: entry.title.clone().unwrap_or_else(|| title.to_string()),
+            year: entry.year.clone().or_else(|| year.map(String::from)),
+            media_type,
+            score: Some(1.0),
+            anime: false,
+            enrichment: None,
+            original_title: None,
+            votes: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+[titles."The Office"]
+simkl = "1234"
+imdb = "tt0386676"
+type = "tv"
+
+[titles."Dune"]
+tmdb = "438631"
+title = "Dune"
+year = "2021"
+"#;
+
+    #[test]
+    fn test_find_matches_case_insensitively_and_respects_type() {
+        let overrides = Overrides::parse(SAMPLE).unwrap();
+        assert_eq!(overrides.len(), 2);
+
+        let office = overrides.find("the office", MediaType::Tv, None).unwrap();
+        assert_eq!(office.ids.simkl.as_deref(), Some("1234"));
+        assert_eq!(office.ids.imdb.as_deref(), Some("tt0386676"));
+        assert_eq!(office.title, "the office");
+        assert!(overrides.find("The Office", MediaType::Movie, None).is_none());
+
+        let dune = overrides.find("Dune", MediaType::Movie, Some("2020")).unwrap();
+        assert_eq!(dune.ids.tmdb.as_deref(), Some("438631"));
+        assert_eq!(dune.year.as_deref(), Some("2021"));
+    }
+
+    #[test]
+    fn test_appended_entries_load_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("overrides.toml");
+        std::fs::write(&path, SAMPLE).unwrap();
+
+        let ids = MediaIds { tmdb: Some("329865".to_string()), ..Default::default() };
+        let entry = TitleOverride::pinning(ids, Some("2016".to_string()), MediaType::Movie);
+        Overrides::append(&path, "Arrival \"Director's Cut\"", &entry).unwrap();
+
+        let overrides = Overrides::load(&path).unwrap();
+        assert_eq!(overrides.len(), 3);
+        let arrival = overrides.find("arrival \"director's cut\"", MediaType::Movie, None).unwrap();
+        assert_eq!(arrival.ids.tmdb.as_deref(), Some("329865"));
+        assert_eq!(arrival.year.as_deref(), Some("2016"));
+        assert!(overrides.find("arrival \"director's cut\"", MediaType::Tv, None).is_none());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert!(Overrides::parse("[titles.\"Dune\"]\ntmbd = \"1\"").is_err());
+        assert!(Overrides::load(Path::new("/nonexistent/overrides.toml")).unwrap().len() == 0);
+    }
+}
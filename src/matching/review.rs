@@ -0,0 +1,252 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::matching::overrides::{Overrides, TitleOverride};
+use crate::metadata::{MediaIds, MetadataResult};
+use crate::models::MediaType;
+
+/// A match that scored below `matching.min_score` and waits for the user's verdict
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewEntry {
+    /// Title as scraped from Prime Video, which is also the key an approval is saved under
+    pub title: String,
+    pub media_type: MediaType,
+    pub candidate: MetadataResult,
+    /// Rejected titles stay queued so later runs keep them out of the export
+    #[serde(default)]
+    pub rejected: bool,
+}
+
+impl ReviewEntry {
+    fn is_for(&self, title: &str, media_type: MediaType) -> bool {
+        self.media_type == media_type && self.title.trim().eq_ignore_ascii_case(title.trim())
+    }
+}
+
+/// How a review session ended
+#[derive(Debug, Default, PartialEq)]
+pub struct ReviewSummary {
+    pub approved: usize,
+    pub rejected: usize,
+    pub pending: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum Action {
+    Approve,
+    Edit,
+    Reject,
+    Skip,
+    Quit,
+}
+
+fn parse_action(input: &str) -> Option<Action> {
+    match input.trim().to_lowercase().as_str() {
+        "a" | "approve" => Some(Action::Approve),
+        "e" | "edit" => Some(Action::Edit),
+        "r" | "reject" => Some(Action::Reject),
+        "" | "s" | "skip" => Some(Action::Skip),
+        "q" | "quit" => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+/// Parses corrected IDs typed as `tmdb=329865 imdb=tt2543164`
+fn parse_ids(input: &str) -> Result<MediaIds, String> {
+    let mut ids = MediaIds::default();
+    for pair in input.split_whitespace() {
+        let (key, value) = pair
+            .split_once('=')
+            .filter(|(_, value)| !value.is_empty())
+            .ok_or_else(|| format!("expected key=value, got '{}'", pair))?;
+        let field = match key.to_lowercase().as_str() {
+            "simkl" => &mut ids.simkl,
+            "tmdb" => &mut ids.tmdb,
+            "tvdb" => &mut ids.tvdb,
+            "imdb" => &mut ids.imdb,
+            "mal" => &mut ids.mal,
+            "anilist" => &mut ids.anilist,
+            "anidb" => &mut ids.anidb,
+            other => return Err(format!("unknown ID '{}' (expected simkl, tmdb, tvdb, imdb, mal, anilist or anidb)", other)),
+        };
+        *field = Some(value.to_string());
+    }
+    if input.trim().is_empty() {
+        return Err("enter at least one ID".to_string());
+    }
+    Ok(ids)
+}
+
+fn prompt(question: &str) -> Result<String, AppError> {
+    print!("{}", question);
+    std::io::stdout().flush()?;
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input)
+}
+
+/// Low-confidence matches held back from the export, persisted between runs
+#[derive(Debug, Default)]
+pub struct ReviewQueue {
+    entries: Vec<ReviewEntry>,
+}
+
+impl ReviewQueue {
+    /// Loads the queue at `path`; a missing file is an empty queue
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let entries = serde_json::from_reader(File::open(path)?)?;
+        Ok(Self { entries })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        serde_json::to_writer_pretty(File::create(path)?, &self.entries)?;
+        Ok(())
+    }
+
+    /// Queues a match for review, refreshing the candidate of a title already waiting.
+    /// Returns false when the title was queued before.
+    pub fn add(&mut self, title: &str, media_type: MediaType, candidate: MetadataResult) -> bool {
+        match self.entries.iter_mut().find(|e| e.is_for(title, media_type)) {
+            Some(entry) => {
+                if !entry.rejected {
+                    entry.candidate = candidate;
+                }
+                false
+            }
+            None => {
+                self.entries.push(ReviewEntry {
+                    title: title.to_string(),
+                    media_type,
+                    candidate,
+                    rejected: false,
+                });
+                true
+            }
+        }
+    }
+
+    pub fn is_rejected(&self, title: &str, media_type: MediaType) -> bool {
+        self.entries.iter().any(|e| e.rejected && e.is_for(title, media_type))
+    }
+
+    pub fn pending(&self) -> usize {
+        self.entries.iter().filter(|e| !e.rejected).count()
+    }
+
+    /// Walks the user through every pending entry. Approved and corrected matches are
+    /// saved as title overrides in `overrides_path`, so the next export includes them.
+    pub fn review(&mut self, overrides_path: &Path) -> Result<ReviewSummary, AppError> {
+        let mut summary = ReviewSummary::default();
+        let mut approved = Vec::new();
+        let total = self.pending();
+
+        for (position, (index, entry)) in self.entries.iter_mut().enumerate().filter(|(_, e)| !e.rejected).enumerate() {
+            let candidate = &entry.candidate;
+            println!();
+            println!("[{}/{}] '{}' ({})", position + 1, total, entry.title, match entry.media_type {
+                MediaType::Movie => "movie",
+                MediaType::Tv => "tv",
+            });
+            println!(
+                "   matched '{}' ({}) [{}] score {:.2}",
+                candidate.title,
+                candidate.year.as_deref().unwrap_or("?"),
+                super::disambiguate::describe_ids(&candidate.ids),
+                candidate.score.unwrap_or_default()
+            );
+
+            let action = loop {
+                let input = prompt("[a]pprove, [e]dit IDs, [r]eject, [s]kip, [q]uit (Enter = skip): ")?;
+                match parse_action(&input) {
+                    Some(action) => break action,
+                    None => println!("Please answer a, e, r, s or q."),
+                }
+            };
+
+            let ids = match action {
+                Action::Approve => candidate.ids.clone(),
+                Action::Edit => loop {
+                    let input = prompt("IDs (e.g. tmdb=329865 imdb=tt2543164): ")?;
+                    match parse_ids(&input) {
+                        Ok(ids) => break ids,
+                        Err(e) => println!("{}", e),
+                    }
+                },
+                Action::Reject => {
+                    entry.rejected = true;
+                    summary.rejected += 1;
+                    continue;
+                }
+                Action::Skip => continue,
+                Action::Quit => break,
+            };
+            let year = if action == Action::Approve { candidate.year.clone() } else { None };
+            Overrides::append(overrides_path, &entry.title, &TitleOverride::pinning(ids, year, entry.media_type))?;
+            approved.push(index);
+            summary.approved += 1;
+        }
+
+        for index in approved.into_iter().rev() {
+            self.entries.remove(index);
+        }
+        summary.pending = self.pending();
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(title: &str, score: f64) -> MetadataResult {
+        MetadataResult {
+            ids: MediaIds { tmdb: Some("1".to_string()), ..Default::default() },
+            title: title.to_string(),
+            year: Some("2016".to_string()),
+            media_type: MediaType::Movie,
+            score: Some(score),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_queue_deduplicates_and_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("review-queue.json");
+
+        let mut queue = ReviewQueue::load(&path).unwrap();
+        assert!(queue.add("Arrival", MediaType::Movie, candidate("Arrivals", 0.4)));
+        assert!(!queue.add("arrival", MediaType::Movie, candidate("Arrival", 0.5)));
+        assert!(queue.add("Arrival", MediaType::Tv, candidate("Arrival", 0.5)));
+        queue.entries[1].rejected = true;
+        queue.save(&path).unwrap();
+
+        let queue = ReviewQueue::load(&path).unwrap();
+        assert_eq!(queue.entries.len(), 2);
+        assert_eq!(queue.entries[0].candidate.title, "Arrival");
+        assert_eq!(queue.pending(), 1);
+        assert!(queue.is_rejected("ARRIVAL", MediaType::Tv));
+        assert!(!queue.is_rejected("Arrival", MediaType::Movie));
+    }
+
+    #[test]
+    fn test_parse_action_and_ids() {
+        assert_eq!(parse_action("\n"), Some(Action::Skip));
+        assert_eq!(parse_action(" A "), Some(Action::Approve));
+        assert_eq!(parse_action("x"), None);
+
+        let ids = parse_ids("tmdb=329865 IMDB=tt2543164\n").unwrap();
+        assert_eq!(ids.tmdb.as_deref(), Some("329865"));
+        assert_eq!(ids.imdb.as_deref(), Some("tt2543164"));
+        assert!(parse_ids("tmbd=1").is_err());
+        assert!(parse_ids("tmdb=").is_err());
+        assert!(parse_ids("").is_err());
+    }
+}
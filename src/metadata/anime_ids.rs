@@ -0,0 +1,242 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use crate::{
+    config::AnimeIdsConfig,
+    error::AppError,
+    models::MediaType,
+    metadata::MediaIds,
+};
+
+const ANIME_LISTS_URL: &str = "https://raw.githubusercontent.com/Fribb/anime-lists/master/anime-list-full.json";
+
+/// Either of the two community mapping files: Fribb's anime-lists (a bare array of ID
+/// objects) or manami's anime-offline-database (`{"data": [...]}` with source URLs)
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MappingFile {
+    AnimeLists(Vec<AnimeListsEntry>),
+    OfflineDatabase { data: Vec<OfflineDatabaseEntry> },
+}
+
+/// IDs are numbers in most entries and strings in a few, so both are accepted
+#[derive(Deserialize)]
+struct AnimeListsEntry {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    mal_id: Option<serde_json::Value>,
+    anilist_id: Option<serde_json::Value>,
+    anidb_id: Option<serde_json::Value>,
+    simkl_id: Option<serde_json::Value>,
+    thetvdb_id: Option<serde_json::Value>,
+    themoviedb_id: Option<serde_json::Value>,
+    imdb_id: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct OfflineDatabaseEntry {
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    #[serde(default)]
+    sources: Vec<String>,
+}
+
+fn id(value: Option<serde_json::Value>) -> Option<String> {
+    match value? {
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// The ID at the end of `url` when it is a page on `site`, e.g. `https://anilist.co/anime/21`
+fn source_id(sources: &[String], site: &str) -> Option<String> {
+    sources.iter().find_map(|url| {
+        let rest = url.strip_prefix("https://")?.strip_prefix(site)?.strip_prefix("/anime/")?;
+        Some(rest.trim_end_matches('/').to_string()).filter(|id| !id.is_empty())
+    })
+}
+
+/// Both files type entries like MAL does; only `MOVIE` maps to a TMDB movie
+fn media_type_of(kind: Option<&str>) -> Option<MediaType> {
+    match kind? {
+        "MOVIE" => Some(MediaType::Movie),
+        "UNKNOWN" => None,
+        _ => Some(MediaType::Tv),
+    }
+}
+
+struct Entry {
+    ids: MediaIds,
+    media_type: Option<MediaType>,
+}
+
+/// Maps between MAL, AniList, AniDB, Simkl, TVDB, TMDB and IMDB IDs of anime from a local
+/// copy of a community mapping file, so anime matches carry every ID without extra requests
+#[derive(Default)]
+pub struct AnimeIdMap {
+    entries: Vec<Entry>,
+    by_id: HashMap<(&'static str, String), Vec<usize>>,
+}
+
+/// Anime databases give every season or movie its own entry, so one of these IDs names exactly one entry
+const ENTRY_IDS: [&str; 4] = ["mal", "anilist", "anidb", "simkl"];
+
+/// TVDB, TMDB and IMDB often file all seasons of a show under one ID; these only count when unambiguous
+const SHARED_IDS: [&str; 3] = ["tvdb", "tmdb", "imdb"];
+
+fn ids_by_kind(ids: &MediaIds) -> [(&'static str, &Option<String>); 7] {
+    [
+        ("mal", &ids.mal),
+        ("anilist", &ids.anilist),
+        ("anidb", &ids.anidb),
+        ("simkl", &ids.simkl),
+        ("tvdb", &ids.tvdb),
+        ("tmdb", &ids.tmdb),
+        ("imdb", &ids.imdb),
+    ]
+}
+
+impl AnimeIdMap {
+    /// Loads `path`; a missing file simply means no mappings
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let parsed: MappingFile = serde_json::from_reader(file).map_err(|e| {
+            AppError::MetadataError(format!("Invalid anime ID mapping file {}: {}", path.display(), e))
+        })?;
+        Ok(Self::from_file(parsed))
+    }
+
+    fn from_file(file: MappingFile) -> Self {
+        let entries: Vec<Entry> = match file {
+            MappingFile::AnimeLists(entries) => entries
+                .into_iter()
+                .map(|entry| Entry {
+                    media_type: media_type_of(entry.kind.as_deref()),
+                    ids: MediaIds {
+                        simkl: id(entry.simkl_id),
+                        tvdb: id(entry.thetvdb_id),
+                        tmdb: id(entry.themoviedb_id),
+                        imdb: id(entry.imdb_id),
+                        mal: id(entry.mal_id),
+                        anilist: id(entry.anilist_id),
+                        anidb: id(entry.anidb_id),
+                    },
+                })
+                .collect(),
+            MappingFile::OfflineDatabase { data } => data
+                .into_iter()
+                .map(|entry| Entry {
+                    media_type: media_type_of(entry.kind.as_deref()),
+                    ids: MediaIds {
+                        simkl: source_id(&entry.sources, "simkl.com"),
+                        mal: source_id(&entry.sources, "myanimelist.net"),
+                        anilist: source_id(&entry.sources, "anilist.co"),
+                        anidb: source_id(&entry.sources, "anidb.net"),
+                        ..Default::default()
+                    },
+                })
+                .collect(),
+        };
+
+        let mut by_id: HashMap<(&'static str, String), Vec<usize>> = HashMap::new();
+        for (index, entry) in entries.iter().enumerate() {
+            for (kind, value) in ids_by_kind(&entry.ids) {
+                if let Some(value) = value {
+                    by_id.entry((kind, value.clone())).or_default().push(index);
+                }
+            }
+        }
+        Self { entries, by_id }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn matching(&self, ids: &MediaIds, kinds: &[&str], media_type: MediaType) -> Option<&Entry> {
+        let mut found: Option<&Entry> = None;
+        for (kind, value) in ids_by_kind(ids).into_iter().filter(|(kind, _)| kinds.contains(kind)) {
+            let Some(indices) = value.as_ref().and_then(|value| self.by_id.get(&(kind, value.clone()))) else {
+                continue;
+            };
+            let mut candidates = indices
+                .iter()
+                .map(|&index| &self.entries[index])
+                .filter(|entry| entry.media_type.is_none_or(|t| t == media_type));
+            match (candidates.next(), candidates.next()) {
+                (Some(entry), None) if found.is_none_or(|f| std::ptr::eq(f, entry)) => found = Some(entry),
+                // Ambiguous, or pointing at a different entry than another ID did
+                (Some(_), _) => return None,
+                (None, _) => {}
+            }
+        }
+        found
+    }
+
+    /// Every ID known for the anime `ids` identify, or None when they name no entry or several
+    pub fn find(&self, ids: &MediaIds, media_type: MediaType) -> Option<MediaIds> {
+        self.matching(ids, &ENTRY_IDS, media_type)
+            .or_else(|| self.matching(ids, &SHARED_IDS, media_type))
+            .map(|entry| entry.ids.clone())
+    }
+}
+
+/// Downloads Fribb's anime-lists mapping to `config.path`
+pub async fn download(config: &AnimeIdsConfig) -> Result<(), AppError> {
+    tracing::info!("Downloading {}", ANIME_LISTS_URL);
+    let body = reqwest::get(ANIME_LISTS_URL).await?.error_for_status()?.bytes().await?;
+    std::fs::write(&config.path, &body)?;
+    println!("✅ Saved {} ({} KB)", config.path.display(), body.len() / 1024);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANIME_LISTS: &str = r#"[
+        {"type": "TV", "mal_id": 16498, "anilist_id": 16498, "anidb_id": 9541, "thetvdb_id": 267440, "imdb_id": "tt2560140"},
+        {"type": "TV", "mal_id": 25777, "anilist_id": 20958, "anidb_id": 10944, "thetvdb_id": 267440, "imdb_id": "tt2560140"},
+        {"type": "MOVIE", "mal_id": 32281, "anidb_id": 11829, "themoviedb_id": 372058, "imdb_id": "tt5311514"},
+        {"type": "UNKNOWN", "anidb_id": "", "kitsu_id": 1}
+    ]"#;
+
+    fn map(contents: &str) -> AnimeIdMap {
+        AnimeIdMap::from_file(serde_json::from_str(contents).unwrap())
+    }
+
+    #[test]
+    fn test_anime_lists_map_between_ids() {
+        let map = map(ANIME_LISTS);
+        assert_eq!(map.len(), 4);
+
+        let season_two = map.find(&MediaIds { mal: Some("25777".to_string()), ..Default::default() }, MediaType::Tv).unwrap();
+        assert_eq!(season_two.anilist.as_deref(), Some("20958"));
+        assert_eq!(season_two.tvdb.as_deref(), Some("267440"));
+
+        let your_name = map.find(&MediaIds { tmdb: Some("372058".to_string()), ..Default::default() }, MediaType::Movie).unwrap();
+        assert_eq!(your_name.mal.as_deref(), Some("32281"));
+        assert!(map.find(&MediaIds { tmdb: Some("372058".to_string()), ..Default::default() }, MediaType::Tv).is_none());
+
+        // Both seasons share the TVDB ID, so it alone cannot say which one was meant
+        assert!(map.find(&MediaIds { tvdb: Some("267440".to_string()), ..Default::default() }, MediaType::Tv).is_none());
+        let disagreeing = MediaIds { mal: Some("16498".to_string()), anilist: Some("20958".to_string()), ..Default::default() };
+        assert!(map.find(&disagreeing, MediaType::Tv).is_none());
+    }
+
+    #[test]
+    fn test_offline_database_ids_come_from_sources() {
+        let map = map(r#"{"data": [{"type": "MOVIE", "title": "Kimi no Na wa.", "sources": [
+            "https://anidb.net/anime/11829", "https://anilist.co/anime/21519",
+            "https://myanimelist.net/anime/32281", "https://kitsu.app/anime/11614"
+        ]}]}"#);
+        let ids = map.find(&MediaIds { anidb: Some("11829".to_string()), ..Default::default() }, MediaType::Movie).unwrap();
+        assert_eq!(ids.mal.as_deref(), Some("32281"));
+        assert_eq!(ids.anilist.as_deref(), Some("21519"));
+        assert_eq!(ids.tmdb, None);
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+use crate::error::AppError;
+use crate::metadata::provider::{MetadataProvider, SearchQuery};
+use crate::metadata::MetadataResult;
+
+/// How long the first queued search waits for others to join its batch
+const DEFAULT_WINDOW: Duration = Duration::from_millis(25);
+
+type Reply = oneshot::Sender<Result<Vec<MetadataResult>, String>>;
+
+/// Groups searches that concurrent lookups send to the same provider, so a provider with
+/// `batch_size() > 1` gets them in one `search_batch` call.
+///
+/// The first search queued for a provider waits a short window for others, then sends
+/// whatever has queued; a queue reaching the provider's batch size is sent at once.
+pub struct SearchBatcher {
+    queues: Mutex<HashMap<&'static str, Vec<(SearchQuery, Reply)>>>,
+    window: Duration,
+}
+
+impl Default for SearchBatcher {
+    fn default() -> Self {
+        Self { queues: Mutex::default(), window: DEFAULT_WINDOW }
+    }
+}
+
+impl SearchBatcher {
+    fn enqueue(&self, provider: &dyn MetadataProvider, query: SearchQuery, reply: Reply) -> (bool, Option<Vec<(SearchQuery, Reply)>>) {
+        let mut queues = self.queues.lock().expect("batch queue mutex poisoned");
+        let queue = queues.entry(provider.name()).or_default();
+        let leader = queue.is_empty();
+        queue.push((query, reply));
+        let full = (queue.len() >= provider.batch_size()).then(|| std::mem::take(queue));
+        (leader, full)
+    }
+
+    fn take(&self, provider: &dyn MetadataProvider) -> Vec<(SearchQuery, Reply)> {
+        let mut queues = self.queues.lock().expect("batch queue mutex poisoned");
+        queues.get_mut(provider.name()).map(std::mem::take).unwrap_or_default()
+    }
+
+    async fn send(provider: &dyn MetadataProvider, batch: Vec<(SearchQuery, Reply)>) {
+        if batch.is_empty() {
+            return;
+        }
+        let (queries, replies): (Vec<_>, Vec<_>) = batch.into_iter().unzip();
+        tracing::debug!("Searching {} titles in one batch on {}", queries.len(), provider.name());
+        match provider.search_batch(&queries).await {
+            Ok(results) if results.len() == queries.len() => {
+                for (reply, results) in replies.into_iter().zip(results) {
+                    let _ = reply.send(Ok(results));
+                }
+            }
+            Ok(results) => {
+                let error = format!("{} answered {} of {} batched searches", provider.name(), results.len(), queries.len());
+                for reply in replies {
+                    let _ = reply.send(Err(error.clone()));
+                }
+            }
+            Err(e) => {
+                let error = e.to_string();
+                for reply in replies {
+                    let _ = reply.send(Err(error.clone()));
+                }
+            }
+        }
+    }
+
+    /// Searches `provider` for `query` as part of the next batch it sends
+    pub async fn search(&self, provider: &dyn MetadataProvider, query: SearchQuery) -> Result<Vec<MetadataResult>, AppError> {
+        let (reply, answer) = oneshot::channel();
+        let (leader, full) = self.enqueue(provider, query, reply);
+        if let Some(batch) = full {
+            Self::send(provider, batch).await;
+        } else if leader {
+            tokio::time::sleep(self.window).await;
+            Self::send(provider, self.take(provider)).await;
+        }
+        match answer.await {
+            Ok(result) => result.map_err(AppError::MetadataError),
+            Err(_) => Err(AppError::MetadataError(format!("Batched search on {} was abandoned", provider.name()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MediaType;
+    use async_trait::async_trait;
+
+    #[derive(Default)]
+    struct BatchingProvider {
+        batches: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for BatchingProvider {
+        fn name(&self) -> &'static str {
+            "Batching"
+        }
+
+        fn batch_size(&self) -> usize {
+            3
+        }
+
+        async fn search(&self, _title: &str, _media_type: MediaType, _year: Option<i32>) -> Result<Vec<MetadataResult>, AppError> {
+            unreachable!("searches go through search_batch")
+        }
+
+        async fn search_batch(&self, queries: &[SearchQuery]) -> Result<Vec<Vec<MetadataResult>>, AppError> {
+            self.batches.lock().unwrap().push(queries.len());
+            Ok(queries
+                .iter()
+                .map(|query| {
+                    vec![MetadataResult {
+                        title: query.title.clone(),
+                        media_type: query.media_type,
+                        ..Default::default()
+                    }]
+                })
+                .collect())
+        }
+    }
+
+    fn query(title: &str) -> SearchQuery {
+        SearchQuery { title: title.to_string(), media_type: MediaType::Movie, year: None, language: None }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_searches_share_batches() {
+        let provider = BatchingProvider::default();
+        let batcher = SearchBatcher::default();
+        let titles = ["Arrival", "Dune", "Heat", "Alien"];
+        let results = futures::future::try_join_all(titles.iter().map(|title| batcher.search(&provider, query(title))))
+            .await
+            .unwrap();
+
+        // Every search gets its own answer back
+        let answered: Vec<_> = results.iter().map(|r| r[0].title.as_str()).collect();
+        assert_eq!(answered, titles);
+        // Three fill a batch, the fourth goes out alone once the window closes
+        assert_eq!(*provider.batches.lock().unwrap(), [3, 1]);
+    }
+}
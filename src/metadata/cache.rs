@@ -0,0 +1,192 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::error::AppError;
+use crate::metadata::MetadataResult;
+use crate::models::MediaType;
+
+/// Local SQLite store of provider responses so repeat runs skip the network
+pub struct MetadataCache {
+    conn: Mutex<Connection>,
+    /// How long a search that found nothing is trusted before the provider is asked again
+    negative_ttl: chrono::Duration,
+}
+
+/// Default lifetime of cached "no results" answers
+const DEFAULT_NEGATIVE_TTL_DAYS: i64 = 30;
+
+impl MetadataCache {
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        Self::init(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn in_memory() -> Result<Self, AppError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, AppError> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS search_results (
+                provider   TEXT NOT NULL,
+                title      TEXT NOT NULL,
+                year       INTEGER NOT NULL,
+                media_type TEXT NOT NULL,
+                results    TEXT NOT NULL,
+                cached_at  TEXT NOT NULL,
+                PRIMARY KEY (provider, title, year, media_type)
+            );
+            CREATE TABLE IF NOT EXISTS details (
+                provider   TEXT NOT NULL,
+                id         TEXT NOT NULL,
+                media_type TEXT NOT NULL,
+                result     TEXT NOT NULL,
+                cached_at  TEXT NOT NULL,
+                PRIMARY KEY (provider, id, media_type)
+            );",
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            negative_ttl: chrono::Duration::days(DEFAULT_NEGATIVE_TTL_DAYS),
+        })
+    }
+
+    /// Expires empty search results after `ttl`; results with matches never expire
+    pub fn with_negative_ttl(mut self, ttl: chrono::Duration) -> Self {
+        self.negative_ttl = ttl;
+        self
+    }
+
+    fn media_type_key(media_type: MediaType) -> &'static str {
+        match media_type {
+            MediaType::Movie => "movie",
+            MediaType::Tv => "tv",
+        }
+    }
+
+    /// Titles are keyed case-insensitively and a missing year is stored as 0
+    fn key(title: &str, year: Option<i32>, media_type: MediaType) -> (String, i32, &'static str) {
+        (title.trim().to_lowercase(), year.unwrap_or(0), Self::media_type_key(media_type))
+    }
+
+    pub fn get_search(
+        &self,
+        provider: &str,
+        title: &str,
+        year: Option<i32>,
+        media_type: MediaType,
+    ) -> Result<Option<Vec<MetadataResult>>, AppError> {
+        let (title, year, media_type) = Self::key(title, year, media_type);
+        let conn = self.conn.lock().expect("cache mutex poisoned");
+        let row: Option<(String, String)> = conn
+            .query_row(
+                "SELECT results, cached_at FROM search_results
+                 WHERE provider = ?1 AND title = ?2 AND year = ?3 AND media_type = ?4",
+                params![provider, title, year, media_type],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((json, cached_at)) = row else {
+            return Ok(None);
+        };
+        let results: Vec<MetadataResult> = serde_json::from_str(&json)?;
+        if results.is_empty() && self.is_expired(&cached_at) {
+            return Ok(None);
+        }
+        Ok(Some(results))
+    }
+
+    /// Unreadable timestamps count as expired, so the search is simply repeated
+    fn is_expired(&self, cached_at: &str) -> bool {
+        chrono::DateTime::parse_from_rfc3339(cached_at)
+            .map_or(true, |at| chrono::Utc::now() - at.with_timezone(&chrono::Utc) >= self.negative_ttl)
+    }
+
+    pub fn put_search(
+        &self,
+        provider: &str,
+        title: &str,
+        year: Option<i32>,
+        media_type: MediaType,
+        results: &[MetadataResult],
+    ) -> Result<(), AppError> {
+        let (title, year, media_type) = Self::key(title, year, media_type);
+        let json = serde_json::to_string(results)?;
+        let conn = self.conn.lock().expect("cache mutex poisoned");
+        conn.execute(
+            "INSERT OR REPLACE INTO search_results (provider, title, year, media_type, results, cached_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![provider, title, year, media_type, json, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// The details `provider` gave for its `id`; they never expire
+    pub fn get_details(&self, provider: &str, id: &str, media_type: MediaType) -> Result<Option<MetadataResult>, AppError> {
+        let conn = self.conn.lock().expect("cache mutex poisoned");
+        let json: Option<String> = conn
+            .query_row(
+                "SELECT result FROM details WHERE provider = ?1 AND id = ?2 AND media_type = ?3",
+                params![provider, id, Self::media_type_key(media_type)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+    }
+
+    pub fn put_details(&self, provider: &str, id: &str, media_type: MediaType, result: &MetadataResult) -> Result<(), AppError> {
+        let json = serde_json::to_string(result)?;
+        let conn = self.conn.lock().expect("cache mutex poisoned");
+        conn.execute(
+            "INSERT OR REPLACE INTO details (provider, id, media_type, result, cached_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![provider, id, Self::media_type_key(media_type), json, chrono::Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::MediaIds;
+
+    #[test]
+    fn test_search_roundtrip_is_case_insensitive() {
+        let cache = MetadataCache::in_memory().unwrap();
+        assert!(cache.get_search("TMDB", "Dune", Some(2021), MediaType::Movie).unwrap().is_none());
+
+        let result = MetadataResult {
+            ids: MediaIds { tmdb: Some("438631".to_string()), ..Default::default() },
+            title: "Dune".to_string(),
+            year: Some("2021".to_string()),
+            media_type: MediaType::Movie,
+            ..Default::default()
+        };
+        cache.put_search("TMDB", "Dune", Some(2021), MediaType::Movie, std::slice::from_ref(&result)).unwrap();
+
+        let cached = cache.get_search("TMDB", " dune ", Some(2021), MediaType::Movie).unwrap().unwrap();
+        assert_eq!(cached[0].ids.tmdb.as_deref(), Some("438631"));
+        assert!(cache.get_search("TMDB", "Dune", None, MediaType::Movie).unwrap().is_none());
+        assert!(cache.get_search("Simkl", "Dune", Some(2021), MediaType::Movie).unwrap().is_none());
+
+        cache.put_details("TMDB", "438631", MediaType::Movie, &result).unwrap();
+        assert_eq!(cache.get_details("TMDB", "438631", MediaType::Movie).unwrap().unwrap().title, "Dune");
+        assert!(cache.get_details("TMDB", "438631", MediaType::Tv).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_empty_results_expire_after_ttl() {
+        let cache = MetadataCache::in_memory().unwrap();
+        cache.put_search("TMDB", "Obscure", None, MediaType::Movie, &[]).unwrap();
+        assert!(cache.get_search("TMDB", "Obscure", None, MediaType::Movie).unwrap().is_some_and(|r| r.is_empty()));
+
+        let old = (chrono::Utc::now() - chrono::Duration::days(45)).to_rfc3339();
+        cache.conn.lock().unwrap().execute("UPDATE search_results SET cached_at = ?1", params![old]).unwrap();
+        assert!(cache.get_search("TMDB", "Obscure", None, MediaType::Movie).unwrap().is_none());
+
+        let cache = cache.with_negative_ttl(chrono::Duration::days(60));
+        assert!(cache.get_search("TMDB", "Obscure", None, MediaType::Movie).unwrap().is_some_and(|r| r.is_empty()));
+    }
+}
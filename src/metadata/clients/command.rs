@@ -0,0 +1,126 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use crate::{
+    config::CustomProviderConfig,
+    error::AppError,
+    models::MediaType,
+    metadata::{MetadataResult, MetadataProvider},
+    metadata::response,
+};
+
+/// Runs an external program per request, so users can plug in their own sources.
+///
+/// The program gets one JSON object on stdin, either
+/// `{"action": "search", "title": ..., "media_type": "movie" | "tv", "year": ...}` or
+/// `{"action": "details", "id": <IMDB ID>, "media_type": ...}`, and prints a JSON array of
+/// results (search) or a single result (details) in the metadata cache's format.
+pub struct CommandProvider {
+    name: &'static str,
+    config: CustomProviderConfig,
+}
+
+impl CommandProvider {
+    pub fn new(name: &str, config: CustomProviderConfig) -> Self {
+        // Providers are named by `&'static str` everywhere; the handful configured live for the whole run
+        let name = Box::leak(name.to_string().into_boxed_str());
+        Self { name, config }
+    }
+
+    async fn run(&self, request: serde_json::Value) -> Result<Vec<u8>, AppError> {
+        let mut child = Command::new(&self.config.command)
+            .args(&self.config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| AppError::MetadataError(format!("{}: cannot start '{}': {}", self.name, self.config.command, e)))?;
+
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        // A program that exits without reading its input still reports through its status and stderr
+        if let Err(e) = stdin.write_all(request.to_string().as_bytes()).await {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+        drop(stdin);
+
+        let timeout = Duration::from_secs(self.config.timeout_secs);
+        let output = tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .map_err(|_| AppError::MetadataError(format!("{} timed out after {:?}", self.name, timeout)))??;
+        if !output.status.success() {
+            return Err(AppError::MetadataError(format!(
+                "{} exited with {}: {}",
+                self.name,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(output.stdout)
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for CommandProvider {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn search(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        let request = json!({ "action": "search", "title": title, "media_type": media_type, "year": year });
+        response::parse(self.name, &String::from_utf8_lossy(&self.run(request).await?))
+    }
+
+    async fn get_details(
+        &self,
+        id: &str,
+        media_type: MediaType,
+    ) -> Result<MetadataResult, AppError> {
+        let request = json!({ "action": "details", "id": id, "media_type": media_type });
+        response::parse(self.name, &String::from_utf8_lossy(&self.run(request).await?))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn script(body: &str) -> CommandProvider {
+        CommandProvider::new("script", CustomProviderConfig {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), body.to_string()],
+            timeout_secs: 5,
+            enabled: true,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_search_reads_results_from_stdout() {
+        // Echoes the requested title back, proving the request arrived on stdin
+        let provider = script(
+            r#"title=$(sed 's/.*"title":"\([^"]*\)".*/\1/'); printf '[{"ids":{"imdb":"tt1"},"title":"%s","year":"2016","media_type":"movie"}]' "$title""#,
+        );
+        let results = provider.search("Arrival", MediaType::Movie, Some(2016)).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Arrival");
+        assert_eq!(results[0].ids.imdb.as_deref(), Some("tt1"));
+        assert_eq!(provider.name(), "script");
+    }
+
+    #[tokio::test]
+    async fn test_failures_carry_stderr() {
+        let error = script("echo 'no API key' >&2; exit 3").search("Arrival", MediaType::Movie, None).await.unwrap_err();
+        assert!(error.to_string().contains("no API key"));
+        assert!(script("echo nope").search("Arrival", MediaType::Movie, None).await.is_err());
+    }
+}
@@ -0,0 +1,280 @@
+use async_trait::async_trait;
+use flate2::read::GzDecoder;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tokio::sync::OnceCell;
+use crate::{
+    config::ImdbDatasetConfig,
+    error::AppError,
+    matching,
+    models::MediaType,
+    metadata::{MediaIds, MetadataResult, MetadataProvider},
+};
+
+const DATASET_URL: &str = "https://datasets.imdbws.com/title.basics.tsv.gz";
+
+/// Results returned per search; exact-title hits are few, this only guards against junk keys
+const MAX_RESULTS: usize = 20;
+
+/// Resolves titles to IMDB IDs from a local index of IMDB's `title.basics` dataset,
+/// without any network request
+pub struct ImdbDatasetProvider {
+    config: ImdbDatasetConfig,
+    index: OnceCell<Mutex<Connection>>,
+}
+
+/// Dataset title types worth matching against, by the media type they represent
+fn media_type_of(title_type: &str) -> Option<MediaType> {
+    match title_type {
+        "movie" | "tvMovie" | "video" => Some(MediaType::Movie),
+        "tvSeries" | "tvMiniSeries" => Some(MediaType::Tv),
+        _ => None,
+    }
+}
+
+fn media_type_key(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Movie => "movie",
+        MediaType::Tv => "tv",
+    }
+}
+
+/// The dataset writes missing values as `\N`
+fn value(field: &str) -> Option<&str> {
+    (field != "\\N").then_some(field)
+}
+
+fn modified_secs(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+/// Opens the index at `index_path`, rebuilding it when the dataset file is newer than
+/// what it was built from. Without a dataset an existing index is used as-is.
+fn open_index(dataset_path: &Path, index_path: &Path) -> Result<Connection, AppError> {
+    let conn = Connection::open(index_path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS titles (
+            tconst         TEXT PRIMARY KEY,
+            title          TEXT NOT NULL,
+            original_title TEXT,
+            title_key      TEXT NOT NULL,
+            original_key   TEXT,
+            year           INTEGER,
+            media_type     TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS titles_by_title ON titles (title_key);
+        CREATE INDEX IF NOT EXISTS titles_by_original ON titles (original_key);
+        CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value INTEGER NOT NULL);",
+    )?;
+
+    let built_from: Option<i64> = conn
+        .query_row("SELECT value FROM meta WHERE key = 'dataset_modified'", [], |row| row.get(0))
+        .optional()?;
+    match (modified_secs(dataset_path), built_from) {
+        (Some(modified), Some(built)) if modified <= built => Ok(conn),
+        (Some(modified), _) => {
+            tracing::info!("Building the IMDB title index from {} (this takes a while once)", dataset_path.display());
+            let mut conn = conn;
+            let count = import(&mut conn, dataset_path, modified)?;
+            tracing::info!("Indexed {} IMDB titles in {}", count, index_path.display());
+            Ok(conn)
+        }
+        (None, Some(_)) => Ok(conn),
+        (None, None) => Err(AppError::MetadataError(format!(
+            "IMDB dataset not found at {}; download it from {}",
+            dataset_path.display(),
+            DATASET_URL
+        ))),
+    }
+}
+
+fn import(conn: &mut Connection, dataset_path: &Path, modified: i64) -> Result<usize, AppError> {
+    let file = File::open(dataset_path)?;
+    let reader: Box<dyn Read> = if dataset_path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM titles", [])?;
+    let mut count = 0;
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR REPLACE INTO titles (tconst, title, original_title, title_key, original_key, year, media_type)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        // Columns: tconst, titleType, primaryTitle, originalTitle, isAdult, startYear, ...
+        for line in BufReader::new(reader).lines().skip(1) {
+            let line = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [tconst, title_type, title, original, is_adult, start_year, ..] = fields[..] else {
+                continue;
+            };
+            let Some(media_type) = media_type_of(title_type) else {
+                continue;
+            };
+            if is_adult == "1" {
+                continue;
+            }
+            let original = value(original).filter(|original| *original != title);
+            let year = value(start_year).and_then(|year| year.parse::<i32>().ok());
+            insert.execute(params![
+                tconst,
+                title,
+                original,
+                matching::simplify(title),
+                original.map(matching::simplify),
+                year,
+                media_type_key(media_type),
+            ])?;
+            count += 1;
+        }
+    }
+    tx.execute("INSERT OR REPLACE INTO meta (key, value) VALUES ('dataset_modified', ?1)", params![modified])?;
+    tx.commit()?;
+    Ok(count)
+}
+
+fn to_result(row: &rusqlite::Row, media_type: MediaType) -> rusqlite::Result<MetadataResult> {
+    Ok(MetadataResult {
+        ids: MediaIds { imdb: Some(row.get(0)?), ..Default::default() },
+        title: row.get(1)?,
+        year: row.get::<_, Option<i32>>(3)?.map(|year| year.to_string()),
+        media_type,
+        score: None,
+        anime: false,
+        enrichment: None,
+        original_title: row.get(2)?,
+        votes: None,
+    })
+}
+
+impl ImdbDatasetProvider {
+    pub fn new(config: ImdbDatasetConfig) -> Self {
+        Self { config, index: OnceCell::new() }
+    }
+
+    /// The index, built or opened on first use so runs that never reach this provider skip the work
+    async fn index(&self) -> Result<&Mutex<Connection>, AppError> {
+        self.index
+            .get_or_try_init(|| async {
+                let (dataset, index) = (self.config.dataset_path.clone(), self.config.index_path.clone());
+                tokio::task::spawn_blocking(move || open_index(&dataset, &index))
+                    .await
+                    .map_err(|e| AppError::MetadataError(format!("IMDB index build failed: {}", e)))?
+                    .map(Mutex::new)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for ImdbDatasetProvider {
+    fn name(&self) -> &'static str {
+        "IMDB dataset"
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    /// Finds titles whose primary or original title equals `title` once case and punctuation
+    /// are ignored; the matcher then weighs years and picks among remakes
+    async fn search(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        _year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        let key = matching::simplify(title);
+        let conn = self.index().await?.lock().expect("IMDB index mutex poisoned");
+        let mut query = conn.prepare_cached(
+            "SELECT tconst, title, original_title, year FROM titles
+             WHERE (title_key = ?1 OR original_key = ?1) AND media_type = ?2
+             LIMIT ?3",
+        )?;
+        let results = query
+            .query_map(params![key, media_type_key(media_type), MAX_RESULTS], |row| to_result(row, media_type))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(results)
+    }
+
+    async fn get_details(
+        &self,
+        id: &str,
+        media_type: MediaType,
+    ) -> Result<MetadataResult, AppError> {
+        let conn = self.index().await?.lock().expect("IMDB index mutex poisoned");
+        conn.query_row(
+            "SELECT tconst, title, original_title, year FROM titles WHERE tconst = ?1",
+            params![id],
+            |row| to_result(row, media_type),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::MetadataError(format!("{} is not in the IMDB dataset", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    const DATASET: &str = "tconst\ttitleType\tprimaryTitle\toriginalTitle\tisAdult\tstartYear\tendYear\truntimeMinutes\tgenres
+tt2543164\tmovie\tArrival\tArrival\t0\t2016\t\\N\t116\tDrama,Sci-Fi
+tt0386676\ttvSeries\tThe Office\tThe Office\t0\t2005\t2013\t22\tComedy
+tt0290978\ttvSeries\tThe Office\tThe Office\t0\t2001\t2003\t30\tComedy
+tt6468322\ttvSeries\tMoney Heist\tLa casa de papel\t0\t2017\t2021\t70\tAction
+tt0000001\ttvEpisode\tArrival\tArrival\t0\t2016\t\\N\t40\tDrama
+";
+
+    fn provider(dir: &Path, dataset_name: &str) -> ImdbDatasetProvider {
+        ImdbDatasetProvider::new(ImdbDatasetConfig {
+            dataset_path: dir.join(dataset_name),
+            index_path: dir.join("imdb-index.sqlite"),
+            enabled: true,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_gzipped_dataset_is_indexed_and_searched() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut gz = GzEncoder::new(File::create(dir.path().join("title.basics.tsv.gz")).unwrap(), Compression::fast());
+        gz.write_all(DATASET.as_bytes()).unwrap();
+        gz.finish().unwrap();
+        let imdb = provider(dir.path(), "title.basics.tsv.gz");
+
+        let arrival = imdb.search("arrival!", MediaType::Movie, None).await.unwrap();
+        assert_eq!(arrival.len(), 1);
+        assert_eq!(arrival[0].ids.imdb.as_deref(), Some("tt2543164"));
+        assert_eq!(arrival[0].year.as_deref(), Some("2016"));
+        assert!(imdb.search("Arrival", MediaType::Tv, None).await.unwrap().is_empty());
+        assert_eq!(imdb.search("The Office", MediaType::Tv, None).await.unwrap().len(), 2);
+
+        let heist = imdb.search("La Casa de Papel", MediaType::Tv, None).await.unwrap();
+        assert_eq!(heist[0].title, "Money Heist");
+        assert_eq!(heist[0].original_title.as_deref(), Some("La casa de papel"));
+        assert_eq!(imdb.get_details("tt6468322", MediaType::Tv).await.unwrap().title, "Money Heist");
+    }
+
+    #[tokio::test]
+    async fn test_index_outlives_the_dataset() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("title.basics.tsv"), DATASET).unwrap();
+        assert_eq!(provider(dir.path(), "title.basics.tsv").search("Arrival", MediaType::Movie, None).await.unwrap().len(), 1);
+
+        std::fs::remove_file(dir.path().join("title.basics.tsv")).unwrap();
+        assert_eq!(provider(dir.path(), "title.basics.tsv").search("Arrival", MediaType::Movie, None).await.unwrap().len(), 1);
+
+        let empty = tempfile::tempdir().unwrap();
+        let error = provider(empty.path(), "title.basics.tsv").search("Arrival", MediaType::Movie, None).await.unwrap_err();
+        assert!(error.to_string().contains(DATASET_URL));
+    }
+}
@@ -1,74 +1,69 @@
 use async_trait::async_trait;
 use reqwest::Client;
+use std::sync::Arc;
 use crate::{
+    auth::{self, TokenStore},
     config::MalConfig,
     error::AppError,
-    metadata::{MediaType, MetadataResult, MediaIds, MetadataProvider},
+    metadata::{MediaType, MetadataResult, MediaIds, MetadataProvider, RateLimiter, RetryPolicy},
+    metadata::response::{self, Checked, Entries},
 };
 
+const API_URL: &str = "https://api.myanimelist.net/v2";
+
+/// MAL returns only `id` and `title` unless asked for more
+const FIELDS: &str = "id,title,alternative_titles,start_date,start_season,media_type,nsfw,num_list_users";
+
 pub struct MalClient {
     client: Client,
     config: MalConfig,
-    access_token: Option<String>,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+    tokens: Arc<TokenStore>,
 }
 
 impl MalClient {
-    pub fn new(config: MalConfig) -> Self {
+    pub fn new(config: MalConfig, tokens: Arc<TokenStore>, limiter: Arc<RateLimiter>, retry: RetryPolicy, client: Client) -> Self {
         Self {
-            client: Client::new(),
+            client,
             config,
-            access_token: None,
+            tokens,
+            limiter,
+            retry,
         }
     }
 
-    async fn authenticate(&mut self) -> Result<(), AppError> {
-        let params = [
-            ("client_id", &self.config.client_id),
-            ("client_secret", &self.config.client_secret),
-            ("grant_type", &"client_credentials".to_string())
-        ];
-
-        let response = self.client
-            .post("https://myanimelist.net/v1/oauth2/token")
-            .form(&params)
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            let auth: MalAuthResponse = response.json().await?;
-            self.access_token = Some(auth.access_token);
-            Ok(())
-        } else {
-            Err(AppError::AuthError("MAL authentication failed".into()))
-        }
+    /// Uses the user's token from `login mal` when present, otherwise the public client ID header
+    async fn authorized(&self, request: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder, AppError> {
+        Ok(match self.tokens.access_token(auth::MAL).await? {
+            Some(token) => request.header("Authorization", format!("Bearer {}", token)),
+            None => request.header("X-MAL-CLIENT-ID", &self.config.client_id),
+        })
     }
 
     async fn search_internal(
-        &mut self,
+        &self,
         title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
     ) -> Result<Vec<MetadataResult>, AppError> {
-        if self.access_token.is_none() {
-            self.authenticate().await?;
-        }
-
-        let url = format!(
-            "https://api.myanimelist.net/v2/anime?q={}&limit=5&fields=id,title,start_date",
-            title
-        );
-
-        let response = self.client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.access_token.as_ref().unwrap()))
-            .send()
-            .await?;
+        let nsfw = if self.config.include_nsfw { "true" } else { "false" };
+        let request = self.authorized(self.client.get(format!("{}/anime", API_URL))).await?
+            .query(&[("q", title), ("limit", "10"), ("fields", FIELDS), ("nsfw", nsfw)]);
+        let response = self.retry.send(request, &self.limiter).await?;
 
         if response.status().is_success() {
-            let results: MalSearchResponse = response.json().await?;
-            Ok(results.data.into_iter().map(|item| item.into()).collect())
-        } else if response.status() == 401 {
-            // Token expired, retry with new auth
-            self.authenticate().await?;
-            Box::pin(self.search_internal(title)).await
+            let results: MalSearchResponse = response::json("MAL", response).await?;
+            let mut results: Vec<MetadataResult> = results.data
+                .into_valid("MAL")
+                .into_iter()
+                .map(|item| item.node)
+                .filter(|anime| self.config.include_nsfw || !anime.is_adult())
+                .map(MetadataResult::from)
+                .filter(|result| result.media_type == media_type)
+                .collect();
+            rank_by_season(&mut results, year);
+            Ok(results)
         } else {
             Err(AppError::MetadataError(format!(
                 "MAL API error: {}",
@@ -77,42 +72,14 @@ impl MalClient {
         }
     }
 
-    async fn get_details_internal(&mut self, mal_id: u32) -> Result<MetadataResult, AppError> {
-        if self.access_token.is_none() {
-            self.authenticate().await?;
-        }
-
-        let url = format!(
-            "https://api.myanimelist.net/v2/anime/{}?fields=id,title,start_date",
-            mal_id
-        );
-
-        let response = self.client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.access_token.as_ref().unwrap()))
-            .send()
-            .await?;
+    async fn get_details_internal(&self, mal_id: u32) -> Result<MetadataResult, AppError> {
+        let request = self.authorized(self.client.get(format!("{}/anime/{}", API_URL, mal_id))).await?
+            .query(&[("fields", FIELDS)]);
+        let response = self.retry.send(request, &self.limiter).await?;
 
         if response.status().is_success() {
-            let item: MalItemResponse = response.json().await?;
-            let year = item.start_date
-                .as_ref()
-                .and_then(|d| d.split('-').next())
-                .map(|y| y.to_string());
-
-            Ok(MetadataResult {
-                ids: MediaIds {
-                    mal: Some(item.id.to_string()),
-                    ..Default::default()
-                },
-                title: item.title,
-                year,
-                media_type: MediaType::Tv,
-            })
-        } else if response.status() == 401 {
-            // Token expired, retry with new auth
-            self.authenticate().await?;
-            Box::pin(self.get_details_internal(mal_id)).await
+            let anime: MalAnime = response::json("MAL", response).await?;
+            Ok(anime.into())
         } else {
             Err(AppError::MetadataError(format!(
                 "MAL API error: {}",
@@ -122,6 +89,15 @@ impl MalClient {
     }
 }
 
+/// Puts the entries that started airing in `year` first. MAL lists every season of a show as
+/// its own entry, so the year is what tells "Attack on Titan" (2013) from its 2017 season 2.
+fn rank_by_season(results: &mut [MetadataResult], year: Option<i32>) {
+    if let Some(year) = year {
+        let year = year.to_string();
+        results.sort_by_key(|result| result.year.as_deref() != Some(year.as_str()));
+    }
+}
+
 #[async_trait]
 impl MetadataProvider for MalClient {
     fn name(&self) -> &'static str {
@@ -132,81 +108,168 @@ impl MetadataProvider for MalClient {
         &self,
         title: &str,
         media_type: MediaType,
-        _year: Option<i32>,
+        year: Option<i32>,
     ) -> Result<Vec<MetadataResult>, AppError> {
-        if media_type != MediaType::Tv {
-            return Ok(vec![]); // MAL only supports anime
-        }
-
-        // Need mutable self for auth
-        let mut this = unsafe { std::ptr::read(self) };
-        let result = this.search_internal(title).await;
-        std::mem::forget(this);
-        result
+        // MAL only knows anime, so a hit here is anime by definition
+        self.search_internal(title, media_type, year).await
     }
 
     async fn get_details(
         &self,
         id: &str,
-        media_type: MediaType,
+        _media_type: MediaType,
     ) -> Result<MetadataResult, AppError> {
-        if media_type != MediaType::Tv {
-            return Err(AppError::MetadataError("MAL only supports anime".into()));
-        }
-
         let mal_id = id.parse::<u32>()?;
-        // Need mutable self for auth
-        let mut this = unsafe { std::ptr::read(self) };
-        let result = this.get_details_internal(mal_id).await;
-        std::mem::forget(this);
-        result
+        self.get_details_internal(mal_id).await
     }
 }
 
 #[derive(serde::Deserialize)]
-struct MalAuthResponse {
-    access_token: String,
+struct MalSearchResponse {
+    data: Entries<MalItem>,
 }
 
-#[derive(serde::Deserialize)]
-struct MalSearchResponse {
-    data: Vec<MalItem>,
+impl Checked for MalItem {
+    fn check(&self) -> Result<(), String> {
+        if self.node.title.is_empty() {
+            return Err(format!("anime {} has an empty `title`", self.node.id));
+        }
+        Ok(())
+    }
 }
 
 #[derive(serde::Deserialize)]
 struct MalItem {
-    node: MalItemDetails,
+    node: MalAnime,
 }
 
+/// An anime as both the search and the details endpoint return it, with the `FIELDS` requested
 #[derive(serde::Deserialize)]
-struct MalItemDetails {
+struct MalAnime {
     id: u32,
     title: String,
+    #[serde(default)]
+    alternative_titles: MalAlternativeTitles,
     start_date: Option<String>,
+    start_season: Option<MalSeason>,
+    /// "tv", "movie", "ova", "ona", "special", ...
+    #[serde(default)]
+    media_type: Option<String>,
+    /// "white" (safe), "gray" (may be unsafe) or "black" (adult)
+    #[serde(default)]
+    nsfw: Option<String>,
+    /// Members who have the anime on their list
+    #[serde(default)]
+    num_list_users: Option<u64>,
 }
 
 #[derive(serde::Deserialize)]
-struct MalItemResponse {
-    id: u32,
-    title: String,
-    start_date: Option<String>,
+struct MalSeason {
+    year: i32,
 }
 
-impl From<MalItem> for MetadataResult {
-    fn from(item: MalItem) -> Self {
-        let year = item.node.start_date
+impl MalAnime {
+    fn is_adult(&self) -> bool {
+        self.nsfw.as_deref() == Some("black")
+    }
+
+    /// The year of the broadcast season, which MAL fills in more often than the exact start date
+    fn year(&self) -> Option<String> {
+        self.start_season
             .as_ref()
-            .and_then(|d| d.split('-').next())
-            .map(|y| y.to_string());
+            .map(|season| season.year.to_string())
+            .or_else(|| self.start_date.as_ref().and_then(|d| d.split('-').next()).map(|y| y.to_string()))
+    }
+}
+
+#[derive(serde::Deserialize, Default)]
+struct MalAlternativeTitles {
+    #[serde(default)]
+    en: Option<String>,
+}
+
+impl MalAlternativeTitles {
+    /// MAL's main title is romaji; Prime Video usually lists the English one, so that leads
+    /// and the romaji title is kept as the original
+    fn split_title(self, romaji: String) -> (String, Option<String>) {
+        match self.en.filter(|en| !en.is_empty() && !en.eq_ignore_ascii_case(&romaji)) {
+            Some(en) => (en, Some(romaji)),
+            None => (romaji, None),
+        }
+    }
+}
+
+impl From<MalAnime> for MetadataResult {
+    fn from(anime: MalAnime) -> Self {
+        let year = anime.year();
+        let votes = anime.num_list_users;
+        let media_type = match anime.media_type.as_deref() {
+            Some("movie") => MediaType::Movie,
+            _ => MediaType::Tv,
+        };
+        let (title, original_title) = anime.alternative_titles.split_title(anime.title);
 
         MetadataResult {
             ids: MediaIds {
-                mal: Some(item.node.id.to_string()),
+                mal: Some(anime.id.to_string()),
                 ..Default::default()
             },
-            title: item.node.title,
+            title,
             year,
-            media_type: MediaType::Tv,
+            media_type,
+            score: None,
+            anime: true,
+            enrichment: None,
+            original_title,
+            votes,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_english_title_leads_with_romaji_as_original() {
+        let json = r#"{"data": [
+            {"node": {"id": 52991, "title": "Sousou no Frieren", "start_date": "2023-09-29", "media_type": "tv",
+                      "alternative_titles": {"synonyms": [], "en": "Frieren: Beyond Journey's End", "ja": "葬送のフリーレン"}}},
+            {"node": {"id": 1, "title": "Akira", "start_date": "1988-07-16", "media_type": "movie",
+                      "alternative_titles": {"synonyms": [], "en": "Akira", "ja": "アキラ"}}}
+        ]}"#;
+        let response: MalSearchResponse = serde_json::from_str(json).unwrap();
+        let results: Vec<MetadataResult> = response.data.into_valid("MAL").into_iter().map(|item| item.node.into()).collect();
+
+        assert_eq!(results[0].title, "Frieren: Beyond Journey's End");
+        assert_eq!(results[0].original_title.as_deref(), Some("Sousou no Frieren"));
+        assert_eq!(results[0].year.as_deref(), Some("2023"));
+        assert_eq!(results[1].title, "Akira");
+        assert_eq!(results[1].original_title, None);
+        assert_eq!(results[1].media_type, MediaType::Movie);
+    }
+
+    #[test]
+    fn test_season_year_ranks_and_adult_flag() {
+        let json = r#"{"data": [
+            {"node": {"id": 16498, "title": "Shingeki no Kyojin", "media_type": "tv", "nsfw": "white",
+                      "start_date": "2013-04-07", "start_season": {"year": 2013, "season": "spring"}}},
+            {"node": {"id": 25777, "title": "Shingeki no Kyojin Season 2", "media_type": "tv", "nsfw": "white",
+                      "start_season": {"year": 2017, "season": "spring"}}},
+            {"node": {"id": 99, "title": "Adult Title", "media_type": "ova", "nsfw": "black"}}
+        ]}"#;
+        let response: MalSearchResponse = serde_json::from_str(json).unwrap();
+        let anime: Vec<MalAnime> = response.data.into_valid("MAL").into_iter().map(|item| item.node).collect();
+        assert!(!anime[0].is_adult());
+        assert!(anime[2].is_adult());
+
+        let mut results: Vec<MetadataResult> = anime.into_iter().take(2).map(MetadataResult::from).collect();
+        // The season's year stands in for a missing start date
+        assert_eq!(results[1].year.as_deref(), Some("2017"));
+
+        rank_by_season(&mut results, Some(2017));
+        assert_eq!(results[0].ids.mal.as_deref(), Some("25777"));
+        rank_by_season(&mut results, None);
+        assert_eq!(results[0].ids.mal.as_deref(), Some("25777"));
+    }
+}
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::Arc;
+use crate::{
+    error::AppError,
+    matching,
+    models::MediaType,
+    metadata::{MetadataResult, MetadataProvider},
+};
+
+/// Fixtures at least this similar to the query are returned, so unrelated titles stay unmatched
+const MIN_SIMILARITY: f64 = 0.5;
+
+/// Answers searches from canned results, for running the pipeline without network or API keys
+#[derive(Clone)]
+pub struct MockProvider {
+    fixtures: Arc<Vec<MetadataResult>>,
+}
+
+impl MockProvider {
+    pub fn new(fixtures: Vec<MetadataResult>) -> Self {
+        Self { fixtures: Arc::new(fixtures) }
+    }
+
+    /// Reads a JSON array of results shaped like the cache entries
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let fixtures = serde_json::from_reader(std::fs::File::open(path)?)?;
+        Ok(Self::new(fixtures))
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        "Mock"
+    }
+
+    async fn search(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        _year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        Ok(self
+            .fixtures
+            .iter()
+            .filter(|fixture| fixture.media_type == media_type)
+            .filter(|fixture| {
+                std::iter::once(&fixture.title)
+                    .chain(&fixture.original_title)
+                    .any(|t| matching::title_similarity(title, t) >= MIN_SIMILARITY)
+            })
+            .cloned()
+            .collect())
+    }
+
+    /// Finds the fixture carrying `id` under any provider
+    async fn get_details(
+        &self,
+        id: &str,
+        media_type: MediaType,
+    ) -> Result<MetadataResult, AppError> {
+        self.fixtures
+            .iter()
+            .find(|fixture| {
+                let ids = &fixture.ids;
+                fixture.media_type == media_type
+                    && [&ids.simkl, &ids.tmdb, &ids.tvdb, &ids.imdb, &ids.mal, &ids.anilist, &ids.anidb]
+                        .iter()
+                        .any(|known| known.as_deref() == Some(id))
+            })
+            .cloned()
+            .ok_or_else(|| AppError::MetadataError(format!("No mock fixture with ID {}", id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_filters_by_type_and_similarity() {
+        let json = r#"[
+            {"ids": {"tmdb": "438631", "imdb": "tt1160419"}, "title": "Dune", "year": "2021", "media_type": "movie"},
+            {"ids": {"simkl": "1"}, "title": "Dune: Prophecy", "year": "2024", "media_type": "tv"},
+            {"ids": {"tmdb": "1"}, "title": "Arrival", "year": "2016", "media_type": "movie"}
+        ]"#;
+        let mock = MockProvider::new(serde_json::from_str(json).unwrap());
+
+        let results = mock.search("dune", MediaType::Movie, None).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].ids.tmdb.as_deref(), Some("438631"));
+        assert!(mock.search("Reacher", MediaType::Movie, None).await.unwrap().is_empty());
+
+        let details = mock.get_details("tt1160419", MediaType::Movie).await.unwrap();
+        assert_eq!(details.title, "Dune");
+        assert!(mock.get_details("tt1160419", MediaType::Tv).await.is_err());
+    }
+}
@@ -2,8 +2,24 @@ pub mod simkl;
 pub mod tmdb;
 pub mod tvdb;
 pub mod mal;
+pub mod trakt;
+pub mod omdb;
+pub mod tvmaze;
+pub mod wikidata;
+pub mod mock;
+pub mod command;
+pub mod imdb_dataset;
+pub mod tmdb_export;
 
 pub use simkl::SimklClient;
 pub use tmdb::TmdbClient;
 pub use tvdb::TvdbClient;
-pub use mal::MalClient;
\ No newline at end of file
+pub use mal::MalClient;
+pub use trakt::TraktClient;
+pub use omdb::OmdbClient;
+pub use tvmaze::TvmazeClient;
+pub use wikidata::WikidataClient;
+pub use mock::MockProvider;
+pub use command::CommandProvider;
+pub use imdb_dataset::ImdbDatasetProvider;
+pub use tmdb_export::TmdbExportProvider;
\ No newline at end of file
@@ -0,0 +1,195 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use std::sync::Arc;
+use crate::{
+    config::OmdbConfig,
+    error::AppError,
+    models::MediaType,
+    metadata::{MetadataResult, MediaIds, MetadataProvider, RateLimiter, RetryPolicy},
+    metadata::response::{self, Checked, Entries},
+};
+
+const API_URL: &str = "https://www.omdbapi.com/";
+
+pub struct OmdbClient {
+    client: Client,
+    config: OmdbConfig,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+}
+
+impl OmdbClient {
+    pub fn new(config: OmdbConfig, limiter: Arc<RateLimiter>, retry: RetryPolicy, client: Client) -> Self {
+        Self {
+            client,
+            config,
+            limiter,
+            retry,
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, query: &[(&str, String)]) -> Result<T, AppError> {
+        if self.config.api_key.is_empty() {
+            return Err(AppError::AuthError("OMDb API key is not configured".into()));
+        }
+
+        let request = self.client
+            .get(API_URL)
+            .query(&[("apikey", self.config.api_key.as_str())])
+            .query(query);
+        let response = self.retry.send(request, &self.limiter).await?;
+
+        if response.status().is_success() {
+            response::json("OMDb", response).await
+        } else {
+            Err(AppError::MetadataError(format!(
+                "OMDb API error: {}",
+                response.status()
+            )))
+        }
+    }
+
+    async fn search_internal(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        let mut query = vec![("s", title.to_string()), ("type", type_param(media_type).to_string())];
+        if let Some(y) = year {
+            query.push(("y", y.to_string()));
+        }
+
+        let response: OmdbSearchResponse = self.get(&query).await?;
+        response.into_results()
+    }
+
+    async fn get_details_internal(&self, imdb_id: &str) -> Result<MetadataResult, AppError> {
+        let item: OmdbItem = self.get(&[("i", imdb_id.to_string())]).await?;
+        Ok(item.into())
+    }
+}
+
+fn type_param(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Movie => "movie",
+        MediaType::Tv => "series",
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for OmdbClient {
+    fn name(&self) -> &'static str {
+        "OMDb"
+    }
+
+    async fn search(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        self.search_internal(title, media_type, year).await
+    }
+
+    async fn get_details(
+        &self,
+        id: &str,
+        _media_type: MediaType,
+    ) -> Result<MetadataResult, AppError> {
+        self.get_details_internal(id).await
+    }
+}
+
+/// OMDb answers 200 with `"Response": "False"` for both "no results" and real errors
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct OmdbSearchResponse {
+    #[serde(default)]
+    search: Entries<OmdbItem>,
+    response: String,
+    error: Option<String>,
+}
+
+impl OmdbSearchResponse {
+    fn into_results(self) -> Result<Vec<MetadataResult>, AppError> {
+        match self.error {
+            Some(error) if self.response == "False" && !error.contains("not found") => {
+                Err(AppError::MetadataError(format!("OMDb API error: {}", error)))
+            }
+            _ => Ok(self.search.into_valid("OMDb").into_iter().map(MetadataResult::from).collect()),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct OmdbItem {
+    #[serde(rename = "Title")]
+    title: String,
+    /// "1999", or "2005–2013" for series
+    #[serde(rename = "Year")]
+    year: Option<String>,
+    #[serde(rename = "imdbID")]
+    imdb_id: String,
+    #[serde(rename = "Type")]
+    kind: Option<String>,
+}
+
+impl Checked for OmdbItem {
+    fn check(&self) -> Result<(), String> {
+        if !self.imdb_id.starts_with("tt") {
+            return Err(format!("`imdbID` {:?} of '{}' is not an IMDB ID", self.imdb_id, self.title));
+        }
+        Ok(())
+    }
+}
+
+impl From<OmdbItem> for MetadataResult {
+    fn from(item: OmdbItem) -> Self {
+        MetadataResult {
+            ids: MediaIds {
+                imdb: Some(item.imdb_id),
+                ..Default::default()
+            },
+            title: item.title,
+            year: item.year.as_deref().and_then(|y| y.get(..4)).map(String::from),
+            media_type: match item.kind.as_deref() {
+                Some("series") => MediaType::Tv,
+                _ => MediaType::Movie,
+            },
+            score: None,
+            anime: false,
+            enrichment: None,
+            original_title: None,
+            votes: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_response_conversion() {
+        let json = r#"{"Search": [{"Title": "The Office", "Year": "2005–2013", "imdbID": "tt0386676", "Type": "series"}],
+            "totalResults": "1", "Response": "True"}"#;
+        let response: OmdbSearchResponse = serde_json::from_str(json).unwrap();
+        let results = response.into_results().unwrap();
+
+        assert_eq!(results[0].ids.imdb.as_deref(), Some("tt0386676"));
+        assert_eq!(results[0].year.as_deref(), Some("2005"));
+        assert_eq!(results[0].media_type, MediaType::Tv);
+    }
+
+    #[test]
+    fn test_not_found_is_empty_but_errors_fail() {
+        let not_found: OmdbSearchResponse =
+            serde_json::from_str(r#"{"Response": "False", "Error": "Movie not found!"}"#).unwrap();
+        assert!(not_found.into_results().unwrap().is_empty());
+
+        let bad_key: OmdbSearchResponse =
+            serde_json::from_str(r#"{"Response": "False", "Error": "Invalid API key!"}"#).unwrap();
+        assert!(bad_key.into_results().is_err());
+    }
+}
@@ -1,25 +1,43 @@
 use async_trait::async_trait;
 use reqwest::Client;
+use std::sync::Arc;
 use crate::{
+    auth::{self, TokenStore},
     config::SimklConfig,
     error::AppError,
     models::MediaType,
-    metadata::{MetadataResult, MediaIds, MetadataProvider},
+    metadata::{MetadataResult, MediaIds, MetadataProvider, RateLimiter, RetryPolicy},
+    metadata::response::{self, Checked, Entries},
 };
 
 pub struct SimklClient {
     client: Client,
     config: SimklConfig,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+    tokens: Arc<TokenStore>,
 }
 
 impl SimklClient {
-    pub fn new(config: SimklConfig) -> Self {
+    pub fn new(config: SimklConfig, tokens: Arc<TokenStore>, limiter: Arc<RateLimiter>, retry: RetryPolicy, client: Client) -> Self {
         Self {
-            client: Client::new(),
+            client,
             config,
+            tokens,
+            limiter,
+            retry,
         }
     }
 
+    /// Adds the app key and, after `login simkl`, the user's token for account context
+    async fn authorized(&self, request: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder, AppError> {
+        let request = request.header("simkl-api-key", &self.config.client_id);
+        Ok(match self.tokens.access_token(auth::SIMKL).await? {
+            Some(token) => request.header("Authorization", format!("Bearer {}", token)),
+            None => request,
+        })
+    }
+
     async fn search_internal(
         &self,
         title: &str,
@@ -40,17 +58,38 @@ impl SimklClient {
             query.push(("year".to_string(), y.to_string()));
         }
 
-        let response = self.client
-            .get("https://api.simkl.com/search")
-            .header("Authorization", format!("Bearer {}", self.config.client_secret))
-            .header("simkl-api-key", &self.config.client_id)
-            .query(&query)
-            .send()
-            .await?;
+        let request = self.authorized(self.client.get("https://api.simkl.com/search")).await?
+            .query(&query);
+        let response = self.retry.send(request, &self.limiter).await?;
+
+        if response.status().is_success() {
+            let results: Entries<SimklSearchItem> = response::json("Simkl", response).await?;
+            Ok(results.into_valid("Simkl").into_iter().map(|item| item.into()).collect())
+        } else {
+            Err(AppError::MetadataError(format!(
+                "Simkl API error: {}",
+                response.status()
+            )))
+        }
+    }
+
+    /// Asks Simkl's filename parser, which shrugs off release tags, punctuation and word
+    /// order that make the regular search come back empty. It answers with one title at most.
+    async fn search_file(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        let request = self.authorized(self.client.post("https://api.simkl.com/search/file")).await?
+            .json(&serde_json::json!({ "file": file_name(title, media_type, year) }));
+        let response = self.retry.send(request, &self.limiter).await?;
 
         if response.status().is_success() {
-            let results: Vec<SimklSearchItem> = response.json().await?;
-            Ok(results.into_iter().map(|item| item.into()).collect())
+            // Unrecognized files come back as an empty array or object
+            let body: serde_json::Value = response::json("Simkl", response).await?;
+            let found = serde_json::from_value::<SimklFileMatch>(body).ok().and_then(|found| found.into_result(media_type));
+            Ok(found.into_iter().collect())
         } else {
             Err(AppError::MetadataError(format!(
                 "Simkl API error: {}",
@@ -75,15 +114,11 @@ impl SimklClient {
             simkl_id
         );
 
-        let response = self.client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.config.client_secret))
-            .header("simkl-api-key", &self.config.client_id)
-            .send()
-            .await?;
+        let request = self.authorized(self.client.get(&url)).await?;
+        let response = self.retry.send(request, &self.limiter).await?;
 
         if response.status().is_success() {
-            let details: SimklDetailsResponse = response.json().await?;
+            let details: SimklDetailsResponse = response::json("Simkl", response).await?;
             Ok(details.into())
         } else {
             Err(AppError::MetadataError(format!(
@@ -106,7 +141,12 @@ impl MetadataProvider for SimklClient {
         media_type: MediaType,
         year: Option<i32>,
     ) -> Result<Vec<MetadataResult>, AppError> {
-        self.search_internal(title, media_type, year).await
+        let results = self.search_internal(title, media_type, year).await?;
+        if !results.is_empty() {
+            return Ok(results);
+        }
+        tracing::debug!("Simkl search found nothing for '{}'; trying its file search", title);
+        self.search_file(title, media_type, year).await
     }
 
     async fn get_details(
@@ -130,6 +170,64 @@ struct SimklIds {
     simkl: String,
     tmdb: Option<String>,
     tvdb: Option<String>,
+    #[serde(default)]
+    imdb: Option<String>,
+    #[serde(default)]
+    mal: Option<String>,
+    #[serde(default)]
+    anilist: Option<String>,
+    #[serde(default)]
+    anidb: Option<String>,
+}
+
+impl Checked for SimklSearchItem {
+    fn check(&self) -> Result<(), String> {
+        if self.title.is_empty() {
+            return Err(format!("{} has an empty `title`", self.ids.simkl));
+        }
+        Ok(())
+    }
+}
+
+/// A file name Simkl's parser reads as `title`: shows need an episode marker to be taken
+/// for shows, and a year in parentheses narrows movies down like a release name would
+fn file_name(title: &str, media_type: MediaType, year: Option<i32>) -> String {
+    match (media_type, year) {
+        (MediaType::Tv, _) => format!("{} S01E01.mkv", title),
+        (MediaType::Movie, Some(year)) => format!("{} ({}).mkv", title, year),
+        (MediaType::Movie, None) => format!("{}.mkv", title),
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SimklFileMatch {
+    movie: Option<SimklFileTitle>,
+    show: Option<SimklFileTitle>,
+}
+
+/// File search reports years as numbers, unlike the text search
+#[derive(serde::Deserialize)]
+struct SimklFileTitle {
+    title: String,
+    year: Option<u32>,
+    ids: SimklIds,
+}
+
+impl SimklFileMatch {
+    fn into_result(self, media_type: MediaType) -> Option<MetadataResult> {
+        let found = match media_type {
+            MediaType::Movie => self.movie,
+            MediaType::Tv => self.show,
+        }?;
+        let mut result: MetadataResult = SimklSearchItem {
+            title: found.title,
+            year: found.year.map(|year| year.to_string()),
+            ids: found.ids,
+        }
+        .into();
+        result.media_type = media_type;
+        Some(result)
+    }
 }
 
 #[derive(serde::Deserialize)]
@@ -146,11 +244,19 @@ impl From<SimklSearchItem> for MetadataResult {
                 simkl: Some(item.ids.simkl),
                 tmdb: item.ids.tmdb,
                 tvdb: item.ids.tvdb,
-                ..Default::default()
+                imdb: item.ids.imdb,
+                mal: item.ids.mal,
+                anilist: item.ids.anilist,
+                anidb: item.ids.anidb,
             },
             title: item.title,
             year: item.year,
             media_type: MediaType::Movie, // Will be overridden
+            score: None,
+            anime: false,
+            enrichment: None,
+            original_title: None,
+            votes: None,
         }
     }
 }
@@ -162,11 +268,19 @@ impl From<SimklDetailsResponse> for MetadataResult {
                 simkl: Some(details.ids.simkl),
                 tmdb: details.ids.tmdb,
                 tvdb: details.ids.tvdb,
-                ..Default::default()
+                imdb: details.ids.imdb,
+                mal: details.ids.mal,
+                anilist: details.ids.anilist,
+                anidb: details.ids.anidb,
             },
             title: details.title,
             year: details.year,
             media_type: MediaType::Movie, // Will be overridden
+            score: None,
+            anime: false,
+            enrichment: None,
+            original_title: None,
+            votes: None,
         }
     }
 }
@@ -184,6 +298,10 @@ mod tests {
                 simkl: "123".to_string(),
                 tmdb: Some("12345".to_string()),
                 tvdb: None,
+                imdb: None,
+                mal: None,
+                anilist: None,
+                anidb: None,
             },
         };
 
@@ -206,6 +324,10 @@ mod tests {
                 simkl: "456".to_string(),
                 tmdb: Some("12345".to_string()),
                 tvdb: Some("789".to_string()),
+                imdb: None,
+                mal: None,
+                anilist: None,
+                anidb: None,
             },
         };
 
@@ -228,6 +350,10 @@ mod tests {
                 simkl: "999".to_string(),
                 tmdb: None,
                 tvdb: None,
+                imdb: None,
+                mal: None,
+                anilist: None,
+                anidb: None,
             },
         };
 
@@ -246,12 +372,46 @@ mod tests {
         let config = SimklConfig {
             client_id: "test_client".to_string(),
             client_secret: "test_secret".to_string(),
+            enabled: true,
         };
 
-        let client = SimklClient::new(config);
+        let limiter = Arc::new(RateLimiter::new(&crate::metadata::RateLimit { calls: 10, per_seconds: 1 }));
+        let client = SimklClient::new(config, Arc::new(TokenStore::in_memory()), limiter, RetryPolicy::from_config(&Default::default()), Client::new());
 
         assert_eq!(client.name(), "Simkl");
         assert_eq!(client.config.client_id, "test_client");
         assert_eq!(client.config.client_secret, "test_secret");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_file_search_match_conversion() {
+        assert_eq!(file_name("Breaking Bad", MediaType::Tv, Some(2008)), "Breaking Bad S01E01.mkv");
+        assert_eq!(file_name("Arrival", MediaType::Movie, Some(2016)), "Arrival (2016).mkv");
+
+        let json = r#"{"type": "episode", "episode": {"title": "Pilot", "season": 1, "episode": 1},
+            "show": {"title": "Breaking Bad", "year": 2008, "ids": {"simkl": "11121", "slug": "breaking-bad", "tvdb": "81189"}}}"#;
+        let found: SimklFileMatch = serde_json::from_str(json).unwrap();
+        let result = found.into_result(MediaType::Tv).unwrap();
+        assert_eq!(result.title, "Breaking Bad");
+        assert_eq!(result.year.as_deref(), Some("2008"));
+        assert_eq!(result.ids.tvdb.as_deref(), Some("81189"));
+        assert_eq!(result.media_type, MediaType::Tv);
+
+        let found: SimklFileMatch = serde_json::from_str(json).unwrap();
+        assert!(found.into_result(MediaType::Movie).is_none());
+    }
+
+    #[test]
+    fn test_simkl_anime_ids() {
+        let json = r#"{"title": "Frieren", "year": "2023",
+            "ids": {"simkl": "2113476", "slug": "frieren", "tmdb": "209867", "tvdb": null,
+                    "mal": "52991", "anilist": "154587", "anidb": "17617"}}"#;
+        let item: SimklSearchItem = serde_json::from_str(json).unwrap();
+        let result: MetadataResult = item.into();
+
+        assert_eq!(result.ids.mal.as_deref(), Some("52991"));
+        assert_eq!(result.ids.anilist.as_deref(), Some("154587"));
+        assert_eq!(result.ids.anidb.as_deref(), Some("17617"));
+        assert_eq!(result.ids.imdb, None);
+    }
+}
@@ -1,29 +1,69 @@
 use async_trait::async_trait;
 use reqwest::Client;
+use std::sync::Arc;
 use crate::{
     config::TmdbConfig,
     error::AppError,
-    metadata::{MediaType, MetadataResult, MediaIds, MetadataProvider},
+    metadata::{Enrichment, EpisodeInfo, MediaType, MetadataResult, MediaIds, MetadataProvider, RateLimiter, RetryPolicy},
+    metadata::response::{self, Checked, Entries},
 };
 
+/// TMDB's "Animation" genre
+const ANIMATION_GENRE: i32 = 16;
+/// TMDB's "anime" keyword
+const ANIME_KEYWORD: i32 = 210024;
+/// Poster paths are relative to TMDB's image CDN; w500 suits dashboards without being huge
+const POSTER_BASE_URL: &str = "https://image.tmdb.org/t/p/w500";
+
+/// TMDB has no anime type; Japanese animation is the closest signal
+fn is_anime(original_language: Option<&str>, genre_ids: impl IntoIterator<Item = i32>) -> bool {
+    original_language == Some("ja") && genre_ids.into_iter().any(|id| id == ANIMATION_GENRE)
+}
+
+/// Keeps the original-language title only when it adds something to the localized one
+fn original_if_different(original: Option<String>, title: &str) -> Option<String> {
+    original.filter(|o| !o.is_empty() && !o.eq_ignore_ascii_case(title))
+}
+
 pub struct TmdbClient {
     client: Client,
     config: TmdbConfig,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
 }
 
 impl TmdbClient {
-    pub fn new(config: TmdbConfig) -> Self {
+    pub fn new(config: TmdbConfig, limiter: Arc<RateLimiter>, retry: RetryPolicy, client: Client) -> Self {
         Self {
-            client: Client::new(),
+            client,
             config,
+            limiter,
+            retry,
         }
     }
 
+    /// Authorized GET carrying the configured language
+    fn get(&self, url: &str) -> reqwest::RequestBuilder {
+        self.get_in(url, self.config.language.as_deref())
+    }
+
+    /// Authorized GET carrying `language` instead of the configured one
+    fn get_in(&self, url: &str, language: Option<&str>) -> reqwest::RequestBuilder {
+        let mut request = self.client
+            .get(url)
+            .header("Authorization", format!("Bearer {}", self.config.access_token));
+        if let Some(language) = language {
+            request = request.query(&[("language", language)]);
+        }
+        request
+    }
+
     async fn search_internal(
         &self,
         title: &str,
         media_type: MediaType,
         year: Option<i32>,
+        language: Option<&str>,
     ) -> Result<Vec<MetadataResult>, AppError> {
         let type_param = match media_type {
             MediaType::Movie => "movie",
@@ -38,19 +78,19 @@ impl TmdbClient {
         if let Some(y) = year {
             query.push(("year".to_string(), y.to_string()));
         }
+        // Only movie search understands region
+        if let (MediaType::Movie, Some(region)) = (media_type, &self.config.region) {
+            query.push(("region".to_string(), region.clone()));
+        }
 
         let url = format!("https://api.themoviedb.org/3/search/{}", type_param);
 
-        let response = self.client
-            .get(&url)
-            .query(&query)
-            .header("Authorization", format!("Bearer {}", self.config.access_token))
-            .send()
-            .await?;
+        let request = self.get_in(&url, language.or(self.config.language.as_deref())).query(&query);
+        let response = self.retry.send(request, &self.limiter).await?;
 
         if response.status().is_success() {
-            let results: TmdbSearchResponse = response.json().await?;
-            Ok(results.results.into_iter().map(|item| item.into()).collect())
+            let results: TmdbSearchResponse = response::json("TMDB", response).await?;
+            Ok(results.results.into_valid("TMDB").into_iter().map(|item| item.into()).collect())
         } else {
             Err(AppError::MetadataError(format!(
                 "TMDB API error: {}",
@@ -70,19 +110,16 @@ impl TmdbClient {
         };
 
         let url = format!(
-            "https://api.themoviedb.org/3/{}/{}?append_to_response=external_ids",
+            "https://api.themoviedb.org/3/{}/{}?append_to_response=external_ids,keywords",
             type_param,
             tmdb_id
         );
 
-        let response = self.client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.config.access_token))
-            .send()
-            .await?;
+        let request = self.get(&url);
+        let response = self.retry.send(request, &self.limiter).await?;
 
         if response.status().is_success() {
-            let details: TmdbDetailsResponse = response.json().await?;
+            let details = response::checked("TMDB", response::json::<TmdbDetailsResponse>("TMDB", response).await?)?;
             Ok(details.into())
         } else {
             Err(AppError::MetadataError(format!(
@@ -91,6 +128,85 @@ impl TmdbClient {
             )))
         }
     }
+
+    /// Maps an IMDB (`tt...`) or TVDB ID to TMDB's ID with `/find`
+    async fn find_tmdb_id(
+        &self,
+        external_id: &str,
+        source: &str,
+        media_type: MediaType,
+    ) -> Result<Option<String>, AppError> {
+        let url = format!("https://api.themoviedb.org/3/find/{}", external_id);
+        let request = self.get(&url).query(&[("external_source", source)]);
+        let response = self.retry.send(request, &self.limiter).await?;
+
+        if response.status().is_success() {
+            let found: TmdbFindResponse = response::json("TMDB", response).await?;
+            let results = match media_type {
+                MediaType::Movie => found.movie_results,
+                MediaType::Tv => found.tv_results,
+            };
+            Ok(results.first().map(|r| r.id.to_string()))
+        } else {
+            Err(AppError::MetadataError(format!(
+                "TMDB API error: {}",
+                response.status()
+            )))
+        }
+    }
+
+    async fn get_episode_internal(
+        &self,
+        tmdb_id: &str,
+        season: u32,
+        episode: u32,
+    ) -> Result<Option<EpisodeInfo>, AppError> {
+        let url = format!(
+            "https://api.themoviedb.org/3/tv/{}/season/{}/episode/{}",
+            tmdb_id, season, episode
+        );
+        let request = self.get(&url);
+        let response = self.retry.send(request, &self.limiter).await?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if response.status().is_success() {
+            let item: TmdbEpisode = response::json("TMDB", response).await?;
+            Ok(Some(item.into()))
+        } else {
+            Err(AppError::MetadataError(format!(
+                "TMDB API error: {}",
+                response.status()
+            )))
+        }
+    }
+
+    async fn complete_ids_internal(
+        &self,
+        ids: &MediaIds,
+        media_type: MediaType,
+    ) -> Result<Option<MediaIds>, AppError> {
+        // Known TMDB ID: its external IDs carry IMDB and TVDB
+        if let Some(tmdb) = &ids.tmdb {
+            if !missing_external_ids(ids, media_type) {
+                return Ok(None);
+            }
+            return Ok(Some(self.get_details_internal(tmdb, media_type).await?.ids));
+        }
+
+        let external = [("imdb_id", &ids.imdb), ("tvdb_id", &ids.tvdb)];
+        for (source, id) in external {
+            let Some(id) = id else { continue };
+            if let Some(tmdb) = self.find_tmdb_id(id, source, media_type).await? {
+                return Ok(Some(MediaIds {
+                    tmdb: Some(tmdb),
+                    ..Default::default()
+                }));
+            }
+        }
+        Ok(None)
+    }
 }
 
 #[async_trait]
@@ -105,7 +221,17 @@ impl MetadataProvider for TmdbClient {
         media_type: MediaType,
         year: Option<i32>,
     ) -> Result<Vec<MetadataResult>, AppError> {
-        self.search_internal(title, media_type, year).await
+        self.search_internal(title, media_type, year, None).await
+    }
+
+    async fn search_in(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+        language: Option<&'static str>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        self.search_internal(title, media_type, year, language).await
     }
 
     async fn get_details(
@@ -115,21 +241,108 @@ impl MetadataProvider for TmdbClient {
     ) -> Result<MetadataResult, AppError> {
         self.get_details_internal(id, media_type).await
     }
+
+    fn can_complete_ids(&self, ids: &MediaIds, media_type: MediaType) -> bool {
+        match ids.tmdb {
+            Some(_) => missing_external_ids(ids, media_type),
+            None => ids.imdb.is_some() || ids.tvdb.is_some(),
+        }
+    }
+
+    async fn complete_ids(
+        &self,
+        ids: &MediaIds,
+        media_type: MediaType,
+    ) -> Result<Option<MediaIds>, AppError> {
+        self.complete_ids_internal(ids, media_type).await
+    }
+
+    fn can_enrich(&self, ids: &MediaIds) -> bool {
+        ids.tmdb.is_some()
+    }
+
+    async fn enrich(
+        &self,
+        ids: &MediaIds,
+        media_type: MediaType,
+    ) -> Result<Option<Enrichment>, AppError> {
+        match &ids.tmdb {
+            Some(tmdb) => Ok(self.get_details_internal(tmdb, media_type).await?.enrichment),
+            None => Ok(None),
+        }
+    }
+
+    async fn get_episode(
+        &self,
+        show_ids: &MediaIds,
+        season: u32,
+        episode: u32,
+    ) -> Result<Option<EpisodeInfo>, AppError> {
+        match &show_ids.tmdb {
+            Some(tmdb_id) => self.get_episode_internal(tmdb_id, season, episode).await,
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct TmdbEpisode {
+    season_number: u32,
+    episode_number: u32,
+    name: Option<String>,
+    air_date: Option<String>,
+}
+
+impl From<TmdbEpisode> for EpisodeInfo {
+    fn from(item: TmdbEpisode) -> Self {
+        EpisodeInfo {
+            season: item.season_number,
+            episode: item.episode_number,
+            title: item.name.filter(|n| !n.is_empty()),
+            air_date: item.air_date.filter(|d| !d.is_empty()),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct TmdbFindResponse {
+    #[serde(default)]
+    movie_results: Vec<TmdbFindResult>,
+    #[serde(default)]
+    tv_results: Vec<TmdbFindResult>,
+}
+
+#[derive(serde::Deserialize)]
+struct TmdbFindResult {
+    id: i32,
 }
 
 #[derive(serde::Deserialize)]
 struct TmdbSearchResponse {
-    results: Vec<TmdbItem>,
+    results: Entries<TmdbItem>,
 }
 
 #[derive(serde::Deserialize)]
 struct TmdbItem {
     id: i32,
+    // Movies carry `title`, shows `name`
+    #[serde(default)]
     title: String,
+    #[serde(default)]
     name: String,
     release_date: Option<String>,
     first_air_date: Option<String>,
     media_type: Option<String>,
+    #[serde(default)]
+    original_title: Option<String>,
+    #[serde(default)]
+    original_name: Option<String>,
+    #[serde(default)]
+    original_language: Option<String>,
+    #[serde(default)]
+    genre_ids: Vec<i32>,
+    #[serde(default)]
+    vote_count: Option<u64>,
 }
 
 #[derive(serde::Deserialize)]
@@ -140,20 +353,108 @@ struct TmdbDetailsResponse {
     release_date: Option<String>,
     first_air_date: Option<String>,
     external_ids: TmdbExternalIds,
+    #[serde(default)]
+    original_title: Option<String>,
+    #[serde(default)]
+    original_name: Option<String>,
+    #[serde(default)]
+    original_language: Option<String>,
+    #[serde(default)]
+    genres: Vec<TmdbGenre>,
+    #[serde(default)]
+    keywords: TmdbKeywords,
+    #[serde(default)]
+    runtime: Option<u32>,
+    #[serde(default)]
+    episode_run_time: Vec<u32>,
+    #[serde(default)]
+    poster_path: Option<String>,
+    #[serde(default)]
+    overview: Option<String>,
+    #[serde(default)]
+    vote_count: Option<u64>,
+    /// Shows only
+    #[serde(default)]
+    seasons: Vec<TmdbSeason>,
+    #[serde(default)]
+    last_episode_to_air: Option<TmdbEpisodeNumber>,
+}
+
+#[derive(serde::Deserialize)]
+struct TmdbSeason {
+    season_number: u32,
+    #[serde(default)]
+    episode_count: u32,
+}
+
+#[derive(serde::Deserialize)]
+struct TmdbEpisodeNumber {
+    season_number: u32,
+    episode_number: u32,
+}
+
+impl TmdbDetailsResponse {
+    /// Episodes of the regular seasons up to the last one aired; `seasons` also counts
+    /// announced episodes of the running season, so that one is counted up to the last aired
+    fn aired_episodes(&self) -> Option<u32> {
+        let last = self.last_episode_to_air.as_ref().filter(|last| last.season_number > 0)?;
+        let earlier: u32 = self
+            .seasons
+            .iter()
+            .filter(|season| season.season_number > 0 && season.season_number < last.season_number)
+            .map(|season| season.episode_count)
+            .sum();
+        Some(earlier + last.episode_number)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct TmdbGenre {
+    id: i32,
+    #[serde(default)]
+    name: String,
+}
+
+/// Movies list keywords under `keywords`, shows under `results`
+#[derive(serde::Deserialize, Default)]
+struct TmdbKeywords {
+    #[serde(default, alias = "results")]
+    keywords: Vec<TmdbGenre>,
 }
 
 #[derive(serde::Deserialize)]
 struct TmdbExternalIds {
-    #[allow(dead_code)]
+    #[serde(default)]
     imdb_id: Option<String>,
+    #[serde(default)]
     tvdb_id: Option<i32>,
 }
 
+impl Checked for TmdbItem {
+    fn check(&self) -> Result<(), String> {
+        if self.title.is_empty() && self.name.is_empty() {
+            return Err("neither `title` nor `name` is set".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Checked for TmdbDetailsResponse {
+    fn check(&self) -> Result<(), String> {
+        if self.title.as_deref().unwrap_or_default().is_empty() && self.name.as_deref().unwrap_or_default().is_empty() {
+            return Err(format!("{} has neither `title` nor `name`", self.id));
+        }
+        Ok(())
+    }
+}
+
 impl From<TmdbItem> for MetadataResult {
     fn from(item: TmdbItem) -> Self {
         let title = if item.title.is_empty() { item.name } else { item.title };
+        let original_title = original_if_different(item.original_title.or(item.original_name), &title);
         let year = item.release_date.or(item.first_air_date)
             .and_then(|d| d.split('-').next().map(|s| s.to_string()));
+        let anime = is_anime(item.original_language.as_deref(), item.genre_ids);
 
         MetadataResult {
             ids: MediaIds {
@@ -167,21 +468,43 @@ impl From<TmdbItem> for MetadataResult {
                 Some("movie") => MediaType::Movie,
                 _ => MediaType::Movie, // Default to movie if unclear
             },
+            score: None,
+            anime,
+            enrichment: None,
+            original_title,
+            votes: item.vote_count,
         }
     }
 }
 
+/// Whether TMDB's external IDs would add IMDB, or TVDB for a show, to `ids`
+fn missing_external_ids(ids: &MediaIds, media_type: MediaType) -> bool {
+    ids.imdb.is_none() || (ids.tvdb.is_none() && media_type == MediaType::Tv)
+}
+
 impl From<TmdbDetailsResponse> for MetadataResult {
     fn from(details: TmdbDetailsResponse) -> Self {
         let has_title = details.title.is_some();
+        let aired_episodes = details.aired_episodes();
         let title = details.title.or(details.name).unwrap_or_default();
+        let original_title = original_if_different(details.original_title.or(details.original_name), &title);
         let year = details.release_date.or(details.first_air_date)
             .and_then(|d| d.split('-').next().map(|s| s.to_string()));
+        let anime = details.keywords.keywords.iter().any(|k| k.id == ANIME_KEYWORD)
+            || is_anime(details.original_language.as_deref(), details.genres.iter().map(|g| g.id));
+        let enrichment = Enrichment {
+            aired_episodes,
+            runtime: details.runtime.or_else(|| details.episode_run_time.first().copied()).filter(|m| *m > 0),
+            poster_url: details.poster_path.map(|path| format!("{}{}", POSTER_BASE_URL, path)),
+            overview: details.overview.filter(|o| !o.is_empty()),
+            genres: details.genres.into_iter().map(|g| g.name).collect(),
+        };
 
         MetadataResult {
             ids: MediaIds {
                 tmdb: Some(details.id.to_string()),
                 tvdb: details.external_ids.tvdb_id.map(|id| id.to_string()),
+                imdb: details.external_ids.imdb_id.filter(|id| !id.is_empty()),
                 ..Default::default()
             },
             title,
@@ -191,6 +514,11 @@ impl From<TmdbDetailsResponse> for MetadataResult {
             } else {
                 MediaType::Tv
             },
+            score: None,
+            anime,
+            enrichment: Some(enrichment),
+            original_title,
+            votes: details.vote_count,
         }
     }
 }
@@ -208,6 +536,11 @@ mod tests {
             release_date: Some("2010-07-16".to_string()),
             first_air_date: None,
             media_type: Some("movie".to_string()),
+            original_title: None,
+            original_name: None,
+            original_language: Some("en".to_string()),
+            genre_ids: vec![28, 878],
+            vote_count: Some(38_000),
         };
 
         let result: MetadataResult = item.into();
@@ -216,6 +549,7 @@ mod tests {
         assert_eq!(result.ids.tmdb, Some("123".to_string()));
         assert_eq!(result.year, Some("2010".to_string()));
         assert_eq!(result.media_type, crate::models::MediaType::Movie);
+        assert_eq!(result.votes, Some(38_000));
     }
 
     #[test]
@@ -227,6 +561,11 @@ mod tests {
             release_date: None,
             first_air_date: Some("2008-01-20".to_string()),
             media_type: Some("tv".to_string()),
+            original_title: None,
+            original_name: None,
+            original_language: None,
+            genre_ids: vec![],
+            vote_count: None,
         };
 
         let result: MetadataResult = item.into();
@@ -249,6 +588,18 @@ mod tests {
                 imdb_id: Some("tt1375666".to_string()),
                 tvdb_id: Some(12345),
             },
+            original_title: None,
+            original_name: None,
+            original_language: None,
+            genres: vec![],
+            keywords: TmdbKeywords::default(),
+            runtime: None,
+            episode_run_time: vec![],
+            poster_path: None,
+            overview: None,
+            vote_count: None,
+            seasons: vec![],
+            last_episode_to_air: None,
         };
 
         let result: MetadataResult = details.into();
@@ -256,6 +607,7 @@ mod tests {
         assert_eq!(result.title, "Inception");
         assert_eq!(result.ids.tmdb, Some("123".to_string()));
         assert_eq!(result.ids.tvdb, Some("12345".to_string()));
+        assert_eq!(result.ids.imdb.as_deref(), Some("tt1375666"));
         assert_eq!(result.year, Some("2010".to_string()));
         assert_eq!(result.media_type, MediaType::Movie);
     }
@@ -272,6 +624,18 @@ mod tests {
                 imdb_id: Some("tt0903747".to_string()),
                 tvdb_id: Some(12345),
             },
+            original_title: None,
+            original_name: None,
+            original_language: None,
+            genres: vec![],
+            keywords: TmdbKeywords::default(),
+            runtime: None,
+            episode_run_time: vec![],
+            poster_path: None,
+            overview: None,
+            vote_count: None,
+            seasons: vec![],
+            last_episode_to_air: None,
         };
 
         let result: MetadataResult = details.into();
@@ -283,13 +647,106 @@ mod tests {
         assert_eq!(result.media_type, MediaType::Tv);
     }
 
+    #[test]
+    fn test_original_title_kept_when_different() {
+        let json = r#"{"id": 71446, "name": "Money Heist", "original_name": "La casa de papel",
+            "first_air_date": "2017-05-02", "media_type": "tv"}"#;
+        let item: TmdbItem = serde_json::from_str(json).unwrap();
+        assert_eq!(MetadataResult::from(item).original_title.as_deref(), Some("La casa de papel"));
+
+        let json = r#"{"id": 1, "title": "Up", "original_title": "Up", "media_type": "movie"}"#;
+        let item: TmdbItem = serde_json::from_str(json).unwrap();
+        assert_eq!(MetadataResult::from(item).original_title, None);
+    }
+
+    #[test]
+    fn test_details_enrichment() {
+        let json = r#"{"id": 1396, "name": "Breaking Bad", "first_air_date": "2008-01-20",
+            "external_ids": {"imdb_id": "tt0903747", "tvdb_id": 81189},
+            "genres": [{"id": 18, "name": "Drama"}, {"id": 80, "name": "Crime"}],
+            "episode_run_time": [47, 45], "poster_path": "/ggFHVNu6YYI5L9pCfOacjizRGt.jpg",
+            "overview": "A chemistry teacher turns to crime.",
+            "seasons": [{"season_number": 0, "episode_count": 9}, {"season_number": 1, "episode_count": 7},
+                        {"season_number": 2, "episode_count": 13}, {"season_number": 3, "episode_count": 13}],
+            "last_episode_to_air": {"season_number": 3, "episode_number": 4}}"#;
+        let details: TmdbDetailsResponse = serde_json::from_str(json).unwrap();
+        let enrichment = MetadataResult::from(details).enrichment.unwrap();
+
+        assert_eq!(enrichment.genres, vec!["Drama", "Crime"]);
+        assert_eq!(enrichment.runtime, Some(47));
+        assert_eq!(
+            enrichment.poster_url.as_deref(),
+            Some("https://image.tmdb.org/t/p/w500/ggFHVNu6YYI5L9pCfOacjizRGt.jpg")
+        );
+        assert_eq!(enrichment.overview.as_deref(), Some("A chemistry teacher turns to crime."));
+        assert_eq!(enrichment.aired_episodes, Some(24));
+    }
+
+    #[test]
+    fn test_anime_detected_from_language_genre_and_keywords() {
+        let json = r#"{"id": 1, "title": "", "name": "Frieren", "first_air_date": "2023-09-29",
+            "media_type": "tv", "original_language": "ja", "genre_ids": [16, 10765]}"#;
+        let item: TmdbItem = serde_json::from_str(json).unwrap();
+        assert!(MetadataResult::from(item).anime);
+
+        let json = r#"{"id": 2, "name": "Vinland Saga", "first_air_date": "2019-07-08",
+            "external_ids": {"imdb_id": null, "tvdb_id": null},
+            "keywords": {"results": [{"id": 210024, "name": "anime"}]}}"#;
+        let details: TmdbDetailsResponse = serde_json::from_str(json).unwrap();
+        assert!(MetadataResult::from(details).anime);
+
+        let json = r#"{"id": 3, "title": "Up", "name": "", "media_type": "movie",
+            "original_language": "en", "genre_ids": [16]}"#;
+        let item: TmdbItem = serde_json::from_str(json).unwrap();
+        assert!(!MetadataResult::from(item).anime);
+    }
+
+    #[test]
+    fn test_find_response_parsing() {
+        let json = r#"{"movie_results": [], "person_results": [], "tv_results": [{"id": 1396, "name": "Breaking Bad"}],
+            "tv_episode_results": [], "tv_season_results": []}"#;
+        let found: TmdbFindResponse = serde_json::from_str(json).unwrap();
+        assert!(found.movie_results.is_empty());
+        assert_eq!(found.tv_results[0].id, 1396);
+    }
+
+    #[test]
+    fn test_episode_conversion() {
+        let json = r#"{"id": 62085, "season_number": 1, "episode_number": 2, "name": "Cat's in the Bag...",
+            "air_date": "2008-01-27"}"#;
+        let episode: EpisodeInfo = serde_json::from_str::<TmdbEpisode>(json).unwrap().into();
+        assert_eq!(episode.code(), "S01E02 - Cat's in the Bag...");
+        assert_eq!(episode.air_date.as_deref(), Some("2008-01-27"));
+    }
+
+    #[test]
+    fn test_requests_carry_language() {
+        let config = TmdbConfig {
+            access_token: "token".to_string(),
+            language: Some("it-IT".to_string()),
+            region: Some("IT".to_string()),
+            enabled: true,
+        };
+        let limiter = Arc::new(RateLimiter::new(&crate::metadata::RateLimit { calls: 10, per_seconds: 1 }));
+        let client = TmdbClient::new(config, limiter, RetryPolicy::from_config(&Default::default()), Client::new());
+
+        let request = client.get("https://api.themoviedb.org/3/tv/1?append_to_response=external_ids").build().unwrap();
+        assert_eq!(request.url().query(), Some("append_to_response=external_ids&language=it-IT"));
+        let detected = client.get_in("https://api.themoviedb.org/3/search/tv", Some("ja")).build().unwrap();
+        assert_eq!(detected.url().query(), Some("language=ja"));
+    }
+
     #[test]
     fn test_client_creation() {
         let config = TmdbConfig {
             access_token: "test_access_token".to_string(),
+            language: Some("de-DE".to_string()),
+            region: None,
+            enabled: true,
         };
 
-        let client = TmdbClient::new(config);
+        let limiter = Arc::new(RateLimiter::new(&crate::metadata::RateLimit { calls: 10, per_seconds: 1 }));
+        let client = TmdbClient::new(config, limiter, RetryPolicy::from_config(&Default::default()), Client::new());
 
         assert_eq!(client.name(), "TMDB");
         assert_eq!(client.config.access_token, "test_access_token");
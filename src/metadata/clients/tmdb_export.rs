@@ -0,0 +1,271 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use flate2::read::GzDecoder;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tokio::sync::OnceCell;
+use crate::{
+    config::TmdbExportConfig,
+    error::AppError,
+    matching,
+    models::MediaType,
+    metadata::{MediaIds, MetadataResult, MetadataProvider},
+};
+
+const EXPORTS_BASE_URL: &str = "https://files.tmdb.org/p/exports";
+
+/// Results returned per search, most popular first
+const MAX_RESULTS: usize = 20;
+
+/// One line of a daily export; movies carry `original_title`, shows `original_name`
+#[derive(Deserialize)]
+struct ExportEntry {
+    id: u64,
+    #[serde(alias = "original_name")]
+    original_title: String,
+    #[serde(default)]
+    popularity: f64,
+    #[serde(default)]
+    adult: bool,
+}
+
+fn media_type_key(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Movie => "movie",
+        MediaType::Tv => "tv",
+    }
+}
+
+/// File name prefix TMDB uses for each export
+fn export_kind(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Movie => "movie",
+        MediaType::Tv => "tv_series",
+    }
+}
+
+/// Downloads the movie and show ID exports published on `date` from `base_url`
+async fn download_from(base_url: &str, config: &TmdbExportConfig, date: NaiveDate) -> Result<(), AppError> {
+    let client = reqwest::Client::new();
+    for (media_type, path) in [(MediaType::Movie, &config.movies_path), (MediaType::Tv, &config.shows_path)] {
+        let url = format!("{}/{}_ids_{}.json.gz", base_url, export_kind(media_type), date.format("%m_%d_%Y"));
+        tracing::info!("Downloading {}", url);
+        let response = client.get(&url).send().await?.error_for_status()?;
+        let body = response.bytes().await?;
+        std::fs::write(path, &body)?;
+        println!("✅ Saved {} ({} KB)", path.display(), body.len() / 1024);
+    }
+    Ok(())
+}
+
+/// Downloads yesterday's exports; TMDB publishes each day's files during the following morning (UTC)
+pub async fn download(config: &TmdbExportConfig) -> Result<(), AppError> {
+    let yesterday = chrono::Utc::now().date_naive() - chrono::Duration::days(1);
+    download_from(EXPORTS_BASE_URL, config, yesterday).await
+}
+
+fn modified_secs(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+/// Opens the index, rebuilding it when either export file is newer than the index's sources.
+/// Without export files an existing index is used as-is.
+fn open_index(config: &TmdbExportConfig) -> Result<Connection, AppError> {
+    let mut conn = Connection::open(&config.index_path)?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS titles (
+            id         INTEGER NOT NULL,
+            media_type TEXT NOT NULL,
+            title      TEXT NOT NULL,
+            title_key  TEXT NOT NULL,
+            popularity REAL NOT NULL,
+            PRIMARY KEY (media_type, id)
+        );
+        CREATE INDEX IF NOT EXISTS titles_by_key ON titles (title_key, media_type);
+        CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value INTEGER NOT NULL);",
+    )?;
+
+    for (media_type, path) in [(MediaType::Movie, &config.movies_path), (MediaType::Tv, &config.shows_path)] {
+        let meta_key = format!("{}_modified", media_type_key(media_type));
+        let built_from: Option<i64> = conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", params![meta_key], |row| row.get(0))
+            .optional()?;
+        match modified_secs(path) {
+            Some(modified) if built_from.is_none_or(|built| modified > built) => {
+                tracing::info!("Indexing TMDB IDs from {}", path.display());
+                let count = import(&mut conn, path, media_type)?;
+                conn.execute("INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)", params![meta_key, modified])?;
+                tracing::info!("Indexed {} TMDB {} IDs", count, media_type_key(media_type));
+            }
+            Some(_) => {}
+            None if built_from.is_none() => tracing::warn!(
+                "TMDB export {} not found; run `fetch-tmdb-exports` to download it",
+                path.display()
+            ),
+            None => {}
+        }
+    }
+    Ok(conn)
+}
+
+fn import(conn: &mut Connection, path: &Path, media_type: MediaType) -> Result<usize, AppError> {
+    let file = File::open(path)?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    let kind = media_type_key(media_type);
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM titles WHERE media_type = ?1", params![kind])?;
+    let mut count = 0;
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR REPLACE INTO titles (id, media_type, title, title_key, popularity) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            // One JSON object per line; the odd malformed line is skipped rather than failing the import
+            let Ok(entry) = serde_json::from_str::<ExportEntry>(&line) else {
+                continue;
+            };
+            if entry.adult {
+                continue;
+            }
+            let key = matching::simplify(&entry.original_title);
+            insert.execute(params![entry.id as i64, kind, entry.original_title, key, entry.popularity])?;
+            count += 1;
+        }
+    }
+    tx.commit()?;
+    Ok(count)
+}
+
+/// Resolves titles to TMDB IDs from a local index of TMDB's daily ID exports, so a chain can
+/// try it before the live API and only spend requests on its misses
+pub struct TmdbExportProvider {
+    config: TmdbExportConfig,
+    index: OnceCell<Mutex<Connection>>,
+}
+
+impl TmdbExportProvider {
+    pub fn new(config: TmdbExportConfig) -> Self {
+        Self { config, index: OnceCell::new() }
+    }
+
+    async fn index(&self) -> Result<&Mutex<Connection>, AppError> {
+        self.index
+            .get_or_try_init(|| async {
+                let config = self.config.clone();
+                tokio::task::spawn_blocking(move || open_index(&config))
+                    .await
+                    .map_err(|e| AppError::MetadataError(format!("TMDB export index build failed: {}", e)))?
+                    .map(Mutex::new)
+            })
+            .await
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for TmdbExportProvider {
+    fn name(&self) -> &'static str {
+        "TMDB export"
+    }
+
+    fn is_local(&self) -> bool {
+        true
+    }
+
+    /// Exports only list original titles and no years, so this finds titles whose original
+    /// title equals `title` after ignoring case and punctuation, most popular first
+    async fn search(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        _year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        let key = matching::simplify(title);
+        let conn = self.index().await?.lock().expect("TMDB export index mutex poisoned");
+        let mut query = conn.prepare_cached(
+            "SELECT id, title FROM titles WHERE title_key = ?1 AND media_type = ?2
+             ORDER BY popularity DESC LIMIT ?3",
+        )?;
+        let results = query
+            .query_map(params![key, media_type_key(media_type), MAX_RESULTS], |row| {
+                Ok(MetadataResult {
+                    ids: MediaIds { tmdb: Some(row.get::<_, i64>(0)?.to_string()), ..Default::default() },
+                    title: row.get(1)?,
+                    year: None,
+                    media_type,
+                    score: None,
+                    anime: false,
+                    enrichment: None,
+                    original_title: None,
+                    votes: None,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVIES: &str = r#"{"adult":false,"id":438631,"original_title":"Dune","popularity":90.5,"video":false}
+{"adult":false,"id":841,"original_title":"Dune","popularity":20.1,"video":false}
+{"adult":true,"id":1,"original_title":"Dune","popularity":99.0,"video":false}
+not json
+"#;
+    const SHOWS: &str = r#"{"id":71446,"original_name":"La casa de papel","popularity":60.2}
+"#;
+
+    fn config(dir: &Path) -> TmdbExportConfig {
+        TmdbExportConfig {
+            movies_path: dir.join("movie_ids.json"),
+            shows_path: dir.join("tv_series_ids.json"),
+            index_path: dir.join("tmdb-index.sqlite"),
+            enabled: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exports_are_indexed_by_original_title() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(dir.path());
+        std::fs::write(&config.movies_path, MOVIES).unwrap();
+        std::fs::write(&config.shows_path, SHOWS).unwrap();
+        let exports = TmdbExportProvider::new(config);
+
+        let dune = exports.search("DUNE", MediaType::Movie, Some(2021)).await.unwrap();
+        let ids: Vec<_> = dune.iter().map(|r| r.ids.tmdb.as_deref().unwrap()).collect();
+        assert_eq!(ids, ["438631", "841"]);
+        let heist = exports.search("La Casa de Papel", MediaType::Tv, None).await.unwrap();
+        assert_eq!(heist[0].ids.tmdb.as_deref(), Some("71446"));
+        assert!(exports.search("Money Heist", MediaType::Tv, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_download_fetches_both_exports() {
+        let mut server = mockito::Server::new_async().await;
+        let movies = server.mock("GET", "/movie_ids_10_14_2026.json.gz").with_body("movies").create_async().await;
+        let shows = server.mock("GET", "/tv_series_ids_10_14_2026.json.gz").with_body("shows").create_async().await;
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(dir.path());
+
+        let date = NaiveDate::from_ymd_opt(2026, 10, 14).unwrap();
+        download_from(&server.url(), &config, date).await.unwrap();
+        movies.assert_async().await;
+        shows.assert_async().await;
+        assert_eq!(std::fs::read_to_string(&config.movies_path).unwrap(), "movies");
+        assert_eq!(std::fs::read_to_string(&config.shows_path).unwrap(), "shows");
+    }
+}
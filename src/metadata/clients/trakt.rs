@@ -0,0 +1,212 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use std::sync::Arc;
+use crate::{
+    config::TraktConfig,
+    error::AppError,
+    models::MediaType,
+    metadata::{MetadataResult, MediaIds, MetadataProvider, RateLimiter, RetryPolicy},
+    metadata::response::{self, Checked, Entries},
+};
+
+const API_URL: &str = "https://api.trakt.tv";
+
+pub struct TraktClient {
+    client: Client,
+    config: TraktConfig,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+}
+
+impl TraktClient {
+    pub fn new(config: TraktConfig, limiter: Arc<RateLimiter>, retry: RetryPolicy, client: Client) -> Self {
+        Self {
+            client,
+            config,
+            limiter,
+            retry,
+        }
+    }
+
+    /// Public Trakt endpoints only need the app's client ID
+    async fn get(&self, url: &str, query: &[(&str, String)]) -> Result<reqwest::Response, AppError> {
+        if self.config.client_id.is_empty() {
+            return Err(AppError::AuthError("Trakt client ID is not configured".into()));
+        }
+
+        let request = self.client
+            .get(url)
+            .query(query)
+            .header("trakt-api-version", "2")
+            .header("trakt-api-key", &self.config.client_id);
+        let response = self.retry.send(request, &self.limiter).await?;
+
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(AppError::MetadataError(format!(
+                "Trakt API error: {}",
+                response.status()
+            )))
+        }
+    }
+
+    async fn search_internal(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        let mut query = vec![("query", title.to_string()), ("limit", "10".to_string())];
+        if let Some(y) = year {
+            query.push(("years", y.to_string()));
+        }
+
+        let url = format!("{}/search/{}", API_URL, type_param(media_type));
+        let results: Entries<TraktSearchItem> = response::json("Trakt", self.get(&url, &query).await?).await?;
+        Ok(results.into_valid("Trakt").into_iter().filter_map(TraktSearchItem::into_result).collect())
+    }
+
+    /// Resolves a Trakt ID or slug, or an IMDB ID (`tt...`) through Trakt's ID lookup
+    async fn get_details_internal(
+        &self,
+        id: &str,
+        media_type: MediaType,
+    ) -> Result<MetadataResult, AppError> {
+        if id.starts_with("tt") {
+            let url = format!("{}/search/imdb/{}", API_URL, id);
+            let results: Entries<TraktSearchItem> =
+                response::json("Trakt", self.get(&url, &[("type", type_param(media_type).to_string())]).await?).await?;
+            return results
+                .into_valid("Trakt")
+                .into_iter()
+                .find_map(TraktSearchItem::into_result)
+                .ok_or_else(|| AppError::MetadataError(format!("Trakt has no entry for {}", id)));
+        }
+
+        let collection = match media_type {
+            MediaType::Movie => "movies",
+            MediaType::Tv => "shows",
+        };
+        let url = format!("{}/{}/{}", API_URL, collection, id);
+        let item: TraktMedia = response::json("Trakt", self.get(&url, &[]).await?).await?;
+        Ok(item.into_result(media_type))
+    }
+}
+
+fn type_param(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Movie => "movie",
+        MediaType::Tv => "show",
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for TraktClient {
+    fn name(&self) -> &'static str {
+        "Trakt"
+    }
+
+    async fn search(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        self.search_internal(title, media_type, year).await
+    }
+
+    async fn get_details(
+        &self,
+        id: &str,
+        media_type: MediaType,
+    ) -> Result<MetadataResult, AppError> {
+        self.get_details_internal(id, media_type).await
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct TraktSearchItem {
+    movie: Option<TraktMedia>,
+    show: Option<TraktMedia>,
+}
+
+#[derive(serde::Deserialize)]
+struct TraktMedia {
+    title: String,
+    year: Option<i32>,
+    ids: TraktIds,
+}
+
+#[derive(serde::Deserialize)]
+struct TraktIds {
+    imdb: Option<String>,
+    tmdb: Option<u64>,
+    tvdb: Option<u64>,
+}
+
+impl Checked for TraktSearchItem {
+    fn check(&self) -> Result<(), String> {
+        if self.movie.is_none() && self.show.is_none() {
+            return Err("neither `movie` nor `show` is set".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl TraktSearchItem {
+    fn into_result(self) -> Option<MetadataResult> {
+        match (self.movie, self.show) {
+            (Some(movie), _) => Some(movie.into_result(MediaType::Movie)),
+            (None, Some(show)) => Some(show.into_result(MediaType::Tv)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl TraktMedia {
+    fn into_result(self, media_type: MediaType) -> MetadataResult {
+        MetadataResult {
+            ids: MediaIds {
+                imdb: self.ids.imdb,
+                tmdb: self.ids.tmdb.map(|id| id.to_string()),
+                tvdb: self.ids.tvdb.map(|id| id.to_string()),
+                ..Default::default()
+            },
+            title: self.title,
+            year: self.year.map(|y| y.to_string()),
+            media_type,
+            score: None,
+            anime: false,
+            enrichment: None,
+            original_title: None,
+            votes: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_results_carry_all_ids() {
+        let json = r#"[
+            {"type": "show", "score": 1000, "show": {"title": "The Office", "year": 2005,
+             "ids": {"trakt": 1, "slug": "the-office", "imdb": "tt0386676", "tmdb": 2316, "tvdb": 73244}}},
+            {"type": "movie", "score": 50, "movie": {"title": "Office Space", "year": 1999,
+             "ids": {"trakt": 2, "slug": "office-space", "imdb": "tt0151804", "tmdb": 1542}}}
+        ]"#;
+        let items: Vec<TraktSearchItem> = serde_json::from_str(json).unwrap();
+        let results: Vec<MetadataResult> = items.into_iter().filter_map(TraktSearchItem::into_result).collect();
+
+        assert_eq!(results[0].title, "The Office");
+        assert_eq!(results[0].media_type, MediaType::Tv);
+        assert_eq!(results[0].ids.imdb.as_deref(), Some("tt0386676"));
+        assert_eq!(results[0].ids.tvdb.as_deref(), Some("73244"));
+        assert_eq!(results[1].media_type, MediaType::Movie);
+        assert_eq!(results[1].ids.tmdb.as_deref(), Some("1542"));
+        assert_eq!(results[1].ids.tvdb, None);
+        assert_eq!(results[1].year.as_deref(), Some("1999"));
+    }
+}
@@ -1,74 +1,73 @@
 use async_trait::async_trait;
 use reqwest::Client;
+use std::sync::Arc;
 use crate::{
+    auth::{self, TokenStore},
     config::TvdbConfig,
     error::AppError,
     models::MediaType,
-    metadata::{MetadataResult, MediaIds, MetadataProvider},
+    metadata::{MetadataResult, MediaIds, MetadataProvider, RateLimiter, RetryPolicy},
+    metadata::response::{self, Checked, Entries},
 };
 
 pub struct TvdbClient {
     client: Client,
     config: TvdbConfig,
-    access_token: Option<String>,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+    tokens: Arc<TokenStore>,
 }
 
 impl TvdbClient {
-    pub fn new(config: TvdbConfig) -> Self {
+    pub fn new(config: TvdbConfig, tokens: Arc<TokenStore>, limiter: Arc<RateLimiter>, retry: RetryPolicy, client: Client) -> Self {
         Self {
-            client: Client::new(),
+            client,
             config,
-            access_token: None,
+            tokens,
+            limiter,
+            retry,
         }
     }
 
-    async fn authenticate(&mut self) -> Result<(), AppError> {
-        let auth = serde_json::json!({
-            "apikey": self.config.api_key
-        });
-
-        let response = self.client
-            .post("https://api.thetvdb.com/login")
-            .json(&auth)
-            .send()
-            .await?;
+    /// Sends a GET with the current JWT, renewing it once if TVDB rejects it as expired
+    async fn get(&self, url: &str) -> Result<reqwest::Response, AppError> {
+        if self.config.api_key.is_empty() {
+            return Err(AppError::AuthError("TVDB API key is not configured".into()));
+        }
 
-        if response.status().is_success() {
-            let auth: TvdbAuthResponse = response.json().await?;
-            self.access_token = Some(auth.token);
-            Ok(())
-        } else {
-            Err(AppError::AuthError("TVDB authentication failed".into()))
+        let mut renewed = false;
+        loop {
+            let token = self.tokens.access_token(auth::TVDB).await?
+                .ok_or_else(|| AppError::AuthError("TVDB authentication failed".into()))?;
+
+            let request = self.client
+                .get(url)
+                .header("Authorization", format!("Bearer {}", token));
+            let response = self.retry.send(request, &self.limiter).await?;
+
+            if response.status() == 401 && !renewed {
+                self.tokens.invalidate(auth::TVDB).await?;
+                renewed = true;
+                continue;
+            }
+            return Ok(response);
         }
     }
 
     async fn search_internal(
-        &mut self,
+        &self,
         title: &str,
-        media_type: MediaType,
     ) -> Result<Vec<MetadataResult>, AppError> {
-        if self.access_token.is_none() {
-            self.authenticate().await?;
-        }
-
         let url = format!(
             "https://api.thetvdb.com/search/series?name={}",
             title
         );
 
-        let response = self.client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.access_token.as_ref().unwrap()))
-            .send()
-            .await?;
+        let response = self.get(&url).await?;
 
         if response.status().is_success() {
-            let results: TvdbSearchResponse = response.json().await?;
-            Ok(results.data.into_iter().map(|item| item.into()).collect())
-        } else if response.status() == 401 {
-            // Token expired, retry with new auth
-            self.authenticate().await?;
-            Box::pin(self.search_internal(title, media_type)).await
+            let results: TvdbSearchResponse = response::json("TVDB", response).await?;
+            Ok(results.data.into_valid("TVDB").into_iter().map(|item| item.into()).collect())
         } else {
             Err(AppError::MetadataError(format!(
                 "TVDB API error: {}",
@@ -78,32 +77,19 @@ impl TvdbClient {
     }
 
     async fn get_details_internal(
-        &mut self,
+        &self,
         tvdb_id: &str,
-        media_type: MediaType,
     ) -> Result<MetadataResult, AppError> {
-        if self.access_token.is_none() {
-            self.authenticate().await?;
-        }
-
         let url = format!(
             "https://api.thetvdb.com/series/{}",
             tvdb_id
         );
 
-        let response = self.client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.access_token.as_ref().unwrap()))
-            .send()
-            .await?;
+        let response = self.get(&url).await?;
 
         if response.status().is_success() {
-            let details: TvdbDetailsResponse = response.json().await?;
+            let details: TvdbDetailsResponse = response::json("TVDB", response).await?;
             Ok(details.data.into())
-        } else if response.status() == 401 {
-            // Token expired, retry with new auth
-            self.authenticate().await?;
-            Box::pin(self.get_details_internal(tvdb_id, media_type)).await
         } else {
             Err(AppError::MetadataError(format!(
                 "TVDB API error: {}",
@@ -122,37 +108,26 @@ impl MetadataProvider for TvdbClient {
     async fn search(
         &self,
         title: &str,
-        media_type: MediaType,
+        _media_type: MediaType,
         _year: Option<i32>,
     ) -> Result<Vec<MetadataResult>, AppError> {
-        // Need mutable self for auth
-        let mut this = unsafe { std::ptr::read(self) };
-        let result = this.search_internal(title, media_type).await;
-        std::mem::forget(this);
-        result
+        self.search_internal(title).await
     }
 
     async fn get_details(
         &self,
         id: &str,
-        media_type: MediaType,
+        _media_type: MediaType,
     ) -> Result<MetadataResult, AppError> {
-        // Need mutable self for auth
-        let mut this = unsafe { std::ptr::read(self) };
-        let result = this.get_details_internal(id, media_type).await;
-        std::mem::forget(this);
-        result
+        self.get_details_internal(id).await
     }
 }
 
-#[derive(serde::Deserialize)]
-struct TvdbAuthResponse {
-    token: String,
-}
-
 #[derive(serde::Deserialize)]
 struct TvdbSearchResponse {
-    data: Vec<TvdbSearchItem>,
+    /// Absent when nothing matched
+    #[serde(default)]
+    data: Entries<TvdbSearchItem>,
 }
 
 #[derive(serde::Deserialize)]
@@ -178,6 +153,15 @@ struct TvdbDetailsItem {
     first_aired: Option<String>,
 }
 
+impl Checked for TvdbSearchItem {
+    fn check(&self) -> Result<(), String> {
+        if self.series_name.is_empty() {
+            return Err(format!("series {} has an empty `seriesName`", self.id));
+        }
+        Ok(())
+    }
+}
+
 impl From<TvdbSearchItem> for MetadataResult {
     fn from(item: TvdbSearchItem) -> Self {
         let year = item.first_aired
@@ -192,6 +176,11 @@ impl From<TvdbSearchItem> for MetadataResult {
             title: item.series_name,
             year,
             media_type: MediaType::Tv,
+            score: None,
+            anime: false,
+            enrichment: None,
+            original_title: None,
+            votes: None,
         }
     }
 }
@@ -210,6 +199,11 @@ impl From<TvdbDetailsItem> for MetadataResult {
             title: item.series_name,
             year,
             media_type: MediaType::Tv,
+            score: None,
+            anime: false,
+            enrichment: None,
+            original_title: None,
+            votes: None,
         }
     }
 }
@@ -270,9 +264,11 @@ mod tests {
     fn test_client_creation() {
         let config = TvdbConfig {
             api_key: "test_api_key".to_string(),
+            enabled: true,
         };
 
-        let client = TvdbClient::new(config);
+        let limiter = Arc::new(RateLimiter::new(&crate::metadata::RateLimit { calls: 10, per_seconds: 1 }));
+        let client = TvdbClient::new(config, Arc::new(TokenStore::in_memory()), limiter, RetryPolicy::from_config(&Default::default()), Client::new());
 
         assert_eq!(client.name(), "TVDB");
         assert_eq!(client.config.api_key, "test_api_key");
@@ -0,0 +1,162 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use std::sync::Arc;
+use crate::{
+    error::AppError,
+    models::MediaType,
+    metadata::{MetadataResult, MediaIds, MetadataProvider, RateLimiter, RetryPolicy},
+    metadata::response::{self, Checked, Entries},
+};
+
+const API_URL: &str = "https://api.tvmaze.com";
+
+/// TVmaze needs no API key, which makes it a free last resort for shows
+pub struct TvmazeClient {
+    client: Client,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+}
+
+impl TvmazeClient {
+    pub fn new(limiter: Arc<RateLimiter>, retry: RetryPolicy, client: Client) -> Self {
+        Self {
+            client,
+            limiter,
+            retry,
+        }
+    }
+
+    async fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<reqwest::Response, AppError> {
+        let request = self.client.get(url).query(query);
+        let response = self.retry.send(request, &self.limiter).await?;
+
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(AppError::MetadataError(format!(
+                "TVmaze API error: {}",
+                response.status()
+            )))
+        }
+    }
+
+    async fn search_internal(&self, title: &str) -> Result<Vec<MetadataResult>, AppError> {
+        let url = format!("{}/search/shows", API_URL);
+        let results: Entries<TvmazeSearchItem> = response::json("TVmaze", self.get(&url, &[("q", title)]).await?).await?;
+        Ok(results.into_valid("TVmaze").into_iter().map(|item| item.show.into()).collect())
+    }
+
+    /// Resolves a TVmaze show ID, or an IMDB ID (`tt...`) through TVmaze's lookup endpoint
+    async fn get_details_internal(&self, id: &str) -> Result<MetadataResult, AppError> {
+        let show: TvmazeShow = if id.starts_with("tt") {
+            let url = format!("{}/lookup/shows", API_URL);
+            response::json("TVmaze", self.get(&url, &[("imdb", id)]).await?).await?
+        } else {
+            let url = format!("{}/shows/{}", API_URL, id);
+            response::json("TVmaze", self.get(&url, &[]).await?).await?
+        };
+        Ok(show.into())
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for TvmazeClient {
+    fn name(&self) -> &'static str {
+        "TVmaze"
+    }
+
+    async fn search(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        _year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        if media_type != MediaType::Tv {
+            return Ok(vec![]); // TVmaze only lists shows
+        }
+
+        self.search_internal(title).await
+    }
+
+    async fn get_details(
+        &self,
+        id: &str,
+        media_type: MediaType,
+    ) -> Result<MetadataResult, AppError> {
+        if media_type != MediaType::Tv {
+            return Err(AppError::MetadataError("TVmaze only supports shows".into()));
+        }
+
+        self.get_details_internal(id).await
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct TvmazeSearchItem {
+    show: TvmazeShow,
+}
+
+#[derive(serde::Deserialize)]
+struct TvmazeShow {
+    name: String,
+    premiered: Option<String>,
+    #[serde(default)]
+    externals: TvmazeExternals,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct TvmazeExternals {
+    thetvdb: Option<u64>,
+    imdb: Option<String>,
+}
+
+impl Checked for TvmazeSearchItem {
+    fn check(&self) -> Result<(), String> {
+        if self.show.name.is_empty() {
+            return Err("show has an empty `name`".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl From<TvmazeShow> for MetadataResult {
+    fn from(show: TvmazeShow) -> Self {
+        MetadataResult {
+            ids: MediaIds {
+                tvdb: show.externals.thetvdb.map(|id| id.to_string()),
+                imdb: show.externals.imdb,
+                ..Default::default()
+            },
+            title: show.name,
+            year: show.premiered.as_deref().and_then(|d| d.get(..4)).map(String::from),
+            media_type: MediaType::Tv,
+            score: None,
+            anime: false,
+            enrichment: None,
+            original_title: None,
+            votes: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_results_use_externals() {
+        let json = r#"[{"score": 0.9, "show": {"id": 526, "name": "The Office", "premiered": "2005-03-24",
+            "externals": {"tvrage": 6061, "thetvdb": 73244, "imdb": "tt0386676"}}},
+            {"score": 0.5, "show": {"id": 1, "name": "Unaired Pilot", "premiered": null,
+            "externals": {"tvrage": null, "thetvdb": null, "imdb": null}}}]"#;
+        let items: Vec<TvmazeSearchItem> = serde_json::from_str(json).unwrap();
+        let results: Vec<MetadataResult> = items.into_iter().map(|item| item.show.into()).collect();
+
+        assert_eq!(results[0].title, "The Office");
+        assert_eq!(results[0].year.as_deref(), Some("2005"));
+        assert_eq!(results[0].ids.tvdb.as_deref(), Some("73244"));
+        assert_eq!(results[0].ids.imdb.as_deref(), Some("tt0386676"));
+        assert_eq!(results[1].year, None);
+        assert_eq!(results[1].ids.tvdb, None);
+    }
+}
@@ -0,0 +1,353 @@
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::{
+    error::AppError,
+    models::MediaType,
+    metadata::{MetadataResult, MediaIds, MetadataProvider, RateLimiter, RetryPolicy, SearchQuery},
+    metadata::response::{self, Checked, Entries},
+};
+
+const API_URL: &str = "https://www.wikidata.org/w/api.php";
+/// `wbgetentities` takes at most this many IDs per request
+const MAX_ENTITIES: usize = 50;
+/// Hits kept per title search
+const SEARCH_LIMIT: usize = 10;
+/// Wikimedia asks API clients to identify themselves
+const USER_AGENT: &str = concat!("primevideo-to-simkl-exporter/", env!("CARGO_PKG_VERSION"));
+
+const IMDB_ID: &str = "P345";
+const TMDB_MOVIE_ID: &str = "P4947";
+const TMDB_TV_ID: &str = "P4983";
+const TVDB_SERIES_ID: &str = "P4835";
+const MAL_ANIME_ID: &str = "P4086";
+const ANIDB_ANIME_ID: &str = "P5646";
+const ANILIST_ANIME_ID: &str = "P8729";
+const INSTANCE_OF: &str = "P31";
+const PUBLICATION_DATE: &str = "P577";
+const START_TIME: &str = "P580";
+
+/// "film", "animated film", "anime film"
+const FILM_CLASSES: &[&str] = &["Q11424", "Q202866", "Q20650540"];
+/// "television series", "animated series", "anime television series", "miniseries"
+const SERIES_CLASSES: &[&str] = &["Q5398426", "Q581714", "Q63952888", "Q1259759"];
+
+/// Keyless cross-referencing through Wikidata's external ID properties
+pub struct WikidataClient {
+    client: Client,
+    limiter: Arc<RateLimiter>,
+    retry: RetryPolicy,
+}
+
+impl WikidataClient {
+    pub fn new(limiter: Arc<RateLimiter>, retry: RetryPolicy, client: Client) -> Self {
+        Self {
+            client,
+            limiter,
+            retry,
+        }
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, query: &[(&str, &str)]) -> Result<T, AppError> {
+        let request = self.client
+            .get(API_URL)
+            .header("User-Agent", USER_AGENT)
+            .query(&[("format", "json")])
+            .query(query);
+        let response = self.retry.send(request, &self.limiter).await?;
+
+        if response.status().is_success() {
+            response::json("Wikidata", response).await
+        } else {
+            Err(AppError::MetadataError(format!(
+                "Wikidata API error: {}",
+                response.status()
+            )))
+        }
+    }
+
+    async fn entities(&self, ids: &[String]) -> Result<HashMap<String, WikidataEntity>, AppError> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let ids = ids.join("|");
+        let response: EntitiesResponse = self
+            .get(&[
+                ("action", "wbgetentities"),
+                ("ids", &ids),
+                ("props", "labels|claims"),
+                ("languages", "en"),
+            ])
+            .await?;
+        Ok(response.entities)
+    }
+
+    /// Searches labels and aliases in `language`, whose hits come labelled in it
+    async fn search_hits(&self, title: &str, language: &str) -> Result<Vec<SearchHit>, AppError> {
+        let limit = SEARCH_LIMIT.to_string();
+        let search: SearchResponse = self
+            .get(&[
+                ("action", "wbsearchentities"),
+                ("search", title),
+                ("language", language),
+                ("uselang", language),
+                ("type", "item"),
+                ("limit", &limit),
+            ])
+            .await?;
+        Ok(search.search.into_valid("Wikidata"))
+    }
+
+    /// Searches each title on its own, then fetches the items they found in as few
+    /// `wbgetentities` requests as possible
+    async fn search_internal(&self, queries: &[SearchQuery]) -> Result<Vec<Vec<MetadataResult>>, AppError> {
+        let searches = futures::future::try_join_all(queries.iter().map(|query| self.search_hits(&query.title, query.language.unwrap_or("en")))).await?;
+
+        let mut ids: Vec<String> = searches.iter().flatten().map(|hit| hit.id.clone()).collect();
+        ids.sort();
+        ids.dedup();
+        let mut entities = HashMap::new();
+        for chunk in ids.chunks(MAX_ENTITIES) {
+            entities.extend(self.entities(chunk).await?);
+        }
+
+        // Keep Wikidata's relevance order
+        Ok(searches
+            .into_iter()
+            .zip(queries)
+            .map(|(hits, query)| {
+                hits.into_iter()
+                    .filter_map(|hit| {
+                        let entity = entities.get(&hit.id)?.clone();
+                        let mut result = entity.into_result(hit.label.clone().unwrap_or_default())?;
+                        // Searched in another language, the hit's label is the title in it, which the matcher also scores
+                        if query.language.is_some() {
+                            result.original_title = hit.label.filter(|label| *label != result.title);
+                        }
+                        Some(result)
+                    })
+                    .filter(|result| result.media_type == query.media_type)
+                    .collect()
+            })
+            .collect())
+    }
+
+    async fn get_details_internal(&self, id: &str) -> Result<MetadataResult, AppError> {
+        self.entities(&[id.to_string()])
+            .await?
+            .remove(id)
+            .and_then(|entity| entity.into_result(String::new()))
+            .ok_or_else(|| AppError::MetadataError(format!("Wikidata item {} is not a film or series", id)))
+    }
+}
+
+#[async_trait]
+impl MetadataProvider for WikidataClient {
+    fn name(&self) -> &'static str {
+        "Wikidata"
+    }
+
+    async fn search(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        self.search_in(title, media_type, year, None).await
+    }
+
+    async fn search_in(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+        language: Option<&'static str>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        let query = SearchQuery { title: title.to_string(), media_type, year, language };
+        Ok(self.search_internal(&[query]).await?.pop().unwrap_or_default())
+    }
+
+    /// Five searches fill one `wbgetentities` request
+    fn batch_size(&self) -> usize {
+        MAX_ENTITIES / SEARCH_LIMIT
+    }
+
+    async fn search_batch(&self, queries: &[SearchQuery]) -> Result<Vec<Vec<MetadataResult>>, AppError> {
+        self.search_internal(queries).await
+    }
+
+    async fn get_details(
+        &self,
+        id: &str,
+        _media_type: MediaType,
+    ) -> Result<MetadataResult, AppError> {
+        self.get_details_internal(id).await
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    search: Entries<SearchHit>,
+}
+
+impl Checked for SearchHit {
+    fn check(&self) -> Result<(), String> {
+        if !self.id.starts_with('Q') {
+            return Err(format!("`id` {:?} is not an item", self.id));
+        }
+        Ok(())
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SearchHit {
+    id: String,
+    label: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct EntitiesResponse {
+    #[serde(default)]
+    entities: HashMap<String, WikidataEntity>,
+}
+
+#[derive(Clone, serde::Deserialize)]
+struct WikidataEntity {
+    #[serde(default)]
+    labels: HashMap<String, WikidataLabel>,
+    #[serde(default)]
+    claims: HashMap<String, Vec<Value>>,
+}
+
+#[derive(Clone, serde::Deserialize)]
+struct WikidataLabel {
+    value: String,
+}
+
+impl WikidataEntity {
+    /// Values of a property's statements: strings for external IDs, item IDs or timestamps otherwise
+    fn values(&self, property: &str) -> Vec<String> {
+        self.claims
+            .get(property)
+            .into_iter()
+            .flatten()
+            .filter_map(|claim| {
+                let value = claim.pointer("/mainsnak/datavalue/value")?;
+                match value {
+                    Value::String(s) => Some(s.clone()),
+                    Value::Object(o) => o
+                        .get("id")
+                        .or_else(|| o.get("time"))
+                        .and_then(Value::as_str)
+                        .map(String::from),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    fn first(&self, property: &str) -> Option<String> {
+        self.values(property).into_iter().next()
+    }
+
+    /// Films and series only; anything else Wikidata knows about is dropped
+    fn media_type(&self) -> Option<MediaType> {
+        let classes = self.values(INSTANCE_OF);
+        let is = |set: &[&str]| classes.iter().any(|c| set.contains(&c.as_str()));
+        if is(FILM_CLASSES) || self.claims.contains_key(TMDB_MOVIE_ID) {
+            Some(MediaType::Movie)
+        } else if is(SERIES_CLASSES) || self.claims.contains_key(TMDB_TV_ID) || self.claims.contains_key(TVDB_SERIES_ID) {
+            Some(MediaType::Tv)
+        } else {
+            None
+        }
+    }
+
+    fn into_result(self, fallback_label: String) -> Option<MetadataResult> {
+        let media_type = self.media_type()?;
+        let tmdb = match media_type {
+            MediaType::Movie => self.first(TMDB_MOVIE_ID),
+            MediaType::Tv => self.first(TMDB_TV_ID),
+        };
+        // Timestamps look like "+2021-09-15T00:00:00Z"
+        let year = self
+            .values(PUBLICATION_DATE)
+            .into_iter()
+            .chain(self.values(START_TIME))
+            .filter_map(|time| time.get(1..5).map(String::from))
+            .min();
+        let title = self
+            .labels
+            .get("en")
+            .map(|label| label.value.clone())
+            .unwrap_or(fallback_label);
+
+        Some(MetadataResult {
+            ids: MediaIds {
+                imdb: self.first(IMDB_ID),
+                tmdb,
+                tvdb: self.first(TVDB_SERIES_ID),
+                mal: self.first(MAL_ANIME_ID),
+                anilist: self.first(ANILIST_ANIME_ID),
+                anidb: self.first(ANIDB_ANIME_ID),
+                ..Default::default()
+            },
+            title,
+            year,
+            media_type,
+            score: None,
+            anime: [MAL_ANIME_ID, ANILIST_ANIME_ID, ANIDB_ANIME_ID].iter().any(|p| self.claims.contains_key(*p)),
+            enrichment: None,
+            original_title: None,
+            votes: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(value: Value) -> Value {
+        serde_json::json!({ "mainsnak": { "datavalue": { "value": value } } })
+    }
+
+    #[test]
+    fn test_entity_conversion_collects_external_ids() {
+        let entity = WikidataEntity {
+            labels: HashMap::from([("en".to_string(), WikidataLabel { value: "Dune".to_string() })]),
+            claims: HashMap::from([
+                (INSTANCE_OF.to_string(), vec![claim(serde_json::json!({ "id": "Q11424" }))]),
+                (IMDB_ID.to_string(), vec![claim("tt1160419".into())]),
+                (TMDB_MOVIE_ID.to_string(), vec![claim("438631".into())]),
+                (
+                    PUBLICATION_DATE.to_string(),
+                    vec![
+                        claim(serde_json::json!({ "time": "+2021-10-22T00:00:00Z" })),
+                        claim(serde_json::json!({ "time": "+2021-09-03T00:00:00Z" })),
+                    ],
+                ),
+            ]),
+        };
+
+        let result = entity.into_result(String::new()).unwrap();
+        assert_eq!(result.title, "Dune");
+        assert_eq!(result.media_type, MediaType::Movie);
+        assert_eq!(result.ids.imdb.as_deref(), Some("tt1160419"));
+        assert_eq!(result.ids.tmdb.as_deref(), Some("438631"));
+        assert_eq!(result.year.as_deref(), Some("2021"));
+        assert!(!result.anime);
+    }
+
+    #[test]
+    fn test_non_media_items_are_dropped() {
+        let entity = WikidataEntity {
+            labels: HashMap::new(),
+            claims: HashMap::from([(INSTANCE_OF.to_string(), vec![claim(serde_json::json!({ "id": "Q5" }))])]),
+        };
+        assert!(entity.into_result("Frank Herbert".to_string()).is_none());
+    }
+}
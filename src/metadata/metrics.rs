@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Counters for one provider over a run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProviderStats {
+    /// Requests sent to the provider's API
+    pub requests: u64,
+    /// Requests that ended in an error
+    pub errors: u64,
+    /// Searches answered by the metadata cache
+    pub cache_hits: u64,
+    /// Searches the cache could not answer
+    pub cache_misses: u64,
+    total_latency: Duration,
+}
+
+impl ProviderStats {
+    /// Share of searches served from the cache, or None when nothing was searched
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let lookups = self.cache_hits + self.cache_misses;
+        (lookups > 0).then(|| self.cache_hits as f64 / lookups as f64)
+    }
+
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.requests > 0).then(|| self.total_latency / self.requests as u32)
+    }
+}
+
+/// Per-provider request statistics, shared by concurrent lookups
+#[derive(Default)]
+pub struct ProviderMetrics {
+    stats: Mutex<BTreeMap<&'static str, ProviderStats>>,
+}
+
+impl ProviderMetrics {
+    fn update(&self, provider: &'static str, apply: impl FnOnce(&mut ProviderStats)) {
+        apply(self.stats.lock().unwrap().entry(provider).or_default());
+    }
+
+    pub fn record_cache(&self, provider: &'static str, hit: bool) {
+        self.update(provider, |stats| {
+            if hit {
+                stats.cache_hits += 1;
+            } else {
+                stats.cache_misses += 1;
+            }
+        });
+    }
+
+    /// Records a request that took `latency` and succeeded or not
+    pub fn record_request(&self, provider: &'static str, latency: Duration, ok: bool) {
+        self.update(provider, |stats| {
+            stats.requests += 1;
+            stats.total_latency += latency;
+            if !ok {
+                stats.errors += 1;
+            }
+        });
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<&'static str, ProviderStats> {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Renders the statistics as a plain-text table, one row per provider
+    pub fn summary_table(&self) -> String {
+        let mut table = format!(
+            "{:<10} {:>8} {:>7} {:>10} {:>12}\n",
+            "Provider", "Requests", "Errors", "Cache hits", "Avg latency"
+        );
+        for (provider, stats) in self.snapshot() {
+            let hit_rate = stats
+                .cache_hit_rate()
+                .map_or("-".to_string(), |rate| format!("{:.0}%", rate * 100.0));
+            let latency = stats
+                .average_latency()
+                .map_or("-".to_string(), |latency| format!("{} ms", latency.as_millis()));
+            let _ = writeln!(
+                table,
+                "{:<10} {:>8} {:>7} {:>10} {:>12}",
+                provider, stats.requests, stats.errors, hit_rate, latency
+            );
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_accumulate_per_provider() {
+        let metrics = ProviderMetrics::default();
+        metrics.record_cache("TMDB", true);
+        metrics.record_cache("TMDB", false);
+        metrics.record_cache("TMDB", false);
+        metrics.record_request("TMDB", Duration::from_millis(100), true);
+        metrics.record_request("TMDB", Duration::from_millis(300), false);
+        metrics.record_cache("Simkl", true);
+
+        let snapshot = metrics.snapshot();
+        let tmdb = &snapshot["TMDB"];
+        assert_eq!((tmdb.requests, tmdb.errors), (2, 1));
+        assert!((tmdb.cache_hit_rate().unwrap() - 1.0 / 3.0).abs() < 1e-9);
+        assert_eq!(tmdb.average_latency(), Some(Duration::from_millis(200)));
+        assert_eq!(snapshot["Simkl"].average_latency(), None);
+
+        let table = metrics.summary_table();
+        assert_eq!(table.lines().count(), 3);
+        assert!(table.lines().any(|line| line.starts_with("TMDB") && line.contains("33%") && line.contains("200 ms")));
+        assert!(table.lines().any(|line| line.starts_with("Simkl") && line.contains("100%")));
+    }
+}
@@ -1,50 +1,385 @@
+pub mod anime_ids;
+mod batch;
+mod cache;
 mod clients;
+mod metrics;
 mod models;
 mod provider;
+mod rate_limit;
+pub(crate) mod response;
+mod retry;
+pub mod unmatched;
 
-pub use models::{ServiceType, MetadataResult, MediaIds, RateLimitConfig, RateLimit, PriorityOrder};
+pub use models::{ServiceType, MetadataResult, MediaIds, EpisodeInfo, Enrichment, EnrichmentField, IdConflictPolicy, RateLimitConfig, RateLimit, ProviderPriority};
 pub use crate::models::MediaType;
 
 // Internal imports needed for implementation
-use crate::config::{SimklConfig, TmdbConfig, TvdbConfig, MalConfig};
+use crate::auth::TokenStore;
+use crate::config::{AppConfig, TimeoutConfig};
+use crate::matching::{self, AnimeDetector, Decision, Disambiguator, Matcher, Overrides, TitleNormalizer};
 use crate::error::AppError;
-use clients::{SimklClient, TmdbClient, TvdbClient, MalClient};
-use provider::MetadataProvider;
+pub use anime_ids::AnimeIdMap;
+pub use cache::MetadataCache;
+pub use clients::{tmdb_export, CommandProvider, MockProvider};
+pub use metrics::ProviderMetrics;
+pub use unmatched::UnmatchedItem;
+use unmatched::{RejectedCandidate, SearchAttempt, UnmatchedLog};
+use clients::{SimklClient, TmdbClient, TvdbClient, MalClient, TraktClient, OmdbClient, TvmazeClient, WikidataClient, ImdbDatasetProvider, TmdbExportProvider};
+use batch::SearchBatcher;
+use provider::{MetadataProvider, SearchQuery};
+pub use rate_limit::RateLimiter;
+pub use retry::RetryPolicy;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
 
 pub struct MetadataService {
-    providers: Vec<Box<dyn MetadataProvider>>,
+    providers: HashMap<ServiceType, Box<dyn MetadataProvider>>,
+    priority: ProviderPriority,
+    request_budget: Option<AtomicUsize>,
+    cache: Option<MetadataCache>,
+    normalizer: Option<TitleNormalizer>,
+    matcher: Matcher,
+    disambiguator: Option<Disambiguator>,
+    overrides: Overrides,
+    unmatched: UnmatchedLog,
+    metrics: ProviderMetrics,
+    batcher: SearchBatcher,
+    anime: Option<AnimeDetector>,
+    anime_ids: Option<AnimeIdMap>,
+    enrich: bool,
+    detect_language: bool,
+    offline: bool,
+    fetch_details: bool,
+}
+
+/// An HTTP client for `service` that gives up on connections and responses after its configured timeouts
+fn http_client(timeouts: &TimeoutConfig, service: &ServiceType) -> reqwest::Client {
+    let (connect, read) = timeouts.for_provider(service.name());
+    reqwest::Client::builder()
+        .connect_timeout(connect)
+        .timeout(read)
+        .build()
+        .unwrap_or_else(|e| {
+            tracing::warn!("Cannot apply timeouts to {}: {}", service.name(), e);
+            reqwest::Client::new()
+        })
 }
 
 impl MetadataService {
     pub fn new(
-        priority_order: PriorityOrder,
-        #[allow(unused_variables)]
-        rate_limits: RateLimitConfig, // Reserved for future rate limiting implementation
-        simkl_config: SimklConfig,
-        tmdb_config: TmdbConfig,
-        tvdb_config: TvdbConfig,
-        mal_config: MalConfig,
+        priority: ProviderPriority,
+        rate_limits: RateLimitConfig,
+        config: &AppConfig,
+        tokens: Arc<TokenStore>,
     ) -> Self {
-        let mut providers: Vec<Box<dyn MetadataProvider>> = Vec::new();
+        let mut providers: HashMap<ServiceType, Box<dyn MetadataProvider>> = HashMap::new();
+        let retry = RetryPolicy::from_config(&config.retry);
+
+        // One bucket per provider, shared by every request the client makes
+        let simkl_limiter = Arc::new(RateLimiter::new(&rate_limits.simkl));
+        let tmdb_limiter = Arc::new(RateLimiter::new(&rate_limits.tmdb));
+        let tvdb_limiter = Arc::new(RateLimiter::new(&rate_limits.tvdb));
+        let mal_limiter = Arc::new(RateLimiter::new(&rate_limits.mal));
+        let trakt_limiter = Arc::new(RateLimiter::new(&rate_limits.trakt));
+        let omdb_limiter = Arc::new(RateLimiter::new(&rate_limits.omdb));
+        let tvmaze_limiter = Arc::new(RateLimiter::new(&rate_limits.tvmaze));
+        let wikidata_limiter = Arc::new(RateLimiter::new(&rate_limits.wikidata));
+
+        // Build each provider once, even when it appears in several chains
+        for service in priority.movies.iter().chain(&priority.shows).chain(&priority.anime) {
+            if providers.contains_key(service) {
+                continue;
+            }
+            if !config.is_enabled(service) {
+                tracing::debug!("Skipping {}: disabled in config", service.name());
+                continue;
+            }
+            let provider: Box<dyn MetadataProvider> = match service {
+                ServiceType::Simkl => Box::new(
+                    SimklClient::new(config.simkl.clone(), tokens.clone(), simkl_limiter.clone(), retry.clone(), http_client(&config.timeouts, service))
+                ),
+                ServiceType::Tmdb => Box::new(
+                    TmdbClient::new(config.tmdb.clone(), tmdb_limiter.clone(), retry.clone(), http_client(&config.timeouts, service))
+                ),
+                ServiceType::Tvdb => Box::new(
+                    TvdbClient::new(config.tvdb.clone(), tokens.clone(), tvdb_limiter.clone(), retry.clone(), http_client(&config.timeouts, service))
+                ),
+                ServiceType::Mal => Box::new(
+                    MalClient::new(config.mal.clone(), tokens.clone(), mal_limiter.clone(), retry.clone(), http_client(&config.timeouts, service))
+                ),
+                ServiceType::Trakt => Box::new(
+                    TraktClient::new(config.trakt.clone(), trakt_limiter.clone(), retry.clone(), http_client(&config.timeouts, service))
+                ),
+                ServiceType::Omdb => Box::new(
+                    OmdbClient::new(config.omdb.clone(), omdb_limiter.clone(), retry.clone(), http_client(&config.timeouts, service))
+                ),
+                ServiceType::Tvmaze => Box::new(
+                    TvmazeClient::new(tvmaze_limiter.clone(), retry.clone(), http_client(&config.timeouts, service))
+                ),
+                ServiceType::Wikidata => Box::new(
+                    WikidataClient::new(wikidata_limiter.clone(), retry.clone(), http_client(&config.timeouts, service))
+                ),
+                ServiceType::Imdb => Box::new(ImdbDatasetProvider::new(config.imdb.clone())),
+                ServiceType::TmdbExport => Box::new(TmdbExportProvider::new(config.tmdb_export.clone())),
+                // Registered by name through `with_provider`
+                ServiceType::Custom(_) => continue,
+            };
+            providers.insert(service.clone(), provider);
+        }
+
+        Self::from_providers(providers, priority)
+    }
+
+    /// Answers every provider in `priority` from `mock`'s fixtures instead of the real APIs
+    pub fn mock(priority: ProviderPriority, mock: MockProvider) -> Self {
+        let mut providers: HashMap<ServiceType, Box<dyn MetadataProvider>> = HashMap::new();
+        for service in priority.movies.iter().chain(&priority.shows).chain(&priority.anime) {
+            providers.entry(service.clone()).or_insert_with(|| Box::new(mock.clone()));
+        }
+        Self::from_providers(providers, priority)
+    }
+
+    fn from_providers(providers: HashMap<ServiceType, Box<dyn MetadataProvider>>, priority: ProviderPriority) -> Self {
+        Self { providers, priority, request_budget: None, cache: None, normalizer: None, matcher: Matcher::default(), disambiguator: None, overrides: Overrides::default(), unmatched: UnmatchedLog::default(), metrics: ProviderMetrics::default(), batcher: SearchBatcher::default(), anime: None, anime_ids: None, enrich: false, detect_language: false, offline: false, fetch_details: false }
+    }
+
+    /// Registers `provider` under `service`, replacing any built-in client, so chains naming
+    /// `service` query it
+    pub fn with_provider(mut self, service: ServiceType, provider: Box<dyn MetadataProvider>) -> Self {
+        self.providers.insert(service, provider);
+        self
+    }
+
+    /// Cleans titles with `normalizer` before searching and scoring
+    pub fn with_normalizer(mut self, normalizer: TitleNormalizer) -> Self {
+        self.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Scores candidates with `matcher` instead of the default settings
+    pub fn with_matcher(mut self, matcher: Matcher) -> Self {
+        self.matcher = matcher;
+        self
+    }
+
+    /// Answers titles the user mapped in `overrides` without asking any provider
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Sends titles that look like anime down the anime chain and adds MAL IDs to anime matches
+    pub fn with_anime_detector(mut self, detector: AnimeDetector) -> Self {
+        self.anime = Some(detector);
+        self
+    }
+
+    /// Fills in the other IDs of anime matches from `map` before asking any provider for them
+    pub fn with_anime_ids(mut self, map: AnimeIdMap) -> Self {
+        self.anime_ids = Some(map);
+        self
+    }
+
+    /// Lets the user pick between candidates that score too close to call
+    pub fn with_disambiguator(mut self, disambiguator: Disambiguator) -> Self {
+        self.disambiguator = Some(disambiguator);
+        self
+    }
+
+    /// Searches titles in a non-Latin script in the language the script suggests
+    pub fn with_language_detection(mut self) -> Self {
+        self.detect_language = true;
+        self
+    }
+
+    /// Fetches genres, runtime, poster and overview for every match
+    pub fn with_enrichment(mut self) -> Self {
+        self.enrich = true;
+        self
+    }
+
+    /// Calls `get_details` on every provider the match has an ID for and merges the IDs it returns
+    pub fn with_details_pass(mut self) -> Self {
+        self.fetch_details = true;
+        self
+    }
+
+    /// Answers lookups from the cache and overrides only, never sending a provider request
+    pub fn with_offline_mode(mut self) -> Self {
+        self.offline = true;
+        self
+    }
+
+    /// Serves repeated searches from `cache` and records fresh provider results in it
+    pub fn with_cache(mut self, cache: MetadataCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    fn cached_search(&self, provider: &str, title: &str, media_type: MediaType, year: Option<i32>) -> Option<Vec<MetadataResult>> {
+        let cache = self.cache.as_ref()?;
+        match cache.get_search(provider, title, year, media_type) {
+            Ok(results) => results,
+            Err(e) => {
+                tracing::warn!("Metadata cache read failed: {}", e);
+                None
+            }
+        }
+    }
+
+    fn cached_details(&self, provider: &str, id: &str, media_type: MediaType) -> Option<MetadataResult> {
+        let cache = self.cache.as_ref()?;
+        match cache.get_details(provider, id, media_type) {
+            Ok(details) => details,
+            Err(e) => {
+                tracing::warn!("Metadata cache read failed: {}", e);
+                None
+            }
+        }
+    }
+
+    fn store_details(&self, provider: &str, id: &str, media_type: MediaType, details: &MetadataResult) {
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.put_details(provider, id, media_type, details) {
+                tracing::warn!("Metadata cache write failed: {}", e);
+            }
+        }
+    }
+
+    fn store_search(&self, provider: &str, title: &str, media_type: MediaType, year: Option<i32>, results: &[MetadataResult]) {
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.put_search(provider, title, year, media_type, results) {
+                tracing::warn!("Metadata cache write failed: {}", e);
+            }
+        }
+    }
+
+    /// Caps the total number of provider requests this service will make
+    pub fn with_request_budget(mut self, budget: usize) -> Self {
+        self.request_budget = Some(AtomicUsize::new(budget));
+        self
+    }
+
+    fn consume_budget(&self) -> bool {
+        match &self.request_budget {
+            Some(remaining) => remaining
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok(),
+            None => true,
+        }
+    }
 
-        for service in priority_order {
-            match service {
-                ServiceType::Simkl => providers.push(Box::new(
-                    SimklClient::new(simkl_config.clone())
-                )),
-                ServiceType::Tmdb => providers.push(Box::new(
-                    TmdbClient::new(tmdb_config.clone())
-                )),
-                ServiceType::Tvdb => providers.push(Box::new(
-                    TvdbClient::new(tvdb_config.clone())
-                )),
-                ServiceType::Mal => providers.push(Box::new(
-                    MalClient::new(mal_config.clone())
-                )),
+    fn budget_exhausted(&self) -> bool {
+        self.request_budget
+            .as_ref()
+            .is_some_and(|remaining| remaining.load(Ordering::SeqCst) == 0)
+    }
+
+    /// Whether follow-up requests (IDs, details, episodes) must be skipped
+    fn requests_blocked(&self) -> bool {
+        self.offline || self.budget_exhausted()
+    }
+
+    /// Searches one provider through the cache; None when the search failed or the budget ran out
+    async fn search(
+        &self,
+        provider: &dyn MetadataProvider,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+        trace: &mut UnmatchedItem,
+    ) -> Option<Vec<MetadataResult>> {
+        let mut attempt = SearchAttempt {
+            provider: provider.name().to_string(),
+            query: title.to_string(),
+            year,
+            results: 0,
+            error: None,
+        };
+        let language = if self.detect_language { matching::detect_language(title) } else { None };
+        let local = provider.is_local();
+        let cached = if local { None } else { self.cached_search(provider.name(), title, media_type, year) };
+        if self.cache.is_some() && !local {
+            self.metrics.record_cache(provider.name(), cached.is_some());
+        }
+        let results = if let Some(results) = cached {
+            tracing::debug!("Cache hit for '{}' on {}", title, provider.name());
+            Some(results)
+        } else if local {
+            match provider.search_in(title, media_type, year, language).await {
+                Ok(results) => Some(results),
+                Err(e) => {
+                    tracing::warn!("Metadata lookup failed on {}: {}", provider.name(), e);
+                    attempt.error = Some(e.to_string());
+                    None
+                }
+            }
+        } else if self.offline {
+            attempt.error = Some("Offline and not in the metadata cache".to_string());
+            None
+        } else if !self.consume_budget() {
+            attempt.error = Some("Provider request budget exhausted".to_string());
+            None
+        } else {
+            let started = Instant::now();
+            if let Some(language) = language {
+                tracing::debug!("Searching {} for '{}' in '{}'", provider.name(), title, language);
+            }
+            let searched = if provider.batch_size() > 1 {
+                let query = SearchQuery { title: title.to_string(), media_type, year, language };
+                self.batcher.search(provider, query).await
+            } else {
+                provider.search_in(title, media_type, year, language).await
+            };
+            self.metrics.record_request(provider.name(), started.elapsed(), searched.is_ok());
+            match searched {
+                Ok(results) => {
+                    self.store_search(provider.name(), title, media_type, year, &results);
+                    Some(results)
+                }
+                Err(e) => {
+                    tracing::warn!("Metadata lookup failed on {}: {}", provider.name(), e);
+                    attempt.error = Some(e.to_string());
+                    None
+                }
+            }
+        };
+        attempt.results = results.as_ref().map_or(0, Vec::len);
+        trace.attempts.push(attempt);
+        results
+    }
+
+    /// Fetches an episode of a matched show from the first show provider that knows it
+    pub async fn episode(&self, show_ids: &MediaIds, season: u32, episode: u32) -> Result<Option<EpisodeInfo>, AppError> {
+        for provider in self.priority.shows.iter().filter_map(|service| self.providers.get(service)) {
+            if self.requests_blocked() {
+                break;
+            }
+            let started = Instant::now();
+            let fetched = provider.get_episode(show_ids, season, episode).await;
+            // Providers without episode data answer None without sending anything
+            if !matches!(fetched, Ok(None)) {
+                self.metrics.record_request(provider.name(), started.elapsed(), fetched.is_ok());
+            }
+            match fetched {
+                Ok(Some(info)) => return Ok(Some(info)),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Episode lookup failed on {}: {}", provider.name(), e),
             }
         }
+        Ok(None)
+    }
 
-        Self { providers }
+    /// Request statistics for every provider used so far
+    pub fn metrics(&self) -> &ProviderMetrics {
+        &self.metrics
+    }
+
+    /// Items whose lookups failed so far, with what was tried for each; drains the log
+    pub fn take_unmatched(&self) -> Vec<UnmatchedItem> {
+        self.unmatched.take()
     }
 
     pub async fn lookup(
@@ -53,25 +388,584 @@ impl MetadataService {
         media_type: MediaType,
         year: Option<&str>,
     ) -> Result<MetadataResult, AppError> {
-        let year_int = year.and_then(|y| y.parse().ok());
-        let media_type_clone = media_type.clone();
-        for provider in &self.providers {
-            match provider.search(title, media_type_clone.clone(), year_int).await {
-                Ok(results) => {
-                    if let Some(result) = results.into_iter().next() {
-                        return Ok(result);
+        let mut trace = UnmatchedItem {
+            title: title.to_string(),
+            media_type,
+            year: year.map(String::from),
+            attempts: Vec::new(),
+            candidates: Vec::new(),
+        };
+        let result = self.lookup_traced(title, media_type, year, &mut trace).await;
+        match &result {
+            Ok(_) => self.unmatched.clear(title, media_type),
+            Err(_) => self.unmatched.record(trace),
+        }
+        result
+    }
+
+    async fn lookup_traced(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<&str>,
+        trace: &mut UnmatchedItem,
+    ) -> Result<MetadataResult, AppError> {
+        let normalized = self.normalizer.as_ref().map(|n| n.normalize(title));
+        if let Some(normalized) = normalized.as_deref().filter(|n| *n != title) {
+            tracing::debug!("Normalized '{}' to '{}'", title, normalized);
+        }
+        let pinned = std::iter::once(title)
+            .chain(normalized.as_deref())
+            .find_map(|t| self.overrides.find(t, media_type, year));
+        if let Some(mut pinned) = pinned {
+            tracing::debug!("Using override for '{}'", title);
+            let chain = if pinned.anime { &self.priority.anime } else { self.priority.for_media_type(media_type) };
+            self.enrich(&mut pinned, chain, media_type).await;
+            return Ok(pinned);
+        }
+        let raw_title = title;
+        let title = normalized.as_deref().unwrap_or(title);
+        let year_int = year.and_then(matching::parse_year);
+
+        let anime_title = self.anime.as_ref().is_some_and(|d| d.is_anime_title(raw_title) || d.is_anime_title(title));
+        let chain = if anime_title {
+            tracing::debug!("'{}' looks like anime", title);
+            &self.priority.anime
+        } else {
+            self.priority.for_media_type(media_type)
+        };
+
+        let mut matched = self.search_chain(chain, title, media_type, year_int, trace).await?;
+        if matched.score.unwrap_or_default() < matching::CONFIDENT_SCORE {
+            matched = self.retry_original_title(matched, chain, title, media_type, year_int, trace).await;
+        }
+        if let Some(ids) = self.anime_ids.as_ref().and_then(|map| map.find(&matched.ids, media_type)) {
+            self.reconcile_ids(&mut matched, ids, "anime ID mapping");
+            matched.anime = true;
+        }
+        if self.anime.is_some() && (anime_title || matched.anime) && matched.ids.mal.is_none() {
+            self.add_mal_id(&mut matched, media_type, trace).await;
+        }
+        if self.fetch_details {
+            self.complete_from_details(&mut matched, chain, media_type).await;
+        }
+        self.cross_reference(&mut matched, chain, media_type).await;
+        self.enrich(&mut matched, chain, media_type).await;
+        Ok(matched)
+    }
+
+    /// Searches `chain` again under the best candidate's original-language (or romaji) title,
+    /// which is what providers without localized titles know it by
+    async fn retry_original_title(
+        &self,
+        best: MetadataResult,
+        chain: &[ServiceType],
+        title: &str,
+        media_type: MediaType,
+        year_int: Option<i32>,
+        trace: &mut UnmatchedItem,
+    ) -> MetadataResult {
+        let Some(original) = best.original_title.clone().filter(|o| !o.eq_ignore_ascii_case(title)) else {
+            return best;
+        };
+        tracing::debug!("No confident match for '{}'; retrying as '{}'", title, original);
+        match self.search_chain(chain, &original, media_type, year_int, trace).await {
+            Ok(mut retried) if retried.score.unwrap_or_default() >= matching::CONFIDENT_SCORE => {
+                // Found under the candidate's own original title, so its IDs describe the same thing
+                self.reconcile_ids(&mut retried, best.ids, "original-title search");
+                retried
+            }
+            _ => best,
+        }
+    }
+
+    /// Second pass over `chain`: details pages often list external IDs that search results don't
+    async fn complete_from_details(&self, matched: &mut MetadataResult, chain: &[ServiceType], media_type: MediaType) {
+        for service in chain {
+            let (Some(provider), Some(id)) = (self.providers.get(service), matched.ids.for_service(service)) else {
+                continue;
+            };
+            let id = id.to_string();
+            let cached = self.cached_details(provider.name(), &id, media_type);
+            if self.cache.is_some() {
+                self.metrics.record_cache(provider.name(), cached.is_some());
+            }
+            let details = match cached {
+                Some(details) => Ok(details),
+                None => {
+                    if self.requests_blocked() || !self.consume_budget() {
+                        return;
+                    }
+                    let started = Instant::now();
+                    let details = provider.get_details(&id, media_type).await;
+                    self.metrics.record_request(provider.name(), started.elapsed(), details.is_ok());
+                    if let Ok(details) = &details {
+                        self.store_details(provider.name(), &id, media_type, details);
                     }
+                    details
                 }
-                Err(e) => {
-                    tracing::warn!(
-                        "Metadata lookup failed on {}: {}",
-                        provider.name(),
-                        e
-                    );
-                    continue;
+            };
+            match details {
+                Ok(details) => self.reconcile_ids(matched, details.ids, provider.name()),
+                Err(e) => tracing::warn!("Fetching details for '{}' from {} failed: {}", matched.title, provider.name(), e),
+            }
+        }
+    }
+
+    /// Fills `matched.enrichment` from the first provider of `chain` that can describe the match
+    async fn enrich(&self, matched: &mut MetadataResult, chain: &[ServiceType], media_type: MediaType) {
+        if !self.enrich || matched.enrichment.is_some() {
+            return;
+        }
+        for provider in chain.iter().filter_map(|service| self.providers.get(service)) {
+            if !provider.can_enrich(&matched.ids) {
+                continue;
+            }
+            if self.requests_blocked() || !self.consume_budget() {
+                return;
+            }
+            match provider.enrich(&matched.ids, media_type).await {
+                Ok(Some(enrichment)) => {
+                    matched.enrichment = Some(enrichment);
+                    return;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Fetching details for '{}' from {} failed: {}", matched.title, provider.name(), e),
+            }
+        }
+    }
+
+    /// Adds `ids` from `source` to the match, settling disagreements by the configured policy
+    fn reconcile_ids(&self, matched: &mut MetadataResult, ids: MediaIds, source: &str) {
+        let policy = self.matcher.id_conflict_policy();
+        for conflict in matched.ids.reconcile(ids, policy) {
+            tracing::warn!(
+                "{} gives {} ID {} for '{}', which already has {}; {}",
+                source,
+                conflict.field,
+                conflict.other,
+                matched.title,
+                conflict.known,
+                match policy {
+                    IdConflictPolicy::KeepFirst => "keeping the first",
+                    IdConflictPolicy::Drop => "dropping both",
+                }
+            );
+        }
+    }
+
+    /// Takes the IDs of other providers' top results that name the same title as `chosen`
+    fn absorb_agreeing(&self, mut chosen: MetadataResult, tops: Vec<(&'static str, MetadataResult)>) -> MetadataResult {
+        let year = chosen.year.as_deref().and_then(matching::parse_year);
+        for (source, top) in tops {
+            if top.media_type == chosen.media_type && self.matcher.score(&chosen.title, year, chosen.media_type, &top) >= matching::CONFIDENT_SCORE {
+                self.reconcile_ids(&mut chosen, top.ids, source);
+            }
+        }
+        chosen
+    }
+
+    /// Asks the providers of `chain` with an external ID index, in order, to fill in the IDs
+    /// the match lacks
+    async fn cross_reference(&self, matched: &mut MetadataResult, chain: &[ServiceType], media_type: MediaType) {
+        for provider in chain.iter().filter_map(|service| self.providers.get(service)) {
+            if !provider.can_complete_ids(&matched.ids, media_type) {
+                continue;
+            }
+            if self.requests_blocked() || !self.consume_budget() {
+                return;
+            }
+            match provider.complete_ids(&matched.ids, media_type).await {
+                Ok(Some(ids)) => self.reconcile_ids(matched, ids, provider.name()),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Cross-referencing '{}' on {} failed: {}", matched.title, provider.name(), e),
+            }
+        }
+    }
+
+    /// Finds the MAL entry for an anime matched on another provider
+    async fn add_mal_id(&self, matched: &mut MetadataResult, media_type: MediaType, trace: &mut UnmatchedItem) {
+        let Some(mal) = self.providers.get(&ServiceType::Mal) else {
+            return;
+        };
+        let year = matched.year.as_deref().and_then(matching::parse_year);
+        let Some(results) = self.search(mal.as_ref(), &matched.title, media_type, year, trace).await else {
+            return;
+        };
+        let best = self.matcher.rank(&matched.title, year, media_type, results).into_iter().next();
+        if let Some(best) = best.filter(|b| b.score.unwrap_or_default() >= matching::CONFIDENT_SCORE) {
+            tracing::debug!("Added MAL ID {:?} to '{}'", best.ids.mal, matched.title);
+            matched.ids.mal = best.ids.mal;
+            matched.anime = true;
+        }
+    }
+
+    /// Walks a provider chain until one returns a confident match, settling for the best otherwise
+    async fn search_chain(
+        &self,
+        chain: &[ServiceType],
+        title: &str,
+        media_type: MediaType,
+        year_int: Option<i32>,
+        trace: &mut UnmatchedItem,
+    ) -> Result<MetadataResult, AppError> {
+        // Each provider's top result, so the chosen match can take IDs from the ones that agree
+        let mut tops: Vec<(&'static str, MetadataResult)> = Vec::new();
+
+        for provider in chain.iter().filter_map(|service| self.providers.get(service)) {
+            let mut results = match self.search(provider.as_ref(), title, media_type, year_int, trace).await {
+                Some(results) => results,
+                None if self.budget_exhausted() => break,
+                None => continue,
+            };
+
+            // Providers filter on the exact year; search again without it so near-miss years can still score
+            if results.is_empty() && year_int.is_some() && self.matcher.tolerates_year_drift() {
+                if let Some(unfiltered) = self.search(provider.as_ref(), title, media_type, None, trace).await {
+                    results = unfiltered;
                 }
             }
+
+            let ranked = self.matcher.rank(title, year_int, media_type, results);
+            if let Some(disambiguator) = self.disambiguator.as_ref().filter(|_| Matcher::is_ambiguous(&ranked)) {
+                match disambiguator.choose(provider.name(), title, year_int, media_type, &ranked).await? {
+                    Some(Decision::Pick(choice)) => return Ok(self.absorb_agreeing(*choice, tops)),
+                    Some(Decision::NoneOfThese) => {
+                        trace.candidates.extend(ranked.iter().map(|c| RejectedCandidate::new(provider.name(), c)));
+                        continue;
+                    }
+                    None => {}
+                }
+            }
+
+            // Stop at the first provider with a confident match, otherwise keep the best so far
+            if let Some(candidate) = ranked.into_iter().next() {
+                let score = candidate.score.unwrap_or_default();
+                tracing::debug!("Best {} match for '{}': '{}' ({:.2})", provider.name(), title, candidate.title, score);
+                if score >= matching::CONFIDENT_SCORE {
+                    return Ok(self.absorb_agreeing(candidate, tops));
+                }
+                tops.push((provider.name(), candidate));
+            }
+        }
+        // Reversed so ties go to the provider earlier in the chain
+        let best = tops
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by(|(_, a), (_, b)| a.1.score.unwrap_or_default().total_cmp(&b.1.score.unwrap_or_default()))
+            .map(|(i, _)| i);
+        match best {
+            Some(i) => {
+                let (_, best) = tops.remove(i);
+                Ok(self.absorb_agreeing(best, tops))
+            }
+            None if self.budget_exhausted() => Err(AppError::MetadataError("Provider request budget exhausted".into())),
+            None => Err(AppError::MetadataError("All providers failed".into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Counts searches and answers each with one movie named after the query
+    #[derive(Default)]
+    struct CountingProvider {
+        searches: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for CountingProvider {
+        fn name(&self) -> &'static str {
+            "Counting"
+        }
+
+        async fn search(&self, title: &str, media_type: MediaType, _year: Option<i32>) -> Result<Vec<MetadataResult>, AppError> {
+            self.searches.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![MetadataResult {
+                ids: MediaIds { tmdb: Some("1".to_string()), ..Default::default() },
+                title: title.to_string(),
+                media_type,
+                ..Default::default()
+            }])
+        }
+
+        async fn get_details(&self, id: &str, media_type: MediaType) -> Result<MetadataResult, AppError> {
+            Ok(MetadataResult {
+                ids: MediaIds { tmdb: Some(id.to_string()), imdb: Some(format!("tt{}", id)), ..Default::default() },
+                title: String::new(),
+                media_type,
+                ..Default::default()
+            })
+        }
+    }
+
+    /// Answers every search with the same result
+    struct FixedProvider {
+        name: &'static str,
+        result: MetadataResult,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for FixedProvider {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn search(&self, _title: &str, _media_type: MediaType, _year: Option<i32>) -> Result<Vec<MetadataResult>, AppError> {
+            Ok(vec![self.result.clone()])
+        }
+    }
+
+    fn movie(title: &str, ids: MediaIds) -> MetadataResult {
+        MetadataResult {
+            ids,
+            title: title.to_string(),
+            year: Some("2016".to_string()),
+            media_type: MediaType::Movie,
+            ..Default::default()
         }
-        Err(AppError::MetadataError("All providers failed".into()))
     }
-}
\ No newline at end of file
+
+    fn service(searches: &Arc<AtomicUsize>) -> MetadataService {
+        let mut providers: HashMap<ServiceType, Box<dyn MetadataProvider>> = HashMap::new();
+        providers.insert(ServiceType::Tmdb, Box::new(CountingProvider { searches: searches.clone() }));
+        let priority = ProviderPriority {
+            movies: vec![ServiceType::Tmdb],
+            shows: vec![ServiceType::Tmdb],
+            anime: vec![ServiceType::Tmdb],
+        };
+        MetadataService::from_providers(providers, priority)
+    }
+
+    #[tokio::test]
+    async fn test_local_providers_answer_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let dataset = dir.path().join("title.basics.tsv");
+        std::fs::write(&dataset, "tconst\ttitleType\tprimaryTitle\toriginalTitle\tisAdult\tstartYear\ntt2543164\tmovie\tArrival\tArrival\t0\t2016\n").unwrap();
+        let imdb = ImdbDatasetProvider::new(crate::config::ImdbDatasetConfig {
+            dataset_path: dataset,
+            index_path: dir.path().join("imdb-index.sqlite"),
+            enabled: true,
+        });
+        let mut providers: HashMap<ServiceType, Box<dyn MetadataProvider>> = HashMap::new();
+        providers.insert(ServiceType::Imdb, Box::new(imdb));
+        let chain = vec![ServiceType::Imdb];
+        let priority = ProviderPriority { movies: chain.clone(), shows: chain.clone(), anime: chain };
+        let service = MetadataService::from_providers(providers, priority)
+            .with_cache(MetadataCache::in_memory().unwrap())
+            .with_offline_mode();
+
+        let arrival = service.lookup("Arrival", MediaType::Movie, Some("2016")).await.unwrap();
+        assert_eq!(arrival.ids.imdb.as_deref(), Some("tt2543164"));
+        assert!(service.metrics().snapshot().get("IMDB dataset").is_none_or(|stats| stats.cache_hits + stats.cache_misses == 0));
+    }
+
+    #[tokio::test]
+    async fn test_offline_mode_only_uses_the_cache() {
+        let searches = Arc::new(AtomicUsize::new(0));
+        let cached = MetadataResult {
+            ids: MediaIds { tmdb: Some("438631".to_string()), ..Default::default() },
+            title: "Dune".to_string(),
+            year: Some("2021".to_string()),
+            media_type: MediaType::Movie,
+            ..Default::default()
+        };
+        let cache = MetadataCache::in_memory().unwrap();
+        cache.put_search("Counting", "Dune", None, MediaType::Movie, &[cached]).unwrap();
+        let service = service(&searches).with_cache(cache).with_offline_mode();
+
+        let dune = service.lookup("Dune", MediaType::Movie, None).await.unwrap();
+        assert_eq!(dune.ids.tmdb.as_deref(), Some("438631"));
+
+        assert!(service.lookup("Arrival", MediaType::Movie, None).await.is_err());
+        let unmatched = service.take_unmatched();
+        assert_eq!(unmatched[0].attempts[0].error.as_deref(), Some("Offline and not in the metadata cache"));
+        assert_eq!(searches.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_online_mode_searches_providers() {
+        let searches = Arc::new(AtomicUsize::new(0));
+        let service = service(&searches);
+
+        let arrival = service.lookup("Arrival", MediaType::Movie, None).await.unwrap();
+        assert_eq!(arrival.title, "Arrival");
+        assert_eq!(arrival.ids.imdb, None);
+        assert_eq!(searches.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_details_pass_merges_external_ids() {
+        let searches = Arc::new(AtomicUsize::new(0));
+        let service = service(&searches)
+            .with_details_pass()
+            .with_cache(MetadataCache::in_memory().unwrap())
+            .with_request_budget(2);
+
+        let arrival = service.lookup("Arrival", MediaType::Movie, None).await.unwrap();
+        assert_eq!(arrival.ids.tmdb.as_deref(), Some("1"));
+        assert_eq!(arrival.ids.imdb.as_deref(), Some("tt1"));
+
+        // The budget is spent, so the second lookup must find both the search and the details in the cache
+        let again = service.lookup("Arrival", MediaType::Movie, None).await.unwrap();
+        assert_eq!(again.ids.imdb.as_deref(), Some("tt1"));
+        assert_eq!(searches.load(Ordering::SeqCst), 1);
+        assert_eq!(service.metrics().snapshot()["Counting"].requests, 2);
+    }
+
+    /// Finds every title and describes it with its own runtime
+    struct DescribingProvider {
+        name: &'static str,
+        runtime: u32,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for DescribingProvider {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn search(&self, title: &str, _media_type: MediaType, _year: Option<i32>) -> Result<Vec<MetadataResult>, AppError> {
+            Ok(vec![movie(title, MediaIds { tmdb: Some("329865".to_string()), ..Default::default() })])
+        }
+
+        fn can_enrich(&self, _ids: &MediaIds) -> bool {
+            true
+        }
+
+        async fn enrich(&self, _ids: &MediaIds, _media_type: MediaType) -> Result<Option<Enrichment>, AppError> {
+            Ok(Some(Enrichment { runtime: Some(self.runtime), ..Default::default() }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enrichment_follows_the_chain() {
+        let mut providers: HashMap<ServiceType, Box<dyn MetadataProvider>> = HashMap::new();
+        providers.insert(ServiceType::Simkl, Box::new(DescribingProvider { name: "Simkl", runtime: 1 }));
+        providers.insert(ServiceType::Tmdb, Box::new(DescribingProvider { name: "TMDB", runtime: 2 }));
+        let chain = vec![ServiceType::Tmdb, ServiceType::Simkl];
+        let priority = ProviderPriority { movies: chain.clone(), shows: chain.clone(), anime: chain };
+        let service = MetadataService::from_providers(providers, priority).with_enrichment();
+
+        let arrival = service.lookup("Arrival", MediaType::Movie, None).await.unwrap();
+        assert_eq!(arrival.enrichment.and_then(|e| e.runtime), Some(2));
+    }
+
+    /// Finds every title, and knows an IMDB ID for anything with a TMDB ID
+    struct IndexProvider {
+        name: &'static str,
+        imdb: &'static str,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for IndexProvider {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn search(&self, title: &str, _media_type: MediaType, _year: Option<i32>) -> Result<Vec<MetadataResult>, AppError> {
+            Ok(vec![movie(title, MediaIds { tmdb: Some("329865".to_string()), ..Default::default() })])
+        }
+
+        fn can_complete_ids(&self, ids: &MediaIds, _media_type: MediaType) -> bool {
+            ids.imdb.is_none()
+        }
+
+        async fn complete_ids(&self, _ids: &MediaIds, _media_type: MediaType) -> Result<Option<MediaIds>, AppError> {
+            Ok(Some(MediaIds { imdb: Some(self.imdb.to_string()), ..Default::default() }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cross_reference_follows_the_chain_within_budget() {
+        let service = |budget: usize| {
+            let mut providers: HashMap<ServiceType, Box<dyn MetadataProvider>> = HashMap::new();
+            providers.insert(ServiceType::Simkl, Box::new(IndexProvider { name: "Simkl", imdb: "tt0000001" }));
+            providers.insert(ServiceType::Tmdb, Box::new(IndexProvider { name: "TMDB", imdb: "tt2543164" }));
+            let chain = vec![ServiceType::Tmdb, ServiceType::Simkl];
+            let priority = ProviderPriority { movies: chain.clone(), shows: chain.clone(), anime: chain };
+            MetadataService::from_providers(providers, priority).with_request_budget(budget)
+        };
+
+        let arrival = service(5).lookup("Arrival", MediaType::Movie, None).await.unwrap();
+        assert_eq!(arrival.ids.imdb.as_deref(), Some("tt2543164"));
+
+        // The search spends the only request, so no IDs are looked up
+        let arrival = service(1).lookup("Arrival", MediaType::Movie, None).await.unwrap();
+        assert_eq!(arrival.ids.imdb, None);
+    }
+
+    #[tokio::test]
+    async fn test_ids_from_agreeing_providers_are_combined() {
+        let mut providers: HashMap<ServiceType, Box<dyn MetadataProvider>> = HashMap::new();
+        providers.insert(ServiceType::Tmdb, Box::new(FixedProvider {
+            name: "TMDB",
+            result: movie("Arrival", MediaIds { tmdb: Some("329865".to_string()), imdb: Some("tt2543164".to_string()), ..Default::default() }),
+        }));
+        providers.insert(ServiceType::Simkl, Box::new(FixedProvider {
+            name: "Simkl",
+            result: movie("Arrival", MediaIds { simkl: Some("12".to_string()), imdb: Some("tt0000001".to_string()), ..Default::default() }),
+        }));
+        providers.insert(ServiceType::Omdb, Box::new(FixedProvider {
+            name: "OMDb",
+            result: movie("Arrivederci", MediaIds { tvdb: Some("99".to_string()), ..Default::default() }),
+        }));
+        let chain = vec![ServiceType::Tmdb, ServiceType::Simkl, ServiceType::Omdb];
+        let priority = ProviderPriority { movies: chain.clone(), shows: chain.clone(), anime: chain };
+
+        // No provider is confident about the misspelled title, so all three are searched
+        let service = MetadataService::from_providers(providers, priority);
+        let arrival = service.lookup("The Arival", MediaType::Movie, None).await.unwrap();
+        assert_eq!(arrival.ids.tmdb.as_deref(), Some("329865"));
+        assert_eq!(arrival.ids.simkl.as_deref(), Some("12"));
+        assert_eq!(arrival.ids.imdb.as_deref(), Some("tt2543164"));
+        assert_eq!(arrival.ids.tvdb, None);
+    }
+
+    /// Answers only the queries it has a result for
+    struct ScriptedProvider {
+        name: &'static str,
+        answers: Vec<(&'static str, MetadataResult)>,
+    }
+
+    #[async_trait]
+    impl MetadataProvider for ScriptedProvider {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn search(&self, title: &str, _media_type: MediaType, _year: Option<i32>) -> Result<Vec<MetadataResult>, AppError> {
+            Ok(self.answers.iter().filter(|(query, _)| *query == title).map(|(_, result)| result.clone()).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_original_title_retry() {
+        let mut money_heist = movie("Money Heist", MediaIds { tmdb: Some("71446".to_string()), ..Default::default() });
+        money_heist.original_title = Some("La casa de papel".to_string());
+        let mut providers: HashMap<ServiceType, Box<dyn MetadataProvider>> = HashMap::new();
+        providers.insert(ServiceType::Simkl, Box::new(ScriptedProvider {
+            name: "Simkl",
+            answers: vec![("La casa de papel", movie("La casa de papel", MediaIds { simkl: Some("6".to_string()), ..Default::default() }))],
+        }));
+        providers.insert(ServiceType::Tmdb, Box::new(ScriptedProvider {
+            name: "TMDB",
+            answers: vec![("La casa di carta", money_heist)],
+        }));
+        let chain = vec![ServiceType::Simkl, ServiceType::Tmdb];
+        let priority = ProviderPriority { movies: chain.clone(), shows: chain.clone(), anime: chain };
+        let service = MetadataService::from_providers(providers, priority);
+
+        // Simkl only knows the Spanish title TMDB reports as the original
+        let matched = service.lookup("La casa di carta", MediaType::Movie, None).await.unwrap();
+        assert_eq!(matched.title, "La casa de papel");
+        assert_eq!(matched.ids.simkl.as_deref(), Some("6"));
+        assert_eq!(matched.ids.tmdb.as_deref(), Some("71446"));
+        assert!(matched.score.unwrap_or_default() >= matching::CONFIDENT_SCORE);
+    }
+}
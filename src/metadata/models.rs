@@ -1,28 +1,270 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum ServiceType {
     Simkl,
     Tmdb,
     Tvdb,
     Mal,
+    Trakt,
+    Omdb,
+    Tvmaze,
+    Wikidata,
+    /// Local index over IMDB's public title dataset
+    Imdb,
+    /// Local index over TMDB's daily ID exports
+    TmdbExport,
+    /// A provider registered under its own name, such as an external command from `custom_providers`
+    Custom(String),
 }
 
+impl ServiceType {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Simkl => "simkl",
+            Self::Tmdb => "tmdb",
+            Self::Tvdb => "tvdb",
+            Self::Mal => "mal",
+            Self::Trakt => "trakt",
+            Self::Omdb => "omdb",
+            Self::Tvmaze => "tvmaze",
+            Self::Wikidata => "wikidata",
+            Self::Imdb => "imdb",
+            Self::TmdbExport => "tmdb_export",
+            Self::Custom(name) => name,
+        }
+    }
+}
 
-#[derive(Debug, Serialize, Deserialize)]
+impl TryFrom<String> for ServiceType {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ServiceType> for String {
+    fn from(service: ServiceType) -> Self {
+        service.name().to_string()
+    }
+}
+
+impl std::str::FromStr for ServiceType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "simkl" => Ok(Self::Simkl),
+            "tmdb" => Ok(Self::Tmdb),
+            "tvdb" => Ok(Self::Tvdb),
+            "mal" => Ok(Self::Mal),
+            "trakt" => Ok(Self::Trakt),
+            "omdb" => Ok(Self::Omdb),
+            "tvmaze" => Ok(Self::Tvmaze),
+            "wikidata" => Ok(Self::Wikidata),
+            "imdb" => Ok(Self::Imdb),
+            "tmdb_export" => Ok(Self::TmdbExport),
+            other if !other.is_empty() && other.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') => {
+                Ok(Self::Custom(other.to_string()))
+            }
+            other => Err(format!(
+                "invalid provider name '{}' (expected simkl, tmdb, tvdb, mal, trakt, omdb, tvmaze, wikidata, imdb, tmdb_export or a custom provider's name)",
+                other
+            )),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetadataResult {
     pub ids: MediaIds,
     pub title: String,
     pub year: Option<String>,
     pub media_type: crate::models::MediaType,
+    /// How well this result matched the searched title (0.0-1.0), set by the matcher
+    #[serde(default)]
+    pub score: Option<f64>,
+    /// Set when the provider itself classifies the title as anime
+    #[serde(default)]
+    pub anime: bool,
+    /// Extra descriptive fields, only fetched when `output.enrichment` asks for them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enrichment: Option<Enrichment>,
+    /// Title in the original language, or MAL's romaji title, when it differs from `title`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_title: Option<String>,
+    /// How many users rated or listed the title on the provider (TMDB votes, MAL members),
+    /// which breaks ties between otherwise equal candidates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub votes: Option<u64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// An empty movie match, so tests only spell out the fields they check
+#[cfg(test)]
+impl Default for MetadataResult {
+    fn default() -> Self {
+        Self {
+            ids: MediaIds::default(),
+            title: String::new(),
+            year: None,
+            media_type: crate::models::MediaType::Movie,
+            score: None,
+            anime: false,
+            enrichment: None,
+            original_title: None,
+            votes: None,
+        }
+    }
+}
+
+/// Descriptive metadata beyond the IDs the export needs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Enrichment {
+    #[serde(default)]
+    pub genres: Vec<String>,
+    /// Minutes; the typical episode length for shows
+    pub runtime: Option<u32>,
+    pub poster_url: Option<String>,
+    pub overview: Option<String>,
+    /// Episodes aired so far, specials left out; shows only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aired_episodes: Option<u32>,
+}
+
+/// Optional export columns filled from [`Enrichment`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnrichmentField {
+    Genres,
+    Runtime,
+    PosterUrl,
+    Overview,
+}
+
+impl EnrichmentField {
+    pub fn header(self) -> &'static str {
+        match self {
+            Self::Genres => "Genres",
+            Self::Runtime => "Runtime",
+            Self::PosterUrl => "PosterURL",
+            Self::Overview => "Overview",
+        }
+    }
+
+    pub fn value(self, enrichment: &Enrichment) -> String {
+        match self {
+            Self::Genres => enrichment.genres.join(", "),
+            Self::Runtime => enrichment.runtime.map(|m| m.to_string()).unwrap_or_default(),
+            Self::PosterUrl => enrichment.poster_url.clone().unwrap_or_default(),
+            Self::Overview => enrichment.overview.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct MediaIds {
     pub simkl: Option<String>,
     pub tvdb: Option<String>,
     pub tmdb: Option<String>,
+    pub imdb: Option<String>,
     pub mal: Option<String>,
+    #[serde(default)]
+    pub anilist: Option<String>,
+    #[serde(default)]
+    pub anidb: Option<String>,
+}
+
+/// What to do when two providers give different IDs of the same kind for one title
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdConflictPolicy {
+    /// Keep the ID from the provider earlier in the chain
+    #[default]
+    KeepFirst,
+    /// Leave the field empty so the importer matches on the IDs everyone agrees on
+    Drop,
+}
+
+/// Two providers disagreeing on one kind of ID
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdConflict {
+    pub field: &'static str,
+    pub known: String,
+    pub other: String,
+}
+
+impl MediaIds {
+    fn fields_mut(&mut self) -> [(&'static str, &mut Option<String>); 7] {
+        [
+            ("simkl", &mut self.simkl),
+            ("tvdb", &mut self.tvdb),
+            ("tmdb", &mut self.tmdb),
+            ("imdb", &mut self.imdb),
+            ("mal", &mut self.mal),
+            ("anilist", &mut self.anilist),
+            ("anidb", &mut self.anidb),
+        ]
+    }
+
+    /// Fills IDs missing here from `other`, never overwriting ones already known unless
+    /// `policy` drops them on conflict; returns every disagreement found
+    pub fn reconcile(&mut self, mut other: MediaIds, policy: IdConflictPolicy) -> Vec<IdConflict> {
+        let mut conflicts = Vec::new();
+        for ((field, known), (_, incoming)) in self.fields_mut().into_iter().zip(other.fields_mut()) {
+            let Some(incoming) = incoming.take() else {
+                continue;
+            };
+            match known.as_deref() {
+                None => *known = Some(incoming),
+                Some(current) if current == incoming => {}
+                Some(current) => {
+                    conflicts.push(IdConflict { field, known: current.to_string(), other: incoming });
+                    if policy == IdConflictPolicy::Drop {
+                        *known = None;
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// The ID `service` looks titles up by; IMDB-keyed services, custom ones included, share the IMDB ID
+    pub fn for_service(&self, service: &ServiceType) -> Option<&str> {
+        match service {
+            ServiceType::Simkl => self.simkl.as_deref(),
+            ServiceType::Tmdb => self.tmdb.as_deref(),
+            ServiceType::Tvdb => self.tvdb.as_deref(),
+            ServiceType::Mal => self.mal.as_deref(),
+            ServiceType::Trakt | ServiceType::Omdb | ServiceType::Tvmaze | ServiceType::Imdb | ServiceType::Custom(_) => {
+                self.imdb.as_deref()
+            }
+            // The export index knows nothing beyond the IDs it already returned
+            ServiceType::Wikidata | ServiceType::TmdbExport => None,
+        }
+    }
+}
+
+/// A single episode as a provider numbers it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeInfo {
+    pub season: u32,
+    pub episode: u32,
+    pub title: Option<String>,
+    pub air_date: Option<String>,
+}
+
+impl EpisodeInfo {
+    /// "S01E02 - Title", the form history items carry their episode in
+    pub fn code(&self) -> String {
+        let code = format!("S{:02}E{:02}", self.season, self.episode);
+        match &self.title {
+            Some(title) if !title.is_empty() => format!("{} - {}", code, title),
+            _ => code,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,6 +273,10 @@ pub struct RateLimitConfig {
     pub tmdb: RateLimit,
     pub tvdb: RateLimit,
     pub mal: RateLimit,
+    pub trakt: RateLimit,
+    pub omdb: RateLimit,
+    pub tvmaze: RateLimit,
+    pub wikidata: RateLimit,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,4 +285,92 @@ pub struct RateLimit {
     pub per_seconds: u64,
 }
 
-pub type PriorityOrder = Vec<ServiceType>;
\ No newline at end of file
+pub type PriorityOrder = Vec<ServiceType>;
+
+/// Provider fallback chains, tried in order until one returns a match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProviderPriority {
+    pub movies: PriorityOrder,
+    pub shows: PriorityOrder,
+    /// Chain for items detected as anime, movies and shows alike
+    pub anime: PriorityOrder,
+}
+
+impl Default for ProviderPriority {
+    fn default() -> Self {
+        Self {
+            movies: vec![ServiceType::Tmdb, ServiceType::Simkl, ServiceType::Wikidata],
+            shows: vec![
+                ServiceType::Simkl,
+                ServiceType::Tmdb,
+                ServiceType::Tvdb,
+                ServiceType::Mal,
+                ServiceType::Tvmaze,
+                ServiceType::Wikidata,
+            ],
+            anime: vec![ServiceType::Mal, ServiceType::Simkl, ServiceType::Tmdb],
+        }
+    }
+}
+
+impl ProviderPriority {
+    pub fn for_media_type(&self, media_type: crate::models::MediaType) -> &PriorityOrder {
+        match media_type {
+            crate::models::MediaType::Movie => &self.movies,
+            crate::models::MediaType::Tv => &self.shows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_keeps_known_ids() {
+        let mut ids = MediaIds {
+            tmdb: Some("1".to_string()),
+            ..Default::default()
+        };
+        ids.reconcile(MediaIds {
+            tmdb: Some("2".to_string()),
+            imdb: Some("tt3".to_string()),
+            anidb: Some("4".to_string()),
+            ..Default::default()
+        }, IdConflictPolicy::KeepFirst);
+        assert_eq!(ids.tmdb.as_deref(), Some("1"));
+        assert_eq!(ids.imdb.as_deref(), Some("tt3"));
+        assert_eq!(ids.anidb.as_deref(), Some("4"));
+        assert_eq!(ids.simkl, None);
+    }
+
+    #[test]
+    fn test_reconcile_reports_and_resolves_conflicts() {
+        let first = MediaIds {
+            tmdb: Some("1".to_string()),
+            imdb: Some("tt1".to_string()),
+            ..Default::default()
+        };
+        let second = MediaIds {
+            tmdb: Some("1".to_string()),
+            imdb: Some("tt2".to_string()),
+            tvdb: Some("3".to_string()),
+            ..Default::default()
+        };
+
+        let mut kept = first.clone();
+        let conflicts = kept.reconcile(second.clone(), IdConflictPolicy::KeepFirst);
+        assert_eq!(
+            conflicts,
+            vec![IdConflict { field: "imdb", known: "tt1".to_string(), other: "tt2".to_string() }]
+        );
+        assert_eq!(kept.imdb.as_deref(), Some("tt1"));
+        assert_eq!(kept.tvdb.as_deref(), Some("3"));
+
+        let mut dropped = first;
+        assert_eq!(dropped.reconcile(second, IdConflictPolicy::Drop).len(), 1);
+        assert_eq!(dropped.imdb, None);
+        assert_eq!(dropped.tmdb.as_deref(), Some("1"));
+    }
+}
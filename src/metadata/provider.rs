@@ -2,12 +2,28 @@ use async_trait::async_trait;
 use crate::error::AppError;
 use crate::{
     models::MediaType,
-    metadata::models::MetadataResult,
+    metadata::models::{Enrichment, EpisodeInfo, MediaIds, MetadataResult},
 };
 
+/// One title to search for, as queued for a batched search
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub title: String,
+    pub media_type: MediaType,
+    pub year: Option<i32>,
+    /// ISO 639-1 language to search in instead of the provider's configured one
+    pub language: Option<&'static str>,
+}
+
 #[async_trait]
 pub trait MetadataProvider: Send + Sync {
     fn name(&self) -> &'static str;
+
+    /// Whether the provider answers from local data, so searches skip the metadata cache,
+    /// the request budget and `--offline`'s ban on provider requests
+    fn is_local(&self) -> bool {
+        false
+    }
     
     async fn search(
         &self,
@@ -16,17 +32,92 @@ pub trait MetadataProvider: Send + Sync {
         year: Option<i32>,
     ) -> Result<Vec<MetadataResult>, AppError>;
     
-    #[allow(unused)]
+    /// Searches like `search`, but in `language` (ISO 639-1) when the provider can localize
+    /// its search; the default ignores the language
+    async fn search_in(
+        &self,
+        title: &str,
+        media_type: MediaType,
+        year: Option<i32>,
+        language: Option<&'static str>,
+    ) -> Result<Vec<MetadataResult>, AppError> {
+        let _ = language;
+        self.search(title, media_type, year).await
+    }
+
+    /// Most queries one `search_batch` call takes; above 1, concurrent searches are queued
+    /// and sent to the provider together
+    fn batch_size(&self) -> usize {
+        1
+    }
+
+    /// Searches for every query at once, answering in query order. Providers with a bulk
+    /// endpoint override this; the default searches one title after the other.
+    async fn search_batch(&self, queries: &[SearchQuery]) -> Result<Vec<Vec<MetadataResult>>, AppError> {
+        let mut results = Vec::with_capacity(queries.len());
+        for query in queries {
+            results.push(self.search_in(&query.title, query.media_type, query.year, query.language).await?);
+        }
+        Ok(results)
+    }
+
+    /// Fetches the title with this provider's `id`; used by the details pass to
+    /// collect external IDs that search results leave out
     async fn get_details(
         &self,
         id: &str,
         media_type: MediaType,
     ) -> Result<MetadataResult, AppError> {
-        // Note: This method is part of the public API but may not be used
-        // by the current CLI application. It's available for library users
-        // who want to get detailed information about a specific item.
         let _ = id;
         let _ = media_type;
         Err(AppError::MetadataError("get_details not implemented".into()))
     }
+
+    /// Whether `complete_ids` would send a request for `ids`; checked before one is paid
+    /// for out of the request budget
+    fn can_complete_ids(&self, ids: &MediaIds, media_type: MediaType) -> bool {
+        let _ = (ids, media_type);
+        false
+    }
+
+    /// Looks up the IDs this provider can add to `ids`, e.g. through an external ID index.
+    /// Returns None when it has nothing to offer for them.
+    async fn complete_ids(
+        &self,
+        ids: &MediaIds,
+        media_type: MediaType,
+    ) -> Result<Option<MediaIds>, AppError> {
+        let _ = (ids, media_type);
+        Ok(None)
+    }
+
+    /// Looks up one episode of the show identified by `show_ids`, using this provider's
+    /// numbering. Returns None when the provider has no such episode or cannot tell.
+    async fn get_episode(
+        &self,
+        show_ids: &MediaIds,
+        season: u32,
+        episode: u32,
+    ) -> Result<Option<EpisodeInfo>, AppError> {
+        let _ = (show_ids, season, episode);
+        Ok(None)
+    }
+
+    /// Whether `enrich` would send a request for `ids`; checked before one is paid for
+    /// out of the request budget
+    fn can_enrich(&self, ids: &MediaIds) -> bool {
+        let _ = ids;
+        false
+    }
+
+    /// Describes the title identified by `ids` (genres, runtime, poster, overview).
+    /// Returns None when the provider does not know the title or has no such fields.
+    async fn enrich(
+        &self,
+        ids: &MediaIds,
+        media_type: MediaType,
+    ) -> Result<Option<Enrichment>, AppError> {
+        let _ = (ids, media_type);
+        Ok(None)
+    }
 }
@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::metadata::RateLimit;
+
+/// Token bucket allowing bursts of `calls` requests, refilled evenly over `per_seconds`
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(limit: &RateLimit) -> Self {
+        let capacity = f64::from(limit.calls.max(1));
+        Self {
+            capacity,
+            refill_per_sec: capacity / limit.per_seconds.max(1) as f64,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent and takes a token for it
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().await;
+                let now = Instant::now();
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_sec)
+            };
+            tracing::trace!("Rate limit reached, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bucket_allows_burst_then_waits() {
+        let limiter = RateLimiter::new(&RateLimit { calls: 2, per_seconds: 1 });
+        let start = Instant::now();
+
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+}
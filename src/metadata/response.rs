@@ -0,0 +1,129 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+
+use crate::error::AppError;
+
+/// Characters of a payload quoted in errors, enough to recognize it without flooding the log
+const EXCERPT_CHARS: usize = 200;
+
+fn excerpt(payload: &str) -> String {
+    match payload.char_indices().nth(EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}…", &payload[..end]),
+        None => payload.to_string(),
+    }
+}
+
+/// Checks a deserialized provider response for what serde cannot express, such as
+/// "either `title` or `name`"; the error names what is wrong
+pub trait Checked {
+    fn check(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Parses `body` as `provider`'s response, naming the field that failed and quoting the payload
+pub fn parse<T: DeserializeOwned>(provider: &str, body: &str) -> Result<T, AppError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(body);
+    serde_path_to_error::deserialize(deserializer).map_err(|e| AppError::ResponseError {
+        provider: provider.to_string(),
+        detail: format!("{} at `{}` in {}", e.inner(), e.path(), excerpt(body)),
+    })
+}
+
+/// Reads and parses a successful response like [`parse`]
+pub async fn json<T: DeserializeOwned>(provider: &str, response: reqwest::Response) -> Result<T, AppError> {
+    parse(provider, &response.text().await?)
+}
+
+/// Fails a single-item response that parsed but does not pass [`Checked::check`]
+pub fn checked<T: Checked>(provider: &str, item: T) -> Result<T, AppError> {
+    item.check().map_err(|problem| AppError::ResponseError { provider: provider.to_string(), detail: problem })?;
+    Ok(item)
+}
+
+/// A list in a provider response whose entries are parsed and checked one by one, so a
+/// malformed entry costs only itself instead of failing the whole search
+pub struct Entries<T> {
+    valid: Vec<T>,
+    rejected: Vec<String>,
+}
+
+impl<T> Default for Entries<T> {
+    fn default() -> Self {
+        Self { valid: Vec::new(), rejected: Vec::new() }
+    }
+}
+
+impl<'de, T: DeserializeOwned + Checked> Deserialize<'de> for Entries<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut entries = Self::default();
+        for (index, value) in Vec::<serde_json::Value>::deserialize(deserializer)?.into_iter().enumerate() {
+            let payload = excerpt(&value.to_string());
+            let problem = match serde_path_to_error::deserialize::<_, T>(value) {
+                Ok(entry) => match entry.check() {
+                    Ok(()) => {
+                        entries.valid.push(entry);
+                        continue;
+                    }
+                    Err(problem) => problem,
+                },
+                Err(e) => format!("{} at `{}`", e.inner(), e.path()),
+            };
+            entries.rejected.push(format!("entry {}: {} in {}", index, problem, payload));
+        }
+        Ok(entries)
+    }
+}
+
+impl<T> Entries<T> {
+    /// The entries that parsed, after logging the ones skipped
+    pub fn into_valid(self, provider: &str) -> Vec<T> {
+        for rejected in self.rejected {
+            tracing::warn!("Skipping malformed {} result, {}", provider, rejected);
+        }
+        self.valid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Deserialize)]
+    struct Item {
+        id: u32,
+        #[serde(default)]
+        title: String,
+    }
+
+    impl Checked for Item {
+        fn check(&self) -> Result<(), String> {
+            if self.title.is_empty() { Err("no `title`".to_string()) } else { Ok(()) }
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Search {
+        results: Entries<Item>,
+    }
+
+    #[test]
+    fn test_malformed_entries_are_skipped_and_named() {
+        let search: Search = parse("Test", r#"{"results": [{"id": 1, "title": "Dune"}, {"id": "x", "title": "Heat"}, {"id": 3}]}"#).unwrap();
+        assert_eq!(search.results.rejected.len(), 2);
+        assert!(search.results.rejected[0].starts_with("entry 1: invalid type") && search.results.rejected[0].contains("`id`"));
+        assert!(search.results.rejected[1].contains("no `title`") && search.results.rejected[1].contains(r#""id":3"#));
+        let valid = search.results.into_valid("Test");
+        assert_eq!(valid.len(), 1);
+        assert_eq!((valid[0].id, valid[0].title.as_str()), (1, "Dune"));
+    }
+
+    #[test]
+    fn test_parse_errors_name_provider_field_and_payload() {
+        let error = parse::<Search>("TMDB", r#"{"page": 1}"#).err().unwrap().to_string();
+        assert!(error.starts_with("Unexpected TMDB response: missing field `results`"), "{}", error);
+        assert!(error.contains(r#"{"page": 1}"#));
+        let long = format!(r#"{{"results": "{}"}}"#, "x".repeat(500));
+        assert!(parse::<Search>("TMDB", &long).err().unwrap().to_string().ends_with('…'));
+    }
+}
@@ -0,0 +1,172 @@
+use rand::Rng;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::RetryConfig;
+use crate::error::AppError;
+use crate::metadata::RateLimiter;
+
+/// Exponential backoff for provider requests that fail transiently
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    /// Fraction of each delay randomised so parallel lookups don't retry in lockstep
+    jitter: f64,
+    /// Cap on waits requested by the provider itself
+    max_retry_after: Duration,
+}
+
+/// Reset time sent by TMDB and others as seconds since the Unix epoch
+const RATE_LIMIT_RESET: &str = "x-ratelimit-reset";
+
+/// How long the provider asked us to wait, from `Retry-After` (seconds or an HTTP date)
+/// or else a rate-limit reset timestamp
+fn requested_delay(headers: &HeaderMap, now: SystemTime) -> Option<Duration> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok()).map(str::trim);
+    let until = |at: SystemTime| at.duration_since(now).unwrap_or_default();
+
+    if let Some(value) = header(RETRY_AFTER.as_str()) {
+        if let Ok(seconds) = value.parse::<u64>() {
+            return Some(Duration::from_secs(seconds));
+        }
+        if let Ok(date) = chrono::DateTime::parse_from_rfc2822(value) {
+            let at = UNIX_EPOCH + Duration::from_secs(date.timestamp().max(0) as u64);
+            return Some(until(at));
+        }
+    }
+    let reset = header(RATE_LIMIT_RESET)?.parse::<u64>().ok()?;
+    Some(until(UNIX_EPOCH + Duration::from_secs(reset)))
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &RetryConfig) -> Self {
+        Self {
+            max_attempts: config.max_attempts.max(1),
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+            jitter: config.jitter.clamp(0.0, 1.0),
+            max_retry_after: Duration::from_millis(config.max_retry_after_ms),
+        }
+    }
+
+    fn is_retryable(status: StatusCode) -> bool {
+        status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+    }
+
+    /// Delay before retry number `attempt` (1-based): base * 2^(attempt-1), capped, +/- jitter
+    fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(attempt - 1));
+        let capped = exponential.min(self.max_delay);
+        if self.jitter == 0.0 {
+            return capped;
+        }
+        let factor = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+        capped.mul_f64(factor)
+    }
+
+    /// Sends `request`, taking a rate-limit token per attempt and retrying 429, 5xx and network errors.
+    /// A wait the provider asks for in the response headers replaces the backoff delay.
+    /// The last response is returned as-is once attempts run out, so callers still see the status.
+    pub async fn send(&self, request: RequestBuilder, limiter: &RateLimiter) -> Result<Response, AppError> {
+        let mut attempt = 1;
+        loop {
+            let this_try = request
+                .try_clone()
+                .ok_or_else(|| AppError::MetadataError("Request cannot be retried".into()))?;
+            limiter.acquire().await;
+
+            let (retry_reason, requested) = match this_try.send().await {
+                Ok(response) if Self::is_retryable(response.status()) && attempt < self.max_attempts => {
+                    let requested = requested_delay(response.headers(), SystemTime::now());
+                    (format!("status {}", response.status()), requested)
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if (e.is_timeout() || e.is_connect() || e.is_request()) && attempt < self.max_attempts => {
+                    (e.to_string(), None)
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let delay = match requested {
+                Some(requested) => requested.min(self.max_retry_after),
+                None => self.delay(attempt),
+            };
+            tracing::debug!(
+                "Provider request failed ({}), retry {}/{} in {:?}",
+                retry_reason,
+                attempt,
+                self.max_attempts - 1,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy::from_config(&RetryConfig {
+            max_attempts: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 500,
+            jitter,
+            max_retry_after_ms: 60_000,
+        })
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_requested_delay_from_headers() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_445_412_470);
+        assert_eq!(requested_delay(&headers(&[("retry-after", "7")]), now), Some(Duration::from_secs(7)));
+        // 2015-10-21 07:28:00 UTC, ten seconds after `now`
+        let date = headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]);
+        assert_eq!(requested_delay(&date, now), Some(Duration::from_secs(10)));
+        let reset = headers(&[("x-ratelimit-reset", "1445412475")]);
+        assert_eq!(requested_delay(&reset, now), Some(Duration::from_secs(5)));
+        let past = headers(&[("x-ratelimit-reset", "1445412000")]);
+        assert_eq!(requested_delay(&past, now), Some(Duration::ZERO));
+        assert_eq!(requested_delay(&headers(&[("retry-after", "soon")]), now), None);
+        assert_eq!(requested_delay(&HeaderMap::new(), now), None);
+    }
+
+    #[test]
+    fn test_delay_doubles_up_to_cap() {
+        let policy = policy(0.0);
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_jitter_stays_in_range() {
+        let policy = policy(0.5);
+        for _ in 0..20 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(300));
+        }
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(RetryPolicy::is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(RetryPolicy::is_retryable(StatusCode::BAD_GATEWAY));
+        assert!(!RetryPolicy::is_retryable(StatusCode::NOT_FOUND));
+        assert!(!RetryPolicy::is_retryable(StatusCode::UNAUTHORIZED));
+    }
+}
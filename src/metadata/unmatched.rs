@@ -0,0 +1,118 @@
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::metadata::{MediaIds, MetadataResult};
+use crate::models::MediaType;
+
+/// Rejected candidates kept per unmatched item
+const MAX_CANDIDATES: usize = 5;
+
+/// One provider search made while looking a title up
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchAttempt {
+    pub provider: String,
+    pub query: String,
+    pub year: Option<i32>,
+    pub results: usize,
+    pub error: Option<String>,
+}
+
+/// A candidate the matcher or the user turned down
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedCandidate {
+    pub provider: String,
+    pub title: String,
+    pub year: Option<String>,
+    pub ids: MediaIds,
+    pub score: Option<f64>,
+}
+
+impl RejectedCandidate {
+    pub fn new(provider: &str, result: &MetadataResult) -> Self {
+        Self {
+            provider: provider.to_string(),
+            title: result.title.clone(),
+            year: result.year.clone(),
+            ids: result.ids.clone(),
+            score: result.score,
+        }
+    }
+}
+
+/// Everything tried for a history item that ended up without a match
+#[derive(Debug, Clone, Serialize)]
+pub struct UnmatchedItem {
+    pub title: String,
+    pub media_type: MediaType,
+    pub year: Option<String>,
+    pub attempts: Vec<SearchAttempt>,
+    pub candidates: Vec<RejectedCandidate>,
+}
+
+/// Collects unmatched items across concurrent lookups, keeping the latest try per title
+#[derive(Default)]
+pub struct UnmatchedLog {
+    items: Mutex<Vec<UnmatchedItem>>,
+}
+
+impl UnmatchedLog {
+    pub fn record(&self, mut item: UnmatchedItem) {
+        item.candidates.sort_by(|a, b| b.score.unwrap_or_default().total_cmp(&a.score.unwrap_or_default()));
+        item.candidates.truncate(MAX_CANDIDATES);
+
+        let mut items = self.items.lock().unwrap_or_else(|e| e.into_inner());
+        match items.iter_mut().find(|i| i.title == item.title && i.media_type == item.media_type) {
+            Some(existing) => *existing = item,
+            None => items.push(item),
+        }
+    }
+
+    /// Drops a title that matched on a later attempt
+    pub fn clear(&self, title: &str, media_type: MediaType) {
+        let mut items = self.items.lock().unwrap_or_else(|e| e.into_inner());
+        items.retain(|i| i.title != title || i.media_type != media_type);
+    }
+
+    pub fn take(&self) -> Vec<UnmatchedItem> {
+        std::mem::take(&mut *self.items.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unmatched(title: &str, attempts: usize) -> UnmatchedItem {
+        UnmatchedItem {
+            title: title.to_string(),
+            media_type: MediaType::Movie,
+            year: None,
+            attempts: vec![
+                SearchAttempt {
+                    provider: "TMDB".to_string(),
+                    query: title.to_string(),
+                    year: None,
+                    results: 0,
+                    error: None,
+                };
+                attempts
+            ],
+            candidates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_retries_replace_earlier_entries_and_matches_clear_them() {
+        let log = UnmatchedLog::default();
+        log.record(unmatched("Obscure", 1));
+        log.record(unmatched("Obscure", 2));
+        log.record(unmatched("Found Later", 1));
+        log.clear("Found Later", MediaType::Movie);
+
+        let items = log.take();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].attempts.len(), 2);
+        assert!(log.take().is_empty());
+    }
+}
@@ -14,9 +14,12 @@ pub struct WatchHistoryItem {
     pub date: String,
     pub rating: Option<u8>,
     pub memo: Option<String>,
+    /// Prime Video detail page the history row linked to
+    #[serde(default)]
+    pub source_url: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum MediaType {
     Movie,
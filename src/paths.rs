@@ -0,0 +1,95 @@
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+
+/// Where config, state and caches live: `~/.config`, `~/.local/share` and `~/.cache` on
+/// Linux, `%APPDATA%` and `%LOCALAPPDATA%` on Windows, `~/Library/...` on macOS
+#[derive(Debug, Clone)]
+pub struct AppDirs {
+    /// `config.json` and `overrides.toml`
+    pub config: PathBuf,
+    /// Session, tokens, watermark and matching decisions
+    pub data: PathBuf,
+    /// Metadata cache, downloaded datasets and the indexes built from them
+    pub cache: PathBuf,
+}
+
+impl AppDirs {
+    /// The platform directories; None when the home directory cannot be told
+    pub fn locate() -> Option<Self> {
+        let dirs = ProjectDirs::from("com", "easly1989", "primevideo-to-simkl")?;
+        Some(Self {
+            config: dirs.config_dir().to_path_buf(),
+            data: dirs.data_dir().to_path_buf(),
+            cache: dirs.cache_dir().to_path_buf(),
+        })
+    }
+
+    /// Config next to the executable and everything else in the working directory, as before
+    /// the platform directories were used
+    pub fn beside(exe_dir: &Path) -> Self {
+        Self { config: exe_dir.to_path_buf(), data: PathBuf::from("."), cache: PathBuf::from(".") }
+    }
+
+    pub fn config_file(&self) -> PathBuf {
+        self.config.join("config.json")
+    }
+}
+
+/// `path` under `dir` when relative; absolute paths are kept as they are
+pub fn rebase(dir: &Path, path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        dir.join(path.strip_prefix(".").unwrap_or(path))
+    }
+}
+
+/// Moves `from` to `to` unless `to` already exists, creating the parent of `to`.
+/// Returns whether a file was moved.
+pub fn migrate(from: &Path, to: &Path) -> std::io::Result<bool> {
+    if to.exists() || !from.is_file() || same_file(from, to) {
+        return Ok(false);
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // A rename fails across file systems, e.g. from a USB stick to the home directory
+    if std::fs::rename(from, to).is_err() {
+        std::fs::copy(from, to)?;
+        std::fs::remove_file(from)?;
+    }
+    tracing::info!("Moved {} to {}", from.display(), to.display());
+    Ok(true)
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_files_move_to_the_new_location_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("config.json");
+        let new = rebase(&dir.path().join("config"), Path::new("./config.json"));
+        assert_eq!(new, dir.path().join("config").join("config.json"));
+        assert_eq!(rebase(dir.path(), Path::new("/srv/tokens.json")), PathBuf::from("/srv/tokens.json"));
+
+        std::fs::write(&old, "{}").unwrap();
+        assert!(migrate(&old, &new).unwrap());
+        assert!(!old.exists());
+        assert_eq!(std::fs::read_to_string(&new).unwrap(), "{}");
+
+        // A file already in the new location is never overwritten
+        std::fs::write(&old, "{\"stale\": true}").unwrap();
+        assert!(!migrate(&old, &new).unwrap());
+        assert_eq!(std::fs::read_to_string(&new).unwrap(), "{}");
+    }
+}
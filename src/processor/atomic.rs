@@ -0,0 +1,58 @@
+use std::{
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+};
+
+use crate::error::AppError;
+
+/// `export.csv` is written as `export.csv.partial` and only renamed once it is complete
+pub fn partial_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.partial", name))
+}
+
+/// Moves a finished partial file over `path` in one rename, after syncing it to disk so a
+/// crash right after the rename cannot leave an empty export
+pub fn commit(partial: &Path, path: &Path) -> Result<(), AppError> {
+    OpenOptions::new().write(true).open(partial)?.sync_all()?;
+    std::fs::rename(partial, path)?;
+    Ok(())
+}
+
+/// Fills the partial file of `path` with `write`, then commits it. If writing fails or the
+/// run is killed, `path` keeps its old contents and the partial file is left beside it.
+pub fn write<F>(path: &Path, write: F) -> Result<(), AppError>
+where
+    F: FnOnce(&Path) -> Result<(), AppError>,
+{
+    let partial = partial_path(path);
+    if partial.exists() {
+        tracing::warn!("Replacing {}, left by an interrupted run", partial.display());
+    }
+    write(&partial)?;
+    commit(&partial, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_write_keeps_the_old_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        std::fs::write(&path, "old").unwrap();
+
+        let failed = write(&path, |partial| {
+            std::fs::write(partial, "half")?;
+            Err(AppError::ParseError("interrupted".to_string()))
+        });
+        assert!(failed.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old");
+        assert_eq!(std::fs::read_to_string(dir.path().join("export.csv.partial")).unwrap(), "half");
+
+        write(&path, |partial| Ok(std::fs::write(partial, "new")?)).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert!(!partial_path(&path).exists());
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+
+/// `export.csv` is backed up as `export.csv.20241015-182419.bak`
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Copies an existing `path` to a timestamped `.bak` beside it, then deletes all but the newest
+/// `keep` backups of it. Does nothing when `keep` is 0 or there is no file yet; returns the
+/// backup written.
+pub fn rotate(path: &Path, keep: usize) -> Result<Option<PathBuf>, AppError> {
+    if keep == 0 || !path.is_file() {
+        return Ok(None);
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let stamp = chrono::Local::now().format(STAMP_FORMAT);
+    let backup = path.with_file_name(format!("{}.{}.bak", name, stamp));
+    std::fs::copy(path, &backup)?;
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let prefix = format!("{}.", name);
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|candidate| {
+            let file_name = candidate.file_name().unwrap_or_default().to_string_lossy();
+            file_name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(".bak"))
+                .is_some_and(|stamp| chrono::NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT).is_ok())
+        })
+        .collect();
+    // The stamps sort by time, so the oldest backups come first
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        if let Err(e) = std::fs::remove_file(old) {
+            tracing::warn!("Failed to remove old backup {}: {}", old.display(), e);
+        }
+    }
+    Ok(Some(backup))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_only_the_newest_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        assert_eq!(rotate(&path, 2).unwrap(), None);
+
+        std::fs::write(&path, "latest").unwrap();
+        for stamp in ["20240101-000000", "20240201-000000", "20240301-000000"] {
+            std::fs::write(dir.path().join(format!("export.csv.{}.bak", stamp)), stamp).unwrap();
+        }
+        std::fs::write(dir.path().join("export.csv.notes.bak"), "not a backup").unwrap();
+
+        let backup = rotate(&path, 2).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "latest");
+        let mut names: Vec<String> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 4);
+        assert_eq!(names[0], "export.csv");
+        assert_eq!(names[1], "export.csv.20240301-000000.bak");
+        assert_eq!(names[3], "export.csv.notes.bak");
+
+        assert_eq!(rotate(&path, 0).unwrap(), None);
+    }
+}
@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::WatchedAtConfig,
+    error::AppError,
+    metadata::EnrichmentField,
+    models::MediaType,
+    processor::history_processor::ProcessedItem,
+};
+
+/// One entry of `output.columns`: either a field name, used as its own header, or a header
+/// with a template such as `"{imdb_id|tmdb_id}"` or `"{title} ({year})"`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ColumnSpec {
+    Field(String),
+    Template { header: String, template: String },
+}
+
+/// A value an export row can hold
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    SimklId,
+    TvdbId,
+    TmdbId,
+    ImdbId,
+    MalId,
+    AnilistId,
+    AnidbId,
+    Type,
+    Title,
+    MatchedTitle,
+    OriginalTitle,
+    Year,
+    Episode,
+    Season,
+    EpisodeNumber,
+    WatchedDate,
+    WatchedAt,
+    Score,
+    Rating,
+    Plays,
+    /// Detail page of the Prime Video history row
+    PrimeUrl,
+    /// Prime Video ID from that page's URL
+    Asin,
+    Enrichment(EnrichmentField),
+}
+
+const FIELD_NAMES: &str = "simkl_id, tvdb_id, tmdb_id, imdb_id, mal_id, anilist_id, anidb_id, type, title, \
+    matched_title, original_title, year, episode, season, episode_number, watched_date, watched_at, score, \
+    rating, plays, prime_url, asin, genres, runtime, poster_url, overview";
+
+impl Field {
+    fn parse(name: &str) -> Result<Self, AppError> {
+        Ok(match name.trim() {
+            "simkl_id" => Self::SimklId,
+            "tvdb_id" => Self::TvdbId,
+            "tmdb_id" => Self::TmdbId,
+            "imdb_id" => Self::ImdbId,
+            "mal_id" => Self::MalId,
+            "anilist_id" => Self::AnilistId,
+            "anidb_id" => Self::AnidbId,
+            "type" => Self::Type,
+            "title" => Self::Title,
+            "matched_title" => Self::MatchedTitle,
+            "original_title" => Self::OriginalTitle,
+            "year" => Self::Year,
+            "episode" => Self::Episode,
+            "season" => Self::Season,
+            "episode_number" => Self::EpisodeNumber,
+            "watched_date" => Self::WatchedDate,
+            "watched_at" => Self::WatchedAt,
+            "score" => Self::Score,
+            "rating" => Self::Rating,
+            "plays" => Self::Plays,
+            "prime_url" => Self::PrimeUrl,
+            "asin" => Self::Asin,
+            "genres" => Self::Enrichment(EnrichmentField::Genres),
+            "runtime" => Self::Enrichment(EnrichmentField::Runtime),
+            "poster_url" => Self::Enrichment(EnrichmentField::PosterUrl),
+            "overview" => Self::Enrichment(EnrichmentField::Overview),
+            other => {
+                return Err(invalid(format!("unknown column field '{}'; expected one of: {}", other, FIELD_NAMES)))
+            }
+        })
+    }
+
+    fn value(self, item: &ProcessedItem, clock: &WatchedAtConfig) -> String {
+        let ids = &item.metadata.ids;
+        let code = item.episode_code();
+        match self {
+            Self::SimklId => ids.simkl.clone().unwrap_or_default(),
+            Self::TvdbId => ids.tvdb.clone().unwrap_or_default(),
+            Self::TmdbId => ids.tmdb.clone().unwrap_or_default(),
+            Self::ImdbId => ids.imdb.clone().unwrap_or_default(),
+            Self::MalId => ids.mal.clone().unwrap_or_default(),
+            Self::AnilistId => ids.anilist.clone().unwrap_or_default(),
+            Self::AnidbId => ids.anidb.clone().unwrap_or_default(),
+            Self::Type => match item.media_type {
+                MediaType::Movie => "movie".to_string(),
+                MediaType::Tv => "tv".to_string(),
+            },
+            Self::Title => item.title.clone(),
+            Self::MatchedTitle => item.metadata.title.clone(),
+            Self::OriginalTitle => item.metadata.original_title.clone().unwrap_or_default(),
+            Self::Year => item.metadata.year.clone().unwrap_or_default(),
+            Self::Episode => item.episode.clone().unwrap_or_default(),
+            Self::Season => code.map(|(season, _)| season.to_string()).unwrap_or_default(),
+            Self::EpisodeNumber => code.map(|(_, episode)| episode.to_string()).unwrap_or_default(),
+            Self::WatchedDate => item.date.clone(),
+            Self::WatchedAt => item.watched_at(clock),
+            Self::Score => item.metadata.score.map(|score| format!("{:.2}", score)).unwrap_or_default(),
+            Self::Rating => item.rating.map(|rating| rating.to_string()).unwrap_or_default(),
+            Self::Plays => item.plays.to_string(),
+            Self::PrimeUrl => item.source_url.clone().unwrap_or_default(),
+            Self::Asin => item.asin().unwrap_or_default().to_string(),
+            Self::Enrichment(field) => item.metadata.enrichment.as_ref().map(|e| field.value(e)).unwrap_or_default(),
+        }
+    }
+}
+
+/// Where a placeholder alternative takes its text from
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+    Field(Field),
+    Literal(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Piece {
+    Text(String),
+    /// `{a|b|"text"}`: the first alternative that is not empty
+    Placeholder(Vec<Source>),
+}
+
+/// A parsed column template: text with `{field}` placeholders. `{a|b}` takes the first
+/// non-empty field, a quoted alternative (`{rating|"5"}`) is used as is, and `{{`/`}}` are
+/// literal braces.
+#[derive(Debug, Clone, PartialEq)]
+struct Template(Vec<Piece>);
+
+impl Template {
+    fn parse(template: &str) -> Result<Self, AppError> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => inner.push(c),
+                            None => return Err(invalid(format!("unclosed '{{' in template '{}'", template))),
+                        }
+                    }
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    let sources = inner
+                        .split('|')
+                        .map(|alternative| {
+                            let alternative = alternative.trim();
+                            match alternative.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+                                Some(literal) => Ok(Source::Literal(literal.to_string())),
+                                None => Field::parse(alternative).map(Source::Field),
+                            }
+                        })
+                        .collect::<Result<_, _>>()?;
+                    pieces.push(Piece::Placeholder(sources));
+                }
+                '}' => return Err(invalid(format!("unmatched '}}' in template '{}'", template))),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Self(pieces))
+    }
+
+    fn render(&self, item: &ProcessedItem, clock: &WatchedAtConfig) -> String {
+        let mut out = String::new();
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(text) => out.push_str(text),
+                Piece::Placeholder(sources) => {
+                    let value = sources
+                        .iter()
+                        .map(|source| match source {
+                            Source::Field(field) => field.value(item, clock),
+                            Source::Literal(literal) => literal.clone(),
+                        })
+                        .find(|value| !value.is_empty());
+                    out.push_str(&value.unwrap_or_default());
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A configured export column, ready to render
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    header: String,
+    template: Template,
+}
+
+impl Column {
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    pub fn value(&self, item: &ProcessedItem, clock: &WatchedAtConfig) -> String {
+        self.template.render(item, clock)
+    }
+
+    /// Whether the column shows genres, runtime, poster or overview, which need a details lookup
+    pub fn uses_enrichment(&self) -> bool {
+        self.template.0.iter().any(|piece| match piece {
+            Piece::Placeholder(sources) => {
+                sources.iter().any(|source| matches!(source, Source::Field(Field::Enrichment(_))))
+            }
+            Piece::Text(_) => false,
+        })
+    }
+}
+
+/// Checks every configured column up front, so a typo fails before anything is written
+pub fn parse(specs: &[ColumnSpec]) -> Result<Vec<Column>, AppError> {
+    specs
+        .iter()
+        .map(|spec| match spec {
+            ColumnSpec::Field(name) => Ok(Column {
+                header: name.clone(),
+                template: Template(vec![Piece::Placeholder(vec![Source::Field(Field::parse(name)?)])]),
+            }),
+            ColumnSpec::Template { header, template } => {
+                Ok(Column { header: header.clone(), template: Template::parse(template)? })
+            }
+        })
+        .collect()
+}
+
+fn invalid(message: String) -> AppError {
+    AppError::ConfigError(config::ConfigError::Message(format!("output.columns: {}", message)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{MediaIds, MetadataResult};
+
+    fn item() -> ProcessedItem {
+        ProcessedItem {
+            title: "Fleabag".to_string(),
+            date: "2024-03-01".to_string(),
+            media_type: MediaType::Tv,
+            metadata: MetadataResult {
+                ids: MediaIds { tmdb: Some("67070".to_string()), ..Default::default() },
+                title: "Fleabag".to_string(),
+                year: Some("2016".to_string()),
+                media_type: MediaType::Tv,
+                score: Some(0.934),
+                ..Default::default()
+            },
+            episode: Some("S02E03 - Episode 3".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_templates_render_fields_fallbacks_and_literals() {
+        let columns = parse(&[
+            ColumnSpec::Field("tmdb_id".to_string()),
+            ColumnSpec::Template { header: "id".to_string(), template: "{imdb_id|tmdb_id}".to_string() },
+            ColumnSpec::Template { header: "name".to_string(), template: "{title} ({year})".to_string() },
+            ColumnSpec::Template { header: "ep".to_string(), template: "{season}x{episode_number}".to_string() },
+            ColumnSpec::Template { header: "rating".to_string(), template: "{{{runtime|\"none\"}}}".to_string() },
+            ColumnSpec::Field("score".to_string()),
+            ColumnSpec::Field("asin".to_string()),
+        ])
+        .unwrap();
+        let item = ProcessedItem {
+            source_url: Some("https://www.primevideo.com/detail/0QRGK8V2PFBFKNZZHKSY5F1XZP/ref=atv_hm_hom".to_string()),
+            ..item()
+        };
+        let values: Vec<String> = columns.iter().map(|column| column.value(&item, &WatchedAtConfig::default())).collect();
+        assert_eq!(columns[0].header(), "tmdb_id");
+        assert!(columns[4].uses_enrichment() && !columns[2].uses_enrichment());
+        assert_eq!(values, ["67070", "67070", "Fleabag (2016)", "2x3", "{none}", "0.93", "0QRGK8V2PFBFKNZZHKSY5F1XZP"]);
+    }
+
+    #[test]
+    fn test_invalid_templates_are_rejected() {
+        for template in ["{nope}", "{title", "title}"] {
+            let spec = ColumnSpec::Template { header: "x".to_string(), template: template.to_string() };
+            assert!(parse(&[spec]).is_err(), "{}", template);
+        }
+        assert!(parse(&[ColumnSpec::Field("rating10".to_string())]).is_err());
+    }
+}
@@ -1,64 +1,913 @@
 use crate::{
-    config::OutputConfig,
+    config::{CsvDialect, OutputConfig, OutputFormat, RewatchMode, SortKey, SortOrder, SplitPeriod, WatchedAtConfig, BOM},
     error::AppError,
+    metadata::{EnrichmentField, MetadataResult},
     models::MediaType,
-    processor::history_processor::ProcessedItem,
+    processor::{atomic, backup, columns::{self, Column, ColumnSpec}, history_processor::ProcessedItem, ical, kodi, merge, output_path::{self, PathVars, Period}, report, serializd, simkl_backup, sqlite, xlsx},
 };
 use csv::Writer;
-use std::{fs::File, path::Path};
+use serde::Serialize;
+use std::{cmp::Ordering, collections::{BTreeMap, HashMap}, fs::File, io::{BufWriter, Write}, path::{Path, PathBuf}};
 
+/// Position of LastEpWatched in the Simkl layout, followed by Season and Episode
+const LAST_EP_COLUMN: usize = 10;
+
+/// The TV-only columns a movies-only Simkl file leaves out
+const EPISODE_COLUMNS: std::ops::Range<usize> = LAST_EP_COLUMN..LAST_EP_COLUMN + 3;
+
+/// One item as the JSON formats write it: what was scraped plus the whole match
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    title: &'a str,
+    watched_date: &'a str,
+    media_type: MediaType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    episode: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    season_number: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    episode_number: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rating: Option<u8>,
+    plays: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_url: Option<&'a str>,
+    #[serde(rename = "match")]
+    matched: &'a MetadataResult,
+}
+
+impl<'a> From<&'a ProcessedItem> for JsonRecord<'a> {
+    fn from(item: &'a ProcessedItem) -> Self {
+        let code = item.episode_code();
+        Self {
+            title: &item.title,
+            watched_date: &item.date,
+            media_type: item.media_type,
+            episode: item.episode.as_deref(),
+            season_number: code.map(|(season, _)| season),
+            episode_number: code.map(|(_, episode)| episode),
+            rating: item.rating,
+            plays: item.plays,
+            source_url: item.source_url.as_deref(),
+            matched: &item.metadata,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct CsvGenerator {
-    output_path: String,
+    /// The configured path, placeholders and all; see [`CsvGenerator::path`]
+    output_path: PathBuf,
+    path_vars: PathVars,
+    split_by: Option<SplitPeriod>,
+    enrichment: Vec<EnrichmentField>,
+    format: OutputFormat,
+    split: bool,
+    merge: bool,
+    columns: Vec<ColumnSpec>,
+    rewatches: RewatchMode,
+    sort: Option<(SortKey, SortOrder)>,
+    dialect: CsvDialect,
+    backups: usize,
+    watched_at: WatchedAtConfig,
+    auto_status: bool,
+}
+
+/// Folds repeat watches of the same movie or episode into the row of the latest one,
+/// counting them in `plays`; rows keep the position of their first watch
+fn count_plays(items: Vec<ProcessedItem>) -> Vec<ProcessedItem> {
+    let mut rows: Vec<ProcessedItem> = Vec::with_capacity(items.len());
+    let mut seen: HashMap<(MediaType, String, Option<String>), usize> = HashMap::new();
+    for item in items {
+        let key = (item.media_type, item.title.clone(), item.episode.clone());
+        match seen.get(&key) {
+            Some(&index) => {
+                let plays = rows[index].plays + item.plays;
+                if item.date > rows[index].date {
+                    rows[index] = item;
+                }
+                rows[index].plays = plays;
+            }
+            None => {
+                seen.insert(key, rows.len());
+                rows.push(item);
+            }
+        }
+    }
+    rows
+}
+
+/// Orders rows by `key`; the sort is stable, so rows that compare equal keep their order.
+/// Rows without a year stay last whichever way a year sort runs.
+fn sort_items(items: &mut [ProcessedItem], key: SortKey, order: SortOrder) {
+    let directed = |ordering: Ordering| match order {
+        SortOrder::Asc => ordering,
+        SortOrder::Desc => ordering.reverse(),
+    };
+    let title = |item: &ProcessedItem| item.title.to_lowercase();
+    let watched = |item: &ProcessedItem| (item.date.clone(), item.episode_code());
+    let year = |item: &ProcessedItem| item.metadata.year.as_deref().and_then(crate::matching::parse_year);
+    items.sort_by(|a, b| match key {
+        SortKey::WatchedAt => directed(watched(a).cmp(&watched(b))),
+        SortKey::Title => directed(title(a).cmp(&title(b)).then_with(|| watched(a).cmp(&watched(b)))),
+        SortKey::Year => match (year(a), year(b)) {
+            (Some(x), Some(y)) => directed(x.cmp(&y).then_with(|| title(a).cmp(&title(b)))),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => directed(title(a).cmp(&title(b))),
+        },
+    });
+}
+
+/// `export.csv` becomes `export-movies.csv` and `export-shows.csv` in the same directory
+fn split_paths(path: &Path) -> (PathBuf, PathBuf) {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    (
+        path.with_file_name(format!("{}-movies{}", stem, extension)),
+        path.with_file_name(format!("{}-shows{}", stem, extension)),
+    )
+}
+
+/// A templated path such as `exports/{year}/export.csv` may name a directory that is not there yet
+fn create_parent(path: &Path) -> Result<(), AppError> {
+    match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => Ok(std::fs::create_dir_all(dir)?),
+        None => Ok(()),
+    }
+}
+
+/// `export.csv` becomes `export-watchlist.csv` in the same directory; the watchlist is
+/// always a Simkl CSV, since Simkl's layout is the one with a list status
+pub fn watchlist_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}-watchlist.csv", stem))
 }
 
 impl CsvGenerator {
     pub fn new(config: OutputConfig) -> Self {
+        let rewatches = config.rewatch_mode();
         Self {
-            output_path: config.path.to_string_lossy().to_string(),
+            output_path: config.path,
+            path_vars: PathVars::today(),
+            split_by: config.split_by,
+            enrichment: config.enrichment,
+            format: config.format,
+            split: config.split,
+            merge: config.merge,
+            columns: config.columns,
+            rewatches,
+            sort: config.sort.map(|key| (key, config.sort_order)),
+            dialect: config.csv,
+            backups: config.backups,
+            watched_at: config.watched_at,
+            auto_status: config.auto_status,
         }
     }
 
-    pub fn generate(&self, items: Vec<ProcessedItem>) -> Result<(), AppError> {
-        let path = Path::new(&self.output_path);
-        let file = File::create(path)?;
-        let mut wtr = Writer::from_writer(file);
-
-        // Write header
-        wtr.write_record(&[
-            "simkl_id", "TVDB_ID", "TMDB", "IMDB_ID", "MAL_ID",
-            "Type", "Title", "Year", "LastEpWatched", "Watchlist",
-            "WatchedDate", "Rating", "Memo"
-        ])?;
+    /// Names the profile the export is for, filling the path's `{profile}`
+    pub fn set_profile(&mut self, profile: Option<String>) {
+        self.path_vars.profile = profile;
+    }
+
+    /// The export path with its placeholders filled in; a split-by-period export writes
+    /// next to it, one file per period
+    pub fn path(&self) -> Result<PathBuf, AppError> {
+        output_path::render(&self.output_path, &self.path_vars, None)
+    }
+
+    /// Rejects setting combinations the format cannot honour, and parses the custom columns
+    fn checked_columns(&self) -> Result<Vec<Column>, AppError> {
+        self.path()?;
+        if self.split && self.format != OutputFormat::Simkl {
+            return Err(AppError::ConfigError(config::ConfigError::Message(
+                "Splitting into movies and shows files is only supported for the simkl format".to_string(),
+            )));
+        }
+        if self.merge && !matches!(self.format, OutputFormat::Simkl | OutputFormat::Trakt) {
+            return Err(AppError::ConfigError(config::ConfigError::Message(
+                "Merging into an existing export is only supported for the CSV formats (simkl, trakt)".to_string(),
+            )));
+        }
+        let columns = columns::parse(&self.columns)?;
+        if !columns.is_empty() && !matches!(self.format, OutputFormat::Simkl | OutputFormat::Trakt) {
+            return Err(AppError::ConfigError(config::ConfigError::Message(
+                "Custom columns only apply to the CSV formats (simkl, trakt)".to_string(),
+            )));
+        }
+        Ok(columns)
+    }
+
+    /// Backs up each file the export to `path` is about to write over
+    fn back_up(&self, path: &Path) -> Result<(), AppError> {
+        let targets = if self.split {
+            let (movies, shows) = split_paths(path);
+            vec![movies, shows]
+        } else {
+            vec![path.to_path_buf()]
+        };
+        for target in targets {
+            if let Some(backup) = backup::rotate(&target, self.backups)? {
+                tracing::info!("Backed up {} to {}", target.display(), backup.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether rows can be written as they are matched; sorting, play counts, merging, the
+    /// JSON array and the workbook all need the whole history first
+    pub fn can_stream(&self) -> bool {
+        matches!(self.format, OutputFormat::Simkl | OutputFormat::Trakt | OutputFormat::Ndjson)
+            && self.sort.is_none()
+            && self.rewatches == RewatchMode::Rows
+            && !self.merge
+            && self.split_by.is_none()
+    }
 
-        // Write each record
+    /// Creates the partial export file (both files with `split`) and writes the header; rows
+    /// are then added batch by batch with [`RowStream::write`] and the file put in place with
+    /// [`RowStream::finish`]
+    pub fn stream(&self) -> Result<RowStream, AppError> {
+        let columns = self.checked_columns()?;
+        let path = &self.path()?;
+        create_parent(path)?;
+        self.back_up(path)?;
+        let targets = if self.split {
+            let (movies, shows) = split_paths(path);
+            vec![movies, shows]
+        } else {
+            vec![path.to_path_buf()]
+        };
+        let files: Vec<(PathBuf, PathBuf)> =
+            targets.into_iter().map(|target| (atomic::partial_path(&target), target)).collect();
+        let open = |partial: &Path, episodes: bool| -> Result<Writer<File>, AppError> {
+            let mut wtr = self.create_csv(partial)?;
+            wtr.write_record(self.csv_header(episodes, &columns))?;
+            wtr.flush()?;
+            Ok(wtr)
+        };
+        let sink = match self.format {
+            OutputFormat::Ndjson => Sink::Ndjson(BufWriter::new(File::create(&files[0].0)?)),
+            _ if self.split => Sink::Split(Box::new(SplitWriters { movies: open(&files[0].0, false)?, shows: open(&files[1].0, true)? })),
+            _ => Sink::Csv(Box::new(open(&files[0].0, true)?)),
+        };
+        Ok(RowStream { generator: self.clone(), columns, sink, files, rows: 0 })
+    }
+
+    /// The rows as they would be exported: rewatches folded into plays and sorted as configured
+    pub fn prepare(&self, items: Vec<ProcessedItem>) -> Result<Vec<ProcessedItem>, AppError> {
+        self.checked_columns()?;
+        let mut items = match self.rewatches {
+            RewatchMode::Rows => items,
+            RewatchMode::Plays => count_plays(items),
+        };
+        if let Some((key, order)) = self.sort {
+            sort_items(&mut items, key, order);
+        }
+        Ok(items)
+    }
+
+    pub fn generate(&self, items: Vec<ProcessedItem>) -> Result<(), AppError> {
+        let columns = self.checked_columns()?;
+        let items = self.prepare(items)?;
+        let Some(split) = self.split_by else {
+            return self.generate_file(&self.path()?, items, &columns);
+        };
+        // Rows keep their order within each period's file
+        let mut periods: BTreeMap<Option<Period>, Vec<ProcessedItem>> = BTreeMap::new();
         for item in items {
-            let ids = item.metadata.ids;
-            let last_ep = item.episode.unwrap_or_default();
-            let watch_status = match item.media_type {
-                MediaType::Movie => "completed",
-                MediaType::Tv => if last_ep.is_empty() { "completed" } else { "watching" },
+            periods.entry(Period::of(&item.date, split)).or_default().push(item);
+        }
+        for (period, items) in periods {
+            let path = match period {
+                Some(period) => output_path::render(&self.output_path, &self.path_vars, Some(period))?,
+                None => self.path()?,
             };
+            tracing::info!("Writing {} rows to {}", items.len(), path.display());
+            self.generate_file(&path, items, &columns)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `items` to `path` (to its movies and shows files with `split`) in the format
+    fn generate_file(&self, path: &Path, items: Vec<ProcessedItem>, columns: &[Column]) -> Result<(), AppError> {
+        create_parent(path)?;
+        self.back_up(path)?;
+        match self.format {
+            OutputFormat::Simkl if self.split => {
+                let (movies, shows): (Vec<_>, Vec<_>) =
+                    items.into_iter().partition(|item| item.media_type == MediaType::Movie);
+                let (movies_path, shows_path) = split_paths(path);
+                for (path, items, episodes) in [(movies_path, movies, false), (shows_path, shows, true)] {
+                    self.write_csv_file(&path, items, episodes, columns)?;
+                }
+            }
+            OutputFormat::Simkl | OutputFormat::Trakt => self.write_csv_file(path, items, true, columns)?,
+            OutputFormat::Json => atomic::write(path, |partial| {
+                let mut out = BufWriter::new(File::create(partial)?);
+                let records: Vec<JsonRecord> = items.iter().map(JsonRecord::from).collect();
+                serde_json::to_writer_pretty(&mut out, &records)?;
+                writeln!(out)?;
+                out.flush()?;
+                Ok(())
+            })?,
+            OutputFormat::Ndjson => atomic::write(path, |partial| {
+                let mut out = BufWriter::new(File::create(partial)?);
+                for item in &items {
+                    serde_json::to_writer(&mut out, &JsonRecord::from(item))?;
+                    writeln!(out)?;
+                }
+                out.flush()?;
+                Ok(())
+            })?,
+            OutputFormat::Xlsx => {
+                atomic::write(path, |partial| xlsx::write(partial, &items, self.rewatches == RewatchMode::Plays))?
+            }
+            OutputFormat::Serializd => atomic::write(path, |partial| {
+                let mut wtr = self.create_csv(partial)?;
+                let seasons = serializd::write(&mut wtr, &items)?;
+                wtr.flush()?;
+                tracing::info!("Wrote {} show seasons for Serializd", seasons);
+                Ok(())
+            })?,
+            OutputFormat::Kodi => atomic::write(path, |partial| kodi::write(partial, &items))?,
+            OutputFormat::Sqlite => atomic::write(path, |partial| {
+                let titles = sqlite::write(partial, &items, &self.watched_at)?;
+                tracing::info!("Wrote {} watches of {} titles to the database", items.len(), titles);
+                Ok(())
+            })?,
+            OutputFormat::SimklBackup => atomic::write(path, |partial| {
+                let titles = simkl_backup::write(partial, &items, &self.watched_at, self.auto_status)?;
+                tracing::info!("Wrote {} titles in Simkl's backup format", titles);
+                Ok(())
+            })?,
+            OutputFormat::Markdown => atomic::write(path, |partial| Ok(std::fs::write(partial, report::markdown(&items))?))?,
+            OutputFormat::Html => atomic::write(path, |partial| Ok(std::fs::write(partial, report::html(&items))?))?,
+            OutputFormat::Ics => atomic::write(path, |partial| {
+                Ok(std::fs::write(partial, ical::calendar(&items, chrono::Utc::now()))?)
+            })?,
+            #[cfg(feature = "parquet")]
+            OutputFormat::Parquet => {
+                atomic::write(path, |partial| super::parquet_export::write(partial, &items, &self.watched_at))?
+            }
+            #[cfg(not(feature = "parquet"))]
+            OutputFormat::Parquet => {
+                return Err(AppError::ConfigError(config::ConfigError::Message(
+                    "--format parquet needs a build with `--features parquet`".to_string(),
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the watchlist items, which carry no watch date, as plan-to-watch rows of a Simkl
+    /// CSV next to the export, and returns its path
+    pub fn generate_watchlist(&self, items: Vec<ProcessedItem>) -> Result<PathBuf, AppError> {
+        let path = watchlist_path(&self.path()?);
+        if let Some(backup) = backup::rotate(&path, self.backups)? {
+            tracing::info!("Backed up {} to {}", path.display(), backup.display());
+        }
+        atomic::write(&path, |partial| {
+            let mut wtr = self.create_csv(partial)?;
+            wtr.write_record(self.simkl_header(true))?;
+            for item in items {
+                wtr.write_record(self.simkl_record(item, true))?;
+            }
+            wtr.flush()?;
+            Ok(())
+        })?;
+        Ok(path)
+    }
+
+    /// Writes a CSV export to `path`, or in merge mode adds just the rows an existing file lacks
+    fn write_csv_file(
+        &self,
+        path: &Path,
+        items: Vec<ProcessedItem>,
+        episodes: bool,
+        columns: &[Column],
+    ) -> Result<(), AppError> {
+        if !(self.merge && path.exists()) {
+            return atomic::write(path, |partial| {
+                let mut wtr = self.create_csv(partial)?;
+                self.write_csv(&mut wtr, items, episodes, columns)?;
+                wtr.flush()?;
+                Ok(())
+            });
+        }
+        let mut rendered = self.dialect.writer().from_writer(Vec::new());
+        self.write_csv(&mut rendered, items, episodes, columns)?;
+        let rendered = rendered.into_inner().map_err(|e| e.into_error())?;
+        // Appended to a copy, so the existing export stays whole until the merge is done
+        atomic::write(path, |partial| {
+            std::fs::copy(path, partial)?;
+            let appended = merge::append(partial, &rendered, &self.dialect)?;
+            tracing::info!("Added {} new rows to {}", appended, path.display());
+            Ok(())
+        })
+    }
+
+    /// A new CSV file in the configured dialect, starting with the BOM if one is wanted
+    fn create_csv(&self, path: &Path) -> Result<Writer<File>, AppError> {
+        let mut file = File::create(path)?;
+        if self.dialect.bom {
+            file.write_all(BOM)?;
+        }
+        Ok(self.dialect.writer().from_writer(file))
+    }
+
+    /// The configured columns if there are any, the format's built-in layout otherwise
+    fn write_csv<W: Write>(
+        &self,
+        wtr: &mut Writer<W>,
+        items: Vec<ProcessedItem>,
+        episodes: bool,
+        columns: &[Column],
+    ) -> Result<(), AppError> {
+        wtr.write_record(self.csv_header(episodes, columns))?;
+        for item in items {
+            wtr.write_record(self.csv_record(item, episodes, columns))?;
+        }
+        Ok(())
+    }
+
+    fn csv_header<'a>(&'a self, episodes: bool, columns: &'a [Column]) -> Vec<&'a str> {
+        if !columns.is_empty() {
+            return columns.iter().map(Column::header).collect();
+        }
+        match self.format {
+            OutputFormat::Trakt => self.trakt_header(),
+            _ => self.simkl_header(episodes),
+        }
+    }
+
+    fn csv_record(&self, item: ProcessedItem, episodes: bool, columns: &[Column]) -> Vec<String> {
+        if !columns.is_empty() {
+            return columns.iter().map(|column| column.value(&item, &self.watched_at)).collect();
+        }
+        match self.format {
+            OutputFormat::Trakt => self.trakt_record(item),
+            _ => self.simkl_record(item, episodes),
+        }
+    }
+
+    /// Simkl's import layout; `episodes` is false for a movies-only file, which drops the
+    /// LastEpWatched, Season and Episode columns
+    fn simkl_header(&self, episodes: bool) -> Vec<&str> {
+        let mut header = vec![
+            "simkl_id", "TVDB_ID", "TMDB", "IMDB_ID", "MAL_ID", "AniList_ID", "AniDB_ID",
+            "Type", "Title", "Year", "LastEpWatched", "Season", "Episode", "Watchlist",
+            "WatchedDate", "Rating", "Memo"
+        ];
+        if !episodes {
+            header.drain(EPISODE_COLUMNS);
+        }
+        if self.rewatches == RewatchMode::Plays {
+            header.push("Plays");
+        }
+        header.extend(self.enrichment.iter().map(|field| field.header()));
+        header
+    }
+
+    fn simkl_record(&self, item: ProcessedItem, episodes: bool) -> Vec<String> {
+        let watched_at = item.watched_at(&self.watched_at);
+        let watch_status = item.watch_status(self.auto_status);
+        let code = item.episode_code();
+        let ids = item.metadata.ids;
+        // Simkl writes episodes as "s1e2"; an episode without a number is kept as scraped
+        let last_ep = match code {
+            Some((season, episode)) => format!("s{}e{}", season, episode),
+            None => item.episode.unwrap_or_default(),
+        };
+
+        let enrichment = item.metadata.enrichment.unwrap_or_default();
+        let mut record = vec![
+            ids.simkl.unwrap_or_default(),
+            ids.tvdb.unwrap_or_default(),
+            ids.tmdb.unwrap_or_default(),
+            ids.imdb.unwrap_or_default(),
+            ids.mal.unwrap_or_default(),
+            ids.anilist.unwrap_or_default(),
+            ids.anidb.unwrap_or_default(),
+            match item.media_type {
+                MediaType::Movie => "movie".to_string(),
+                MediaType::Tv => "tv".to_string(),
+            },
+            item.title,
+            item.metadata.year.unwrap_or_default(),
+            last_ep,
+            code.map(|(season, _)| season.to_string()).unwrap_or_default(),
+            code.map(|(_, episode)| episode.to_string()).unwrap_or_default(),
+            watch_status.to_string(),
+            watched_at,
+            item.rating.map(|rating| rating.to_string()).unwrap_or_default(),
+            "".to_string(), // Memo (empty)
+        ];
+        if !episodes {
+            record.drain(EPISODE_COLUMNS);
+        }
+        if self.rewatches == RewatchMode::Plays {
+            record.push(item.plays.to_string());
+        }
+        record.extend(self.enrichment.iter().map(|field| field.value(&enrichment)));
+        record
+    }
+
+    /// One row per movie or episode, as Trakt's importer expects. Episodes without a number
+    /// become a `show` row, which Trakt treats as the whole show.
+    fn trakt_header(&self) -> Vec<&str> {
+        let mut header = vec![
+            "imdb_id", "tmdb_id", "tvdb_id", "type", "title", "year", "season", "episode", "watched_at", "rating",
+        ];
+        if self.rewatches == RewatchMode::Plays {
+            header.push("plays");
+        }
+        header
+    }
+
+    fn trakt_record(&self, item: ProcessedItem) -> Vec<String> {
+        let (kind, season, episode) = match (item.media_type, item.episode_code()) {
+            (MediaType::Movie, _) => ("movie", String::new(), String::new()),
+            (MediaType::Tv, Some((season, episode))) => ("episode", season.to_string(), episode.to_string()),
+            (MediaType::Tv, None) => ("show", String::new(), String::new()),
+        };
+        let watched_at = item.watched_at(&self.watched_at);
+        let ids = item.metadata.ids;
+        let mut record = vec![
+            ids.imdb.unwrap_or_default(),
+            ids.tmdb.unwrap_or_default(),
+            ids.tvdb.unwrap_or_default(),
+            kind.to_string(),
+            item.title,
+            item.metadata.year.unwrap_or_default(),
+            season,
+            episode,
+            watched_at,
+            item.rating.map(|rating| rating.to_string()).unwrap_or_default(),
+        ];
+        if self.rewatches == RewatchMode::Plays {
+            record.push(item.plays.to_string());
+        }
+        record
+    }
+}
+
+/// An export written batch by batch while the history is being matched. Each batch is
+/// flushed to the partial file, so the rows written so far survive a run that fails halfway,
+/// while the export itself is only replaced by [`RowStream::finish`].
+pub struct RowStream {
+    generator: CsvGenerator,
+    columns: Vec<Column>,
+    sink: Sink,
+    /// Each partial file with the export it becomes
+    files: Vec<(PathBuf, PathBuf)>,
+    rows: usize,
+}
+
+// CSV writers carry large buffers, so they are boxed to keep the variants alike in size
+enum Sink {
+    Csv(Box<Writer<File>>),
+    Split(Box<SplitWriters>),
+    Ndjson(BufWriter<File>),
+}
+
+struct SplitWriters {
+    movies: Writer<File>,
+    shows: Writer<File>,
+}
 
-            wtr.write_record(&[
-                ids.simkl.unwrap_or_default(),
-                ids.tvdb.unwrap_or_default(),
-                ids.tmdb.unwrap_or_default(),
-                ids.mal.unwrap_or_default(),
-                match item.media_type {
-                    MediaType::Movie => "movie".to_string(),
-                    MediaType::Tv => "tv".to_string(),
-                },
-                item.title,
-                item.metadata.year.unwrap_or_default(),
-                last_ep,
-                watch_status.to_string(),
-                item.date,
-                "".to_string(), // Rating (empty)
-                "".to_string(), // Memo (empty)
-            ])?;
-        }
-
-        wtr.flush()?;
+impl RowStream {
+    pub fn write(&mut self, items: Vec<ProcessedItem>) -> Result<(), AppError> {
+        self.rows += items.len();
+        match &mut self.sink {
+            Sink::Csv(wtr) => {
+                for item in items {
+                    wtr.write_record(self.generator.csv_record(item, true, &self.columns))?;
+                }
+                wtr.flush()?;
+            }
+            Sink::Split(writers) => {
+                let SplitWriters { movies, shows } = &mut **writers;
+                for item in items {
+                    let (wtr, episodes) = match item.media_type {
+                        MediaType::Movie => (&mut *movies, false),
+                        MediaType::Tv => (&mut *shows, true),
+                    };
+                    wtr.write_record(self.generator.csv_record(item, episodes, &self.columns))?;
+                }
+                movies.flush()?;
+                shows.flush()?;
+            }
+            Sink::Ndjson(out) => {
+                for item in &items {
+                    serde_json::to_writer(&mut *out, &JsonRecord::from(item))?;
+                    writeln!(out)?;
+                }
+                out.flush()?;
+            }
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Rows written so far
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The partial files holding the rows so far
+    pub fn partials(&self) -> Vec<&Path> {
+        self.files.iter().map(|(partial, _)| partial.as_path()).collect()
+    }
+
+    /// Closes the partial files and renames each over its export
+    pub fn finish(self) -> Result<(), AppError> {
+        drop(self.sink);
+        for (partial, path) in &self.files {
+            atomic::commit(partial, path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{MediaIds, MetadataResult};
+    use std::collections::BTreeMap;
+
+    fn output_config(path: &Path, format: OutputFormat) -> OutputConfig {
+        OutputConfig {
+            path: path.to_path_buf(),
+            unmatched_report: path.with_file_name("unmatched.json"),
+            unmatched_csv: true,
+            watermark: path.with_file_name("watermark.json"),
+            enrichment: Vec::new(),
+            format,
+            split: false,
+            split_by: None,
+            episode_rows: false,
+            auto_status: false,
+            merge: false,
+            columns: Vec::new(),
+            rewatches: BTreeMap::new(),
+            dedup: Vec::new(),
+            sort: None,
+            sort_order: SortOrder::Asc,
+            filter: Default::default(),
+            csv: CsvDialect::default(),
+            backups: 0,
+            watched_at: WatchedAtConfig { timezone: crate::config::Timezone::Utc, ..Default::default() },
+        }
+    }
+
+    fn item(title: &str, media_type: MediaType, episode: Option<&str>) -> ProcessedItem {
+        ProcessedItem {
+            title: title.to_string(),
+            date: "2024-03-01".to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids: MediaIds { imdb: Some("tt5687612".to_string()), tmdb: Some("67070".to_string()), ..Default::default() },
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_trakt_rows_per_movie_and_episode() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trakt.csv");
+        let generator = CsvGenerator::new(output_config(&path, OutputFormat::Trakt));
+        generator
+            .generate(vec![
+                item("Fleabag", MediaType::Tv, Some("S02E03 - Episode 3")),
+                ProcessedItem { rating: Some(8), ..item("Fleabag", MediaType::Tv, Some("S00 - Special")) },
+            ])
+            .unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "imdb_id,tmdb_id,tvdb_id,type,title,year,season,episode,watched_at,rating");
+        assert_eq!(lines[1], "tt5687612,67070,,episode,Fleabag,2016,2,3,2024-03-01T00:00:00Z,");
+        assert_eq!(lines[2], "tt5687612,67070,,show,Fleabag,2016,,,2024-03-01T00:00:00Z,8");
+    }
+
+    #[test]
+    fn test_ndjson_writes_one_full_match_per_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.ndjson");
+        let generator = CsvGenerator::new(output_config(&path, OutputFormat::Ndjson));
+        generator
+            .generate(vec![item("Fleabag", MediaType::Tv, Some("S02E03")), item("Arrival", MediaType::Movie, None)])
+            .unwrap();
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["season_number"], 2);
+        assert_eq!(lines[0]["match"]["ids"]["imdb"], "tt5687612");
+        assert_eq!(lines[1]["media_type"], "movie");
+        assert!(lines[1].get("episode").is_none());
+    }
+
+    #[test]
+    fn test_split_writes_movies_and_shows_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        let generator = CsvGenerator::new(OutputConfig { split: true, ..output_config(&path, OutputFormat::Simkl) });
+        generator
+            .generate(vec![item("Fleabag", MediaType::Tv, Some("S02E03")), item("Arrival", MediaType::Movie, None)])
+            .unwrap();
+
+        assert!(!path.exists());
+        let movies = std::fs::read_to_string(dir.path().join("export-movies.csv")).unwrap();
+        let movies: Vec<&str> = movies.lines().collect();
+        assert_eq!(movies.len(), 2);
+        assert!(!movies[0].contains("LastEpWatched"));
+        assert_eq!(movies[1], ",,67070,tt5687612,,,,movie,Arrival,2016,completed,2024-03-01T00:00:00Z,,");
+
+        let shows = std::fs::read_to_string(dir.path().join("export-shows.csv")).unwrap();
+        let shows: Vec<&str> = shows.lines().collect();
+        assert_eq!(shows.len(), 2);
+        assert!(shows[0].contains("LastEpWatched"));
+        assert!(shows[1].contains(",tv,Fleabag,2016,s2e3,2,3,watching,"));
+    }
+
+    #[test]
+    fn test_watchlist_rows_are_plan_to_watch_without_a_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.json");
+        let generator = CsvGenerator::new(output_config(&path, OutputFormat::Json));
+        let planned = |title, media_type| ProcessedItem { date: String::new(), ..item(title, media_type, None) };
+        let written = generator
+            .generate_watchlist(vec![planned("Arrival", MediaType::Movie), planned("Fleabag", MediaType::Tv)])
+            .unwrap();
+
+        assert_eq!(written, dir.path().join("export-watchlist.csv"));
+        let csv = std::fs::read_to_string(&written).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].contains("Watchlist"));
+        assert_eq!(lines[1], ",,67070,tt5687612,,,,movie,Arrival,2016,,,,plantowatch,,,");
+        assert_eq!(lines[2], ",,67070,tt5687612,,,,tv,Fleabag,2016,,,,plantowatch,,,");
+    }
+
+    #[test]
+    fn test_simkl_layout_matches_golden_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        let generator = CsvGenerator::new(output_config(&path, OutputFormat::Simkl));
+        let mut anime = item("Frieren", MediaType::Tv, Some("S01E28 - It Would Be Embarrassing When We Meet Again"));
+        anime.metadata.ids = MediaIds { mal: Some("52991".to_string()), anilist: Some("154587".to_string()), ..Default::default() };
+        anime.metadata.year = Some("2023".to_string());
+        anime.date = "2024-03-22".to_string();
+        anime.rating = Some(9);
+        generator
+            .generate(vec![
+                item("Arrival", MediaType::Movie, None),
+                item("Fleabag", MediaType::Tv, Some("S02E03 - Episode 3")),
+                item("Fleabag", MediaType::Tv, Some("S00 - Special")),
+                anime,
+            ])
+            .unwrap();
+
+        let golden = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/simkl_export.csv");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), std::fs::read_to_string(golden).unwrap());
+    }
+
+    #[test]
+    fn test_rewatches_counted_as_plays_for_one_format_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trakt.csv");
+        let config = OutputConfig {
+            rewatches: BTreeMap::from([(OutputFormat::Trakt, RewatchMode::Plays)]),
+            ..output_config(&path, OutputFormat::Trakt)
+        };
+        let items = || {
+            vec![
+                item("Arrival", MediaType::Movie, None),
+                item("Fleabag", MediaType::Tv, Some("S02E03")),
+                ProcessedItem { date: "2024-05-10".to_string(), ..item("Arrival", MediaType::Movie, None) },
+            ]
+        };
+        CsvGenerator::new(config.clone()).generate(items()).unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with(",rating,plays"));
+        assert_eq!(lines[1], "tt5687612,67070,,movie,Arrival,2016,,,2024-05-10T00:00:00Z,,2");
+        assert!(lines[2].ends_with(",1"));
+
+        // The setting is per format, so the Simkl export still has a row per watch
+        let simkl = OutputConfig { format: OutputFormat::Simkl, ..config };
+        CsvGenerator::new(simkl).generate(items()).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(csv.lines().count(), 4);
+        assert!(!csv.contains("Plays"));
+    }
+
+    #[test]
+    fn test_split_by_year_writes_a_file_per_year() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("{year}").join("export.csv");
+        let config = OutputConfig { split_by: Some(SplitPeriod::Year), ..output_config(&path, OutputFormat::Trakt) };
+        let generator = CsvGenerator::new(config);
+        assert!(!generator.can_stream());
+        generator
+            .generate(vec![
+                item("Arrival", MediaType::Movie, None),
+                ProcessedItem { date: "2023-12-31".to_string(), ..item("Fleabag", MediaType::Tv, Some("S01E01")) },
+                ProcessedItem { date: "2024-05-10".to_string(), ..item("Fleabag", MediaType::Tv, Some("S01E02")) },
+            ])
+            .unwrap();
+
+        let older = std::fs::read_to_string(dir.path().join("2023").join("export.csv")).unwrap();
+        assert_eq!(older.lines().count(), 2);
+        let newer = std::fs::read_to_string(dir.path().join("2024").join("export.csv")).unwrap();
+        let lines: Vec<&str> = newer.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].contains(",Arrival,") && lines[2].contains(",2024-05-10T"));
+    }
+
+    #[test]
+    fn test_sort_by_year_puts_rows_without_a_year_last() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trakt.csv");
+        let config = OutputConfig { sort: Some(SortKey::Year), sort_order: SortOrder::Desc, ..output_config(&path, OutputFormat::Trakt) };
+        let mut unknown = item("Unknown", MediaType::Movie, None);
+        unknown.metadata.year = None;
+        let mut dune = item("Dune", MediaType::Movie, None);
+        dune.metadata.year = Some("2021".to_string());
+        CsvGenerator::new(config)
+            .generate(vec![unknown, item("Arrival", MediaType::Movie, None), dune])
+            .unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let titles: Vec<&str> = csv.lines().skip(1).map(|line| line.split(',').nth(4).unwrap()).collect();
+        assert_eq!(titles, ["Dune", "Arrival", "Unknown"]);
+    }
+
+    #[test]
+    fn test_stream_flushes_each_batch_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        let generator = CsvGenerator::new(OutputConfig { split: true, ..output_config(&path, OutputFormat::Simkl) });
+        assert!(generator.can_stream());
+        let mut stream = generator.stream().unwrap();
+        stream.write(vec![item("Arrival", MediaType::Movie, None)]).unwrap();
+
+        // Readable in the partial files while the stream is still open, as after a crash
+        let movies = std::fs::read_to_string(dir.path().join("export-movies.csv.partial")).unwrap();
+        assert_eq!(movies.lines().count(), 2);
+        let shows = std::fs::read_to_string(dir.path().join("export-shows.csv.partial")).unwrap();
+        assert_eq!(shows.lines().count(), 1);
+        assert!(shows.contains("LastEpWatched"));
+        assert!(!dir.path().join("export-shows.csv").exists());
+
+        stream.write(vec![item("Fleabag", MediaType::Tv, Some("S02E03"))]).unwrap();
+        assert_eq!(stream.rows(), 2);
+        stream.finish().unwrap();
+        let shows = std::fs::read_to_string(dir.path().join("export-shows.csv")).unwrap();
+        assert!(shows.lines().nth(1).unwrap().contains(",tv,Fleabag,2016,s2e3,2,3,watching,"));
+        assert!(!dir.path().join("export-shows.csv.partial").exists());
+
+        let sorted = OutputConfig { sort: Some(SortKey::Title), ..output_config(&path, OutputFormat::Simkl) };
+        assert!(!CsvGenerator::new(sorted).can_stream());
+    }
+
+    #[test]
+    fn test_semicolons_quotes_and_bom_for_excel() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trakt.csv");
+        let dialect = CsvDialect {
+            delimiter: crate::config::Delimiter::Semicolon,
+            quote: crate::config::QuoteStyle::Always,
+            bom: true,
+        };
+        let config = OutputConfig { csv: dialect, merge: true, ..output_config(&path, OutputFormat::Trakt) };
+        let generator = CsvGenerator::new(config);
+        generator.generate(vec![item("Arrival", MediaType::Movie, None)]).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(BOM));
+        let csv = String::from_utf8(bytes[BOM.len()..].to_vec()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("\"imdb_id\";\"tmdb_id\";"));
+        assert_eq!(lines[1], r#""tt5687612";"67070";"";"movie";"Arrival";"2016";"";"";"2024-03-01T00:00:00Z";"""#);
+
+        // Merging reads the file back in the same dialect, BOM and all
+        generator
+            .generate(vec![item("Arrival", MediaType::Movie, None), item("Fleabag", MediaType::Tv, Some("S02E03"))])
+            .unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(csv.lines().count(), 3);
+        assert!(csv.lines().nth(2).unwrap().contains(r#";"episode";"Fleabag";"2016";"2";"3";"#));
+    }
+}
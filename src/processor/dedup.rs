@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::{
+    matching::{simplify, TitleNormalizer},
+    processor::history_processor::ProcessedItem,
+};
+
+/// A way of telling that two history rows are the same watch listed twice, as Prime Video
+/// does for the HD and UHD listings of one movie
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupRule {
+    /// Same provider ID (any of them), watch date and episode
+    ProviderId,
+    /// Same title after normalization (quality and edition tags stripped), watch date and episode
+    TitleDate,
+}
+
+pub fn default_rules() -> Vec<DedupRule> {
+    vec![DedupRule::ProviderId, DedupRule::TitleDate]
+}
+
+impl DedupRule {
+    fn keys(self, item: &ProcessedItem, normalizer: &TitleNormalizer) -> Vec<String> {
+        let watch = format!(
+            "{:?}\u{1f}{}\u{1f}{}",
+            item.media_type,
+            item.date,
+            match item.episode_code() {
+                Some((season, episode)) => format!("S{}E{}", season, episode),
+                None => item.episode.clone().unwrap_or_default(),
+            }
+        );
+        match self {
+            Self::ProviderId => {
+                let ids = &item.metadata.ids;
+                [
+                    ("simkl", &ids.simkl),
+                    ("tvdb", &ids.tvdb),
+                    ("tmdb", &ids.tmdb),
+                    ("imdb", &ids.imdb),
+                    ("mal", &ids.mal),
+                    ("anilist", &ids.anilist),
+                    ("anidb", &ids.anidb),
+                ]
+                .into_iter()
+                .filter_map(|(name, id)| id.as_ref().map(|id| format!("{}={}\u{1f}{}", name, id, watch)))
+                .collect()
+            }
+            Self::TitleDate => {
+                let title = simplify(&normalizer.normalize(&item.title));
+                vec![format!("title={}\u{1f}{}", title, watch)]
+            }
+        }
+    }
+}
+
+/// Remembers every listing seen so far, so items can be deduplicated batch by batch as
+/// they are matched
+pub struct Deduper<'a> {
+    rules: &'a [DedupRule],
+    normalizer: &'a TitleNormalizer,
+    seen: HashSet<String>,
+}
+
+impl<'a> Deduper<'a> {
+    pub fn new(rules: &'a [DedupRule], normalizer: &'a TitleNormalizer) -> Self {
+        Self { rules, normalizer, seen: HashSet::new() }
+    }
+
+    /// Drops every item that one of the rules says repeats an earlier one (in this or an
+    /// earlier batch), keeping the first listing; returns how many were dropped
+    pub fn apply(&mut self, items: &mut Vec<ProcessedItem>) -> usize {
+        if self.rules.is_empty() {
+            return 0;
+        }
+        let before = items.len();
+        items.retain(|item| {
+            let keys: Vec<String> = self.rules.iter().flat_map(|rule| rule.keys(item, self.normalizer)).collect();
+            if keys.iter().any(|key| self.seen.contains(key)) {
+                tracing::debug!("Dropping duplicate listing of '{}' watched {}", item.title, item.date);
+                return false;
+            }
+            self.seen.extend(keys);
+            true
+        });
+        before - items.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::NormalizationConfig;
+    use crate::metadata::{MediaIds, MetadataResult};
+    use crate::models::MediaType;
+
+    fn movie(title: &str, date: &str, tmdb: Option<&str>) -> ProcessedItem {
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type: MediaType::Movie,
+            metadata: MetadataResult {
+                ids: MediaIds { tmdb: tmdb.map(String::from), ..Default::default() },
+                title: title.to_string(),
+                media_type: MediaType::Movie,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hd_and_uhd_listings_collapse() {
+        let normalizer = TitleNormalizer::from_config(&NormalizationConfig::default()).unwrap();
+        let items = || {
+            vec![
+                movie("Dune", "2024-03-01", Some("438631")),
+                movie("Dune [Ultra HD]", "2024-03-01", None),
+                movie("Dune (2021)", "2024-03-01", Some("438631")),
+                movie("Dune", "2024-04-01", Some("438631")),
+            ]
+        };
+
+        let rules = default_rules();
+        let mut both = items();
+        assert_eq!(Deduper::new(&rules, &normalizer).apply(&mut both), 2);
+        assert_eq!(both.iter().map(|item| item.date.as_str()).collect::<Vec<_>>(), ["2024-03-01", "2024-04-01"]);
+
+        let mut by_id = items();
+        assert_eq!(Deduper::new(&[DedupRule::ProviderId], &normalizer).apply(&mut by_id), 1);
+        assert_eq!(by_id[1].title, "Dune [Ultra HD]");
+
+        let mut none = items();
+        assert_eq!(Deduper::new(&[], &normalizer).apply(&mut none), 0);
+
+        // Listings seen in an earlier batch still count
+        let mut deduper = Deduper::new(&rules, &normalizer);
+        let mut first = vec![movie("Dune", "2024-03-01", Some("438631"))];
+        let mut second = vec![movie("Dune [4K UHD]", "2024-03-01", None)];
+        assert_eq!(deduper.apply(&mut first) + deduper.apply(&mut second), 1);
+        assert!(second.is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use chrono::NaiveDate;
+use regex::Regex;
+
+use crate::{
+    config::{FilterConfig, OnlyKind},
+    error::AppError,
+    models::MediaType,
+    processor::history_processor::ProcessedItem,
+};
+
+/// The `output.filter` settings, checked and with the title pattern compiled
+pub struct ExportFilter {
+    only: Option<OnlyKind>,
+    since: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    title: Option<Regex>,
+}
+
+impl ExportFilter {
+    pub fn from_config(config: &FilterConfig) -> Result<Self, AppError> {
+        if let (Some(since), Some(until)) = (config.since, config.until) {
+            if since > until {
+                return Err(AppError::ConfigError(config::ConfigError::Message(format!(
+                    "Filter since ({}) is after until ({})",
+                    since, until
+                ))));
+            }
+        }
+        let title = config
+            .title_regex
+            .as_deref()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| {
+                    AppError::ConfigError(config::ConfigError::Message(format!(
+                        "Invalid title filter '{}': {}",
+                        pattern, e
+                    )))
+                })
+            })
+            .transpose()?;
+        Ok(Self { only: config.only, since: config.since, until: config.until, title })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.only.is_none() && self.since.is_none() && self.until.is_none() && self.title.is_none()
+    }
+
+    fn keeps(&self, item: &ProcessedItem) -> bool {
+        let kind = match self.only {
+            Some(OnlyKind::Movies) => item.media_type == MediaType::Movie,
+            Some(OnlyKind::Shows) => item.media_type == MediaType::Tv,
+            None => true,
+        };
+        let dated = (self.since.is_none() && self.until.is_none())
+            || NaiveDate::parse_from_str(&item.date, "%Y-%m-%d").is_ok_and(|date| {
+                self.since.is_none_or(|since| date >= since) && self.until.is_none_or(|until| date <= until)
+            });
+        let titled = self.title.as_ref().is_none_or(|title| title.is_match(&item.title));
+        kind && dated && titled
+    }
+
+    /// Drops the items the filter rules out; returns how many were dropped
+    pub fn apply(&self, items: &mut Vec<ProcessedItem>) -> usize {
+        let before = items.len();
+        items.retain(|item| self.keeps(item));
+        before - items.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::MetadataResult;
+
+    fn item(title: &str, date: &str, media_type: MediaType) -> ProcessedItem {
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                title: title.to_string(),
+                media_type,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_only_movies_watched_in_2024() {
+        let config = FilterConfig {
+            only: Some(OnlyKind::Movies),
+            since: NaiveDate::from_ymd_opt(2024, 1, 1),
+            until: NaiveDate::from_ymd_opt(2024, 12, 31),
+            title_regex: Some("(?i)^d".to_string()),
+        };
+        let filter = ExportFilter::from_config(&config).unwrap();
+        let mut items = vec![
+            item("Dune", "2024-03-01", MediaType::Movie),
+            item("Dune", "2023-12-31", MediaType::Movie),
+            item("dark", "2024-12-31", MediaType::Tv),
+            item("Arrival", "2024-05-10", MediaType::Movie),
+            item("Django", "2024-12-31", MediaType::Movie),
+        ];
+        assert_eq!(filter.apply(&mut items), 3);
+        assert_eq!(items.iter().map(|item| item.date.as_str()).collect::<Vec<_>>(), ["2024-03-01", "2024-12-31"]);
+    }
+
+    #[test]
+    fn test_rejects_reversed_range_and_bad_regex() {
+        let reversed = FilterConfig {
+            since: NaiveDate::from_ymd_opt(2024, 6, 1),
+            until: NaiveDate::from_ymd_opt(2024, 1, 1),
+            ..FilterConfig::default()
+        };
+        assert!(ExportFilter::from_config(&reversed).is_err());
+        let bad = FilterConfig { title_regex: Some("(".to_string()), ..FilterConfig::default() };
+        assert!(ExportFilter::from_config(&bad).is_err());
+        assert!(ExportFilter::from_config(&FilterConfig::default()).unwrap().is_empty());
+    }
+}
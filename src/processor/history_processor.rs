@@ -1,24 +1,37 @@
 use crate::{
+    config::WatchedAtConfig,
     error::AppError,
-    metadata::{MetadataService, MetadataResult},
+    matching,
+    metadata::{EpisodeInfo, MediaIds, MetadataService, MetadataResult},
     models::MediaType,
     models::WatchHistoryItem,
     processor::progress_tracker::ProgressTracker,
 };
 #[cfg(test)]
 use crate::models::WatchStatus;
-use std::collections::HashMap;
+use futures::stream::{FuturesOrdered, StreamExt};
+use std::collections::{HashMap, HashSet};
 use tokio::sync::Semaphore;
 use std::sync::Arc;
 
 #[async_trait::async_trait]
-pub trait MetadataLookup {
+pub trait MetadataLookup: Sync {
     async fn lookup(
         &self,
         title: &str,
         media_type: MediaType,
         year: Option<&str>,
     ) -> Result<MetadataResult, AppError>;
+
+    /// Looks an episode up in provider numbering; None when no provider can tell
+    async fn lookup_episode(
+        &self,
+        _show_ids: &MediaIds,
+        _season: u32,
+        _episode: u32,
+    ) -> Result<Option<EpisodeInfo>, AppError> {
+        Ok(None)
+    }
 }
 
 #[async_trait::async_trait]
@@ -31,6 +44,15 @@ impl MetadataLookup for MetadataService {
     ) -> Result<MetadataResult, AppError> {
         MetadataService::lookup(self, title, media_type, year).await
     }
+
+    async fn lookup_episode(
+        &self,
+        show_ids: &MediaIds,
+        season: u32,
+        episode: u32,
+    ) -> Result<Option<EpisodeInfo>, AppError> {
+        MetadataService::episode(self, show_ids, season, episode).await
+    }
 }
 
 #[async_trait::async_trait]
@@ -43,16 +65,53 @@ impl MetadataLookup for &MetadataService {
     ) -> Result<MetadataResult, AppError> {
         MetadataService::lookup(*self, title, media_type, year).await
     }
+
+    async fn lookup_episode(
+        &self,
+        show_ids: &MediaIds,
+        season: u32,
+        episode: u32,
+    ) -> Result<Option<EpisodeInfo>, AppError> {
+        MetadataService::episode(*self, show_ids, season, episode).await
+    }
 }
 
 pub struct HistoryProcessor {
     semaphore: Arc<Semaphore>,
 }
 
-impl Default for HistoryProcessor {
+impl HistoryProcessor {
+    fn with_concurrency(concurrency: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(concurrency.max(1))),
+        }
+    }
+}
+
+/// Tuning knobs for a processing run
+#[derive(Debug, Clone)]
+pub struct ProcessOptions {
+    /// Lookup attempts per item before giving up
+    pub max_attempts: u64,
+    /// Export unmatched items with title-only metadata instead of failing the run
+    pub keep_unmatched: bool,
+    /// Leave unmatched items out instead of failing the run; the metadata service still
+    /// reports them
+    pub skip_unmatched: bool,
+    /// Items looked up in parallel; provider rate limits still apply across all of them
+    pub concurrency: usize,
+    /// Keep every watched episode as its own item instead of only the latest one per show
+    pub episode_rows: bool,
+}
+
+impl Default for ProcessOptions {
     fn default() -> Self {
         Self {
-            semaphore: Arc::new(Semaphore::new(5)), // Max 5 concurrent requests
+            max_attempts: 3,
+            keep_unmatched: false,
+            skip_unmatched: false,
+            concurrency: 4,
+            episode_rows: false,
         }
     }
 }
@@ -62,108 +121,209 @@ impl HistoryProcessor {
         items: Vec<WatchHistoryItem>,
         metadata: &T,
         progress: &mut ProgressTracker,
+        options: &ProcessOptions,
     ) -> Result<Vec<ProcessedItem>, AppError>
     where
         T: MetadataLookup,
     {
-        let processor = Self::default();
         let mut processed = Vec::with_capacity(items.len());
-        let mut tv_shows: HashMap<String, WatchHistoryItem> = HashMap::new();
+        Self::process_each(items, metadata, progress, options, |batch| {
+            processed.extend(batch);
+            Ok(())
+        })
+        .await?;
+        Ok(processed)
+    }
+
+    /// Like `process`, but hands each title's items to `on_batch` as soon as it is matched,
+    /// in input order, instead of collecting the whole history
+    pub async fn process_each<T, F>(
+        items: Vec<WatchHistoryItem>,
+        metadata: &T,
+        progress: &mut ProgressTracker,
+        options: &ProcessOptions,
+        mut on_batch: F,
+    ) -> Result<(), AppError>
+    where
+        T: MetadataLookup,
+        F: FnMut(Vec<ProcessedItem>) -> Result<(), AppError>,
+    {
+        let processor = Self::with_concurrency(options.concurrency);
+        let mut work = Vec::with_capacity(items.len());
+        let mut tv_shows: HashMap<String, Vec<WatchHistoryItem>> = HashMap::new();
+        let mut watched_episodes: HashMap<String, HashSet<(u32, u32)>> = HashMap::new();
 
-        // First pass: Deduplicate TV shows and process items
+        // First pass: group episodes by show, keeping only the latest unless every episode is wanted
         for item in items {
             progress.log_processing(&item.title);
 
-            let media_type = if item.episode.is_some() {
+            // A watchlist show has no episode yet
+            let media_type = if item.episode.is_some() || item.media_type == MediaType::Tv {
                 MediaType::Tv
             } else {
                 MediaType::Movie
             };
 
             if media_type == MediaType::Tv {
-                if let Some(existing) = tv_shows.get_mut(&item.title) {
-                    if item.date > existing.date {
-                        *existing = item;
-                    }
-                    continue;
-                } else {
-                    tv_shows.insert(item.title.clone(), item);
-                    continue;
-                }
-            }
-
-            // Process item directly without spawning
-            let _permit = processor.semaphore.acquire().await?;
-
-            // Retry logic (3 attempts)
-            let mut attempts = 0;
-            let mut last_error = None;
-
-            while attempts < 3 {
-                match metadata.lookup(&item.title, media_type, None).await {
-                    Ok(meta) => {
-                        processed.push(ProcessedItem::from_watch_history(item, meta));
-                        break;
-                    }
-                    Err(e) => {
-                        last_error = Some(e);
-                        attempts += 1;
-                        if attempts < 3 {
-                            tokio::time::sleep(std::time::Duration::from_secs(attempts)).await;
+                let codes = watched_episodes.entry(item.title.clone()).or_default();
+                // Specials don't count towards the aired episodes a show is completed at
+                codes.extend(item.episode.as_deref().and_then(parse_episode_code).filter(|(season, _)| *season > 0));
+                let episodes = tv_shows.entry(item.title.clone()).or_default();
+                match episodes.first_mut() {
+                    Some(existing) if !options.episode_rows => {
+                        if item.date > existing.date {
+                            *existing = item;
                         }
                     }
+                    _ => episodes.push(item),
                 }
+                continue;
             }
 
-            if let Some(e) = last_error {
-                if attempts >= 3 {
-                    return Err(e);
-                }
+            work.push((vec![item], media_type));
+        }
+        work.extend(tv_shows.into_values().map(|episodes| (episodes, MediaType::Tv)));
+
+        // Look items up concurrently, bounded by the semaphore; results come out in input order
+        let semaphore = &processor.semaphore;
+        let mut lookups: FuturesOrdered<_> = work
+            .into_iter()
+            .map(|(items, media_type)| async move {
+                let _permit = semaphore.acquire().await?;
+                Self::lookup_item(items, media_type, metadata, options).await
+            })
+            .collect();
+        let mut count = 0;
+        while let Some(batch) = lookups.next().await {
+            let mut batch = batch?;
+            for item in &mut batch {
+                item.watched_episodes = watched_episodes.get(&item.title).map_or(0, |codes| codes.len() as u32);
             }
+            count += batch.len();
+            on_batch(batch)?;
         }
 
-        // Process TV shows
-        for (_, item) in tv_shows {
-            let _permit = processor.semaphore.acquire().await?;
-
-            // Retry logic for TV shows
-            let mut attempts = 0;
-            let mut last_error = None;
+        progress.log_processed(count);
+        Ok(())
+    }
 
-            while attempts < 3 {
-                match metadata.lookup(&item.title, MediaType::Tv, None).await {
-                    Ok(meta) => {
-                        processed.push(ProcessedItem::from_watch_history(item, meta));
-                        break;
+    /// Looks up one title and pairs the match with each of its watches (a movie, or the
+    /// episodes of one show)
+    async fn lookup_item<T>(
+        items: Vec<WatchHistoryItem>,
+        media_type: MediaType,
+        metadata: &T,
+        options: &ProcessOptions,
+    ) -> Result<Vec<ProcessedItem>, AppError>
+    where
+        T: MetadataLookup,
+    {
+        let item = &items[0];
+        // Prime Video only shows a year when it is part of the title, as in "Dune (2021)"
+        let year = item.year.clone().or_else(|| matching::year_in_title(&item.title));
+
+        // Retry logic with linear backoff
+        let mut attempts = 0;
+
+        loop {
+            match metadata.lookup(&item.title, media_type, year.as_deref()).await {
+                Ok(meta) => {
+                    let mut processed = Vec::with_capacity(items.len());
+                    for mut item in items {
+                        if media_type == MediaType::Tv {
+                            item.episode = Self::validate_episode(&item, &meta.ids, metadata).await;
+                        }
+                        processed.push(ProcessedItem::from_watch_history(item, meta.clone()));
                     }
-                    Err(e) => {
-                        last_error = Some(e);
-                        attempts += 1;
-                        if attempts < 3 {
-                            tokio::time::sleep(std::time::Duration::from_secs(attempts)).await;
+                    return Ok(processed);
+                }
+                Err(e) => {
+                    attempts += 1;
+                    if attempts >= options.max_attempts {
+                        if options.keep_unmatched {
+                            tracing::warn!("Exporting '{}' without metadata: {}", item.title, e);
+                            let meta = MetadataResult {
+                                ids: Default::default(),
+                                title: item.title.clone(),
+                                year: item.year.clone(),
+                                media_type,
+                                score: None,
+                                anime: false,
+                                enrichment: None,
+                                original_title: None,
+                                votes: None,
+                            };
+                            return Ok(items
+                                .into_iter()
+                                .map(|item| ProcessedItem::from_watch_history(item, meta.clone()))
+                                .collect());
                         }
+                        if options.skip_unmatched {
+                            tracing::warn!("Setting '{}' aside: {}", item.title, e);
+                            return Ok(Vec::new());
+                        }
+                        return Err(e);
                     }
+                    tokio::time::sleep(std::time::Duration::from_secs(attempts)).await;
                 }
             }
+        }
+    }
 
-            if let Some(e) = last_error {
-                if attempts >= 3 {
-                    return Err(e);
-                }
+    /// Rewrites a scraped "SxxEyy" episode with the provider's numbering and title,
+    /// keeping the scraped value when no provider knows the episode
+    async fn validate_episode<T>(
+        item: &WatchHistoryItem,
+        show_ids: &MediaIds,
+        metadata: &T,
+    ) -> Option<String>
+    where
+        T: MetadataLookup,
+    {
+        let scraped = item.episode.clone()?;
+        let Some((season, episode)) = parse_episode_code(&scraped) else {
+            if scraped.starts_with("S00") {
+                tracing::info!("'{}' {} has no episode number; exporting it as an unnumbered special", item.title, scraped);
+            }
+            return Some(scraped);
+        };
+        match metadata.lookup_episode(show_ids, season, episode).await {
+            Ok(Some(info)) => Some(info.code()),
+            Ok(None) => {
+                tracing::debug!("No provider confirmed {} of '{}'", scraped, item.title);
+                Some(scraped)
+            }
+            Err(e) => {
+                tracing::warn!("Could not validate {} of '{}': {}", scraped, item.title, e);
+                Some(scraped)
             }
         }
-
-        progress.log_processed(processed.len());
-        Ok(processed)
     }
 }
 
+/// Reads season and episode numbers from codes like "S01E02" or "S01E02 - Title"
+fn parse_episode_code(code: &str) -> Option<(u32, u32)> {
+    let rest = code.trim_start().strip_prefix(['S', 's'])?;
+    let (season, rest) = rest.split_once(['E', 'e'])?;
+    let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+    Some((season.parse().ok()?, digits.parse().ok()?))
+}
+
 pub struct ProcessedItem {
     pub title: String,
     pub date: String,
     pub media_type: MediaType,
     pub metadata: MetadataResult,
     pub episode: Option<String>,
+    /// 1-10, from the Prime Video thumbs or the configured default
+    pub rating: Option<u8>,
+    /// Watches this row stands for; above 1 only once rewatches are counted as plays
+    pub plays: u32,
+    /// Distinct numbered episodes of the show, specials left out, watched across the whole
+    /// history, whether or not they have rows of their own; 0 for movies
+    pub watched_episodes: u32,
+    /// Prime Video detail page the history row linked to
+    pub source_url: Option<String>,
 }
 
 impl ProcessedItem {
@@ -171,28 +331,94 @@ impl ProcessedItem {
         Self {
             title: item.title,
             date: item.date,
-            media_type: if item.episode.is_some() {
+            media_type: if item.episode.is_some() || item.media_type == MediaType::Tv {
                 MediaType::Tv
             } else {
                 MediaType::Movie
             },
             metadata,
             episode: item.episode,
+            rating: item.rating,
+            plays: 1,
+            watched_episodes: 0,
+            source_url: item.source_url,
+        }
+    }
+
+    /// Season and episode numbers, when the episode starts with an `SxxEyy` code
+    pub fn episode_code(&self) -> Option<(u32, u32)> {
+        self.episode.as_deref().and_then(parse_episode_code)
+    }
+
+    /// The Prime Video ID (ASIN) in the detail-page URL, `/detail/<ID>/` or `/dp/<ID>`
+    pub fn asin(&self) -> Option<&str> {
+        let url = self.source_url.as_deref()?;
+        let mut segments = url.split(['/', '?']);
+        segments.find(|segment| *segment == "detail" || *segment == "dp")?;
+        segments.next().filter(|id| !id.is_empty())
+    }
+
+    /// The watch time as an RFC 3339 timestamp; Prime Video only reports the day, which is
+    /// read in the configured zone. Empty for a watchlist item.
+    pub fn watched_at(&self, clock: &WatchedAtConfig) -> String {
+        if self.date.is_empty() {
+            return String::new();
+        }
+        clock.timestamp(&self.date)
+    }
+
+    /// Simkl's list status for the title. A watchlist item, which has no watch date, is plan
+    /// to watch. With `auto` (`output.auto_status`), a show is completed once the history has
+    /// as many episodes as the provider says have aired; otherwise, or without that count, a
+    /// show with an episode watched stays watching.
+    pub fn watch_status(&self, auto: bool) -> &'static str {
+        if self.date.is_empty() {
+            return "plantowatch";
+        }
+        if self.media_type == MediaType::Movie {
+            return "completed";
+        }
+        let aired = self.metadata.enrichment.as_ref().and_then(|enrichment| enrichment.aired_episodes);
+        match aired.filter(|aired| auto && *aired > 0 && self.watched_episodes > 0) {
+            Some(aired) if self.watched_episodes >= aired => "completed",
+            Some(_) => "watching",
+            None if self.episode.is_none() => "completed",
+            None => "watching",
         }
     }
 }
 
 
+/// A single watch of an unmatched movie, so tests only spell out the fields they check
+#[cfg(test)]
+impl Default for ProcessedItem {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            date: String::new(),
+            media_type: MediaType::Movie,
+            metadata: MetadataResult::default(),
+            episode: None,
+            rating: None,
+            plays: 1,
+            watched_episodes: 0,
+            source_url: None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::metadata::{MetadataResult, MediaIds};
+    use crate::metadata::{Enrichment, MetadataResult, MediaIds};
     use std::sync::atomic::{AtomicUsize, Ordering};
     use tokio::sync::Mutex;
 
     struct MockMetadataService {
         call_count: AtomicUsize,
         should_fail: Mutex<bool>,
+        /// The year each lookup was asked with
+        years: Mutex<Vec<Option<String>>>,
     }
 
     impl MockMetadataService {
@@ -200,6 +426,7 @@ mod tests {
             Self {
                 call_count: AtomicUsize::new(0),
                 should_fail: Mutex::new(false),
+                years: Mutex::new(Vec::new()),
             }
         }
 
@@ -214,9 +441,10 @@ mod tests {
             &self,
             title: &str,
             media_type: MediaType,
-            _year: Option<&str>,
+            year: Option<&str>,
         ) -> Result<MetadataResult, AppError> {
             self.call_count.fetch_add(1, Ordering::SeqCst);
+            self.years.lock().await.push(year.map(String::from));
 
             if *self.should_fail.lock().await {
                 return Err(AppError::MetadataError("Mock failure".to_string()));
@@ -227,11 +455,15 @@ mod tests {
                     simkl: Some(format!("simkl_{}", title)),
                     tvdb: Some(format!("tvdb_{}", title)),
                     tmdb: Some(format!("tmdb_{}", title)),
+                    imdb: None,
                     mal: Some(format!("mal_{}", title)),
+                    anilist: None,
+                    anidb: None,
                 },
                 title: title.to_string(),
                 year: Some("2020".to_string()),
                 media_type,
+                ..Default::default()
             })
         }
     }
@@ -267,6 +499,7 @@ mod tests {
                 date: "2023-01-01".to_string(),
                 rating: None,
                 memo: None,
+                source_url: None,
             },
             WatchHistoryItem {
                 simkl_id: None,
@@ -281,15 +514,56 @@ mod tests {
                 date: "2023-01-02".to_string(),
                 rating: None,
                 memo: None,
+                source_url: None,
             },
         ];
 
-        let processed = HistoryProcessor::process(items, &metadata, &mut progress)
+        let processed = HistoryProcessor::process(items, &metadata, &mut progress, &ProcessOptions::default())
             .await
             .unwrap();
 
         assert_eq!(processed.len(), 1);
         assert_eq!(processed[0].date, "2023-01-02");
+        assert_eq!(processed[0].watched_episodes, 2);
+
+        let mut show = processed.into_iter().next().unwrap();
+        assert_eq!(show.watch_status(true), "watching");
+        show.metadata.enrichment = Some(Enrichment { aired_episodes: Some(3), ..Default::default() });
+        assert_eq!(show.watch_status(true), "watching");
+        show.metadata.enrichment = Some(Enrichment { aired_episodes: Some(2), ..Default::default() });
+        assert_eq!(show.watch_status(true), "completed");
+        // Enrichment asked for by other settings leaves the status alone
+        assert_eq!(show.watch_status(false), "watching");
+    }
+
+    #[tokio::test]
+    async fn test_episode_rows_keep_every_episode_with_one_lookup() {
+        let metadata = MockMetadataService::new();
+        let mut progress = ProgressTracker::new();
+        let episode = |code: &str, date: &str| WatchHistoryItem {
+            simkl_id: None,
+            tvdb_id: None,
+            tmdb_id: None,
+            mal_id: None,
+            media_type: MediaType::Tv,
+            title: "Show A".to_string(),
+            year: None,
+            episode: Some(code.to_string()),
+            watch_status: WatchStatus::Completed,
+            date: date.to_string(),
+            rating: None,
+            memo: None,
+            source_url: None,
+        };
+        let items = vec![episode("S01E01", "2023-01-01"), episode("S01E02", "2023-01-02"), episode("S01E03", "2023-01-03")];
+        let options = ProcessOptions { episode_rows: true, ..ProcessOptions::default() };
+
+        let processed = HistoryProcessor::process(items, &metadata, &mut progress, &options).await.unwrap();
+
+        assert_eq!(metadata.call_count.load(Ordering::SeqCst), 1);
+        let codes: Vec<_> = processed.iter().map(|item| item.episode_code()).collect();
+        assert_eq!(codes, [Some((1, 1)), Some((1, 2)), Some((1, 3))]);
+        assert!(processed.iter().all(|item| item.metadata.ids.tvdb.as_deref() == Some("tvdb_Show A")));
     }
 
     #[tokio::test]
@@ -310,9 +584,10 @@ mod tests {
             date: "2023-01-01".to_string(),
             rating: None,
             memo: None,
+            source_url: None,
         }).collect();
 
-        let processed = HistoryProcessor::process(items, &metadata, &mut progress)
+        let processed = HistoryProcessor::process(items, &metadata, &mut progress, &ProcessOptions::default())
             .await
             .unwrap();
 
@@ -339,11 +614,218 @@ mod tests {
             date: "2023-01-01".to_string(),
             rating: None,
             memo: None,
+            source_url: None,
         }];
 
-        let result = HistoryProcessor::process(items, &metadata, &mut progress)
+        let result = HistoryProcessor::process(items, &metadata, &mut progress, &ProcessOptions::default())
             .await;
 
         assert!(result.is_err());
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_keep_unmatched_exports_title_only() {
+        let metadata = MockMetadataService::new();
+        metadata.set_fail(true).await;
+        let mut progress = ProgressTracker::new();
+        let options = ProcessOptions {
+            max_attempts: 1,
+            keep_unmatched: true,
+            ..ProcessOptions::default()
+        };
+
+        let items = vec![WatchHistoryItem {
+            simkl_id: None,
+            tvdb_id: None,
+            tmdb_id: None,
+            mal_id: None,
+            media_type: MediaType::Movie,
+            title: "Obscure Movie".to_string(),
+            year: None,
+            episode: None,
+            watch_status: WatchStatus::Completed,
+            date: "2023-01-01".to_string(),
+            rating: None,
+            memo: None,
+            source_url: None,
+        }];
+
+        let processed = HistoryProcessor::process(items, &metadata, &mut progress, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(processed.len(), 1);
+        assert_eq!(processed[0].metadata.title, "Obscure Movie");
+        assert_eq!(processed[0].metadata.ids.simkl, None);
+        assert_eq!(metadata.call_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_skip_unmatched_leaves_items_out() {
+        let metadata = MockMetadataService::new();
+        metadata.set_fail(true).await;
+        let mut progress = ProgressTracker::new();
+        let options = ProcessOptions {
+            max_attempts: 1,
+            skip_unmatched: true,
+            ..ProcessOptions::default()
+        };
+
+        let items = vec![WatchHistoryItem {
+            simkl_id: None,
+            tvdb_id: None,
+            tmdb_id: None,
+            mal_id: None,
+            media_type: MediaType::Movie,
+            title: "Obscure Movie".to_string(),
+            year: None,
+            episode: None,
+            watch_status: WatchStatus::Completed,
+            date: "2023-01-01".to_string(),
+            rating: None,
+            memo: None,
+            source_url: None,
+        }];
+
+        let processed = HistoryProcessor::process(items, &metadata, &mut progress, &options)
+            .await
+            .unwrap();
+
+        assert!(processed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_lookups_keep_input_order() {
+        let metadata = MockMetadataService::new();
+        let mut progress = ProgressTracker::new();
+        let options = ProcessOptions {
+            concurrency: 3,
+            ..ProcessOptions::default()
+        };
+
+        let items: Vec<WatchHistoryItem> = (0..10)
+            .map(|i| WatchHistoryItem {
+                simkl_id: None,
+                tvdb_id: None,
+                tmdb_id: None,
+                mal_id: None,
+                media_type: MediaType::Movie,
+                title: format!("Movie {}", i),
+                year: None,
+                episode: None,
+                watch_status: WatchStatus::Completed,
+                date: "2023-01-01".to_string(),
+                rating: None,
+                memo: None,
+                source_url: None,
+            })
+            .collect();
+
+        let processed = HistoryProcessor::process(items, &metadata, &mut progress, &options)
+            .await
+            .unwrap();
+
+        let titles: Vec<&str> = processed.iter().map(|p| p.title.as_str()).collect();
+        let expected: Vec<String> = (0..10).map(|i| format!("Movie {}", i)).collect();
+        assert_eq!(titles, expected);
+        assert_eq!(metadata.call_count.load(Ordering::SeqCst), 10);
+    }
+
+    #[tokio::test]
+    async fn test_year_in_scraped_title_reaches_the_lookup() {
+        let metadata = MockMetadataService::new();
+        let mut progress = ProgressTracker::new();
+        let items = vec![WatchHistoryItem {
+            simkl_id: None,
+            tvdb_id: None,
+            tmdb_id: None,
+            mal_id: None,
+            media_type: MediaType::Movie,
+            title: "Dune (2021)".to_string(),
+            year: None,
+            episode: None,
+            watch_status: WatchStatus::Completed,
+            date: "2023-01-01".to_string(),
+            rating: None,
+            memo: None,
+            source_url: None,
+        }];
+
+        HistoryProcessor::process(items, &metadata, &mut progress, &ProcessOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(*metadata.years.lock().await, vec![Some("2021".to_string())]);
+    }
+
+    struct EpisodeAwareMock;
+
+    #[async_trait::async_trait]
+    impl MetadataLookup for EpisodeAwareMock {
+        async fn lookup(
+            &self,
+            title: &str,
+            media_type: MediaType,
+            _year: Option<&str>,
+        ) -> Result<MetadataResult, AppError> {
+            MockMetadataService::new().lookup(title, media_type, None).await
+        }
+
+        async fn lookup_episode(
+            &self,
+            _show_ids: &MediaIds,
+            season: u32,
+            episode: u32,
+        ) -> Result<Option<EpisodeInfo>, AppError> {
+            Ok((season == 1).then(|| EpisodeInfo {
+                season,
+                episode,
+                title: Some("Pilot".to_string()),
+                air_date: None,
+            }))
+        }
+    }
+
+    #[test]
+    fn test_parse_episode_code() {
+        assert_eq!(parse_episode_code("S01E02"), Some((1, 2)));
+        assert_eq!(parse_episode_code("S10E101 - Finale"), Some((10, 101)));
+        assert_eq!(parse_episode_code("Episode 5"), None);
+        assert_eq!(parse_episode_code("S00E03"), Some((0, 3)));
+        assert_eq!(parse_episode_code("S00 - OVA"), None);
+        assert_eq!(parse_episode_code("S00 - Episode 0"), None);
+    }
+
+    #[tokio::test]
+    async fn test_episodes_take_provider_numbering() {
+        let mut progress = ProgressTracker::new();
+        let items = ["S01E01", "S09E01"]
+            .iter()
+            .enumerate()
+            .map(|(i, code)| WatchHistoryItem {
+                simkl_id: None,
+                tvdb_id: None,
+                tmdb_id: None,
+                mal_id: None,
+                media_type: MediaType::Tv,
+                title: format!("Show {}", i),
+                year: None,
+                episode: Some(code.to_string()),
+                watch_status: WatchStatus::Completed,
+                date: "2023-01-01".to_string(),
+                rating: None,
+                memo: None,
+                source_url: None,
+            })
+            .collect();
+
+        let mut processed = HistoryProcessor::process(items, &EpisodeAwareMock, &mut progress, &ProcessOptions::default())
+            .await
+            .unwrap();
+        processed.sort_by(|a, b| a.title.cmp(&b.title));
+
+        assert_eq!(processed[0].episode.as_deref(), Some("S01E01 - Pilot"));
+        // Unknown to the provider, so the scraped code is kept
+        assert_eq!(processed[1].episode.as_deref(), Some("S09E01"));
+    }
+}
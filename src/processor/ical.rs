@@ -0,0 +1,161 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::{models::MediaType, processor::history_processor::ProcessedItem};
+
+/// Content lines longer than this many octets are folded onto continuation lines
+const LINE_OCTETS: usize = 75;
+
+/// Escapes a TEXT value: backslashes, semicolons, commas and line breaks
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes one content line, folded at 75 octets without splitting a character, and ended
+/// with CRLF as the format requires
+fn line(out: &mut String, content: &str) {
+    let mut width = 0;
+    for c in content.chars() {
+        if width + c.len_utf8() > LINE_OCTETS {
+            out.push_str("\r\n ");
+            // The leading space of the continuation counts towards its 75 octets
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// Lowercase letters and digits of `text` joined by dashes, for UIDs
+fn slug(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn summary(item: &ProcessedItem) -> String {
+    match (&item.episode, &item.metadata.year) {
+        (Some(episode), _) => format!("{} {}", item.title, episode),
+        (None, Some(year)) => format!("{} ({})", item.title, year),
+        (None, None) => item.title.clone(),
+    }
+}
+
+fn description(item: &ProcessedItem) -> String {
+    let mut lines = vec![match item.media_type {
+        MediaType::Movie => "Movie".to_string(),
+        MediaType::Tv => "Episode".to_string(),
+    }];
+    if let Some(rating) = item.rating {
+        lines.push(format!("Rated {}/10", rating));
+    }
+    if let Some(imdb) = &item.metadata.ids.imdb {
+        lines.push(format!("https://www.imdb.com/title/{}/", imdb));
+    }
+    lines.join("\n")
+}
+
+/// An iCalendar file with an all-day event per watch, summarised by title and episode.
+/// UIDs are built from the date, the title and the episode, so importing a newer export
+/// into the same calendar updates the events rather than doubling them. Rows without a
+/// readable date are left out. `stamp` is the DTSTAMP of every event.
+pub fn calendar(items: &[ProcessedItem], stamp: DateTime<Utc>) -> String {
+    let mut out = String::new();
+    line(&mut out, "BEGIN:VCALENDAR");
+    line(&mut out, "VERSION:2.0");
+    line(&mut out, "PRODID:-//primevideo-to-simkl//Watch history//EN");
+    line(&mut out, "CALSCALE:GREGORIAN");
+    line(&mut out, "X-WR-CALNAME:Watch history");
+    let stamp = stamp.format("%Y%m%dT%H%M%SZ").to_string();
+    for item in items {
+        let Ok(day) = NaiveDate::parse_from_str(&item.date, "%Y-%m-%d") else {
+            continue;
+        };
+        let uid = format!(
+            "{}-{}-{}@primevideo-to-simkl",
+            day.format("%Y%m%d"),
+            slug(&item.title),
+            slug(item.episode.as_deref().unwrap_or("movie"))
+        );
+        line(&mut out, "BEGIN:VEVENT");
+        line(&mut out, &format!("UID:{}", uid));
+        line(&mut out, &format!("DTSTAMP:{}", stamp));
+        line(&mut out, &format!("DTSTART;VALUE=DATE:{}", day.format("%Y%m%d")));
+        line(&mut out, &format!("DTEND;VALUE=DATE:{}", (day + Duration::days(1)).format("%Y%m%d")));
+        line(&mut out, &format!("SUMMARY:{}", escape(&summary(item))));
+        line(&mut out, &format!("DESCRIPTION:{}", escape(&description(item))));
+        if let Some(url) = &item.source_url {
+            line(&mut out, &format!("URL:{}", url));
+        }
+        // Shown as free time, so the history does not block the calendar
+        line(&mut out, "TRANSP:TRANSPARENT");
+        line(&mut out, "END:VEVENT");
+    }
+    line(&mut out, "END:VCALENDAR");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{MediaIds, MetadataResult};
+
+    fn item(title: &str, date: &str, episode: Option<&str>) -> ProcessedItem {
+        let media_type = if episode.is_some() { MediaType::Tv } else { MediaType::Movie };
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids: MediaIds { imdb: Some("tt2543164".to_string()), ..Default::default() },
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                score: None,
+                anime: false,
+                enrichment: None,
+                original_title: None,
+                votes: None,
+            },
+            episode: episode.map(String::from),
+            rating: Some(9),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_an_all_day_event_per_watch() {
+        let stamp = DateTime::parse_from_rfc3339("2024-04-01T08:00:00Z").unwrap().with_timezone(&Utc);
+        let ics = calendar(
+            &[
+                item("Arrival", "2024-03-31", None),
+                item("Fleabag", "2024-03-01", Some("S01E02 - Episode 2, Part; One")),
+                item("Undated", "", None),
+            ],
+            stamp,
+        );
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ics.contains("UID:20240331-arrival-movie@primevideo-to-simkl\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240331\r\nDTEND;VALUE=DATE:20240401\r\n"));
+        assert!(ics.contains("SUMMARY:Arrival (2016)\r\n"));
+        assert!(ics.contains("SUMMARY:Fleabag S01E02 - Episode 2\\, Part\\; One\r\n"));
+        assert!(ics.contains("DESCRIPTION:Movie\\nRated 9/10\\nhttps://www.imdb.com/title/tt2543164/\r\n"));
+        assert!(ics.split("\r\n").all(|line| line.len() <= LINE_OCTETS));
+    }
+}
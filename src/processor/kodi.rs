@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use crate::{error::AppError, metadata::MediaIds, models::MediaType, processor::history_processor::ProcessedItem};
+
+/// Watches of one movie or episode
+#[derive(Default)]
+struct Plays<'a> {
+    count: u32,
+    last: &'a str,
+}
+
+impl<'a> Plays<'a> {
+    fn add(&mut self, item: &'a ProcessedItem) {
+        self.count += item.plays;
+        self.last = self.last.max(item.date.as_str());
+    }
+}
+
+struct Movie<'a> {
+    item: &'a ProcessedItem,
+    plays: Plays<'a>,
+}
+
+struct Show<'a> {
+    item: &'a ProcessedItem,
+    episodes: BTreeMap<(u32, u32), Plays<'a>>,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// What groups the rows of one title: its first provider ID, or its title without one
+fn title_key(item: &ProcessedItem) -> String {
+    let ids = &item.metadata.ids;
+    [&ids.tmdb, &ids.imdb, &ids.tvdb]
+        .into_iter()
+        .find_map(|id| id.clone())
+        .unwrap_or_else(|| item.title.to_lowercase())
+}
+
+/// Kodi's `uniqueid` elements, the first one marked as the default
+fn unique_ids(xml: &mut String, ids: &MediaIds, indent: &str) {
+    let ids = [("tmdb", &ids.tmdb), ("imdb", &ids.imdb), ("tvdb", &ids.tvdb)];
+    for (index, (kind, id)) in ids.into_iter().filter_map(|(kind, id)| id.as_ref().map(|id| (kind, id))).enumerate() {
+        let default = if index == 0 { " default=\"true\"" } else { "" };
+        let _ = writeln!(xml, "{}<uniqueid type=\"{}\"{}>{}</uniqueid>", indent, kind, default, escape(id));
+    }
+}
+
+fn title_and_year(xml: &mut String, item: &ProcessedItem, indent: &str) {
+    let _ = writeln!(xml, "{}<title>{}</title>", indent, escape(&item.title));
+    if let Some(year) = &item.metadata.year {
+        let _ = writeln!(xml, "{}<year>{}</year>", indent, escape(year));
+    }
+}
+
+fn play_state(xml: &mut String, plays: &Plays, indent: &str) {
+    let _ = writeln!(xml, "{}<playcount>{}</playcount>", indent, plays.count);
+    let _ = writeln!(xml, "{}<lastplayed>{} 00:00:00</lastplayed>", indent, escape(plays.last));
+}
+
+/// The watched state of `items` in the layout of Kodi's `videodb.xml` library export: one
+/// `movie` per film and one `tvshow` per show with an `episodedetails` per episode, each with
+/// its play count and last play date. Episodes without a season number are left out.
+fn render(items: &[ProcessedItem]) -> String {
+    let mut movies: BTreeMap<String, Movie> = BTreeMap::new();
+    let mut shows: BTreeMap<String, Show> = BTreeMap::new();
+    let mut unnumbered = 0;
+    for item in items {
+        match item.media_type {
+            MediaType::Movie => movies
+                .entry(title_key(item))
+                .or_insert_with(|| Movie { item, plays: Plays::default() })
+                .plays
+                .add(item),
+            MediaType::Tv => {
+                let Some(code) = item.episode_code() else {
+                    unnumbered += 1;
+                    continue;
+                };
+                shows
+                    .entry(title_key(item))
+                    .or_insert_with(|| Show { item, episodes: BTreeMap::new() })
+                    .episodes
+                    .entry(code)
+                    .or_default()
+                    .add(item);
+            }
+        }
+    }
+    if unnumbered > 0 {
+        tracing::warn!("Left {} episodes without a season number out of the Kodi export", unnumbered);
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\" ?>\n<videodb>\n");
+    xml.push_str("    <version>1</version>\n");
+    for movie in movies.values() {
+        xml.push_str("    <movie>\n");
+        title_and_year(&mut xml, movie.item, "        ");
+        unique_ids(&mut xml, &movie.item.metadata.ids, "        ");
+        play_state(&mut xml, &movie.plays, "        ");
+        xml.push_str("    </movie>\n");
+    }
+    for show in shows.values() {
+        xml.push_str("    <tvshow>\n");
+        title_and_year(&mut xml, show.item, "        ");
+        unique_ids(&mut xml, &show.item.metadata.ids, "        ");
+        for ((season, episode), plays) in &show.episodes {
+            xml.push_str("        <episodedetails>\n");
+            let _ = writeln!(xml, "            <season>{}</season>", season);
+            let _ = writeln!(xml, "            <episode>{}</episode>", episode);
+            play_state(&mut xml, plays, "            ");
+            xml.push_str("        </episodedetails>\n");
+        }
+        xml.push_str("    </tvshow>\n");
+    }
+    xml.push_str("</videodb>\n");
+    xml
+}
+
+/// Writes the watched state of `items` to `path` as a Kodi library export
+pub fn write(path: &Path, items: &[ProcessedItem]) -> Result<(), AppError> {
+    std::fs::write(path, render(items))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::MetadataResult;
+
+    fn item(title: &str, date: &str, episode: Option<&str>, tmdb: &str) -> ProcessedItem {
+        let media_type = if episode.is_some() { MediaType::Tv } else { MediaType::Movie };
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids: MediaIds { tmdb: Some(tmdb.to_string()), imdb: Some("tt0000001".to_string()), ..Default::default() },
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_watches_become_play_counts() {
+        let items = vec![
+            item("Arrival", "2024-01-05", None, "329865"),
+            item("Arrival", "2024-03-01", None, "329865"),
+            item("Fleabag", "2024-03-02", Some("S01E01"), "67070"),
+            item("Fleabag", "2024-03-03", Some("S01E02"), "67070"),
+            item("Tom & Jerry", "2024-03-04", Some("Pilot"), "1"),
+        ];
+        let xml = render(&items);
+
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains(
+            "    <movie>\n        <title>Arrival</title>\n        <year>2016</year>\n        \
+             <uniqueid type=\"tmdb\" default=\"true\">329865</uniqueid>\n        <uniqueid type=\"imdb\">tt0000001</uniqueid>\n        \
+             <playcount>2</playcount>\n        <lastplayed>2024-03-01 00:00:00</lastplayed>\n    </movie>\n"
+        ));
+        assert_eq!(xml.matches("<tvshow>").count(), 1);
+        assert_eq!(xml.matches("<episodedetails>").count(), 2);
+        assert!(xml.contains("<season>1</season>\n            <episode>2</episode>"));
+        assert!(!xml.contains("Tom"));
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashSet, fs::OpenOptions, path::Path};
+
+use csv::StringRecord;
+
+use crate::{config::{CsvDialect, BOM}, error::AppError};
+
+/// Headers, lowercased, holding an ID a row can be recognized by
+const ID_COLUMNS: [&str; 8] = ["simkl_id", "tvdb_id", "tmdb", "tmdb_id", "imdb_id", "mal_id", "anilist_id", "anidb_id"];
+
+/// Headers saying when an item was watched
+const WATCHED_COLUMNS: [&str; 3] = ["watcheddate", "watched_at", "watched_date"];
+
+/// Headers saying which episode a row is about
+const EPISODE_COLUMNS: [&str; 4] = ["lastepwatched", "season", "episode", "episode_number"];
+
+/// Where the identifying values sit in one CSV layout
+struct KeyColumns {
+    ids: Vec<usize>,
+    watched: Vec<usize>,
+    episode: Vec<usize>,
+    title: Option<usize>,
+}
+
+impl KeyColumns {
+    fn new(header: &StringRecord) -> Self {
+        let find = |names: &[&str]| -> Vec<usize> {
+            header
+                .iter()
+                .enumerate()
+                .filter(|(_, column)| names.contains(&column.trim().to_lowercase().as_str()))
+                .map(|(index, _)| index)
+                .collect()
+        };
+        Self {
+            ids: find(&ID_COLUMNS),
+            watched: find(&WATCHED_COLUMNS),
+            episode: find(&EPISODE_COLUMNS),
+            title: header.iter().position(|column| column.trim().eq_ignore_ascii_case("title")),
+        }
+    }
+
+    /// Every key the row can be recognized by: one per ID it carries, or its title if it has
+    /// none, each combined with the watch time and episode
+    fn keys(&self, row: &StringRecord) -> Vec<String> {
+        let value = |index: &usize| row.get(*index).unwrap_or_default().trim();
+        let when: Vec<&str> = self.watched.iter().chain(&self.episode).map(value).collect();
+        let when = when.join("\u{1f}");
+        let mut keys: Vec<String> = self
+            .ids
+            .iter()
+            .filter(|index| !value(index).is_empty())
+            .map(|index| format!("{}={}\u{1f}{}", index, value(index), when))
+            .collect();
+        if keys.is_empty() {
+            if let Some(title) = &self.title {
+                keys.push(format!("title={}\u{1f}{}", value(title).to_lowercase(), when));
+            }
+        }
+        keys
+    }
+}
+
+/// Appends the rows of `rendered`, a complete CSV in the layout of the file at `path`, that
+/// the file does not have yet: a row is already there when it shares any ID (or, without IDs,
+/// the title) and the watch time and episode with an existing row. Both are read, and the
+/// rows written, in `dialect`. Returns the rows appended.
+pub fn append(path: &Path, rendered: &[u8], dialect: &CsvDialect) -> Result<usize, AppError> {
+    let data = std::fs::read(path)?;
+    let data = data.strip_prefix(BOM).unwrap_or(&data);
+    let mut existing = dialect.reader().flexible(true).from_reader(data);
+    let header = existing.headers()?.clone();
+    let mut new = dialect.reader().from_reader(rendered);
+    if new.headers()? != &header {
+        return Err(AppError::ConfigError(config::ConfigError::Message(format!(
+            "Cannot merge into {}: its columns differ from this export's; write to a new file instead",
+            path.display()
+        ))));
+    }
+
+    let columns = KeyColumns::new(&header);
+    let mut known: HashSet<String> = HashSet::new();
+    for row in existing.records() {
+        known.extend(columns.keys(&row?));
+    }
+
+    let file = OpenOptions::new().append(true).open(path)?;
+    let mut wtr = dialect.writer().has_headers(false).from_writer(file);
+    let mut appended = 0;
+    for row in new.records() {
+        let row = row?;
+        let keys = columns.keys(&row);
+        if keys.iter().any(|key| known.contains(key)) {
+            continue;
+        }
+        known.extend(keys);
+        wtr.write_record(&row)?;
+        appended += 1;
+    }
+    wtr.flush()?;
+    Ok(appended)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_new_rows_are_appended() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trakt.csv");
+        std::fs::write(
+            &path,
+            "imdb_id,tmdb_id,type,title,season,episode,watched_at\n\
+             tt2543164,329865,movie,Arrival,,,2024-03-01T00:00:00Z\n\
+             ,67070,episode,Fleabag,2,3,2024-03-02T00:00:00Z\n",
+        )
+        .unwrap();
+
+        let rendered = "imdb_id,tmdb_id,type,title,season,episode,watched_at\n\
+             tt2543164,,movie,Arrival,,,2024-03-01T00:00:00Z\n\
+             tt5687612,67070,episode,Fleabag,2,3,2024-03-02T00:00:00Z\n\
+             tt5687612,67070,episode,Fleabag,2,4,2024-03-02T00:00:00Z\n\
+             tt2543164,329865,movie,Arrival,,,2024-06-01T00:00:00Z\n";
+        assert_eq!(append(&path, rendered.as_bytes(), &CsvDialect::default()).unwrap(), 2);
+
+        let merged = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = merged.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[3], "tt5687612,67070,episode,Fleabag,2,4,2024-03-02T00:00:00Z");
+        assert_eq!(lines[4], "tt2543164,329865,movie,Arrival,,,2024-06-01T00:00:00Z");
+
+        // A second run with the same history adds nothing
+        assert_eq!(append(&path, rendered.as_bytes(), &CsvDialect::default()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_different_layout_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.csv");
+        std::fs::write(&path, "title,watched_at\nArrival,2024-03-01T00:00:00Z\n").unwrap();
+        assert!(append(&path, b"imdb_id,title,watched_at\n", &CsvDialect::default()).is_err());
+    }
+}
@@ -1,6 +1,25 @@
+pub mod atomic;
+pub mod backup;
+pub mod columns;
 pub mod csv_generator;
+pub mod dedup;
+pub mod filter;
 pub mod history_processor;
+pub mod ical;
+pub mod kodi;
+pub mod merge;
+pub mod output_path;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
+pub mod preview;
 pub mod progress_tracker;
+pub mod report;
+pub mod serializd;
+pub mod simkl_backup;
+pub mod sqlite;
+pub mod unmatched_report;
+pub mod watermark;
+pub mod xlsx;
 
 // Re-export the main structs for easier access
 pub use csv_generator::CsvGenerator;
@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, NaiveDate};
+use regex::{Captures, Regex};
+
+use crate::{config::SplitPeriod, error::AppError};
+
+/// What the placeholders of `output.path` stand for
+#[derive(Debug, Clone)]
+pub struct PathVars {
+    /// `{date}`: the day of the run
+    pub date: NaiveDate,
+    /// `{profile}`: the Prime Video or Netflix profile exported; `default` when unknown
+    pub profile: Option<String>,
+}
+
+impl PathVars {
+    pub fn today() -> Self {
+        Self { date: chrono::Local::now().date_naive(), profile: None }
+    }
+}
+
+/// The watches one file of a split-by-period export holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Period {
+    pub year: i32,
+    /// Left out when splitting by year
+    pub month: Option<u32>,
+}
+
+impl Period {
+    /// The period of a watch on `date` (`YYYY-MM-DD`); None for an undated row
+    pub fn of(date: &str, split: SplitPeriod) -> Option<Self> {
+        let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+        Some(Self { year: day.year(), month: (split == SplitPeriod::Month).then(|| day.month()) })
+    }
+}
+
+/// A profile name as part of a file name: path separators and the like become `_`
+fn file_safe(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | ' ' | '.') { c } else { '_' })
+        .collect()
+}
+
+/// `export.csv` with `2024` becomes `export-2024.csv`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    path.with_file_name(format!("{}-{}{}", stem, suffix, extension))
+}
+
+/// Fills in the placeholders of `template`. `{year}` and `{month}` are those of `period` for
+/// a file of a split export, and of the run otherwise. A period the template does not name
+/// in full is appended to the file name, so `export.csv` split by month gives
+/// `export-2024-03.csv`, and `export-{year}.csv` gives the same.
+pub fn render(template: &Path, vars: &PathVars, period: Option<Period>) -> Result<PathBuf, AppError> {
+    let text = template.to_string_lossy();
+    let placeholder = Regex::new(r"\{(\w*)\}").expect("valid placeholder pattern");
+    let year = period.map_or(vars.date.year(), |period| period.year);
+    let month = period.and_then(|period| period.month).unwrap_or(vars.date.month());
+    let mut unknown = None;
+    let rendered = placeholder.replace_all(&text, |caps: &Captures| match &caps[1] {
+        "date" => vars.date.format("%Y-%m-%d").to_string(),
+        "profile" => file_safe(vars.profile.as_deref().unwrap_or("default")),
+        "year" => year.to_string(),
+        "month" => format!("{:02}", month),
+        other => {
+            unknown.get_or_insert_with(|| other.to_string());
+            String::new()
+        }
+    });
+    if let Some(name) = unknown {
+        return Err(AppError::ConfigError(config::ConfigError::Message(format!(
+            "Unknown placeholder {{{}}} in output.path; use {{date}}, {{profile}}, {{year}} or {{month}}",
+            name
+        ))));
+    }
+
+    let path = PathBuf::from(rendered.as_ref());
+    let Some(period) = period else {
+        return Ok(path);
+    };
+    let mut missing = Vec::new();
+    if !text.contains("{year}") {
+        missing.push(period.year.to_string());
+    }
+    if let Some(month) = period.month.filter(|_| !text.contains("{month}")) {
+        missing.push(format!("{:02}", month));
+    }
+    Ok(if missing.is_empty() { path } else { with_suffix(&path, &missing.join("-")) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholders_and_periods_in_file_names() {
+        let vars = PathVars { date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(), profile: Some("Kids/TV".to_string()) };
+        let render_str = |template: &str, period| render(Path::new(template), &vars, period).unwrap();
+
+        assert_eq!(render_str("out/{profile}-{date}.csv", None), PathBuf::from("out/Kids_TV-2024-03-01.csv"));
+        assert_eq!(render_str("export-{year}.csv", None), PathBuf::from("export-2024.csv"));
+
+        let march = Period::of("2023-03-14", SplitPeriod::Month);
+        assert_eq!(march, Some(Period { year: 2023, month: Some(3) }));
+        assert_eq!(render_str("export.csv", march), PathBuf::from("export-2023-03.csv"));
+        assert_eq!(render_str("export-{year}.csv", march), PathBuf::from("export-2023-03.csv"));
+        assert_eq!(render_str("{year}/{month}.csv", march), PathBuf::from("2023/03.csv"));
+        assert_eq!(render_str("export.csv", Period::of("2023-03-14", SplitPeriod::Year)), PathBuf::from("export-2023.csv"));
+
+        assert!(render(Path::new("export-{week}.csv"), &vars, None).is_err());
+    }
+}
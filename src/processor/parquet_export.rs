@@ -0,0 +1,209 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BooleanArray, Date32Array, Float64Array, Int32Array, ListBuilder, StringArray, StringBuilder,
+    TimestampMillisecondArray, UInt32Array, UInt8Array,
+};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use chrono::{DateTime, NaiveDate};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::{
+    config::WatchedAtConfig,
+    error::AppError,
+    metadata::Enrichment,
+    models::MediaType,
+    processor::history_processor::ProcessedItem,
+};
+
+fn schema() -> Schema {
+    let text = |name: &str| Field::new(name, DataType::Utf8, true);
+    Schema::new(vec![
+        Field::new("watched_date", DataType::Date32, true),
+        Field::new("watched_at", DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())), true),
+        Field::new("media_type", DataType::Utf8, false),
+        Field::new("title", DataType::Utf8, false),
+        Field::new("matched_title", DataType::Utf8, false),
+        text("original_title"),
+        Field::new("year", DataType::Int32, true),
+        text("episode"),
+        Field::new("season", DataType::UInt32, true),
+        Field::new("episode_number", DataType::UInt32, true),
+        Field::new("rating", DataType::UInt8, true),
+        Field::new("plays", DataType::UInt32, false),
+        Field::new("score", DataType::Float64, true),
+        Field::new("anime", DataType::Boolean, false),
+        text("simkl_id"),
+        text("tvdb_id"),
+        text("tmdb_id"),
+        text("imdb_id"),
+        text("mal_id"),
+        text("anilist_id"),
+        text("anidb_id"),
+        Field::new("genres", DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))), true),
+        Field::new("runtime", DataType::UInt32, true),
+        text("poster_url"),
+        text("overview"),
+        text("prime_url"),
+    ])
+}
+
+/// Days since 1970-01-01, Arrow's `Date32`
+fn epoch_days(date: &str) -> Option<i32> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date");
+    i32::try_from((date - epoch).num_days()).ok()
+}
+
+/// The watch as an instant, or nothing when `watched_at` is configured to carry just the day
+fn epoch_millis(item: &ProcessedItem, clock: &WatchedAtConfig) -> Option<i64> {
+    DateTime::parse_from_rfc3339(&item.watched_at(clock)).ok().map(|at| at.timestamp_millis())
+}
+
+/// One column of optional strings picked from each item
+fn strings<'a>(items: &'a [ProcessedItem], value: impl Fn(&'a ProcessedItem) -> Option<&'a str>) -> ArrayRef {
+    Arc::new(StringArray::from(items.iter().map(value).collect::<Vec<_>>()))
+}
+
+fn enrichment(item: &ProcessedItem) -> Option<&Enrichment> {
+    item.metadata.enrichment.as_ref()
+}
+
+fn record_batch(items: &[ProcessedItem], clock: &WatchedAtConfig) -> Result<RecordBatch, AppError> {
+    let codes: Vec<Option<(u32, u32)>> = items.iter().map(ProcessedItem::episode_code).collect();
+    let mut genres = ListBuilder::new(StringBuilder::new());
+    for item in items {
+        match enrichment(item) {
+            Some(enrichment) if !enrichment.genres.is_empty() => {
+                for genre in &enrichment.genres {
+                    genres.values().append_value(genre);
+                }
+                genres.append(true);
+            }
+            _ => genres.append(false),
+        }
+    }
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(Date32Array::from(items.iter().map(|item| epoch_days(&item.date)).collect::<Vec<_>>())),
+        Arc::new(
+            TimestampMillisecondArray::from(items.iter().map(|item| epoch_millis(item, clock)).collect::<Vec<_>>())
+                .with_timezone("UTC"),
+        ),
+        strings(items, |item| {
+            Some(match item.media_type {
+                MediaType::Movie => "movie",
+                MediaType::Tv => "tv",
+            })
+        }),
+        strings(items, |item| Some(item.title.as_str())),
+        strings(items, |item| Some(item.metadata.title.as_str())),
+        strings(items, |item| item.metadata.original_title.as_deref()),
+        Arc::new(Int32Array::from(
+            items
+                .iter()
+                .map(|item| item.metadata.year.as_deref().and_then(crate::matching::parse_year))
+                .collect::<Vec<_>>(),
+        )),
+        strings(items, |item| item.episode.as_deref()),
+        Arc::new(UInt32Array::from(codes.iter().map(|code| code.map(|(season, _)| season)).collect::<Vec<_>>())),
+        Arc::new(UInt32Array::from(codes.iter().map(|code| code.map(|(_, episode)| episode)).collect::<Vec<_>>())),
+        Arc::new(UInt8Array::from(items.iter().map(|item| item.rating).collect::<Vec<_>>())),
+        Arc::new(UInt32Array::from(items.iter().map(|item| item.plays).collect::<Vec<_>>())),
+        Arc::new(Float64Array::from(items.iter().map(|item| item.metadata.score).collect::<Vec<_>>())),
+        Arc::new(BooleanArray::from(items.iter().map(|item| item.metadata.anime).collect::<Vec<_>>())),
+        strings(items, |item| item.metadata.ids.simkl.as_deref()),
+        strings(items, |item| item.metadata.ids.tvdb.as_deref()),
+        strings(items, |item| item.metadata.ids.tmdb.as_deref()),
+        strings(items, |item| item.metadata.ids.imdb.as_deref()),
+        strings(items, |item| item.metadata.ids.mal.as_deref()),
+        strings(items, |item| item.metadata.ids.anilist.as_deref()),
+        strings(items, |item| item.metadata.ids.anidb.as_deref()),
+        Arc::new(genres.finish()),
+        Arc::new(UInt32Array::from(
+            items.iter().map(|item| enrichment(item).and_then(|enrichment| enrichment.runtime)).collect::<Vec<_>>(),
+        )),
+        strings(items, |item| enrichment(item).and_then(|enrichment| enrichment.poster_url.as_deref())),
+        strings(items, |item| enrichment(item).and_then(|enrichment| enrichment.overview.as_deref())),
+        strings(items, |item| item.source_url.as_deref()),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema()), columns)?)
+}
+
+/// Writes `items` to `path` as a Snappy-compressed Parquet file with one row per export row
+/// and typed columns: dates and timestamps, numbers, and genres as a list
+pub fn write(path: &Path, items: &[ProcessedItem], clock: &WatchedAtConfig) -> Result<(), AppError> {
+    let batch = record_batch(items, clock)?;
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Timezone;
+    use crate::metadata::{MediaIds, MetadataResult};
+    use arrow::array::{Array, ListArray};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn item(title: &str, date: &str, episode: Option<&str>) -> ProcessedItem {
+        let media_type = if episode.is_some() { MediaType::Tv } else { MediaType::Movie };
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids: MediaIds { tmdb: Some("67070".to_string()), ..Default::default() },
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                score: Some(0.9),
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            rating: Some(8),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_rows_round_trip_with_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.parquet");
+        let mut fleabag = item("Fleabag", "2024-03-02", Some("S02E03"));
+        fleabag.metadata.enrichment =
+            Some(Enrichment { genres: vec!["Comedy".to_string(), "Drama".to_string()], ..Default::default() });
+        let items = vec![item("Arrival", "2024-01-05", None), fleabag];
+        let clock = WatchedAtConfig { timezone: Timezone::Utc, ..Default::default() };
+        write(&path, &items, &clock).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap().build().unwrap();
+        let batches: Vec<RecordBatch> = reader.collect::<Result<_, _>>().unwrap();
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+
+        let column = |name: &str| batch.column(batch.schema().index_of(name).unwrap()).clone();
+        let dates = column("watched_date");
+        let dates = dates.as_any().downcast_ref::<Date32Array>().unwrap();
+        assert_eq!(dates.value(0), 19727);
+        let at = column("watched_at");
+        let at = at.as_any().downcast_ref::<TimestampMillisecondArray>().unwrap();
+        assert_eq!(at.value(1), 1_709_337_600_000);
+        let seasons = column("season");
+        let seasons = seasons.as_any().downcast_ref::<UInt32Array>().unwrap();
+        assert!(seasons.is_null(0));
+        assert_eq!(seasons.value(1), 2);
+        let genres = column("genres");
+        let genres = genres.as_any().downcast_ref::<ListArray>().unwrap();
+        assert!(genres.is_null(0));
+        assert_eq!(genres.value(1).len(), 2);
+    }
+}
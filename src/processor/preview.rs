@@ -0,0 +1,122 @@
+use std::fmt::Write;
+
+use crate::{models::MediaType, processor::history_processor::ProcessedItem};
+
+/// Longest title shown before it is cut short
+const TITLE_WIDTH: usize = 40;
+
+/// The first ID a row carries, as `tmdb:438631`; `-` for an unmatched row
+fn match_id(item: &ProcessedItem) -> String {
+    let ids = &item.metadata.ids;
+    [
+        ("simkl", &ids.simkl),
+        ("tmdb", &ids.tmdb),
+        ("imdb", &ids.imdb),
+        ("tvdb", &ids.tvdb),
+        ("mal", &ids.mal),
+        ("anilist", &ids.anilist),
+        ("anidb", &ids.anidb),
+    ]
+    .into_iter()
+    .find_map(|(name, id)| id.as_ref().map(|id| format!("{}:{}", name, id)))
+    .unwrap_or_else(|| "-".to_string())
+}
+
+fn clip(title: &str) -> String {
+    if title.chars().count() <= TITLE_WIDTH {
+        return title.to_string();
+    }
+    let mut clipped: String = title.chars().take(TITLE_WIDTH - 1).collect();
+    clipped.push('…');
+    clipped
+}
+
+fn write_row(table: &mut String, item: &ProcessedItem) {
+    let kind = match item.media_type {
+        MediaType::Movie => "movie",
+        MediaType::Tv => "tv",
+    };
+    let episode = match item.episode_code() {
+        Some((season, episode)) => format!("S{:02}E{:02}", season, episode),
+        None => item.episode.as_deref().unwrap_or("").chars().take(10).collect(),
+    };
+    let score = item.metadata.score.map_or("-".to_string(), |score| format!("{:.2}", score));
+    let _ = writeln!(
+        table,
+        "{:<5} {:<width$} {:<4} {:<10} {:<10} {:<16} {:>5}",
+        kind,
+        clip(&item.title),
+        item.metadata.year.as_deref().unwrap_or(""),
+        episode,
+        item.date,
+        match_id(item),
+        score,
+        width = TITLE_WIDTH
+    );
+}
+
+/// A table of the first and last `rows` rows of an export, followed by totals
+pub fn table(items: &[ProcessedItem], rows: usize) -> String {
+    let mut table = format!(
+        "{:<5} {:<width$} {:<4} {:<10} {:<10} {:<16} {:>5}\n",
+        "Type", "Title", "Year", "Episode", "Watched", "Match", "Score",
+        width = TITLE_WIDTH
+    );
+    if items.len() <= rows * 2 {
+        items.iter().for_each(|item| write_row(&mut table, item));
+    } else {
+        items[..rows].iter().for_each(|item| write_row(&mut table, item));
+        let _ = writeln!(table, "… {} more rows …", items.len() - rows * 2);
+        items[items.len() - rows..].iter().for_each(|item| write_row(&mut table, item));
+    }
+
+    let movies = items.iter().filter(|item| item.media_type == MediaType::Movie).count();
+    let unmatched = items.iter().filter(|item| match_id(item) == "-").count();
+    let _ = writeln!(
+        table,
+        "{} rows: {} movies, {} shows or episodes, {} without any ID",
+        items.len(),
+        movies,
+        items.len() - movies,
+        unmatched
+    );
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{MediaIds, MetadataResult};
+
+    fn movie(title: &str, tmdb: Option<&str>) -> ProcessedItem {
+        ProcessedItem {
+            title: title.to_string(),
+            date: "2024-03-01".to_string(),
+            media_type: MediaType::Movie,
+            metadata: MetadataResult {
+                ids: MediaIds { tmdb: tmdb.map(String::from), ..Default::default() },
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type: MediaType::Movie,
+                score: Some(0.93),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_long_exports_show_both_ends_and_totals() {
+        let mut items: Vec<ProcessedItem> = (1..=7).map(|n| movie(&format!("Movie {}", n), Some("329865"))).collect();
+        items.push(movie("Unknown", None));
+
+        let table = table(&items, 2);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[0].starts_with("Type  Title"));
+        assert!(lines[1].contains("Movie 1") && lines[1].contains("tmdb:329865") && lines[1].ends_with(" 0.93"));
+        assert_eq!(lines[3], "… 4 more rows …");
+        assert!(lines[5].contains("Unknown") && lines[5].contains(" - "));
+        assert_eq!(lines[6], "8 rows: 8 movies, 0 shows or episodes, 1 without any ID");
+    }
+}
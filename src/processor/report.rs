@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use chrono::NaiveDate;
+
+use crate::{models::MediaType, processor::history_processor::ProcessedItem};
+
+/// What one movie or show amounts to within a month
+struct Entry<'a> {
+    item: &'a ProcessedItem,
+    /// Episode codes, or the episode names that have none, in watch order
+    episodes: Vec<String>,
+    first: &'a str,
+    last: &'a str,
+    plays: u32,
+}
+
+impl<'a> Entry<'a> {
+    fn title(&self) -> String {
+        match &self.item.metadata.year {
+            Some(year) => format!("{} ({})", self.item.title, year),
+            None => self.item.title.clone(),
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self.item.media_type {
+            MediaType::Movie if self.plays > 1 => format!("Movie, watched {} times", self.plays),
+            MediaType::Movie => "Movie".to_string(),
+            MediaType::Tv if self.episodes.len() == 1 => format!("1 episode: {}", self.episodes[0]),
+            MediaType::Tv => format!("{} episodes: {}", self.episodes.len(), self.episodes.join(", ")),
+        }
+    }
+
+    fn watched(&self) -> String {
+        if self.first == self.last {
+            self.last.to_string()
+        } else {
+            format!("{} – {}", self.first, self.last)
+        }
+    }
+
+    fn rating(&self) -> Option<String> {
+        self.item.rating.map(|rating| format!("{}/10", rating))
+    }
+
+    fn poster(&self) -> Option<&str> {
+        self.item.metadata.enrichment.as_ref().and_then(|enrichment| enrichment.poster_url.as_deref())
+    }
+}
+
+struct Month<'a> {
+    label: String,
+    entries: Vec<Entry<'a>>,
+}
+
+/// Groups `items` by the month they were watched in, newest month first. Within a month every
+/// movie or show gets one entry, the most recently watched first.
+fn months(items: &[ProcessedItem]) -> Vec<Month<'_>> {
+    let mut sorted: Vec<&ProcessedItem> = items.iter().collect();
+    sorted.sort_by(|a, b| a.date.cmp(&b.date));
+
+    // Keyed by "YYYY-MM" so the months sort by date; rows without a usable date go last
+    let mut grouped: BTreeMap<String, (String, Vec<Entry>)> = BTreeMap::new();
+    for item in sorted {
+        let (key, label) = match NaiveDate::parse_from_str(&item.date, "%Y-%m-%d") {
+            Ok(date) => (date.format("%Y-%m").to_string(), date.format("%B %Y").to_string()),
+            Err(_) => (String::new(), "Undated".to_string()),
+        };
+        let entries = &mut grouped.entry(key).or_insert_with(|| (label, Vec::new())).1;
+        let existing = entries
+            .iter()
+            .position(|entry| entry.item.media_type == item.media_type && entry.item.title == item.title);
+        let index = existing.unwrap_or_else(|| {
+            entries.push(Entry { item, episodes: Vec::new(), first: &item.date, last: &item.date, plays: 0 });
+            entries.len() - 1
+        });
+        let entry = &mut entries[index];
+        entry.last = &item.date;
+        entry.plays += item.plays;
+        if let Some(episode) = &item.episode {
+            let name = match item.episode_code() {
+                Some((season, number)) => format!("S{:02}E{:02}", season, number),
+                None => episode.clone(),
+            };
+            if !entry.episodes.contains(&name) {
+                entry.episodes.push(name);
+            }
+        }
+    }
+
+    grouped
+        .into_iter()
+        .rev()
+        .map(|(_, (label, mut entries))| {
+            entries.sort_by(|a, b| b.last.cmp(a.last));
+            Month { label, entries }
+        })
+        .collect()
+}
+
+/// One line of totals under the report's heading
+fn summary(items: &[ProcessedItem]) -> String {
+    let movies = items.iter().filter(|item| item.media_type == MediaType::Movie).count();
+    let episodes = items.len() - movies;
+    let dates = items.iter().map(|item| item.date.as_str()).filter(|date| !date.is_empty());
+    match (dates.clone().min(), dates.max()) {
+        (Some(first), Some(last)) => {
+            format!("{} movies and {} episodes watched between {} and {}", movies, episodes, first, last)
+        }
+        _ => format!("{} movies and {} episodes", movies, episodes),
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '|' | '*' | '_' | '[' | ']' | '<' | '>' | '`' | '#') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The history as a Markdown page: a table per month with a poster column when any title
+/// has a poster from enrichment
+pub fn markdown(items: &[ProcessedItem]) -> String {
+    let months = months(items);
+    let posters = months.iter().flat_map(|month| &month.entries).any(|entry| entry.poster().is_some());
+
+    let mut out = String::from("# Viewing history\n\n");
+    let _ = writeln!(out, "{}\n", summary(items));
+    for month in &months {
+        let _ = writeln!(out, "## {}\n", month.label);
+        if posters {
+            out.push_str("| | Title | Watched | Date | Rating |\n|---|---|---|---|---|\n");
+        } else {
+            out.push_str("| Title | Watched | Date | Rating |\n|---|---|---|---|\n");
+        }
+        for entry in &month.entries {
+            if posters {
+                match entry.poster() {
+                    Some(url) => {
+                        let _ = write!(out, "| <img src=\"{}\" alt=\"\" width=\"60\"> ", escape_html(url));
+                    }
+                    None => out.push_str("| "),
+                }
+            }
+            let _ = writeln!(
+                out,
+                "| **{}** | {} | {} | {} |",
+                escape_markdown(&entry.title()),
+                escape_markdown(&entry.detail()),
+                entry.watched(),
+                entry.rating().unwrap_or_default()
+            );
+        }
+        out.push('\n');
+    }
+    out
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:60rem;margin:2rem auto;padding:0 1rem;color:#222}\
+    h2{border-bottom:1px solid #ddd;padding-bottom:.3rem;margin-top:2.5rem}\
+    ul{list-style:none;padding:0}li{display:flex;gap:1rem;align-items:flex-start;margin:.8rem 0}\
+    img{width:60px;border-radius:4px}.meta{color:#666;font-size:.9rem}";
+
+/// The history as a standalone HTML page with a section per month; posters from enrichment are
+/// linked, not embedded, so viewing it needs a connection
+pub fn html(items: &[ProcessedItem]) -> String {
+    let mut out = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<title>Viewing history</title>\n");
+    let _ = writeln!(out, "<style>{}</style>", STYLE);
+    out.push_str("</head>\n<body>\n<h1>Viewing history</h1>\n");
+    let _ = writeln!(out, "<p class=\"meta\">{}</p>", escape_html(&summary(items)));
+    for month in months(items) {
+        let _ = writeln!(out, "<h2>{}</h2>\n<ul>", escape_html(&month.label));
+        for entry in &month.entries {
+            out.push_str("<li>");
+            if let Some(url) = entry.poster() {
+                let _ = write!(out, "<img src=\"{}\" alt=\"\" loading=\"lazy\">", escape_html(url));
+            }
+            let _ = write!(out, "<div><strong>{}</strong><br>", escape_html(&entry.title()));
+            let _ = write!(out, "<span class=\"meta\">{} · {}", escape_html(&entry.detail()), escape_html(&entry.watched()));
+            if let Some(rating) = entry.rating() {
+                let _ = write!(out, " · {}", rating);
+            }
+            out.push_str("</span></div></li>\n");
+        }
+        out.push_str("</ul>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{Enrichment, MetadataResult};
+
+    fn item(title: &str, date: &str, episode: Option<&str>) -> ProcessedItem {
+        let media_type = if episode.is_some() { MediaType::Tv } else { MediaType::Movie };
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_history_is_grouped_by_month() {
+        let mut arrival = item("Arrival", "2024-01-05", None);
+        arrival.rating = Some(9);
+        arrival.metadata.enrichment =
+            Some(Enrichment { poster_url: Some("https://image.tmdb.org/t/p/w185/x.jpg".to_string()), ..Default::default() });
+        let items = vec![
+            item("Fleabag", "2024-03-03", Some("S01E02")),
+            arrival,
+            item("Fleabag", "2024-03-02", Some("S01E01 - Episode 1")),
+            item("Tom & Jerry", "2024-01-20", Some("Pilot")),
+        ];
+
+        let md = markdown(&items);
+        assert!(md.contains("1 movies and 3 episodes watched between 2024-01-05 and 2024-03-03"));
+        let march = md.find("## March 2024").unwrap();
+        let january = md.find("## January 2024").unwrap();
+        assert!(march < january);
+        assert!(md.contains("| | **Fleabag (2016)** | 2 episodes: S01E01, S01E02 | 2024-03-02 – 2024-03-03 |  |\n"));
+        assert!(md.contains("| <img src=\"https://image.tmdb.org/t/p/w185/x.jpg\" alt=\"\" width=\"60\"> | **Arrival (2016)** | Movie | 2024-01-05 | 9/10 |"));
+
+        let html = html(&items);
+        assert!(html.contains("<strong>Tom &amp; Jerry (2016)</strong>"));
+        assert!(html.contains("1 episode: Pilot · 2024-01-20"));
+        assert_eq!(html.matches("<h2>").count(), 2);
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+
+use csv::Writer;
+
+use crate::{error::AppError, models::MediaType, processor::history_processor::ProcessedItem};
+
+const HEADER: [&str; 9] = [
+    "title", "year", "tmdb_id", "tvdb_id", "imdb_id", "season", "episodes_watched", "first_watched", "last_watched",
+];
+
+/// One season of a show as Serializd logs it, with the episodes of it in the history
+struct SeasonRow<'a> {
+    item: &'a ProcessedItem,
+    season: u32,
+    episodes: BTreeSet<u32>,
+    first_watched: &'a str,
+    last_watched: &'a str,
+}
+
+/// Folds the episodes of `items` into one row per show season, ordered by show and season.
+/// Serializd only tracks TV, so movies are left out, as are episodes without a season number.
+fn seasons(items: &[ProcessedItem]) -> Vec<SeasonRow<'_>> {
+    let mut rows: BTreeMap<(String, u32), SeasonRow> = BTreeMap::new();
+    let mut unnumbered = 0;
+    for item in items.iter().filter(|item| item.media_type == MediaType::Tv) {
+        let Some((season, episode)) = item.episode_code() else {
+            unnumbered += 1;
+            continue;
+        };
+        let ids = &item.metadata.ids;
+        let show = ids.tmdb.clone().or_else(|| ids.tvdb.clone()).unwrap_or_else(|| item.title.to_lowercase());
+        let row = rows.entry((show, season)).or_insert(SeasonRow {
+            item,
+            season,
+            episodes: BTreeSet::new(),
+            first_watched: &item.date,
+            last_watched: &item.date,
+        });
+        row.episodes.insert(episode);
+        row.first_watched = row.first_watched.min(item.date.as_str());
+        row.last_watched = row.last_watched.max(item.date.as_str());
+    }
+    if unnumbered > 0 {
+        tracing::warn!("Left {} episodes without a season number out of the Serializd export", unnumbered);
+    }
+    let mut rows: Vec<SeasonRow> = rows.into_values().collect();
+    rows.sort_by(|a, b| a.item.title.cmp(&b.item.title).then(a.season.cmp(&b.season)));
+    rows
+}
+
+/// Writes the shows of `items` in Serializd's layout, one row per season watched; returns
+/// how many rows were written
+pub fn write<W: Write>(wtr: &mut Writer<W>, items: &[ProcessedItem]) -> Result<usize, AppError> {
+    let rows = seasons(items);
+    wtr.write_record(HEADER)?;
+    for row in &rows {
+        let ids = &row.item.metadata.ids;
+        wtr.write_record([
+            row.item.title.as_str(),
+            row.item.metadata.year.as_deref().unwrap_or_default(),
+            ids.tmdb.as_deref().unwrap_or_default(),
+            ids.tvdb.as_deref().unwrap_or_default(),
+            ids.imdb.as_deref().unwrap_or_default(),
+            row.season.to_string().as_str(),
+            row.episodes.len().to_string().as_str(),
+            row.first_watched,
+            row.last_watched,
+        ])?;
+    }
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{MediaIds, MetadataResult};
+
+    fn item(title: &str, date: &str, media_type: MediaType, episode: Option<&str>) -> ProcessedItem {
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids: MediaIds { tmdb: Some("67070".to_string()), ..Default::default() },
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_episodes_fold_into_seasons() {
+        let items = vec![
+            item("Fleabag", "2024-03-02", MediaType::Tv, Some("S01E02")),
+            item("Fleabag", "2024-03-01", MediaType::Tv, Some("S01E01")),
+            item("Fleabag", "2024-03-05", MediaType::Tv, Some("S01E01")),
+            item("Fleabag", "2024-04-01", MediaType::Tv, Some("S02E01")),
+            item("Fleabag", "2024-04-02", MediaType::Tv, None),
+            item("Arrival", "2024-01-01", MediaType::Movie, None),
+        ];
+
+        let mut wtr = Writer::from_writer(Vec::new());
+        assert_eq!(write(&mut wtr, &items).unwrap(), 2);
+        let csv = String::from_utf8(wtr.into_inner().unwrap()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "title,year,tmdb_id,tvdb_id,imdb_id,season,episodes_watched,first_watched,last_watched");
+        assert_eq!(lines[1], "Fleabag,2016,67070,,,1,2,2024-03-01,2024-03-05");
+        assert_eq!(lines[2], "Fleabag,2016,67070,,,2,1,2024-04-01,2024-04-01");
+    }
+}
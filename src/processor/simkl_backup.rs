@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{
+    config::WatchedAtConfig,
+    error::AppError,
+    metadata::MediaIds,
+    models::MediaType,
+    processor::history_processor::ProcessedItem,
+};
+
+/// IDs as Simkl writes them in a backup: its own as a number, the others as text
+#[derive(Debug, Serialize)]
+struct Ids {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    simkl: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    imdb: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tmdb: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tvdb: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anilist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anidb: Option<String>,
+}
+
+impl From<&MediaIds> for Ids {
+    fn from(ids: &MediaIds) -> Self {
+        Self {
+            simkl: ids.simkl.as_deref().and_then(|id| id.parse().ok()),
+            imdb: ids.imdb.clone(),
+            tmdb: ids.tmdb.clone(),
+            tvdb: ids.tvdb.clone(),
+            mal: ids.mal.clone(),
+            anilist: ids.anilist.clone(),
+            anidb: ids.anidb.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Title {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    year: Option<i32>,
+    ids: Ids,
+}
+
+impl From<&ProcessedItem> for Title {
+    fn from(item: &ProcessedItem) -> Self {
+        Self {
+            title: item.title.clone(),
+            year: item.metadata.year.as_deref().and_then(crate::matching::parse_year),
+            ids: Ids::from(&item.metadata.ids),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MovieEntry {
+    last_watched_at: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_rating: Option<u8>,
+    movie: Title,
+}
+
+#[derive(Debug, Serialize)]
+struct ShowEntry {
+    last_watched_at: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_rating: Option<u8>,
+    watched_episodes_count: usize,
+    show: Title,
+    seasons: Vec<Season>,
+}
+
+#[derive(Debug, Serialize)]
+struct Season {
+    number: u32,
+    episodes: Vec<Episode>,
+}
+
+#[derive(Debug, Serialize)]
+struct Episode {
+    number: u32,
+    watched_at: String,
+}
+
+/// The three lists of a Simkl backup, as `/sync/all-items` returns them
+#[derive(Debug, Default, Serialize)]
+struct Backup {
+    movies: Vec<MovieEntry>,
+    shows: Vec<ShowEntry>,
+    anime: Vec<ShowEntry>,
+}
+
+/// What groups the rows of one title
+fn title_key(item: &ProcessedItem) -> String {
+    format!("{}\u{1f}{}", serde_json::to_string(&item.metadata.ids).unwrap_or_default(), item.title.to_lowercase())
+}
+
+impl ShowEntry {
+    fn add(&mut self, item: &ProcessedItem, watched_at: String) {
+        if let Some((season, number)) = item.episode_code() {
+            let season = match self.seasons.iter().position(|s| s.number == season) {
+                Some(index) => &mut self.seasons[index],
+                None => {
+                    self.seasons.push(Season { number: season, episodes: Vec::new() });
+                    self.seasons.last_mut().expect("just pushed")
+                }
+            };
+            match season.episodes.iter_mut().find(|episode| episode.number == number) {
+                Some(episode) => episode.watched_at = episode.watched_at.clone().max(watched_at.clone()),
+                None => season.episodes.push(Episode { number, watched_at: watched_at.clone() }),
+            }
+        }
+        self.last_watched_at = self.last_watched_at.clone().max(watched_at);
+        self.user_rating = item.rating.or(self.user_rating);
+    }
+}
+
+fn backup(items: &[ProcessedItem], clock: &WatchedAtConfig, auto_status: bool) -> Backup {
+    let mut backup = Backup::default();
+    let mut movies: HashMap<String, usize> = HashMap::new();
+    let mut shows: HashMap<String, (bool, usize)> = HashMap::new();
+    for item in items {
+        let watched_at = item.watched_at(clock);
+        match item.media_type {
+            MediaType::Movie => match movies.get(&title_key(item)) {
+                Some(&index) => {
+                    let entry = &mut backup.movies[index];
+                    entry.last_watched_at = entry.last_watched_at.clone().max(watched_at);
+                    entry.user_rating = item.rating.or(entry.user_rating);
+                }
+                None => {
+                    movies.insert(title_key(item), backup.movies.len());
+                    backup.movies.push(MovieEntry {
+                        last_watched_at: watched_at,
+                        status: "completed",
+                        user_rating: item.rating,
+                        movie: Title::from(item),
+                    });
+                }
+            },
+            MediaType::Tv => {
+                let anime = item.metadata.anime;
+                let (anime, index) = *shows.entry(title_key(item)).or_insert_with(|| {
+                    let list = if anime { &mut backup.anime } else { &mut backup.shows };
+                    list.push(ShowEntry {
+                        last_watched_at: String::new(),
+                        status: item.watch_status(auto_status),
+                        user_rating: None,
+                        watched_episodes_count: 0,
+                        show: Title::from(item),
+                        seasons: Vec::new(),
+                    });
+                    (anime, list.len() - 1)
+                });
+                let list = if anime { &mut backup.anime } else { &mut backup.shows };
+                list[index].add(item, watched_at);
+            }
+        }
+    }
+    for show in backup.shows.iter_mut().chain(backup.anime.iter_mut()) {
+        show.seasons.sort_by_key(|season| season.number);
+        for season in &mut show.seasons {
+            season.episodes.sort_by_key(|episode| episode.number);
+        }
+        show.watched_episodes_count = show.seasons.iter().map(|season| season.episodes.len()).sum();
+    }
+    backup
+}
+
+/// Writes `items` to `path` in the layout of a Simkl backup: movies, shows and anime, each
+/// title once with its IDs, status, rating and last watch, and shows with every numbered
+/// episode and its watch date. Returns the number of titles.
+pub fn write(path: &Path, items: &[ProcessedItem], clock: &WatchedAtConfig, auto_status: bool) -> Result<usize, AppError> {
+    let backup = backup(items, clock, auto_status);
+    let mut out = BufWriter::new(File::create(path)?);
+    serde_json::to_writer_pretty(&mut out, &backup)?;
+    writeln!(out)?;
+    out.flush()?;
+    Ok(backup.movies.len() + backup.shows.len() + backup.anime.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Timezone;
+    use crate::metadata::MetadataResult;
+
+    fn item(title: &str, date: &str, episode: Option<&str>, ids: MediaIds) -> ProcessedItem {
+        let media_type = if episode.is_some() { MediaType::Tv } else { MediaType::Movie };
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids,
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_titles_are_grouped_like_a_simkl_backup() {
+        let arrival = MediaIds { simkl: Some("53536".to_string()), imdb: Some("tt2543164".to_string()), ..Default::default() };
+        let fleabag = MediaIds { tmdb: Some("67070".to_string()), ..Default::default() };
+        let mut rated = item("Arrival", "2024-03-01", None, arrival.clone());
+        rated.rating = Some(9);
+        let mut anime = item("Frieren", "2024-02-01", Some("S01E01"), MediaIds { mal: Some("52991".to_string()), ..Default::default() });
+        anime.metadata.anime = true;
+        let items = vec![
+            item("Arrival", "2024-01-05", None, arrival),
+            rated,
+            item("Fleabag", "2024-03-03", Some("S01E02"), fleabag.clone()),
+            item("Fleabag", "2024-03-02", Some("S01E01"), fleabag.clone()),
+            item("Fleabag", "2024-03-04", Some("Pilot"), fleabag),
+            anime,
+        ];
+        let clock = WatchedAtConfig { timezone: Timezone::Utc, ..Default::default() };
+        let json = serde_json::to_value(backup(&items, &clock, false)).unwrap();
+
+        assert_eq!(
+            json["movies"],
+            serde_json::json!([{
+                "last_watched_at": "2024-03-01T00:00:00Z",
+                "status": "completed",
+                "user_rating": 9,
+                "movie": {"title": "Arrival", "year": 2016, "ids": {"simkl": 53536, "imdb": "tt2543164"}}
+            }])
+        );
+        let show = &json["shows"][0];
+        assert_eq!(json["shows"].as_array().unwrap().len(), 1);
+        assert_eq!(show["last_watched_at"], "2024-03-04T00:00:00Z");
+        assert_eq!(show["watched_episodes_count"], 2);
+        assert_eq!(show["seasons"][0]["episodes"][0], serde_json::json!({"number": 1, "watched_at": "2024-03-02T00:00:00Z"}));
+        assert_eq!(json["anime"][0]["show"]["ids"]["mal"], "52991");
+    }
+}
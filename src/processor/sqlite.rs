@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::{
+    config::WatchedAtConfig,
+    error::AppError,
+    models::MediaType,
+    processor::history_processor::ProcessedItem,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE titles (
+        id             INTEGER PRIMARY KEY,
+        media_type     TEXT NOT NULL,
+        title          TEXT NOT NULL,
+        original_title TEXT,
+        year           INTEGER,
+        score          REAL,
+        anime          INTEGER NOT NULL,
+        simkl_id       TEXT,
+        tvdb_id        TEXT,
+        tmdb_id        TEXT,
+        imdb_id        TEXT,
+        mal_id         TEXT,
+        anilist_id     TEXT,
+        anidb_id       TEXT,
+        genres         TEXT,
+        runtime        INTEGER,
+        poster_url     TEXT,
+        overview       TEXT
+    );
+    CREATE TABLE watches (
+        id             INTEGER PRIMARY KEY,
+        title_id       INTEGER NOT NULL REFERENCES titles(id),
+        prime_title    TEXT NOT NULL,
+        watched_date   TEXT NOT NULL,
+        watched_at     TEXT NOT NULL,
+        episode        TEXT,
+        season         INTEGER,
+        episode_number INTEGER,
+        rating         INTEGER,
+        plays          INTEGER NOT NULL,
+        source_url     TEXT
+    );
+    CREATE INDEX watches_title ON watches(title_id);
+    CREATE INDEX watches_date ON watches(watched_date);
+    CREATE VIEW history AS
+        SELECT watches.watched_date, watches.watched_at, titles.media_type, titles.title, titles.year, watches.season,
+               watches.episode_number, watches.rating, watches.plays, titles.tmdb_id, titles.imdb_id
+        FROM watches JOIN titles ON titles.id = watches.title_id;
+";
+
+fn media_type(item: &ProcessedItem) -> &'static str {
+    match item.media_type {
+        MediaType::Movie => "movie",
+        MediaType::Tv => "tv",
+    }
+}
+
+/// Writes `items` to a new SQLite database at `path`: a `titles` table with one row per
+/// matched movie or show and its IDs, a `watches` table with one row per export row pointing
+/// at its title, and a `history` view joining the two. Returns the number of titles.
+pub fn write(path: &Path, items: &[ProcessedItem], clock: &WatchedAtConfig) -> Result<usize, AppError> {
+    // The tables are created fresh, so a file left by an interrupted run has to go first
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let mut conn = Connection::open(path)?;
+    conn.execute_batch(SCHEMA)?;
+
+    let tx = conn.transaction()?;
+    let mut titles: HashMap<String, i64> = HashMap::new();
+    {
+        let mut insert_title = tx.prepare(
+            "INSERT INTO titles (media_type, title, original_title, year, score, anime, simkl_id, tvdb_id,
+                                 tmdb_id, imdb_id, mal_id, anilist_id, anidb_id, genres, runtime, poster_url, overview)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+        )?;
+        let mut insert_watch = tx.prepare(
+            "INSERT INTO watches (title_id, prime_title, watched_date, watched_at, episode, season, episode_number,
+                                  rating, plays, source_url)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        )?;
+        for item in items {
+            let meta = &item.metadata;
+            let key = format!("{}\u{1f}{}\u{1f}{}", media_type(item), serde_json::to_string(&meta.ids)?, meta.title);
+            let title_id = match titles.get(&key) {
+                Some(id) => *id,
+                None => {
+                    let ids = &meta.ids;
+                    let enrichment = meta.enrichment.clone().unwrap_or_default();
+                    insert_title.execute(params![
+                        media_type(item),
+                        meta.title,
+                        meta.original_title,
+                        meta.year.as_deref().and_then(crate::matching::parse_year),
+                        meta.score,
+                        meta.anime,
+                        ids.simkl,
+                        ids.tvdb,
+                        ids.tmdb,
+                        ids.imdb,
+                        ids.mal,
+                        ids.anilist,
+                        ids.anidb,
+                        (!enrichment.genres.is_empty()).then(|| enrichment.genres.join(", ")),
+                        enrichment.runtime,
+                        enrichment.poster_url,
+                        enrichment.overview,
+                    ])?;
+                    let id = tx.last_insert_rowid();
+                    titles.insert(key, id);
+                    id
+                }
+            };
+            let code = item.episode_code();
+            insert_watch.execute(params![
+                title_id,
+                item.title,
+                item.date,
+                item.watched_at(clock),
+                item.episode,
+                code.map(|(season, _)| season),
+                code.map(|(_, episode)| episode),
+                item.rating,
+                item.plays,
+                item.source_url,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(titles.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Timezone;
+    use crate::metadata::{MediaIds, MetadataResult};
+
+    fn item(title: &str, date: &str, episode: Option<&str>) -> ProcessedItem {
+        let media_type = if episode.is_some() { MediaType::Tv } else { MediaType::Movie };
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids: MediaIds { tmdb: Some(if episode.is_some() { "67070" } else { "329865" }.to_string()), ..Default::default() },
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                score: Some(0.9),
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            rating: Some(8),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_watches_point_at_shared_titles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.sqlite");
+        let items = vec![
+            item("Arrival", "2024-01-05", None),
+            item("Fleabag", "2024-03-02", Some("S01E01")),
+            item("Fleabag", "2024-03-03", Some("S01E02")),
+        ];
+        let clock = WatchedAtConfig { timezone: Timezone::Utc, ..Default::default() };
+        assert_eq!(write(&path, &items, &clock).unwrap(), 2);
+
+        let conn = Connection::open(&path).unwrap();
+        let (episodes, last): (i64, String) = conn
+            .query_row(
+                "SELECT COUNT(*), MAX(watched_at) FROM history WHERE title = 'Fleabag' AND season = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(episodes, 2);
+        assert_eq!(last, "2024-03-03T00:00:00Z");
+        let year: i64 = conn.query_row("SELECT year FROM titles WHERE title = 'Arrival'", [], |row| row.get(0)).unwrap();
+        assert_eq!(year, 2016);
+    }
+}
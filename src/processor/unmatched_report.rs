@@ -0,0 +1,234 @@
+use crate::{
+    config::{CsvDialect, BOM},
+    error::AppError,
+    metadata::{MediaType, UnmatchedItem},
+    models::WatchHistoryItem,
+};
+use csv::Writer;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// One scraped watch, kept until the run knows whether its title matched
+pub struct Watch {
+    pub title: String,
+    pub media_type: MediaType,
+    pub date: String,
+    pub episode: Option<String>,
+}
+
+impl From<&WatchHistoryItem> for Watch {
+    fn from(item: &WatchHistoryItem) -> Self {
+        Self {
+            title: item.title.clone(),
+            media_type: item.media_type,
+            date: item.date.clone(),
+            episode: item.episode.clone(),
+        }
+    }
+}
+
+/// `export.csv` becomes `export.unmatched.csv` in the same directory
+pub fn companion_path(export: &Path) -> PathBuf {
+    let stem = export.file_stem().unwrap_or_default().to_string_lossy();
+    export.with_file_name(format!("{}.unmatched.csv", stem))
+}
+
+/// Lists every watch of a title no provider matched, with the searches tried for it, in the
+/// export's CSV dialect. Simkl's importer can match these rows by title, and the queries show
+/// what an override needs. Returns the rows written.
+pub fn write_companion(
+    path: &Path,
+    dialect: &CsvDialect,
+    watches: &[Watch],
+    unmatched: &[UnmatchedItem],
+) -> Result<usize, AppError> {
+    let queries: HashMap<(&str, MediaType), String> = unmatched
+        .iter()
+        .map(|item| ((item.title.as_str(), item.media_type), describe_attempts(item)))
+        .collect();
+    let mut file = File::create(path)?;
+    if dialect.bom {
+        file.write_all(BOM)?;
+    }
+    let mut wtr = dialect.writer().from_writer(file);
+    wtr.write_record(["Title", "Type", "Episode", "WatchedDate", "Queries"])?;
+    let mut rows = 0;
+    for watch in watches {
+        let Some(queries) = queries.get(&(watch.title.as_str(), watch.media_type)) else {
+            continue;
+        };
+        wtr.write_record([
+            watch.title.as_str(),
+            type_name(watch.media_type),
+            watch.episode.as_deref().unwrap_or_default(),
+            watch.date.as_str(),
+            queries,
+        ])?;
+        rows += 1;
+    }
+    wtr.flush()?;
+    Ok(rows)
+}
+
+fn type_name(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Movie => "movie",
+        MediaType::Tv => "tv",
+    }
+}
+
+/// Every search tried for an item, e.g. `TMDB: 'Dune' (2021) -> 0 results`
+fn describe_attempts(item: &UnmatchedItem) -> String {
+    item.attempts
+        .iter()
+        .map(|a| {
+            let mut query = format!("{}: '{}'", a.provider, a.query);
+            if let Some(year) = a.year {
+                query.push_str(&format!(" ({})", year));
+            }
+            match &a.error {
+                Some(error) => query.push_str(&format!(" failed: {}", error)),
+                None => query.push_str(&format!(" -> {} results", a.results)),
+            }
+            query
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Writes unmatched items as CSV when `path` ends in `.csv`, as pretty JSON otherwise
+pub fn write(path: &Path, items: &[UnmatchedItem]) -> Result<(), AppError> {
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    if is_csv {
+        write_csv(File::create(path)?, items)
+    } else {
+        serde_json::to_writer_pretty(File::create(path)?, items)?;
+        Ok(())
+    }
+}
+
+fn write_csv<W: std::io::Write>(writer: W, items: &[UnmatchedItem]) -> Result<(), AppError> {
+    let mut wtr = Writer::from_writer(writer);
+    wtr.write_record(["Title", "Type", "Year", "Queries", "Candidates"])?;
+
+    for item in items {
+        let queries = describe_attempts(item);
+        let candidates = item
+            .candidates
+            .iter()
+            .map(|c| {
+                format!(
+                    "{}: '{}' ({}) score {:.2}",
+                    c.provider,
+                    c.title,
+                    c.year.as_deref().unwrap_or("?"),
+                    c.score.unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        wtr.write_record([
+            item.title.as_str(),
+            type_name(item.media_type),
+            item.year.as_deref().unwrap_or_default(),
+            &queries,
+            &candidates,
+        ])?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::MediaIds;
+    use crate::metadata::unmatched::{RejectedCandidate, SearchAttempt};
+
+    #[test]
+    fn test_csv_lists_queries_and_candidates() {
+        let items = vec![UnmatchedItem {
+            title: "Obscure Film".to_string(),
+            media_type: MediaType::Movie,
+            year: Some("2019".to_string()),
+            attempts: vec![
+                SearchAttempt {
+                    provider: "TMDB".to_string(),
+                    query: "Obscure Film".to_string(),
+                    year: Some(2019),
+                    results: 2,
+                    error: None,
+                },
+                SearchAttempt {
+                    provider: "Simkl".to_string(),
+                    query: "Obscure Film".to_string(),
+                    year: Some(2019),
+                    results: 0,
+                    error: Some("timeout".to_string()),
+                },
+            ],
+            candidates: vec![RejectedCandidate {
+                provider: "TMDB".to_string(),
+                title: "Obscure Films".to_string(),
+                year: Some("2018".to_string()),
+                ids: MediaIds::default(),
+                score: Some(0.8),
+            }],
+        }];
+
+        let mut out = Vec::new();
+        write_csv(&mut out, &items).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        assert!(csv.starts_with("Title,Type,Year,Queries,Candidates\n"));
+        assert!(csv.contains("TMDB: 'Obscure Film' (2019) -> 2 results; Simkl: 'Obscure Film' (2019) failed: timeout"));
+        assert!(csv.contains("TMDB: 'Obscure Films' (2018) score 0.80"));
+    }
+
+    #[test]
+    fn test_companion_lists_each_watch_of_unmatched_titles() {
+        let dir = tempfile::tempdir().unwrap();
+        let export = dir.path().join("export.csv");
+        let path = companion_path(&export);
+        assert_eq!(path, dir.path().join("export.unmatched.csv"));
+
+        let watch = |title: &str, media_type, date: &str, episode: Option<&str>| Watch {
+            title: title.to_string(),
+            media_type,
+            date: date.to_string(),
+            episode: episode.map(String::from),
+        };
+        let watches = vec![
+            watch("Obscure Show", MediaType::Tv, "2024-03-01", Some("S01E02")),
+            watch("Arrival", MediaType::Movie, "2024-03-02", None),
+            watch("Obscure Show", MediaType::Tv, "2024-03-03", Some("S01E03")),
+        ];
+        let unmatched = vec![UnmatchedItem {
+            title: "Obscure Show".to_string(),
+            media_type: MediaType::Tv,
+            year: None,
+            attempts: vec![SearchAttempt {
+                provider: "Simkl".to_string(),
+                query: "Obscure Show".to_string(),
+                year: None,
+                results: 0,
+                error: None,
+            }],
+            candidates: Vec::new(),
+        }];
+
+        assert_eq!(write_companion(&path, &CsvDialect::default(), &watches, &unmatched).unwrap(), 2);
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "Title,Type,Episode,WatchedDate,Queries");
+        assert_eq!(lines[1], "Obscure Show,tv,S01E02,2024-03-01,Simkl: 'Obscure Show' -> 0 results");
+        assert!(lines[2].contains("S01E03,2024-03-03"));
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, path::Path};
+
+use crate::error::AppError;
+
+/// The newest watch a run exported, so `--incremental` can skip everything before it
+#[derive(Debug, Serialize, Deserialize)]
+struct Watermark {
+    newest_watched_at: DateTime<Local>,
+    saved_at: DateTime<Local>,
+}
+
+/// The saved watermark, or None before the first run
+pub fn load(path: &Path) -> Result<Option<DateTime<Local>>, AppError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let watermark: Watermark = serde_json::from_reader(File::open(path)?)?;
+    Ok(Some(watermark.newest_watched_at))
+}
+
+/// Records `newest` unless the saved watermark is already later, which happens when an
+/// incremental run finds nothing new
+pub fn save(path: &Path, newest: DateTime<Local>) -> Result<(), AppError> {
+    if load(path)?.is_some_and(|saved| saved >= newest) {
+        return Ok(());
+    }
+    let watermark = Watermark { newest_watched_at: newest, saved_at: Local::now() };
+    serde_json::to_writer_pretty(File::create(path)?, &watermark)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_watermark_only_moves_forward() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watermark.json");
+        assert!(load(&path).unwrap().is_none());
+
+        let march = Local.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let june = Local.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        save(&path, june).unwrap();
+        save(&path, march).unwrap();
+        assert_eq!(load(&path).unwrap(), Some(june));
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use rust_xlsxwriter::{ExcelDateTime, Format, Workbook, Worksheet};
+
+use crate::{error::AppError, models::MediaType, processor::history_processor::ProcessedItem};
+
+const COLUMNS: [&str; 12] = [
+    "Title", "Year", "Watched", "simkl_id", "TMDB", "IMDB_ID", "TVDB_ID", "MAL_ID", "AniList_ID", "AniDB_ID", "Score",
+    "Rating",
+];
+
+/// Writes `items` to an Excel workbook with a Movies and a Shows sheet. Watch dates are
+/// real date cells, so they sort and filter as dates in a spreadsheet. With `plays`, a Plays
+/// column counts the watches each row stands for.
+pub fn write(path: &Path, items: &[ProcessedItem], plays: bool) -> Result<(), AppError> {
+    let mut workbook = Workbook::new();
+    let (movies, shows): (Vec<&ProcessedItem>, Vec<&ProcessedItem>) =
+        items.iter().partition(|item| item.media_type == MediaType::Movie);
+
+    write_sheet(workbook.add_worksheet().set_name("Movies")?, &movies, false, plays)?;
+    write_sheet(workbook.add_worksheet().set_name("Shows")?, &shows, true, plays)?;
+    workbook.save(path)?;
+    Ok(())
+}
+
+fn write_sheet(sheet: &mut Worksheet, items: &[&ProcessedItem], episodes: bool, plays: bool) -> Result<(), AppError> {
+    let bold = Format::new().set_bold();
+    let date = Format::new().set_num_format("yyyy-mm-dd");
+
+    let mut header = COLUMNS.to_vec();
+    if episodes {
+        header.insert(1, "Episode");
+    }
+    if plays {
+        header.push("Plays");
+    }
+    for (col, name) in header.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *name, &bold)?;
+    }
+
+    for (index, item) in items.iter().enumerate() {
+        let row = index as u32 + 1;
+        let mut col = 0;
+        sheet.write_string(row, col, &item.title)?;
+        if episodes {
+            col += 1;
+            sheet.write_string(row, col, item.episode.as_deref().unwrap_or_default())?;
+        }
+
+        col += 1;
+        let year = item.metadata.year.as_deref().unwrap_or_default();
+        match crate::matching::parse_year(year) {
+            Some(number) => sheet.write_number(row, col, number)?,
+            None => sheet.write_string(row, col, year)?,
+        };
+
+        col += 1;
+        match ExcelDateTime::parse_from_str(&item.date) {
+            Ok(watched) => sheet.write_datetime_with_format(row, col, &watched, &date)?,
+            Err(_) => sheet.write_string(row, col, &item.date)?,
+        };
+
+        let ids = &item.metadata.ids;
+        for id in [&ids.simkl, &ids.tmdb, &ids.imdb, &ids.tvdb, &ids.mal, &ids.anilist, &ids.anidb] {
+            col += 1;
+            sheet.write_string(row, col, id.as_deref().unwrap_or_default())?;
+        }
+
+        col += 1;
+        if let Some(score) = item.metadata.score {
+            sheet.write_number(row, col, score)?;
+        }
+
+        col += 1;
+        if let Some(rating) = item.rating {
+            sheet.write_number(row, col, rating)?;
+        }
+
+        if plays {
+            col += 1;
+            sheet.write_number(row, col, item.plays)?;
+        }
+    }
+
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{MediaIds, MetadataResult};
+
+    fn item(title: &str, media_type: MediaType, episode: Option<&str>) -> ProcessedItem {
+        ProcessedItem {
+            title: title.to_string(),
+            date: "2024-03-01".to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids: MediaIds { tmdb: Some("67070".to_string()), ..Default::default() },
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                score: Some(0.9),
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_workbook_is_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.xlsx");
+        let items = vec![
+            item("Arrival", MediaType::Movie, None),
+            item("Fleabag", MediaType::Tv, Some("S01E01")),
+        ];
+        write(&path, &items, true).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"PK"));
+    }
+}
@@ -37,7 +37,6 @@ impl BrowserController {
 
     pub async fn shutdown(&mut self) -> Result<(), AppError> {
         if let Some(client) = self.client.take() {
-            let mut client = client;
             client.close().await.map_err(|e| AppError::BrowserError(e.to_string()))?;
         }
         Ok(())
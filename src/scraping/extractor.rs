@@ -1,12 +1,14 @@
 use fantoccini::{Client, Locator, elements::Element};
 use crate::error::AppError;
-use crate::scraping::models::HistoryItem;
+use crate::scraping::models::{HistoryItem, PrimeRating};
+use chrono::{DateTime, Local};
 use std::time::Duration;
 
 pub struct HistoryExtractor<'a> {
     client: &'a mut Client,
     max_attempts: usize,
     scroll_delay: Duration,
+    stop_before: Option<DateTime<Local>>,
 }
 
 impl<'a> HistoryExtractor<'a> {
@@ -15,9 +17,22 @@ impl<'a> HistoryExtractor<'a> {
             client,
             max_attempts: 100,
             scroll_delay: Duration::from_secs(2),
+            stop_before: None,
         }
     }
 
+    /// Limits how many times the page is scrolled to load older entries
+    pub fn with_max_scrolls(mut self, max_scrolls: usize) -> Self {
+        self.max_attempts = max_scrolls;
+        self
+    }
+
+    /// Stops loading older history once the page reaches items watched before `since`
+    pub fn with_stop_before(mut self, since: DateTime<Local>) -> Self {
+        self.stop_before = Some(since);
+        self
+    }
+
     pub async fn extract(&mut self) -> Result<Vec<HistoryItem>, AppError> {
         self.load_all_items().await?;
         self.parse_history().await
@@ -40,11 +55,28 @@ impl<'a> HistoryExtractor<'a> {
 
             // Check for new height with retry logic
             current_height = self.get_scroll_height().await?;
+
+            if let Some(since) = self.stop_before {
+                if self.oldest_loaded().await.is_some_and(|oldest| oldest < since) {
+                    log::info!("Reached items watched before {}; not loading older history", since.date_naive());
+                    break;
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Watch date of the last history item on the page; the list runs newest first
+    async fn oldest_loaded(&mut self) -> Option<DateTime<Local>> {
+        let mut items = self.client
+            .find_all(Locator::Css("div[data-automation-id='activity-history-items'] li"))
+            .await
+            .ok()?;
+        let text = self.extract_item_text(items.last_mut()?).await.ok()?;
+        HistoryItem::parse(&text).map(|item| item.watched_at)
+    }
+
     async fn scroll_to_bottom(&mut self) -> Result<(), AppError> {
         for attempts in 0..3 { // Retry up to 3 times
             match self.client
@@ -107,7 +139,9 @@ impl<'a> HistoryExtractor<'a> {
         for mut item in items {
             match self.extract_item_text(&mut item).await {
                 Ok(text) => {
-                    if let Some(parsed) = HistoryItem::parse(&text) {
+                    if let Some(mut parsed) = HistoryItem::parse(&text) {
+                        parsed.rating = Self::extract_item_rating(&mut item).await;
+                        parsed.source_url = Self::extract_item_link(&mut item).await;
                         history.push(parsed);
                     } else {
                         log::warn!("Failed to parse history item: {}", text);
@@ -124,6 +158,25 @@ impl<'a> HistoryExtractor<'a> {
         }
     }
 
+    /// The pressed thumbs button of a row, if any; rows without rating controls have none
+    async fn extract_item_rating(item: &mut Element) -> Option<PrimeRating> {
+        let buttons = item.find_all(Locator::Css("button[aria-pressed='true']")).await.ok()?;
+        for button in buttons {
+            if let Ok(Some(label)) = button.attr("aria-label").await {
+                if let Some(rating) = PrimeRating::from_label(&label) {
+                    return Some(rating);
+                }
+            }
+        }
+        None
+    }
+
+    /// The detail page the row's title links to, resolved to a full URL by the browser
+    async fn extract_item_link(item: &mut Element) -> Option<String> {
+        let link = item.find(Locator::Css("a[href*='/detail/'], a[href*='/dp/']")).await.ok()?;
+        link.prop("href").await.ok().flatten()
+    }
+
     async fn extract_item_text(&mut self, item: &mut Element) -> Result<String, AppError> {
         item.text()
             .await
@@ -0,0 +1,135 @@
+use fantoccini::{Client, Locator};
+use crate::error::AppError;
+
+/// Amazon pages that can appear between sign-in and the destination page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interstitial {
+    /// "Verify it's you" - a one-time code sent by email/SMS or an app approval
+    VerifyIdentity,
+    /// Two-step verification code from an authenticator app or SMS
+    TwoStepVerification,
+    /// Amazon requires a new password before continuing
+    PasswordReset,
+    /// "Enter the characters you see" puzzle
+    Captcha,
+    /// Prompt to add or confirm a phone number
+    AccountFixup,
+}
+
+impl Interstitial {
+    /// Classifies an interstitial from the page URL path
+    pub fn from_path(path: &str) -> Option<Self> {
+        let path = path.to_lowercase();
+        if path.contains("/ap/cvf") {
+            Some(Self::VerifyIdentity)
+        } else if path.contains("/ap/mfa") {
+            Some(Self::TwoStepVerification)
+        } else if path.contains("forgotpassword") || path.contains("reset-password") {
+            Some(Self::PasswordReset)
+        } else if path.contains("captcha") {
+            Some(Self::Captcha)
+        } else if path.contains("/ap/accountfixup") {
+            Some(Self::AccountFixup)
+        } else {
+            None
+        }
+    }
+
+    /// Page elements identifying an interstitial served under a generic sign-in URL
+    const MARKERS: &'static [(&'static str, Interstitial)] = &[
+        ("#cvf-page-content, .cvf-widget-input-code, #cvf-input-code", Self::VerifyIdentity),
+        ("#auth-mfa-otpcode, #auth-mfa-form", Self::TwoStepVerification),
+        ("#auth-password-reset-required, #ap_fpp_password", Self::PasswordReset),
+        ("#auth-captcha-image, #ap_captcha_img, #captchacharacters", Self::Captcha),
+        ("#ap-account-fixup-phone-skip-link", Self::AccountFixup),
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::VerifyIdentity => "Amazon wants to verify it's you",
+            Self::TwoStepVerification => "Two-step verification required",
+            Self::PasswordReset => "Amazon requires a password reset",
+            Self::Captcha => "Amazon is showing a CAPTCHA",
+            Self::AccountFixup => "Amazon is asking about your phone number",
+        }
+    }
+
+    pub fn instructions(&self) -> &'static [&'static str] {
+        match self {
+            Self::VerifyIdentity => &[
+                "Check your email or phone for the code Amazon just sent, or approve the sign-in in the Amazon app",
+                "Enter the code in the browser window and continue",
+            ],
+            Self::TwoStepVerification => &[
+                "Enter the code from your authenticator app or SMS in the browser window",
+                "Tick \"Don't require OTP on this browser\" to make saved sessions last longer",
+            ],
+            Self::PasswordReset => &[
+                "Follow the reset steps in the browser window (Amazon emails you a link or code)",
+                "If you store your password in the keyring, run `credentials set-amazon` afterwards",
+            ],
+            Self::Captcha => &[
+                "Type the characters shown in the browser window and submit",
+                "You may be asked for your password again afterwards",
+            ],
+            Self::AccountFixup => &[
+                "Add or confirm your phone number, or choose \"Not now\" in the browser window",
+            ],
+        }
+    }
+}
+
+/// Detects an interstitial on the current page, by URL first and then by page markers
+pub async fn detect(client: &Client) -> Result<Option<Interstitial>, AppError> {
+    let url = client
+        .current_url()
+        .await
+        .map_err(|e| AppError::BrowserError(e.to_string()))?;
+
+    if let Some(interstitial) = Interstitial::from_path(url.path()) {
+        return Ok(Some(interstitial));
+    }
+
+    for (selector, interstitial) in Interstitial::MARKERS {
+        if client.find(Locator::Css(selector)).await.is_ok() {
+            return Ok(Some(*interstitial));
+        }
+    }
+    Ok(None)
+}
+
+/// Prints guidance for each interstitial and blocks until the user has dealt with it.
+/// Returns whether any interstitial was shown.
+pub async fn wait_until_cleared(client: &Client) -> Result<bool, AppError> {
+    let mut shown = false;
+    while let Some(interstitial) = detect(client).await? {
+        shown = true;
+        println!();
+        println!("🛡️  {}", interstitial.title());
+        for (step, line) in interstitial.instructions().iter().enumerate() {
+            println!("{}. {}", step + 1, line);
+        }
+        println!("Press Enter here once the browser has moved past this page...");
+
+        let mut input = String::new();
+        std::io::stdin()
+            .read_line(&mut input)
+            .map_err(|e| AppError::AuthError(format!("Failed to read input: {}", e)))?;
+    }
+    Ok(shown)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interstitials_detected_from_path() {
+        assert_eq!(Interstitial::from_path("/ap/cvf/request"), Some(Interstitial::VerifyIdentity));
+        assert_eq!(Interstitial::from_path("/ap/mfa"), Some(Interstitial::TwoStepVerification));
+        assert_eq!(Interstitial::from_path("/ap/forgotpassword/reverification"), Some(Interstitial::PasswordReset));
+        assert_eq!(Interstitial::from_path("/errors/validateCaptcha"), Some(Interstitial::Captcha));
+        assert_eq!(Interstitial::from_path("/settings/watch-history"), None);
+        assert_eq!(Interstitial::from_path("/ap/signin"), None);
+    }
+}
@@ -1,5 +1,7 @@
 use fantoccini::{Client, Locator};
 use crate::error::AppError;
+use crate::scraping::interstitial;
+use crate::scraping::marketplace::Marketplace;
 use std::time::Duration;
 
 pub enum LoginMethod {
@@ -43,16 +45,17 @@ async fn click_element(
 pub async fn handle_login(
     client: &mut Client,
     method: LoginMethod,
+    marketplace: &Marketplace,
 ) -> Result<(), AppError> {
     match method {
-        LoginMethod::Manual => manual_login(client).await,
+        LoginMethod::Manual => manual_login(client, marketplace).await,
         LoginMethod::Automated { email, password } => {
-            automated_login(client, &email, &password).await
+            automated_login(client, &email, &password, marketplace).await
         }
     }
 }
 
-async fn manual_login(client: &mut Client) -> Result<(), AppError> {
+async fn manual_login(client: &mut Client, marketplace: &Marketplace) -> Result<(), AppError> {
     println!("🔐 MANUAL LOGIN REQUIRED");
     println!("========================");
     println!("Please complete the following steps in the browser window:");
@@ -62,9 +65,9 @@ async fn manual_login(client: &mut Client) -> Result<(), AppError> {
     println!();
     println!("The browser window should open automatically. Please log in and press Enter when ready...");
 
-    // Navigate to global Prime Video domain
+    // Navigate to the marketplace's Prime Video domain
     client
-        .goto("https://www.primevideo.com/settings/watch-history")
+        .goto(&marketplace.watch_history_url())
         .await
         .map_err(|e| AppError::BrowserError(e.to_string()))?;
 
@@ -82,6 +85,14 @@ async fn manual_login(client: &mut Client) -> Result<(), AppError> {
         }
     }
 
+    // Verification pages can appear right after sign-in; guide the user through them
+    if interstitial::wait_until_cleared(client).await? {
+        client
+            .goto(&marketplace.watch_history_url())
+            .await
+            .map_err(|e| AppError::BrowserError(e.to_string()))?;
+    }
+
     // Do a final URL check
     let current_url = client
         .current_url()
@@ -121,21 +132,11 @@ async fn automated_login(
     client: &mut Client,
     email: &str,
     password: &str,
+    marketplace: &Marketplace,
 ) -> Result<(), AppError> {
-    // Use regional Amazon site based on TLD in email
-    let domain = if email.contains(".co.uk") {
-        "amazon.co.uk"
-    } else if email.contains(".de") {
-        "amazon.de"
-    } else if email.contains(".it") {
-        "amazon.it"
-    } else {
-        "amazon.com"
-    };
-    let login_url = format!("https://www.{}/ap/signin", domain);
-
+    // Sign in on the configured marketplace (amazon.region in config)
     client
-        .goto(&login_url)
+        .goto(&marketplace.signin_url())
         .await
         .map_err(|e| AppError::BrowserError(e.to_string()))?;
 
@@ -145,15 +146,14 @@ async fn automated_login(
     fill_form_field(client, "input[name='password'], input[name='ap_password']", password).await?;
     click_element(client, "#signInSubmit").await?;
 
-    // Handle 2FA if present
-    if let Ok(_element) = client
-        .find(Locator::Css("#auth-mfa-otpcode, .cvf-widget-input-code"))
+    // 2FA, "verify it's you" and similar pages need the user to finish them in the browser
+    interstitial::wait_until_cleared(client).await?;
+
+    // Sign-in lands on the Amazon storefront; continue to the Prime Video history page
+    client
+        .goto(&marketplace.watch_history_url())
         .await
-    {
-        return Err(AppError::AuthError(
-            "2FA detected - manual login required".into(),
-        ));
-    }
+        .map_err(|e| AppError::BrowserError(e.to_string()))?;
 
     // Verify login success
     if !is_logged_in(client).await? {
@@ -163,18 +163,18 @@ async fn automated_login(
     Ok(())
 }
 
-async fn is_logged_in(client: &mut Client) -> Result<bool, AppError> {
+pub(super) async fn is_logged_in(client: &mut Client) -> Result<bool, AppError> {
     let current_url = client
         .current_url()
         .await
         .map_err(|e| AppError::BrowserError(e.to_string()))?;
 
-    let url_str = current_url.to_string();
-
-    // Check URL patterns first (quick check)
-    let url_check = url_str.contains("watch-history") &&
-        !url_str.contains("signin") &&
-        !url_str.contains("auth");
+    // Check URL patterns first (quick check); a sign-in redirect carries the history URL
+    // in its query, so only the path counts
+    let path = current_url.path();
+    let url_check = path.contains("watch-history") &&
+        !path.contains("signin") &&
+        !path.contains("auth");
 
     if !url_check {
         return Ok(false);
@@ -201,12 +201,34 @@ async fn is_logged_in(client: &mut Client) -> Result<bool, AppError> {
 
     for selector in page_content_checks {
         // If we find login form elements, user is not logged in
-        if let Ok(_) = client.find(Locator::Css(selector)).await {
-            if selector.contains("email") || selector.contains("password") {
-                return Ok(false); // Login form detected
-            }
+        if client.find(Locator::Css(selector)).await.is_ok()
+            && (selector.contains("email") || selector.contains("password"))
+        {
+            return Ok(false); // Login form detected
         }
     }
 
     Ok(true)
-}
\ No newline at end of file
+}
+
+/// Reads the name of the active account or profile from the page header, if present
+pub async fn active_profile(client: &mut Client) -> Option<String> {
+    let selectors = [
+        "[data-testid='pv-nav-profile-name']",
+        "[data-automation-id='profile-name']",
+        ".pv-profile-name",
+        "#nav-link-accountList-nav-line-1",
+    ];
+
+    for selector in selectors {
+        if let Ok(element) = client.find(Locator::Css(selector)).await {
+            if let Ok(text) = element.text().await {
+                let name = text.trim().trim_start_matches("Hello,").trim();
+                if !name.is_empty() {
+                    return Some(name.to_string());
+                }
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,162 @@
+use crate::error::AppError;
+
+const HELP_URL: &str = "https://github.com/easly1989/primevideo-to-simkl-csv-exporter/issues";
+
+/// How well a marketplace feature is known to work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Support {
+    /// Verified against the live site
+    Supported,
+    /// Expected to work but never verified
+    Untested,
+    /// Known not to work yet
+    Unsupported,
+}
+
+/// Capabilities of one Amazon marketplace, keyed by its region (domain suffix)
+#[derive(Debug)]
+pub struct Marketplace {
+    pub region: &'static str,
+    pub amazon_domain: &'static str,
+    pub prime_domain: &'static str,
+    pub watch_history: Support,
+    pub watchlist: Support,
+    /// Whether the history page uses date formats the parser understands
+    pub date_formats: Support,
+    pub profile_picker: Support,
+}
+
+use Support::{Supported, Unsupported, Untested};
+
+const fn marketplace(
+    region: &'static str,
+    amazon_domain: &'static str,
+    watch_history: Support,
+    watchlist: Support,
+    date_formats: Support,
+    profile_picker: Support,
+) -> Marketplace {
+    Marketplace {
+        region,
+        amazon_domain,
+        prime_domain: "primevideo.com",
+        watch_history,
+        watchlist,
+        date_formats,
+        profile_picker,
+    }
+}
+
+pub const MARKETPLACES: &[Marketplace] = &[
+    marketplace("com", "amazon.com", Supported, Supported, Supported, Supported),
+    marketplace("ca", "amazon.ca", Supported, Untested, Supported, Untested),
+    marketplace("co.uk", "amazon.co.uk", Supported, Untested, Untested, Untested),
+    marketplace("com.au", "amazon.com.au", Untested, Untested, Untested, Untested),
+    marketplace("in", "amazon.in", Untested, Untested, Untested, Untested),
+    marketplace("de", "amazon.de", Supported, Untested, Untested, Untested),
+    marketplace("it", "amazon.it", Supported, Untested, Untested, Untested),
+    marketplace("fr", "amazon.fr", Untested, Untested, Untested, Untested),
+    marketplace("es", "amazon.es", Untested, Untested, Untested, Untested),
+    marketplace("nl", "amazon.nl", Untested, Untested, Untested, Untested),
+    marketplace("com.br", "amazon.com.br", Untested, Untested, Untested, Untested),
+    marketplace("com.mx", "amazon.com.mx", Untested, Untested, Untested, Untested),
+    marketplace("co.jp", "amazon.co.jp", Untested, Unsupported, Unsupported, Untested),
+];
+
+impl Marketplace {
+    pub fn for_region(region: &str) -> Result<&'static Marketplace, AppError> {
+        let region = region.trim().trim_start_matches('.').to_lowercase();
+        MARKETPLACES.iter().find(|m| m.region == region).ok_or_else(|| {
+            let known: Vec<&str> = MARKETPLACES.iter().map(|m| m.region).collect();
+            AppError::ConfigError(config::ConfigError::Message(format!(
+                "Amazon region '{}' is not yet supported (known regions: {}). \
+                 Please open an issue at {} with your marketplace and a sample of your watch-history page so it can be added.",
+                region,
+                known.join(", "),
+                HELP_URL
+            )))
+        })
+    }
+
+    pub fn watch_history_url(&self) -> String {
+        format!("https://www.{}/settings/watch-history", self.prime_domain)
+    }
+
+    /// The watchlist filtered to `kind`, `movie` or `tv`
+    pub fn watchlist_url(&self, kind: &str) -> String {
+        format!("https://www.{}/mystuff/watchlist/{}", self.prime_domain, kind)
+    }
+
+    pub fn signin_url(&self) -> String {
+        format!("https://www.{}/ap/signin", self.amazon_domain)
+    }
+
+    fn capabilities(&self) -> [(&'static str, Support); 4] {
+        [
+            ("watch history", self.watch_history),
+            ("watchlist", self.watchlist),
+            ("date formats", self.date_formats),
+            ("profile picker", self.profile_picker),
+        ]
+    }
+
+    /// Fails with actionable guidance when features required for scraping are known not to work
+    pub fn check_scraping_support(&self) -> Result<(), AppError> {
+        tracing::info!("Using Amazon marketplace {} ({})", self.amazon_domain, self.prime_domain);
+        for (name, support) in self.capabilities() {
+            match support {
+                Supported => tracing::debug!("amazon.{}: {} supported", self.region, name),
+                Untested => tracing::warn!(
+                    "amazon.{}: {} has not been tested yet - if something breaks please report it at {}",
+                    self.region, name, HELP_URL
+                ),
+                Unsupported => tracing::debug!("amazon.{}: {} unsupported", self.region, name),
+            }
+        }
+
+        let blocking: Vec<&str> = [("watch history", self.watch_history), ("date formats", self.date_formats)]
+            .into_iter()
+            .filter(|(_, support)| *support == Unsupported)
+            .map(|(name, _)| name)
+            .collect();
+
+        if blocking.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::ConfigError(config::ConfigError::Message(format!(
+                "Scraping amazon.{} is not yet supported ({} not handled). \
+                 You can help by opening an issue at {} with a few lines of your watch-history page.",
+                self.region,
+                blocking.join(", "),
+                HELP_URL
+            ))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup_normalizes_region() {
+        let marketplace = Marketplace::for_region(".CO.UK").unwrap();
+        assert_eq!(marketplace.amazon_domain, "amazon.co.uk");
+        assert_eq!(marketplace.signin_url(), "https://www.amazon.co.uk/ap/signin");
+        assert_eq!(marketplace.watch_history_url(), "https://www.primevideo.com/settings/watch-history");
+        assert_eq!(marketplace.watchlist_url("tv"), "https://www.primevideo.com/mystuff/watchlist/tv");
+    }
+
+    #[test]
+    fn test_unknown_region_explains_how_to_help() {
+        let err = Marketplace::for_region("se").unwrap_err().to_string();
+        assert!(err.contains("not yet supported"));
+        assert!(err.contains(HELP_URL));
+    }
+
+    #[test]
+    fn test_unsupported_date_formats_block_scraping() {
+        assert!(Marketplace::for_region("com").unwrap().check_scraping_support().is_ok());
+        assert!(Marketplace::for_region("co.jp").unwrap().check_scraping_support().is_err());
+    }
+}
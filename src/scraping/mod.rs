@@ -1,9 +1,17 @@
 pub mod models;
+pub mod netflix;
 mod login;
+mod interstitial;
 mod extractor;
+mod watchlist;
 mod browser;
+pub mod session;
+pub mod marketplace;
 use login::{handle_login, LoginMethod};
+use session::SessionStore;
+use marketplace::Marketplace;
 use extractor::HistoryExtractor;
+use watchlist::WatchlistExtractor;
 use browser::BrowserController;
 
 use fantoccini::Client;
@@ -11,14 +19,34 @@ use crate::error::AppError;
 use crate::config::AmazonConfig;
 use std::time::Duration;
 
+/// Outcome of validating a saved session
+#[derive(Debug, Default)]
+pub struct SessionStatus {
+    /// A session file exists
+    pub saved: bool,
+    /// The saved cookies reach the watch-history page without a login prompt
+    pub valid: bool,
+    /// Page the browser ended up on
+    pub url: Option<String>,
+    /// Name of the active account/profile, when it could be read from the page
+    pub profile: Option<String>,
+}
+
 pub struct Scraper {
     browser: BrowserController,
     client: Option<Client>,
     config: AmazonConfig,
+    marketplace: &'static Marketplace,
+    max_scrolls: Option<usize>,
+    stop_before: Option<chrono::DateTime<chrono::Local>>,
+    session: Option<SessionStore>,
 }
 
 impl Scraper {
     pub async fn new(config: AmazonConfig, headless: bool) -> Result<Self, AppError> {
+        let marketplace = Marketplace::for_region(&config.region)?;
+        marketplace.check_scraping_support()?;
+
         let mut browser = BrowserController::new(headless, 30);
         browser.start().await?;
         let client = browser.client().cloned();
@@ -27,24 +55,119 @@ impl Scraper {
             browser,
             client,
             config,
+            marketplace,
+            max_scrolls: None,
+            stop_before: None,
+            session: None,
         })
     }
 
+    /// Persists the login session between runs using the given encrypted store
+    pub fn use_session_store(&mut self, store: SessionStore) {
+        self.session = Some(store);
+    }
+
+    /// Stops loading older history after the given number of page scrolls
+    pub fn limit_scrolling(&mut self, max_scrolls: usize) {
+        self.max_scrolls = Some(max_scrolls);
+    }
+
+    /// Stops loading history older than `since`, for incremental runs
+    pub fn stop_before(&mut self, since: chrono::DateTime<chrono::Local>) {
+        self.stop_before = Some(since);
+    }
+
     pub async fn login(&mut self, attempt_auto_login: bool) -> Result<(), AppError> {
+        if self.restore_session().await {
+            println!("✅ Restored saved Prime Video session - skipping login");
+            return Ok(());
+        }
+
         let method = if attempt_auto_login {
-            LoginMethod::Automated {
-                email: self.config.email.clone(),
-                password: self.config.password.clone(),
-            }
+            let (email, password) = crate::credentials::resolve_amazon_credentials(&self.config)?;
+            LoginMethod::Automated { email, password }
         } else {
             LoginMethod::Manual
         };
 
         if let Some(client) = &mut self.client {
-            handle_login(client, method).await?;
-            Ok(())
+            handle_login(client, method, self.marketplace).await?;
         } else {
-            Err(AppError::BrowserError("Browser client not initialized".into()))
+            return Err(AppError::BrowserError("Browser client not initialized".into()));
+        }
+
+        self.save_session().await;
+        Ok(())
+    }
+
+    /// Loads saved cookies and checks whether they still grant access to the watch history
+    async fn restore_session(&mut self) -> bool {
+        let (Some(store), Some(client)) = (&self.session, &mut self.client) else {
+            return false;
+        };
+        if !store.exists() {
+            return false;
+        }
+
+        // Cookies can only be added for the domain currently loaded
+        let home = format!("https://www.{}", self.marketplace.prime_domain);
+        if client.goto(&home).await.is_err() {
+            return false;
+        }
+        match store.restore(client).await {
+            Ok(count) => tracing::info!("Restored {} session cookies", count),
+            Err(e) => {
+                tracing::warn!("Discarding unreadable saved session: {}", e);
+                let _ = store.clear();
+                return false;
+            }
+        }
+
+        let reached = self.navigate_to_history().await.is_ok();
+        let logged_in = match &mut self.client {
+            Some(client) if reached => login::is_logged_in(client).await.unwrap_or(false),
+            _ => false,
+        };
+        if !logged_in {
+            tracing::info!("Saved session has expired");
+        }
+        logged_in
+    }
+
+    /// Reports whether the saved session still grants access to the watch history
+    pub async fn check_session(&mut self) -> Result<SessionStatus, AppError> {
+        match &self.session {
+            Some(store) if store.exists() => {}
+            _ => return Ok(SessionStatus::default()),
+        }
+
+        let valid = self.restore_session().await;
+        let client = self.client.as_mut()
+            .ok_or_else(|| AppError::BrowserError("Browser client not initialized".into()))?;
+        let url = client.current_url().await
+            .map_err(|e| AppError::BrowserError(e.to_string()))?
+            .to_string();
+        let profile = if valid { login::active_profile(client).await } else { None };
+
+        Ok(SessionStatus { saved: true, valid, url: Some(url), profile })
+    }
+
+    /// Name of the signed-in account or profile, when the current page shows it
+    pub async fn active_profile(&mut self) -> Option<String> {
+        login::active_profile(self.client.as_mut()?).await
+    }
+
+    pub async fn shutdown(&mut self) -> Result<(), AppError> {
+        self.client = None;
+        self.browser.shutdown().await
+    }
+
+    async fn save_session(&self) {
+        if let (Some(store), Some(client)) = (&self.session, &self.client) {
+            match store.save(client).await {
+                Ok(count) => tracing::info!("Saved {} session cookies (encrypted)", count),
+                Err(e) => tracing::warn!("Failed to save session: {}", e),
+            }
         }
     }
 
@@ -72,10 +195,41 @@ impl Scraper {
         ))
     }
 
+    /// Reads the movies and shows on the account's watchlist; the session must already be
+    /// logged in
+    pub async fn scrape_watchlist(&mut self) -> Result<Vec<models::WatchlistItem>, AppError> {
+        if self.marketplace.watchlist == marketplace::Support::Unsupported {
+            return Err(AppError::ConfigError(config::ConfigError::Message(format!(
+                "Reading the watchlist is not supported on amazon.{} yet",
+                self.marketplace.region
+            ))));
+        }
+        let Some(client) = &mut self.client else {
+            return Err(AppError::BrowserError("Browser client not initialized".into()));
+        };
+        let mut items = Vec::new();
+        for (kind, show) in [("movie", false), ("tv", true)] {
+            client
+                .goto(&self.marketplace.watchlist_url(kind))
+                .await
+                .map_err(|e| AppError::BrowserError(e.to_string()))?;
+            let found = WatchlistExtractor::new(client).extract(show).await?;
+            tracing::info!("Found {} {} titles on the watchlist", found.len(), kind);
+            items.extend(found);
+        }
+        Ok(items)
+    }
+
     async fn try_scrape(&mut self) -> Result<Vec<models::HistoryItem>, AppError> {
         self.navigate_to_history().await?;
         if let Some(client) = &mut self.client {
             let mut extractor = HistoryExtractor::new(client);
+            if let Some(max_scrolls) = self.max_scrolls {
+                extractor = extractor.with_max_scrolls(max_scrolls);
+            }
+            if let Some(since) = self.stop_before {
+                extractor = extractor.with_stop_before(since);
+            }
             extractor.extract().await
         } else {
             Err(AppError::BrowserError("Browser client not initialized".into()))
@@ -85,7 +239,7 @@ impl Scraper {
     async fn navigate_to_history(&mut self) -> Result<(), AppError> {
         if let Some(client) = &mut self.client {
             client
-                .goto("https://www.primevideo.com/settings/watch-history")
+                .goto(&self.marketplace.watch_history_url())
                 .await
                 .map_err(|e| AppError::BrowserError(e.to_string()))?;
 
@@ -93,7 +247,8 @@ impl Scraper {
             let current_url = client.current_url().await
                 .map_err(|e| AppError::BrowserError(e.to_string()))?;
 
-            if !current_url.as_str().contains("watch-history") {
+            // A sign-in redirect carries the history URL in its query, so only the path counts
+            if !current_url.path().contains("watch-history") {
                 return Err(AppError::BrowserError("Failed to navigate to history page".into()));
             }
 
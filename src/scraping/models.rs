@@ -13,12 +13,53 @@ pub enum MediaType {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HistoryItem {
+    #[serde(default)]
     pub raw_text: String,
     pub title: String,
     pub original_title: Option<String>,
     pub media_type: MediaType,
     pub watched_at: DateTime<Local>,
+    #[serde(default)]
     pub is_original_language: bool,
+    /// The thumbs up or down given on Prime Video, when the history row shows one
+    #[serde(default)]
+    pub rating: Option<PrimeRating>,
+    /// Detail page the history row links to
+    #[serde(default)]
+    pub source_url: Option<String>,
+}
+
+/// A title on the Prime Video watchlist; it has not been watched, so it has no date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchlistItem {
+    pub title: String,
+    /// A show is listed by season; the season is kept when the card names it
+    pub media_type: MediaType,
+    /// Detail page the watchlist card links to
+    #[serde(default)]
+    pub source_url: Option<String>,
+}
+
+/// Prime Video only rates with a thumbs up or down
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrimeRating {
+    Like,
+    Dislike,
+}
+
+impl PrimeRating {
+    /// Reads the accessible label of a pressed rating button, e.g. "Dislike" or "Thumbs up"
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.to_lowercase();
+        if label.contains("dislike") || label.contains("thumbs down") {
+            Some(Self::Dislike)
+        } else if label.contains("like") || label.contains("thumbs up") {
+            Some(Self::Like)
+        } else {
+            None
+        }
+    }
 }
 
 impl HistoryItem {
@@ -34,6 +75,8 @@ impl HistoryItem {
             media_type,
             watched_at,
             is_original_language: original_title.is_none(),
+            rating: None,
+            source_url: None,
         })
     }
 
@@ -55,7 +98,7 @@ impl HistoryItem {
                         .or_else(|_| NaiveDate::parse_from_str(&caps[1], "%m/%d/%Y"))
                         .or_else(|_| NaiveDate::parse_from_str(&caps[1], "%Y-%m-%d"))
                     {
-                        return Some(naive_date.and_hms_opt(0, 0, 0).unwrap().and_local_timezone(Local).unwrap().into());
+                        return Some(naive_date.and_hms_opt(0, 0, 0).unwrap().and_local_timezone(Local).unwrap());
                     }
                 }
             }
@@ -80,25 +123,70 @@ impl HistoryItem {
     fn determine_media_type(text: &str) -> MediaType {
         use regex::Regex;
 
-        // Check for TV show patterns
-        let tv_patterns = [
-            r"(?i)season\s+(\d+)\s+episode\s+(\d+)",
-            r"(?i)s(\d+)e(\d+)",
-            r"(?i)episode\s+(\d+)",
-        ];
-
-        for pattern in tv_patterns {
-            if let Ok(re) = Regex::new(pattern) {
-                if let Some(caps) = re.captures(text) {
-                    return MediaType::TvShow {
-                        season: caps.get(1).and_then(|m| m.as_str().parse().ok()),
-                        episode: caps.get(2).and_then(|m| m.as_str().parse().ok()),
-                        episode_title: None,
-                    };
-                }
+        // Regular episodes: "Season 1 Episode 2", "S01E02"
+        let numbered = [r"(?i)season\s+(\d+)\s+episode\s+(\d+)", r"(?i)\bs(\d+)e(\d+)"];
+        for pattern in numbered {
+            if let Some(caps) = Regex::new(pattern).ok().and_then(|re| re.captures(text)) {
+                let number = |i: usize| caps.get(i).and_then(|m| m.as_str().parse().ok());
+                return Self::episode(number(1), number(2));
             }
         }
 
+        // Specials and OVAs sit in season 0 on TMDB and TVDB: "Specials Episode 3", "OVA 2", "OVA"
+        let special = r"(?i)\b(specials|special episode|ova|oad)\b(?:\s*[:#-]?\s*(?:episode|ep\.?)?\s*(\d+))?";
+        if let Some(caps) = Regex::new(special).ok().and_then(|re| re.captures(text)) {
+            return MediaType::TvShow {
+                season: Some(0),
+                episode: caps.get(2).and_then(|m| m.as_str().parse().ok()),
+                // Without a number the label at least marks the row as a special
+                episode_title: caps.get(2).is_none().then(|| caps[1].to_string()),
+            };
+        }
+
+        // A bare episode number says nothing about the season
+        if let Some(caps) = Regex::new(r"(?i)episode\s+(\d+)").ok().and_then(|re| re.captures(text)) {
+            return Self::episode(None, caps[1].parse().ok());
+        }
+
         MediaType::Movie
     }
-}
\ No newline at end of file
+
+    /// An "Episode 0" is a pilot or prologue that providers file under specials with their
+    /// own number, so it is kept as an unnumbered season 0 episode rather than exported as E00
+    fn episode(season: Option<u32>, episode: Option<u32>) -> MediaType {
+        match episode {
+            Some(0) => MediaType::TvShow { season: Some(0), episode: None, episode_title: Some("Episode 0".to_string()) },
+            _ => MediaType::TvShow { season, episode, episode_title: None },
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episode_of(text: &str) -> (Option<u32>, Option<u32>, Option<String>) {
+        match HistoryItem::determine_media_type(text) {
+            MediaType::TvShow { season, episode, episode_title } => (season, episode, episode_title),
+            MediaType::Movie => panic!("'{}' parsed as a movie", text),
+        }
+    }
+
+    #[test]
+    fn test_specials_map_to_season_zero() {
+        assert_eq!(episode_of("The Boys Season 3 Episode 6"), (Some(3), Some(6), None));
+        assert_eq!(episode_of("Mushoku Tensei Specials Episode 2"), (Some(0), Some(2), None));
+        assert_eq!(episode_of("Attack on Titan OVA 3"), (Some(0), Some(3), None));
+        assert_eq!(episode_of("Made in Abyss OVA"), (Some(0), None, Some("OVA".to_string())));
+        assert_eq!(episode_of("Re:Zero Season 1 Episode 0"), (Some(0), None, Some("Episode 0".to_string())));
+        assert_eq!(episode_of("Invincible Episode 4"), (None, Some(4), None));
+        assert!(matches!(HistoryItem::determine_media_type("Special Forces"), MediaType::Movie));
+    }
+
+    #[test]
+    fn test_rating_read_from_button_label() {
+        assert_eq!(PrimeRating::from_label("Like"), Some(PrimeRating::Like));
+        assert_eq!(PrimeRating::from_label("Dislike"), Some(PrimeRating::Dislike));
+        assert_eq!(PrimeRating::from_label("Thumbs down"), Some(PrimeRating::Dislike));
+        assert_eq!(PrimeRating::from_label("Remove from watch history"), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Reads Netflix's viewing history export into the same history items the Prime Video
+//! scraper produces, so it goes through the usual matching and export
+
+use std::collections::{BTreeSet, HashSet};
+use std::io::Read;
+use std::path::Path;
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::error::AppError;
+use crate::scraping::models::{HistoryItem, MediaType};
+
+/// Shorter plays are autoplayed previews and accidental clicks, not watches
+const MIN_SESSION_SECONDS: u32 = 120;
+
+/// A row of either `ViewingActivity.csv` from the account's data download, or the shorter
+/// `NetflixViewingHistory.csv` offered on the profile's viewing activity page
+#[derive(Debug, Deserialize)]
+struct Row {
+    #[serde(rename = "Title")]
+    title: String,
+    #[serde(rename = "Start Time", default)]
+    start_time: Option<String>,
+    #[serde(rename = "Date", default)]
+    date: Option<String>,
+    #[serde(rename = "Profile Name", default)]
+    profile: Option<String>,
+    #[serde(rename = "Duration", default)]
+    duration: Option<String>,
+    /// TRAILER, HOOK, TEASER_TRAILER and the like; empty for the programme itself
+    #[serde(rename = "Supplemental Video Type", default)]
+    supplemental: Option<String>,
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|value| !value.is_empty())
+}
+
+/// `Start Time` is UTC, `2024-03-01 21:15:03`; the short export only has the day, in the
+/// account's locale, which for most exports is US month/day/year
+fn watched_at(row: &Row) -> Option<DateTime<Local>> {
+    if let Some(start) = non_empty(&row.start_time) {
+        let start = NaiveDateTime::parse_from_str(start, "%Y-%m-%d %H:%M:%S").ok()?;
+        return Some(start.and_utc().with_timezone(&Local));
+    }
+    let date = non_empty(&row.date)?;
+    let day = ["%m/%d/%y", "%m/%d/%Y", "%Y-%m-%d", "%d.%m.%y", "%d.%m.%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())?;
+    day.and_hms_opt(0, 0, 0)?.and_local_timezone(Local).single()
+}
+
+/// `00:45:12` as seconds
+fn seconds(duration: &str) -> Option<u32> {
+    let mut parts = duration.trim().split(':').map(|part| part.parse::<u32>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(hours * 3600 + minutes * 60 + seconds)
+}
+
+fn season_number(word: &str) -> Option<u32> {
+    const WORDS: [&str; 10] = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"];
+    word.parse().ok().or_else(|| {
+        WORDS.iter().position(|w| w.eq_ignore_ascii_case(word)).map(|index| index as u32 + 1)
+    })
+}
+
+/// Splits a Netflix title such as `Fleabag: Season 1: Episode 1` or `Dark: Limited Series:
+/// Secrets` into the show and its episode. Netflix names the episode but does not number it,
+/// unless the name itself is `Episode N`. Titles without a season part are movies.
+fn media_type(title: &str) -> (String, MediaType) {
+    let season = Regex::new(r"(?i)^(?:season|series|part|volume|book|collection)\s+(\w+)$")
+        .expect("valid season pattern");
+    let limited = Regex::new(r"(?i)^(?:limited series|miniseries|mini-series)$").expect("valid pattern");
+    let numbered = Regex::new(r"(?i)^episode\s+(\d+)$").expect("valid episode pattern");
+
+    let parts: Vec<&str> = title.split(": ").collect();
+    for (index, part) in parts.iter().enumerate().skip(1) {
+        let number = match season.captures(part.trim()) {
+            Some(caps) => season_number(&caps[1]),
+            None if limited.is_match(part.trim()) => Some(1),
+            None => continue,
+        };
+        let Some(number) = number else {
+            continue;
+        };
+        let show = parts[..index].join(": ");
+        let name = parts[index + 1..].join(": ");
+        let name = name.trim().trim_matches('"');
+        let media_type = match numbered.captures(name) {
+            Some(caps) => MediaType::TvShow { season: Some(number), episode: caps[1].parse().ok(), episode_title: None },
+            None => MediaType::TvShow {
+                season: Some(number),
+                episode: None,
+                episode_title: (!name.is_empty()).then(|| name.to_string()),
+            },
+        };
+        return (show, media_type);
+    }
+    (title.trim().to_string(), MediaType::Movie)
+}
+
+/// Reads a Netflix viewing history export. Trailers and previews, plays under two minutes
+/// and repeated plays of the same title on the same day are left out. `profile` keeps only
+/// the rows of that profile; the full data download has one row per play for every profile.
+pub fn parse<R: Read>(reader: R, profile: Option<&str>) -> Result<Vec<HistoryItem>, AppError> {
+    let mut csv = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+    let mut items = Vec::new();
+    let mut profiles = BTreeSet::new();
+    let mut seen = HashSet::new();
+    let (mut previews, mut undated) = (0, 0);
+
+    for row in csv.deserialize::<Row>() {
+        let row = row?;
+        if let Some(name) = non_empty(&row.profile) {
+            profiles.insert(name.to_string());
+            if profile.is_some_and(|wanted| !wanted.eq_ignore_ascii_case(name)) {
+                continue;
+            }
+        }
+        let too_short = non_empty(&row.duration).and_then(seconds).is_some_and(|s| s < MIN_SESSION_SECONDS);
+        if non_empty(&row.supplemental).is_some() || too_short {
+            previews += 1;
+            continue;
+        }
+        let Some(watched_at) = watched_at(&row) else {
+            undated += 1;
+            continue;
+        };
+        if !seen.insert((row.title.clone(), watched_at.date_naive())) {
+            continue;
+        }
+        let (title, media_type) = media_type(&row.title);
+        items.push(HistoryItem {
+            raw_text: row.title,
+            title,
+            original_title: None,
+            media_type,
+            watched_at,
+            is_original_language: true,
+            rating: None,
+            source_url: None,
+        });
+    }
+
+    if previews > 0 {
+        tracing::info!("Skipped {} Netflix trailers, previews and plays under two minutes", previews);
+    }
+    if undated > 0 {
+        tracing::warn!("Skipped {} Netflix rows without a readable date", undated);
+    }
+    if profile.is_none() && profiles.len() > 1 {
+        let names: Vec<String> = profiles.into_iter().collect();
+        tracing::warn!(
+            "The Netflix export has several profiles ({}); pass --netflix-profile to import just one",
+            names.join(", ")
+        );
+    }
+    Ok(items)
+}
+
+pub fn read(path: &Path, profile: Option<&str>) -> Result<Vec<HistoryItem>, AppError> {
+    parse(std::fs::File::open(path)?, profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWING_ACTIVITY: &str = "\
+Profile Name,Start Time,Duration,Attributes,Title,Supplemental Video Type,Device Type,Bookmark,Latest Bookmark,Country
+Alice,2024-03-02 21:10:00,00:27:13,,Fleabag: Series 1: Episode 2,,Chrome PC,00:27:13,00:27:13,GB (United Kingdom)
+Alice,2024-03-02 20:30:00,00:25:40,,Fleabag: Series 1: Episode 1,,Chrome PC,00:25:40,00:25:40,GB (United Kingdom)
+Alice,2024-03-02 20:29:00,00:00:45,Autoplayed: user action: None; ,Dark: Season 1: Secrets,,Chrome PC,00:00:45,00:00:45,GB (United Kingdom)
+Alice,2024-03-01 19:00:00,00:01:58,,Arrival,TRAILER,Chrome PC,00:01:58,00:01:58,GB (United Kingdom)
+Alice,2024-03-01 10:00:00,01:56:00,,Arrival,,Chrome PC,01:56:00,01:56:00,GB (United Kingdom)
+Alice,2024-03-01 12:10:00,00:03:00,,Arrival,,Chrome PC,01:59:00,01:59:00,GB (United Kingdom)
+Bob,2024-02-20 18:00:00,00:50:00,,Stranger Things: Season 2: Chapter One: MADMAX,,TV,00:50:00,00:50:00,US (United States)
+";
+
+    #[test]
+    fn test_viewing_activity_rows_become_history_items() {
+        let items = parse(VIEWING_ACTIVITY.as_bytes(), Some("alice")).unwrap();
+        let titles: Vec<&str> = items.iter().map(|item| item.title.as_str()).collect();
+        assert_eq!(titles, ["Fleabag", "Fleabag", "Arrival"]);
+        assert!(matches!(
+            items[0].media_type,
+            MediaType::TvShow { season: Some(1), episode: Some(2), episode_title: None }
+        ));
+        assert!(matches!(items[2].media_type, MediaType::Movie));
+
+        let items = parse(VIEWING_ACTIVITY.as_bytes(), Some("Bob")).unwrap();
+        assert_eq!(items[0].title, "Stranger Things");
+        match &items[0].media_type {
+            MediaType::TvShow { season, episode, episode_title } => {
+                assert_eq!(*season, Some(2));
+                assert_eq!(*episode, None);
+                assert_eq!(episode_title.as_deref(), Some("Chapter One: MADMAX"));
+            }
+            MediaType::Movie => panic!("parsed as a movie"),
+        }
+    }
+
+    #[test]
+    fn test_short_export_dates_and_movie_titles_with_colons() {
+        let csv = "Title,Date\n\"Star Wars: Episode IV - A New Hope\",3/1/24\n\"Dark: Limited Series: \"\"Secrets\"\"\",12/31/23\n";
+        let items = parse(csv.as_bytes(), None).unwrap();
+        assert_eq!(items[0].title, "Star Wars: Episode IV - A New Hope");
+        assert!(matches!(items[0].media_type, MediaType::Movie));
+        assert_eq!(items[0].watched_at.date_naive(), NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert_eq!(items[1].title, "Dark");
+        assert!(matches!(
+            &items[1].media_type,
+            MediaType::TvShow { season: Some(1), episode_title: Some(name), .. } if name == "Secrets"
+        ));
+    }
+}
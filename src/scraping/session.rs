@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+use fantoccini::cookies::Cookie;
+use fantoccini::Client;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::crypto::{self, KeySource};
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredCookie {
+    name: String,
+    value: String,
+    domain: Option<String>,
+    path: Option<String>,
+    secure: Option<bool>,
+    http_only: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedSession {
+    saved_at: DateTime<Utc>,
+    cookies: Vec<StoredCookie>,
+}
+
+/// Encrypted on-disk store for the browser's Amazon session cookies
+pub struct SessionStore {
+    path: PathBuf,
+    key_source: KeySource,
+}
+
+impl SessionStore {
+    pub fn new(path: PathBuf, key_source: KeySource) -> Self {
+        Self { path, key_source }
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    /// Seals all cookies of the current page's domain to disk
+    pub async fn save(&self, client: &Client) -> Result<usize, AppError> {
+        let cookies = client
+            .get_all_cookies()
+            .await
+            .map_err(|e| AppError::BrowserError(e.to_string()))?;
+
+        let session = SavedSession {
+            saved_at: Utc::now(),
+            cookies: cookies
+                .iter()
+                .map(|c| StoredCookie {
+                    name: c.name().to_string(),
+                    value: c.value().to_string(),
+                    domain: c.domain().map(String::from),
+                    path: c.path().map(String::from),
+                    secure: c.secure(),
+                    http_only: c.http_only(),
+                })
+                .collect(),
+        };
+
+        let plaintext = serde_json::to_vec(&session)?;
+        std::fs::write(&self.path, crypto::seal(&plaintext, &self.key_source)?)?;
+        Ok(session.cookies.len())
+    }
+
+    /// Unseals saved cookies and adds them to the browser, returning how many were accepted.
+    /// The browser must already be on the domain the cookies belong to.
+    pub async fn restore(&self, client: &Client) -> Result<usize, AppError> {
+        let sealed = std::fs::read(&self.path)?;
+        let session: SavedSession = serde_json::from_slice(&crypto::unseal(&sealed, &self.key_source)?)?;
+        tracing::debug!("Restoring session saved at {}", session.saved_at);
+
+        let mut restored = 0;
+        for stored in session.cookies {
+            let mut cookie = Cookie::new(stored.name, stored.value);
+            if let Some(domain) = stored.domain {
+                cookie.set_domain(domain);
+            }
+            if let Some(path) = stored.path {
+                cookie.set_path(path);
+            }
+            cookie.set_secure(stored.secure);
+            cookie.set_http_only(stored.http_only);
+
+            // Cookies for other domains are rejected by WebDriver; skip them quietly
+            match client.add_cookie(cookie).await {
+                Ok(()) => restored += 1,
+                Err(e) => tracing::debug!("Skipping cookie: {}", e),
+            }
+        }
+        Ok(restored)
+    }
+
+    pub fn clear(&self) -> Result<(), AppError> {
+        if self.exists() {
+            std::fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use fantoccini::{Client, Locator};
+use crate::error::AppError;
+use crate::scraping::models::{MediaType, WatchlistItem};
+use regex::Regex;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Cards of the watchlist grid; each links to the title's detail page
+const CARD_LINKS: &str = "main a[href*='/detail/'], main a[href*='/dp/']";
+
+pub struct WatchlistExtractor<'a> {
+    client: &'a mut Client,
+    max_scrolls: usize,
+    scroll_delay: Duration,
+}
+
+impl<'a> WatchlistExtractor<'a> {
+    pub fn new(client: &'a mut Client) -> Self {
+        Self {
+            client,
+            max_scrolls: 50,
+            scroll_delay: Duration::from_secs(2),
+        }
+    }
+
+    /// Reads every card of the watchlist page the browser is on; `show` says whether the
+    /// page is the TV filter of the watchlist
+    pub async fn extract(&mut self, show: bool) -> Result<Vec<WatchlistItem>, AppError> {
+        self.load_all_cards().await?;
+        let links = self.client
+            .find_all(Locator::Css(CARD_LINKS))
+            .await
+            .map_err(|e| AppError::BrowserError(e.to_string()))?;
+
+        let mut seen = HashSet::new();
+        let mut items = Vec::new();
+        for link in links {
+            let href = link.prop("href").await.ok().flatten();
+            // The image link carries the title as its label, the text link as its text
+            let label = match link.attr("aria-label").await.ok().flatten() {
+                Some(label) if !label.trim().is_empty() => label,
+                _ => link.text().await.unwrap_or_default(),
+            };
+            let Some(item) = WatchlistItem::from_card(&label, href, show) else {
+                continue;
+            };
+            // Each card links to its detail page more than once
+            let key = item.source_url.clone().unwrap_or_else(|| item.title.to_lowercase());
+            if seen.insert(key) {
+                items.push(item);
+            }
+        }
+        Ok(items)
+    }
+
+    async fn load_all_cards(&mut self) -> Result<(), AppError> {
+        let mut previous_height = 0;
+        for _ in 0..self.max_scrolls {
+            self.client
+                .execute("window.scrollTo(0, document.body.scrollHeight)", vec![])
+                .await
+                .map_err(|e| AppError::BrowserError(e.to_string()))?;
+            tokio::time::sleep(self.scroll_delay).await;
+            let height = self.client
+                .execute("return document.body.scrollHeight", vec![])
+                .await
+                .map_err(|e| AppError::BrowserError(e.to_string()))?
+                .as_i64()
+                .unwrap_or(0);
+            if height == previous_height {
+                break;
+            }
+            previous_height = height;
+        }
+        Ok(())
+    }
+}
+
+impl WatchlistItem {
+    /// Builds an item from a card's title, e.g. "The Boys - Season 3" or "Arrival", and
+    /// its link. Cards without a title are controls rather than titles and give `None`.
+    pub fn from_card(label: &str, href: Option<String>, show: bool) -> Option<Self> {
+        let label = label.trim();
+        if label.is_empty() {
+            return None;
+        }
+        let season = Regex::new(r"(?i)^(.*?)\s*[-–—:,]?\s*(?:season|staffel|stagione|saison|temporada)\s+(\d+)$")
+            .expect("valid season pattern");
+        let (title, season) = match season.captures(label) {
+            Some(caps) => (caps[1].trim().to_string(), caps[2].parse().ok()),
+            None => (label.to_string(), None),
+        };
+        let media_type = if show || season.is_some() {
+            MediaType::TvShow { season, episode: None, episode_title: None }
+        } else {
+            MediaType::Movie
+        };
+        Some(Self { title, media_type, source_url: href })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cards_become_watchlist_items() {
+        let show = WatchlistItem::from_card(" The Boys - Season 3 ", Some("https://www.primevideo.com/detail/0KRGHGZCHKS920ZQGY5LBRF7MA".to_string()), false).unwrap();
+        assert_eq!(show.title, "The Boys");
+        assert!(matches!(show.media_type, MediaType::TvShow { season: Some(3), episode: None, .. }));
+
+        let movie = WatchlistItem::from_card("Arrival", None, false).unwrap();
+        assert_eq!(movie.title, "Arrival");
+        assert!(matches!(movie.media_type, MediaType::Movie));
+
+        let listed_as_show = WatchlistItem::from_card("Fleabag", None, true).unwrap();
+        assert!(matches!(listed_as_show.media_type, MediaType::TvShow { season: None, .. }));
+        assert!(WatchlistItem::from_card("  ", None, false).is_none());
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::config::{AppConfig, JellyfinConfig, WatchedAtConfig};
+use crate::error::AppError;
+use crate::metadata::{response, RateLimit, RateLimiter, RetryPolicy};
+use crate::models::MediaType;
+use crate::processor::history_processor::ProcessedItem;
+use crate::sync::library::{Library, Lookup, ServerItem};
+use crate::sync::SyncReport;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct User {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ItemsResponse {
+    #[serde(default)]
+    items: Vec<LibraryItem>,
+}
+
+/// A movie, series or episode of the library, as `/Users/{id}/Items` lists it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LibraryItem {
+    id: String,
+    /// Keyed `Tmdb`, `Imdb`, `Tvdb`...
+    #[serde(default)]
+    provider_ids: HashMap<String, String>,
+    series_id: Option<String>,
+    /// Season number of an episode
+    parent_index_number: Option<u32>,
+    /// Episode number of an episode
+    index_number: Option<u32>,
+    #[serde(default)]
+    user_data: UserData,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct UserData {
+    #[serde(default)]
+    played: bool,
+}
+
+impl ServerItem for LibraryItem {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.provider_ids
+            .iter()
+            .filter(|(_, id)| !id.is_empty())
+            .map(|(provider, id)| format!("{}={}", provider.to_lowercase(), id))
+            .collect()
+    }
+
+    fn watched(&self) -> bool {
+        self.user_data.played
+    }
+
+    fn episode_of(&self) -> Option<(&str, u32, u32)> {
+        Some((self.series_id.as_deref()?, self.parent_index_number?, self.index_number?))
+    }
+}
+
+/// Talks to one Jellyfin server on behalf of the configured user
+struct Server<'a> {
+    config: &'a JellyfinConfig,
+    clock: &'a WatchedAtConfig,
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    limiter: RateLimiter,
+}
+
+impl Server<'_> {
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.config.url.trim_end_matches('/'), path)
+    }
+
+    fn auth(&self) -> String {
+        format!("MediaBrowser Token=\"{}\"", self.config.api_key)
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T, AppError> {
+        let request = self.client.get(self.url(path)).header("Authorization", self.auth()).query(query);
+        let response = self.retry.send(request, &self.limiter).await?;
+        if !response.status().is_success() {
+            return Err(AppError::MetadataError(format!("Jellyfin API error: {}", response.status())));
+        }
+        response::json("Jellyfin", response).await
+    }
+
+    /// The ID of the configured user, or of the only user when none is configured
+    async fn user_id(&self) -> Result<String, AppError> {
+        let users: Vec<User> = self.get("/Users", &[]).await?;
+        if self.config.user.is_empty() && users.len() == 1 {
+            return Ok(users[0].id.clone());
+        }
+        let names: Vec<&str> = users.iter().map(|user| user.name.as_str()).collect();
+        users
+            .iter()
+            .find(|user| user.name.eq_ignore_ascii_case(&self.config.user))
+            .map(|user| user.id.clone())
+            .ok_or_else(|| {
+                AppError::ConfigError(config::ConfigError::Message(format!(
+                    "Set jellyfin.user to one of the server's users: {}",
+                    names.join(", ")
+                )))
+            })
+    }
+
+    async fn items(&self, user_id: &str, kind: &str) -> Result<Vec<LibraryItem>, AppError> {
+        let response: ItemsResponse = self
+            .get(
+                &format!("/Users/{}/Items", user_id),
+                &[("Recursive", "true"), ("IncludeItemTypes", kind), ("Fields", "ProviderIds")],
+            )
+            .await?;
+        Ok(response.items)
+    }
+
+    async fn mark_played(&self, user_id: &str, item_id: &str, item: &ProcessedItem) -> Result<(), AppError> {
+        let watched_at = item.watched_at(self.clock);
+        let request = self
+            .client
+            .post(self.url(&format!("/Users/{}/PlayedItems/{}", user_id, item_id)))
+            .header("Authorization", self.auth())
+            .query(&[("datePlayed", watched_at.as_str())]);
+        let response = self.retry.send(request, &self.limiter).await?;
+        if !response.status().is_success() {
+            return Err(AppError::MetadataError(format!("Jellyfin played update error: {}", response.status())));
+        }
+        Ok(())
+    }
+}
+
+/// Marks every matched movie and episode that is in the Jellyfin library as played, with its
+/// watch date. Items already played are left alone; a failed item is reported and the rest
+/// are still sent.
+pub async fn push(config: &AppConfig, items: &[ProcessedItem]) -> Result<SyncReport, AppError> {
+    let jellyfin = &config.jellyfin;
+    if jellyfin.url.is_empty() || jellyfin.api_key.is_empty() {
+        return Err(AppError::ConfigError(config::ConfigError::Message(
+            "Jellyfin sync needs jellyfin.url and jellyfin.api_key in config".to_string(),
+        )));
+    }
+    let server = Server {
+        config: jellyfin,
+        clock: &config.output.watched_at,
+        client: reqwest::Client::new(),
+        retry: RetryPolicy::from_config(&config.retry),
+        limiter: RateLimiter::new(&RateLimit { calls: 20, per_seconds: 1 }),
+    };
+    let user_id = server.user_id().await?;
+    let movies = server.items(&user_id, "Movie").await?;
+    let episodes = server.items(&user_id, "Episode").await?;
+    tracing::info!("Jellyfin library has {} movies and {} episodes", movies.len(), episodes.len());
+    let library = Library::new(&movies, &server.items(&user_id, "Series").await?, &episodes);
+
+    let mut report = SyncReport::default();
+    let mut marked = HashSet::new();
+    for item in items {
+        let id = match library.find(item) {
+            Lookup::Found { watched: true, .. } => continue,
+            Lookup::Found { id, .. } => id,
+            Lookup::NotInLibrary => {
+                report.not_found.push(item.label());
+                continue;
+            }
+            Lookup::Skipped(reason) => {
+                report.skipped.push((item.label(), reason.to_string()));
+                continue;
+            }
+        };
+        // A rewatch marks the same item again; the first watch listed is enough
+        if !marked.insert(id) {
+            continue;
+        }
+        match server.mark_played(&user_id, id, item).await {
+            Ok(()) if item.media_type == MediaType::Movie => report.movies_added += 1,
+            Ok(()) => report.episodes_added += 1,
+            Err(e) => report.failed.push((item.label(), e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{MediaIds, MetadataResult};
+
+    fn item(title: &str, media_type: MediaType, episode: Option<&str>, ids: MediaIds) -> ProcessedItem {
+        ProcessedItem {
+            title: title.to_string(),
+            date: "2024-03-01".to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids,
+                title: title.to_string(),
+                media_type,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn items(json: &str) -> Vec<LibraryItem> {
+        serde_json::from_str::<ItemsResponse>(json).unwrap().items
+    }
+
+    #[test]
+    fn test_history_items_are_found_by_provider_id() {
+        let library = Library::new(
+            &items(r#"{"Items": [{"Id": "m1", "ProviderIds": {"Tmdb": "329865", "Imdb": "tt2543164"}, "UserData": {"Played": true}}]}"#),
+            &items(r#"{"Items": [{"Id": "s1", "ProviderIds": {"Tvdb": "318221"}}]}"#),
+            &items(
+                r#"{"Items": [{"Id": "e1", "SeriesId": "s1", "ParentIndexNumber": 1, "IndexNumber": 2},
+                              {"Id": "e0", "SeriesId": "s1"}]}"#,
+            ),
+        );
+        let tvdb = MediaIds { tvdb: Some("318221".to_string()), ..Default::default() };
+        let imdb = MediaIds { imdb: Some("tt2543164".to_string()), ..Default::default() };
+
+        assert_eq!(
+            library.find(&item("Arrival", MediaType::Movie, None, imdb)),
+            Lookup::Found { id: "m1", watched: true }
+        );
+        assert_eq!(
+            library.find(&item("Fleabag", MediaType::Tv, Some("S01E02"), tvdb.clone())),
+            Lookup::Found { id: "e1", watched: false }
+        );
+        assert_eq!(library.find(&item("Fleabag", MediaType::Tv, Some("S01E03"), tvdb.clone())), Lookup::NotInLibrary);
+        assert_eq!(
+            library.find(&item("Fleabag", MediaType::Tv, None, tvdb)),
+            Lookup::Skipped("no episode number")
+        );
+        assert_eq!(
+            library.find(&item("Unknown", MediaType::Movie, None, MediaIds::default())),
+            Lookup::Skipped("no TMDB, IMDB or TVDB ID")
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! Finding history items in a media server's library by their provider IDs
+
+use std::collections::HashMap;
+
+use crate::metadata::MediaIds;
+use crate::models::MediaType;
+use crate::processor::history_processor::ProcessedItem;
+
+/// A movie, show or episode as a media server lists it; each backend reads these from its own API
+pub trait ServerItem {
+    /// The server's ID for the item, used to mark it as watched
+    fn id(&self) -> &str;
+    /// `tmdb=438631`-style keys for the provider IDs the server stores for the item
+    fn keys(&self) -> Vec<String>;
+    /// Whether the user has watched the item already
+    fn watched(&self) -> bool;
+    /// The show's ID, season and episode number of an episode
+    fn episode_of(&self) -> Option<(&str, u32, u32)>;
+}
+
+fn history_keys(ids: &MediaIds) -> Vec<String> {
+    [("tmdb", &ids.tmdb), ("imdb", &ids.imdb), ("tvdb", &ids.tvdb)]
+        .into_iter()
+        .filter_map(|(provider, id)| id.as_ref().map(|id| format!("{}={}", provider, id)))
+        .collect()
+}
+
+/// Where a history item is in the library
+#[derive(Debug, PartialEq)]
+pub enum Lookup<'a> {
+    Found { id: &'a str, watched: bool },
+    NotInLibrary,
+    Skipped(&'static str),
+}
+
+/// A watched state to check and a server ID to mark
+struct Target {
+    id: String,
+    watched: bool,
+}
+
+impl Target {
+    fn new(item: &impl ServerItem) -> Self {
+        Self { id: item.id().to_string(), watched: item.watched() }
+    }
+}
+
+/// The movies and episodes of a server's libraries, indexed by provider ID
+#[derive(Default)]
+pub struct Library {
+    movies: HashMap<String, Target>,
+    shows: HashMap<String, String>,
+    episodes: HashMap<(String, u32, u32), Target>,
+}
+
+impl Library {
+    pub fn new<T: ServerItem>(movies: &[T], shows: &[T], episodes: &[T]) -> Self {
+        let mut library = Self::default();
+        for movie in movies {
+            for key in movie.keys() {
+                library.movies.insert(key, Target::new(movie));
+            }
+        }
+        for show in shows {
+            for key in show.keys() {
+                library.shows.insert(key, show.id().to_string());
+            }
+        }
+        for episode in episodes {
+            if let Some((show, season, number)) = episode.episode_of() {
+                library.episodes.insert((show.to_string(), season, number), Target::new(episode));
+            }
+        }
+        library
+    }
+
+    pub fn find(&self, item: &ProcessedItem) -> Lookup<'_> {
+        let keys = history_keys(&item.metadata.ids);
+        if keys.is_empty() {
+            return Lookup::Skipped("no TMDB, IMDB or TVDB ID");
+        }
+        let found = match item.media_type {
+            MediaType::Movie => keys.iter().find_map(|key| self.movies.get(key)),
+            MediaType::Tv => {
+                let Some((season, episode)) = item.episode_code() else {
+                    return Lookup::Skipped("no episode number");
+                };
+                keys.iter()
+                    .filter_map(|key| self.shows.get(key))
+                    .find_map(|show| self.episodes.get(&(show.clone(), season, episode)))
+            }
+        };
+        match found {
+            Some(entry) => Lookup::Found { id: &entry.id, watched: entry.watched },
+            None => Lookup::NotInLibrary,
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::auth::{self, TokenStore};
+use crate::config::AppConfig;
+use crate::error::AppError;
+use crate::metadata::{response, RateLimit, RateLimiter, RetryPolicy};
+use crate::models::MediaType;
+use crate::processor::history_processor::ProcessedItem;
+use crate::sync::SyncReport;
+
+const API_URL: &str = "https://api.myanimelist.net/v2";
+
+/// What the history says about one MAL entry (MAL lists each season on its own)
+#[derive(Debug, PartialEq)]
+struct Progress<'a> {
+    mal_id: u64,
+    title: &'a str,
+    movie: bool,
+    /// Highest episode number watched; 1 for a movie
+    episodes: u32,
+    /// Latest watch date, the finish date once the entry is complete
+    last_watched: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnimeStatus {
+    #[serde(default)]
+    num_episodes: u32,
+    my_list_status: Option<ListStatus>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListStatus {
+    status: String,
+    #[serde(default)]
+    num_episodes_watched: u32,
+}
+
+/// The fields of a `my_list_status` update
+#[derive(Debug, PartialEq)]
+struct ListUpdate {
+    status: &'static str,
+    num_watched_episodes: u32,
+    finish_date: Option<String>,
+}
+
+/// Groups the anime among `items` by MAL entry; items without a MAL ID are left out, those
+/// flagged as anime reported as skipped
+fn progress<'a>(items: &'a [ProcessedItem], report: &mut SyncReport) -> Vec<Progress<'a>> {
+    let mut entries: BTreeMap<u64, Progress> = BTreeMap::new();
+    for item in items {
+        let Some(mal_id) = item.metadata.ids.mal.as_deref().and_then(|id| id.parse().ok()) else {
+            if item.metadata.anime {
+                report.skipped.push((item.title.clone(), "no MAL ID".to_string()));
+            }
+            continue;
+        };
+        let episodes = match item.media_type {
+            MediaType::Movie => 1,
+            MediaType::Tv => match item.episode_code() {
+                Some((_, episode)) => episode,
+                None => {
+                    report.skipped.push((item.title.clone(), "no episode number".to_string()));
+                    continue;
+                }
+            },
+        };
+        let entry = entries.entry(mal_id).or_insert(Progress {
+            mal_id,
+            title: &item.title,
+            movie: item.media_type == MediaType::Movie,
+            episodes,
+            last_watched: &item.date,
+        });
+        entry.episodes = entry.episodes.max(episodes);
+        if item.date.as_str() > entry.last_watched {
+            entry.last_watched = &item.date;
+        }
+    }
+    entries.into_values().collect()
+}
+
+/// The update that brings MAL up to the history, or None when the list already has at least
+/// as much. Progress never goes down and a completed entry is left alone.
+fn update(progress: &Progress, status: &AnimeStatus) -> Option<ListUpdate> {
+    let watched = match status.num_episodes {
+        0 => progress.episodes,
+        total => progress.episodes.min(total),
+    };
+    let complete = status.num_episodes > 0 && watched >= status.num_episodes;
+    if let Some(current) = &status.my_list_status {
+        if current.status == "completed" || (current.num_episodes_watched >= watched && !complete) {
+            return None;
+        }
+    }
+    Some(ListUpdate {
+        status: if complete { "completed" } else { "watching" },
+        num_watched_episodes: watched,
+        finish_date: complete.then(|| progress.last_watched.to_string()),
+    })
+}
+
+/// What became of one entry
+enum Outcome {
+    /// Updated, with the episodes added to its progress
+    Updated(u32),
+    UpToDate,
+    NotFound,
+}
+
+/// Reads the list entry of `entry`'s anime and raises it to what the history says
+async fn sync_entry(
+    client: &reqwest::Client,
+    retry: &RetryPolicy,
+    limiter: &RateLimiter,
+    token: &str,
+    entry: &Progress<'_>,
+) -> Result<Outcome, AppError> {
+    let url = format!("{}/anime/{}", API_URL, entry.mal_id);
+    let request = client
+        .get(&url)
+        .header("Authorization", format!("Bearer {}", token))
+        .query(&[("fields", "num_episodes,my_list_status")]);
+    let response = retry.send(request, limiter).await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(Outcome::NotFound);
+    }
+    if !response.status().is_success() {
+        return Err(AppError::MetadataError(format!("MAL API error: {}", response.status())));
+    }
+    let status: AnimeStatus = response::json("MAL", response).await?;
+    let Some(update) = update(entry, &status) else {
+        tracing::debug!("'{}' is already up to date on MAL", entry.title);
+        return Ok(Outcome::UpToDate);
+    };
+
+    let mut form = vec![
+        ("status", update.status.to_string()),
+        ("num_watched_episodes", update.num_watched_episodes.to_string()),
+    ];
+    if let Some(date) = &update.finish_date {
+        form.push(("finish_date", date.clone()));
+    }
+    let request = client
+        .patch(format!("{}/my_list_status", url))
+        .header("Authorization", format!("Bearer {}", token))
+        .form(&form);
+    let response = retry.send(request, limiter).await?;
+    if !response.status().is_success() {
+        return Err(AppError::MetadataError(format!("MAL list update error: {}", response.status())));
+    }
+    let before = status.my_list_status.map_or(0, |current| current.num_episodes_watched);
+    Ok(Outcome::Updated(update.num_watched_episodes.saturating_sub(before)))
+}
+
+/// Updates the MAL list of the account authorized with `login mal` for every matched anime:
+/// its status, episodes watched and, once complete, the finish date. A failed entry is
+/// reported and the rest are still sent.
+pub async fn push(config: &AppConfig, items: &[ProcessedItem]) -> Result<SyncReport, AppError> {
+    let tokens = TokenStore::load(config)?;
+    let token = tokens
+        .access_token(auth::MAL)
+        .await?
+        .ok_or_else(|| AppError::AuthError("No MAL token found; run `login mal` first".to_string()))?;
+
+    let client = reqwest::Client::new();
+    let retry = RetryPolicy::from_config(&config.retry);
+    let limiter = RateLimiter::new(&RateLimit { calls: 60, per_seconds: 60 });
+    let mut report = SyncReport::default();
+    let entries = progress(items, &mut report);
+    tracing::info!("{} MAL entries to check", entries.len());
+
+    for entry in &entries {
+        match sync_entry(&client, &retry, &limiter, &token, entry).await {
+            Ok(Outcome::Updated(_)) if entry.movie => report.movies_added += 1,
+            Ok(Outcome::Updated(episodes)) => report.episodes_added += episodes as usize,
+            Ok(Outcome::UpToDate) => {}
+            Ok(Outcome::NotFound) => report.not_found.push(entry.title.to_string()),
+            Err(e) => report.failed.push((entry.title.to_string(), e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{MediaIds, MetadataResult};
+
+    fn item(title: &str, date: &str, episode: Option<&str>, mal: Option<&str>) -> ProcessedItem {
+        let media_type = if episode.is_some() { MediaType::Tv } else { MediaType::Movie };
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids: MediaIds { mal: mal.map(String::from), ..Default::default() },
+                title: title.to_string(),
+                media_type,
+                anime: true,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_episodes_fold_into_one_entry_per_mal_id() {
+        let items = vec![
+            item("Frieren", "2024-03-22", Some("S01E28"), Some("52991")),
+            item("Frieren", "2024-03-15", Some("S01E27"), Some("52991")),
+            item("Your Name.", "2024-01-05", None, Some("32281")),
+            item("Mystery Anime", "2024-01-06", None, None),
+        ];
+        let mut report = SyncReport::default();
+        let entries = progress(&items, &mut report);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1], Progress {
+            mal_id: 52991,
+            title: "Frieren",
+            movie: false,
+            episodes: 28,
+            last_watched: "2024-03-22",
+        });
+        assert_eq!(report.skipped, [("Mystery Anime".to_string(), "no MAL ID".to_string())]);
+    }
+
+    #[test]
+    fn test_updates_only_move_forward() {
+        let frieren = Progress { mal_id: 52991, title: "Frieren", movie: false, episodes: 28, last_watched: "2024-03-22" };
+        let status = |current: Option<(&str, u32)>| AnimeStatus {
+            num_episodes: 28,
+            my_list_status: current.map(|(status, watched)| ListStatus { status: status.to_string(), num_episodes_watched: watched }),
+        };
+
+        assert_eq!(
+            update(&frieren, &status(Some(("watching", 20)))),
+            Some(ListUpdate { status: "completed", num_watched_episodes: 28, finish_date: Some("2024-03-22".to_string()) })
+        );
+        assert_eq!(update(&frieren, &status(Some(("completed", 28)))), None);
+
+        let halfway = Progress { episodes: 10, ..frieren };
+        assert_eq!(update(&halfway, &status(Some(("watching", 12)))), None);
+        assert_eq!(
+            update(&halfway, &status(None)),
+            Some(ListUpdate { status: "watching", num_watched_episodes: 10, finish_date: None })
+        );
+    }
+}
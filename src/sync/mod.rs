@@ -0,0 +1,85 @@
+//! Pushing matched history straight to a tracking service instead of writing an import file
+
+use crate::processor::history_processor::ProcessedItem;
+
+pub mod jellyfin;
+mod library;
+pub mod mal;
+pub mod plex;
+pub mod sheets;
+pub mod simkl;
+
+/// A service the `sync` command can push the matched history to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncTarget {
+    Simkl,
+    Mal,
+    Jellyfin,
+    Plex,
+    Sheets,
+}
+
+impl SyncTarget {
+    /// The service name used in progress messages and the report
+    pub fn name(self) -> &'static str {
+        match self {
+            SyncTarget::Simkl => "Simkl",
+            SyncTarget::Mal => "MyAnimeList",
+            SyncTarget::Jellyfin => "Jellyfin",
+            SyncTarget::Plex => "Plex",
+            SyncTarget::Sheets => "Google Sheets",
+        }
+    }
+}
+
+impl ProcessedItem {
+    /// Names the item in sync reports: its title, followed by the episode for episode rows
+    pub fn label(&self) -> String {
+        match &self.episode {
+            Some(episode) => format!("{} {}", self.title, episode),
+            None => self.title.clone(),
+        }
+    }
+}
+
+/// What happened to the items of one sync run
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub movies_added: usize,
+    pub episodes_added: usize,
+    /// Items the service could not find by their IDs
+    pub not_found: Vec<String>,
+    /// Items left out before sending, with the reason
+    pub skipped: Vec<(String, String)>,
+    /// Items in batches the service rejected, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+impl SyncReport {
+    /// Prints the totals and every item that did not make it
+    pub fn print(&self, service: &str) {
+        println!();
+        println!(
+            "✅ Added {} movies and {} episodes to {}",
+            self.movies_added, self.episodes_added, service
+        );
+        if !self.not_found.is_empty() {
+            println!("⚠️  {} items were not found on {}:", self.not_found.len(), service);
+            for item in &self.not_found {
+                println!("   - {}", item);
+            }
+        }
+        if !self.skipped.is_empty() {
+            println!("⚠️  {} items were skipped:", self.skipped.len());
+            for (item, reason) in &self.skipped {
+                println!("   - {}: {}", item, reason);
+            }
+        }
+        if !self.failed.is_empty() {
+            println!("❌ {} items could not be sent:", self.failed.len());
+            for (item, error) in &self.failed {
+                println!("   - {}: {}", item, error);
+            }
+        }
+    }
+}
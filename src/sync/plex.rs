@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::config::{AppConfig, PlexConfig};
+use crate::error::AppError;
+use crate::metadata::{response, RateLimit, RateLimiter, RetryPolicy};
+use crate::models::MediaType;
+use crate::processor::history_processor::ProcessedItem;
+use crate::sync::library::{Library, Lookup, ServerItem};
+use crate::sync::SyncReport;
+
+/// Plex's type numbers for `/library/sections/{key}/all?type=`
+const MOVIE: &str = "1";
+const SHOW: &str = "2";
+const EPISODE: &str = "4";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Envelope<T> {
+    media_container: T,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Sections {
+    #[serde(default)]
+    directory: Vec<Section>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Section {
+    key: String,
+    /// `movie`, `show`, `artist` or `photo`
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Items {
+    /// Left out of an empty listing
+    #[serde(default)]
+    metadata: Vec<LibraryItem>,
+}
+
+/// A movie, show or episode of a library section
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryItem {
+    rating_key: String,
+    /// The legacy agent's ID, e.g. `com.plexapp.agents.imdb://tt2543164?lang=en`
+    #[serde(default)]
+    guid: String,
+    /// The current agents' IDs, e.g. `tmdb://329865`, listed with `includeGuids=1`
+    #[serde(default, rename = "Guid")]
+    guids: Vec<Guid>,
+    /// Show of an episode
+    grandparent_rating_key: Option<String>,
+    /// Season number of an episode
+    parent_index: Option<u32>,
+    /// Episode number of an episode
+    index: Option<u32>,
+    /// Left out until the item has been watched
+    #[serde(default)]
+    view_count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct Guid {
+    id: String,
+}
+
+/// `tmdb=438631`-style keys for a `scheme://id` GUID, old agents' schemes included
+fn guid_key(guid: &str) -> Option<String> {
+    let (scheme, rest) = guid.split_once("://")?;
+    let provider = match scheme.rsplit('.').next()? {
+        "imdb" => "imdb",
+        "tmdb" | "themoviedb" => "tmdb",
+        "tvdb" | "thetvdb" => "tvdb",
+        _ => return None,
+    };
+    // Old agents append the language, and for episodes the season and episode
+    let id = rest.split(['?', '/']).next().filter(|id| !id.is_empty())?;
+    Some(format!("{}={}", provider, id))
+}
+
+impl ServerItem for LibraryItem {
+    fn id(&self) -> &str {
+        &self.rating_key
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.guids.iter().map(|guid| guid.id.as_str()).chain([self.guid.as_str()]).filter_map(guid_key).collect()
+    }
+
+    fn watched(&self) -> bool {
+        self.view_count > 0
+    }
+
+    fn episode_of(&self) -> Option<(&str, u32, u32)> {
+        Some((self.grandparent_rating_key.as_deref()?, self.parent_index?, self.index?))
+    }
+}
+
+/// Talks to one Plex Media Server as the owner of the configured token
+struct Server<'a> {
+    config: &'a PlexConfig,
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    limiter: RateLimiter,
+}
+
+impl Server<'_> {
+    fn request(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .get(format!("{}{}", self.config.url.trim_end_matches('/'), path))
+            .header("X-Plex-Token", &self.config.token)
+            .header("Accept", "application/json")
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str, query: &[(&str, &str)]) -> Result<T, AppError> {
+        let response = self.retry.send(self.request(path).query(query), &self.limiter).await?;
+        if !response.status().is_success() {
+            return Err(AppError::MetadataError(format!("Plex API error: {}", response.status())));
+        }
+        let envelope: Envelope<T> = response::json("Plex", response).await?;
+        Ok(envelope.media_container)
+    }
+
+    /// Every item of `kind` in the server's movie or show sections
+    async fn items(&self, sections: &[Section], kind: &str) -> Result<Vec<LibraryItem>, AppError> {
+        let section_kind = if kind == MOVIE { "movie" } else { "show" };
+        let mut items = Vec::new();
+        for section in sections.iter().filter(|section| section.kind == section_kind) {
+            let listing: Items = self
+                .get(&format!("/library/sections/{}/all", section.key), &[("type", kind), ("includeGuids", "1")])
+                .await?;
+            items.extend(listing.metadata);
+        }
+        Ok(items)
+    }
+
+    /// Marks an item as watched. Plex records the time of the request as the view date;
+    /// its API has no way to pass the date of the watch.
+    async fn scrobble(&self, key: &str) -> Result<(), AppError> {
+        let request = self.request("/:/scrobble").query(&[("identifier", "com.plexapp.plugins.library"), ("key", key)]);
+        let response = self.retry.send(request, &self.limiter).await?;
+        if !response.status().is_success() {
+            return Err(AppError::MetadataError(format!("Plex scrobble error: {}", response.status())));
+        }
+        Ok(())
+    }
+}
+
+/// Marks every matched movie and episode found in the Plex server's movie and show libraries
+/// as watched. Items already watched are left alone; a failed item is reported and the rest
+/// are still sent.
+pub async fn push(config: &AppConfig, items: &[ProcessedItem]) -> Result<SyncReport, AppError> {
+    let plex = &config.plex;
+    if plex.url.is_empty() || plex.token.is_empty() {
+        return Err(AppError::ConfigError(config::ConfigError::Message(
+            "Plex sync needs plex.url and plex.token in config".to_string(),
+        )));
+    }
+    let server = Server {
+        config: plex,
+        client: reqwest::Client::new(),
+        retry: RetryPolicy::from_config(&config.retry),
+        limiter: RateLimiter::new(&RateLimit { calls: 20, per_seconds: 1 }),
+    };
+    let sections: Sections = server.get("/library/sections", &[]).await?;
+    let movies = server.items(&sections.directory, MOVIE).await?;
+    let episodes = server.items(&sections.directory, EPISODE).await?;
+    tracing::info!("Plex libraries have {} movies and {} episodes", movies.len(), episodes.len());
+    let library = Library::new(&movies, &server.items(&sections.directory, SHOW).await?, &episodes);
+
+    let mut report = SyncReport::default();
+    let mut marked = HashSet::new();
+    for item in items {
+        let id = match library.find(item) {
+            Lookup::Found { watched: true, .. } => continue,
+            Lookup::Found { id, .. } => id,
+            Lookup::NotInLibrary => {
+                report.not_found.push(item.label());
+                continue;
+            }
+            Lookup::Skipped(reason) => {
+                report.skipped.push((item.label(), reason.to_string()));
+                continue;
+            }
+        };
+        // Scrobbling again would count a second view
+        if !marked.insert(id) {
+            continue;
+        }
+        match server.scrobble(id).await {
+            Ok(()) if item.media_type == MediaType::Movie => report.movies_added += 1,
+            Ok(()) => report.episodes_added += 1,
+            Err(e) => report.failed.push((item.label(), e.to_string())),
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::{MediaIds, MetadataResult};
+
+    fn item(title: &str, media_type: MediaType, episode: Option<&str>, ids: MediaIds) -> ProcessedItem {
+        ProcessedItem {
+            title: title.to_string(),
+            date: "2024-03-01".to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids,
+                title: title.to_string(),
+                media_type,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn items(json: &str) -> Vec<LibraryItem> {
+        serde_json::from_str::<Envelope<Items>>(json).unwrap().media_container.metadata
+    }
+
+    #[test]
+    fn test_history_items_are_found_by_guid() {
+        let library = Library::new(
+            &items(
+                r#"{"MediaContainer": {"Metadata": [
+                    {"ratingKey": "10", "guid": "plex://movie/5d77", "Guid": [{"id": "imdb://tt2543164"}, {"id": "tmdb://329865"}], "viewCount": 1},
+                    {"ratingKey": "11", "guid": "com.plexapp.agents.imdb://tt0816692?lang=en"}]}}"#,
+            ),
+            &items(r#"{"MediaContainer": {"Metadata": [{"ratingKey": "20", "guid": "com.plexapp.agents.thetvdb://318221?lang=en"}]}}"#),
+            &items(
+                r#"{"MediaContainer": {"Metadata": [
+                    {"ratingKey": "21", "grandparentRatingKey": "20", "parentIndex": 1, "index": 2},
+                    {"ratingKey": "22", "grandparentRatingKey": "20"}]}}"#,
+            ),
+        );
+        let imdb = |id: &str| MediaIds { imdb: Some(id.to_string()), ..Default::default() };
+        let tvdb = MediaIds { tvdb: Some("318221".to_string()), ..Default::default() };
+
+        assert_eq!(
+            library.find(&item("Arrival", MediaType::Movie, None, imdb("tt2543164"))),
+            Lookup::Found { id: "10", watched: true }
+        );
+        assert_eq!(
+            library.find(&item("Interstellar", MediaType::Movie, None, imdb("tt0816692"))),
+            Lookup::Found { id: "11", watched: false }
+        );
+        assert_eq!(
+            library.find(&item("Fleabag", MediaType::Tv, Some("S01E02"), tvdb.clone())),
+            Lookup::Found { id: "21", watched: false }
+        );
+        assert_eq!(library.find(&item("Fleabag", MediaType::Tv, Some("S01E03"), tvdb)), Lookup::NotInLibrary);
+        assert_eq!(items(r#"{"MediaContainer": {"size": 0}}"#).len(), 0);
+    }
+}
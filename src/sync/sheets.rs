@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{self, TokenStore};
+use crate::config::{AppConfig, WatchedAtConfig};
+use crate::error::AppError;
+use crate::metadata::{RateLimit, RateLimiter, RetryPolicy};
+use crate::models::MediaType;
+use crate::processor::columns::{self, Column};
+use crate::processor::history_processor::ProcessedItem;
+use crate::sync::SyncReport;
+
+const API_URL: &str = "https://sheets.googleapis.com/v4/spreadsheets";
+
+/// Rows appended per request; the API takes far more, but a rejected batch loses less
+const BATCH_SIZE: usize = 500;
+
+/// Body of both a values read and an append
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ValueRange {
+    /// Left out by the API when the range is empty
+    #[serde(default)]
+    values: Vec<Vec<String>>,
+}
+
+/// A row without its trailing empty cells, which the API drops when reading a sheet back
+fn key(row: &[String]) -> Vec<&str> {
+    let len = row.iter().rposition(|cell| !cell.is_empty()).map_or(0, |last| last + 1);
+    row[..len].iter().map(String::as_str).collect()
+}
+
+/// Renders the rows of `items` and keeps those not already in the sheet, along with the
+/// item each row came from. A sheet without any rows gets the header row first.
+fn new_rows<'a>(
+    items: &'a [ProcessedItem],
+    columns: &[Column],
+    existing: &[Vec<String>],
+    clock: &WatchedAtConfig,
+) -> Vec<(Option<&'a ProcessedItem>, Vec<String>)> {
+    let mut rows = Vec::new();
+    if existing.is_empty() {
+        rows.push((None, columns.iter().map(|column| column.header().to_string()).collect()));
+    }
+    let mut seen: HashSet<Vec<String>> =
+        existing.iter().map(|row| key(row).into_iter().map(String::from).collect()).collect();
+    for item in items {
+        let row: Vec<String> = columns.iter().map(|column| column.value(item, clock)).collect();
+        if seen.insert(key(&row).into_iter().map(String::from).collect()) {
+            rows.push((Some(item), row));
+        }
+    }
+    rows
+}
+
+/// `{API_URL}/{id}/values/{range}` plus `suffix` on the last segment, with the tab name quoted
+/// the way A1 notation wants and the whole range percent-encoded
+fn values_url(spreadsheet_id: &str, sheet: &str, suffix: &str) -> Url {
+    let mut url = Url::parse(API_URL).expect("valid Sheets API URL");
+    let range = format!("'{}'{}", sheet.replace('\'', "''"), suffix);
+    url.path_segments_mut().expect("base URL").extend([spreadsheet_id, "values", range.as_str()]);
+    url
+}
+
+/// Appends the rows of `items` that are not in the configured Google Sheet yet, so running it
+/// after every export keeps one shared log without duplicates
+pub async fn push(config: &AppConfig, items: &[ProcessedItem]) -> Result<SyncReport, AppError> {
+    let sheets = &config.sheets;
+    if sheets.spreadsheet_id.is_empty() {
+        return Err(AppError::ConfigError(config::ConfigError::Message(
+            "Set sheets.spreadsheet_id in config to the ID in the sheet's URL".to_string(),
+        )));
+    }
+    let columns = columns::parse(&sheets.columns)?;
+    let tokens = TokenStore::load(config)?;
+    let token = tokens
+        .access_token(auth::GOOGLE)
+        .await?
+        .ok_or_else(|| AppError::AuthError("No Google token found; run `login google` first".to_string()))?;
+
+    let client = reqwest::Client::new();
+    let retry = RetryPolicy::from_config(&config.retry);
+    // Google allows 60 requests per minute per user
+    let limiter = RateLimiter::new(&RateLimit { calls: 60, per_seconds: 60 });
+
+    let request = client.get(values_url(&sheets.spreadsheet_id, &sheets.sheet, "")).bearer_auth(&token);
+    let response = retry.send(request, &limiter).await?;
+    if !response.status().is_success() {
+        return Err(AppError::MetadataError(format!(
+            "Could not read sheet '{}' of spreadsheet {}: {}",
+            sheets.sheet,
+            sheets.spreadsheet_id,
+            response.status()
+        )));
+    }
+    let existing: ValueRange = response.json().await?;
+
+    let rows = new_rows(items, &columns, &existing.values, &config.output.watched_at);
+    let appended = rows.iter().filter(|(item, _)| item.is_some()).count();
+    tracing::info!(
+        "{} of {} rows are already in the sheet",
+        items.len().saturating_sub(appended),
+        items.len()
+    );
+
+    let mut report = SyncReport::default();
+    let append_url = values_url(&sheets.spreadsheet_id, &sheets.sheet, ":append");
+    for batch in rows.chunks(BATCH_SIZE) {
+        let body = ValueRange { values: batch.iter().map(|(_, row)| row.clone()).collect() };
+        let request = client
+            .post(append_url.clone())
+            .query(&[("valueInputOption", "RAW"), ("insertDataOption", "INSERT_ROWS")])
+            .bearer_auth(&token)
+            .json(&body);
+        let result = match retry.send(request, &limiter).await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err(AppError::MetadataError(format!("Google Sheets append error: {}", response.status()))),
+            Err(e) => Err(e),
+        };
+        let batch_items = batch.iter().filter_map(|(item, _)| *item);
+        match result {
+            Ok(()) => {
+                tracing::debug!("Appended {} rows to the sheet", batch.len());
+                for item in batch_items {
+                    match item.media_type {
+                        MediaType::Movie => report.movies_added += 1,
+                        MediaType::Tv => report.episodes_added += 1,
+                    }
+                }
+            }
+            Err(e) => {
+                let error = e.to_string();
+                report.failed.extend(batch_items.map(|item| (item.label(), error.clone())));
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Timezone;
+    use crate::metadata::MetadataResult;
+    use crate::processor::columns::ColumnSpec;
+
+    fn item(title: &str, date: &str, episode: Option<&str>) -> ProcessedItem {
+        let media_type = if episode.is_some() { MediaType::Tv } else { MediaType::Movie };
+        ProcessedItem {
+            title: title.to_string(),
+            date: date.to_string(),
+            media_type,
+            metadata: MetadataResult {
+                title: title.to_string(),
+                media_type,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    fn parse_columns(names: &[&str]) -> Vec<Column> {
+        let specs: Vec<ColumnSpec> = names.iter().map(|name| ColumnSpec::Field(name.to_string())).collect();
+        columns::parse(&specs).unwrap()
+    }
+
+    #[test]
+    fn test_only_rows_missing_from_the_sheet_are_appended() {
+        let columns = parse_columns(&["watched_date", "title", "episode", "rating"]);
+        let clock = WatchedAtConfig { timezone: Timezone::Utc, ..Default::default() };
+        let items = vec![
+            item("Arrival", "2024-01-05", None),
+            item("Fleabag", "2024-03-02", Some("S01E01")),
+            item("Fleabag", "2024-03-03", Some("S01E02")),
+        ];
+
+        let rows = new_rows(&items, &columns, &[], &clock);
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].0.is_none());
+        assert_eq!(rows[0].1, ["watched_date", "title", "episode", "rating"]);
+
+        // Read back, the header is there and the movie row has lost its empty trailing cells
+        let existing = vec![
+            vec!["watched_date".to_string(), "title".to_string(), "episode".to_string(), "rating".to_string()],
+            vec!["2024-01-05".to_string(), "Arrival".to_string()],
+            vec!["2024-03-02".to_string(), "Fleabag".to_string(), "S01E01".to_string()],
+        ];
+        let rows = new_rows(&items, &columns, &existing, &clock);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].1, ["2024-03-03", "Fleabag", "S01E02", ""]);
+    }
+
+    #[test]
+    fn test_values_url_quotes_the_tab_name() {
+        let url = values_url("abc", "Bob's log", ":append");
+        assert_eq!(url.as_str(), "https://sheets.googleapis.com/v4/spreadsheets/abc/values/'Bob''s%20log':append");
+    }
+}
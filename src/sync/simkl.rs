@@ -0,0 +1,303 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{self, TokenStore};
+use crate::config::{AppConfig, WatchedAtConfig};
+use crate::error::AppError;
+use crate::metadata::{MediaIds, RateLimit, RateLimiter, RetryPolicy};
+use crate::models::MediaType;
+use crate::processor::history_processor::ProcessedItem;
+use crate::sync::SyncReport;
+
+const HISTORY_URL: &str = "https://api.simkl.com/sync/history";
+
+/// Movies plus episodes sent per request, well within what Simkl accepts in one call
+const BATCH_SIZE: usize = 100;
+
+/// IDs as Simkl's sync API takes them: numbers, except IMDB's `tt...`
+#[derive(Debug, Default, Serialize, PartialEq)]
+struct SyncIds {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    simkl: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    imdb: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tmdb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tvdb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mal: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anidb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    anilist: Option<u64>,
+}
+
+impl SyncIds {
+    fn from_ids(ids: &MediaIds) -> Self {
+        let number = |id: &Option<String>| id.as_deref().and_then(|id| id.parse().ok());
+        Self {
+            simkl: number(&ids.simkl),
+            imdb: ids.imdb.clone(),
+            tmdb: number(&ids.tmdb),
+            tvdb: number(&ids.tvdb),
+            mal: number(&ids.mal),
+            anidb: number(&ids.anidb),
+            anilist: number(&ids.anilist),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct SyncMovie {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    year: Option<i32>,
+    ids: SyncIds,
+    watched_at: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SyncShow {
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    year: Option<i32>,
+    ids: SyncIds,
+    seasons: Vec<SyncSeason>,
+}
+
+#[derive(Debug, Serialize)]
+struct SyncSeason {
+    number: u32,
+    episodes: Vec<SyncEpisode>,
+}
+
+#[derive(Debug, Serialize)]
+struct SyncEpisode {
+    number: u32,
+    watched_at: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct HistoryPayload {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    movies: Vec<SyncMovie>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    shows: Vec<SyncShow>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct HistoryResponse {
+    #[serde(default)]
+    added: Added,
+    #[serde(default)]
+    not_found: NotFound,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Added {
+    #[serde(default)]
+    movies: usize,
+    #[serde(default)]
+    episodes: usize,
+}
+
+/// Simkl echoes back the entries it could not resolve, as they were sent
+#[derive(Debug, Default, Deserialize)]
+struct NotFound {
+    #[serde(default)]
+    movies: Vec<serde_json::Value>,
+    #[serde(default)]
+    shows: Vec<serde_json::Value>,
+    #[serde(default)]
+    episodes: Vec<serde_json::Value>,
+}
+
+impl NotFound {
+    fn describe(self) -> Vec<String> {
+        self.movies
+            .into_iter()
+            .chain(self.shows)
+            .chain(self.episodes)
+            .map(|entry| match entry.get("title").and_then(|title| title.as_str()) {
+                Some(title) => title.to_string(),
+                None => entry.to_string(),
+            })
+            .collect()
+    }
+}
+
+/// A history item ready to send
+enum Entry<'a> {
+    Movie(&'a ProcessedItem),
+    Episode(&'a ProcessedItem, u32, u32),
+}
+
+fn year(item: &ProcessedItem) -> Option<i32> {
+    item.metadata.year.as_deref().and_then(crate::matching::parse_year)
+}
+
+/// Sorts items into sendable entries, skipping those Simkl could not place
+fn entries<'a>(items: &'a [ProcessedItem], report: &mut SyncReport) -> Vec<Entry<'a>> {
+    let mut entries = Vec::with_capacity(items.len());
+    for item in items {
+        if SyncIds::from_ids(&item.metadata.ids).is_empty() {
+            report.skipped.push((item.label(), "no provider IDs".to_string()));
+            continue;
+        }
+        match item.media_type {
+            MediaType::Movie => entries.push(Entry::Movie(item)),
+            MediaType::Tv => match item.episode_code() {
+                Some((season, episode)) => entries.push(Entry::Episode(item, season, episode)),
+                None => report.skipped.push((item.label(), "no episode number".to_string())),
+            },
+        }
+    }
+    entries
+}
+
+/// Builds one request body, grouping the episodes of each show under it
+fn payload(batch: &[Entry], clock: &WatchedAtConfig) -> HistoryPayload {
+    let mut payload = HistoryPayload::default();
+    let mut shows: BTreeMap<String, SyncShow> = BTreeMap::new();
+    for entry in batch {
+        match *entry {
+            Entry::Movie(item) => payload.movies.push(SyncMovie {
+                title: item.title.clone(),
+                year: year(item),
+                ids: SyncIds::from_ids(&item.metadata.ids),
+                watched_at: item.watched_at(clock),
+            }),
+            Entry::Episode(item, season, episode) => {
+                let key = serde_json::to_string(&SyncIds::from_ids(&item.metadata.ids)).unwrap_or_default();
+                let show = shows.entry(key).or_insert_with(|| SyncShow {
+                    title: item.title.clone(),
+                    year: year(item),
+                    ids: SyncIds::from_ids(&item.metadata.ids),
+                    seasons: Vec::new(),
+                });
+                let episode = SyncEpisode { number: episode, watched_at: item.watched_at(clock) };
+                match show.seasons.iter_mut().find(|s| s.number == season) {
+                    Some(existing) => existing.episodes.push(episode),
+                    None => show.seasons.push(SyncSeason { number: season, episodes: vec![episode] }),
+                }
+            }
+        }
+    }
+    payload.shows = shows.into_values().collect();
+    payload
+}
+
+/// Adds `items` to the Simkl watch history of the account authorized with `login simkl`,
+/// in batches; a rejected batch is reported and the rest are still sent
+pub async fn push(config: &AppConfig, items: &[ProcessedItem]) -> Result<SyncReport, AppError> {
+    let tokens = TokenStore::load(config)?;
+    let token = tokens
+        .access_token(auth::SIMKL)
+        .await?
+        .ok_or_else(|| AppError::AuthError("No Simkl token found; run `login simkl` first".to_string()))?;
+
+    let client = reqwest::Client::new();
+    let retry = RetryPolicy::from_config(&config.retry);
+    let limiter = RateLimiter::new(&RateLimit { calls: 10, per_seconds: 10 });
+    let mut report = SyncReport::default();
+    let entries = entries(items, &mut report);
+
+    for batch in entries.chunks(BATCH_SIZE) {
+        let request = client
+            .post(HISTORY_URL)
+            .header("simkl-api-key", &config.simkl.client_id)
+            .header("Authorization", format!("Bearer {}", token))
+            .json(&payload(batch, &config.output.watched_at));
+        let result = match retry.send(request, &limiter).await {
+            Ok(response) if response.status().is_success() => {
+                response.json::<HistoryResponse>().await.map_err(AppError::from)
+            }
+            Ok(response) => Err(AppError::MetadataError(format!("Simkl sync error: {}", response.status()))),
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(response) => {
+                tracing::debug!("Simkl accepted a batch of {} items", batch.len());
+                report.movies_added += response.added.movies;
+                report.episodes_added += response.added.episodes;
+                report.not_found.extend(response.not_found.describe());
+            }
+            Err(e) => {
+                let error = e.to_string();
+                report.failed.extend(batch.iter().map(|entry| {
+                    let item = match entry {
+                        Entry::Movie(item) | Entry::Episode(item, _, _) => item,
+                    };
+                    (item.label(), error.clone())
+                }));
+            }
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Timezone;
+    use crate::metadata::MetadataResult;
+
+    fn item(title: &str, media_type: MediaType, episode: Option<&str>, ids: MediaIds) -> ProcessedItem {
+        ProcessedItem {
+            title: title.to_string(),
+            date: "2024-03-01".to_string(),
+            media_type,
+            metadata: MetadataResult {
+                ids,
+                title: title.to_string(),
+                year: Some("2016".to_string()),
+                media_type,
+                ..Default::default()
+            },
+            episode: episode.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_episodes_are_grouped_under_their_show() {
+        let tmdb = |id: &str| MediaIds { tmdb: Some(id.to_string()), ..Default::default() };
+        let items = vec![
+            item("Arrival", MediaType::Movie, None, tmdb("329865")),
+            item("Fleabag", MediaType::Tv, Some("S01E01 - Episode 1"), tmdb("67070")),
+            item("Fleabag", MediaType::Tv, Some("S01E02"), tmdb("67070")),
+            item("Fleabag", MediaType::Tv, Some("S02E01"), tmdb("67070")),
+            item("Fleabag", MediaType::Tv, Some("S00"), tmdb("67070")),
+            item("Unknown", MediaType::Movie, None, MediaIds::default()),
+        ];
+        let mut report = SyncReport::default();
+        let entries = entries(&items, &mut report);
+        assert_eq!(entries.len(), 4);
+        assert_eq!(report.skipped.len(), 2);
+
+        let body = serde_json::to_value(payload(&entries, &WatchedAtConfig { timezone: Timezone::Utc, ..Default::default() })).unwrap();
+        assert_eq!(body["movies"][0]["ids"], serde_json::json!({"tmdb": 329865}));
+        assert_eq!(body["movies"][0]["watched_at"], "2024-03-01T00:00:00Z");
+        let shows = body["shows"].as_array().unwrap();
+        assert_eq!(shows.len(), 1);
+        assert_eq!(shows[0]["seasons"][0]["episodes"].as_array().unwrap().len(), 2);
+        assert_eq!(shows[0]["seasons"][1]["number"], 2);
+    }
+
+    #[test]
+    fn test_not_found_entries_are_named() {
+        let response: HistoryResponse = serde_json::from_str(
+            r#"{"added": {"movies": 1, "shows": 1, "episodes": 3},
+                "not_found": {"movies": [{"title": "Obscure Film", "ids": {"tmdb": 1}}], "shows": [], "episodes": [{"ids": {"simkl": 5}}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(response.added.episodes, 3);
+        assert_eq!(response.not_found.describe(), ["Obscure Film", r#"{"ids":{"simkl":5}}"#]);
+    }
+}
@@ -222,9 +222,9 @@ mod api_integration_tests {
             assert_eq!(status_unauthorized, 401);
 
             // Test common HTTP status ranges
-            assert!(status_ok >= 200 && status_ok < 300); // Success range
-            assert!(status_not_found >= 400 && status_not_found < 500); // Client error range
-            assert!(status_server_error >= 500 && status_server_error < 600); // Server error range
+            assert!((200..300).contains(&status_ok)); // Success range
+            assert!((400..500).contains(&status_not_found)); // Client error range
+            assert!((500..600).contains(&status_server_error)); // Server error range
 
             println!("✅ HTTP status code definitions test passed");
         }
@@ -240,37 +240,35 @@ mod test_documentation {
     fn test_api_integration_documentation() {
         println!("🚀 API Integration Testing Guide");
         println!("================================");
-        println!("");
+        println!();
         println!("🔧 Setup:");
         println!("1. Build project: cargo build --release");
-        println!("2. Edit target/release/config.json with real API keys");
+        println!("2. Edit config.json in the platform config directory with real API keys");
         println!("3. Run integration tests: cargo test --release integration_tests");
-        println!("");
+        println!();
         println!("📋 Test Categories:");
         println!("• Unit Tests: cargo test --lib");
         println!("• Integration Tests: cargo test --test api_integration_tests");
         println!("• Configuration Tests: cargo test --test config_tests");
         println!("• Mock Tests: cargo test --test api_integration_tests -- --nocapture");
-        println!("");
+        println!();
         println!("🎯 Testing with Real APIs:");
         println!("• Set SKIP_REAL_API_TESTS=1 to skip tests requiring real credentials");
         println!("• Use CONFIG_PATH=/path/to/config.json for custom config location");
         println!("• Check test output for API rate limit warnings");
-        println!("");
+        println!();
         println!("🔍 Troubleshooting:");
         println!("• 'API key invalid': Check your credentials in config.json");
         println!("• 'Rate limit exceeded': Wait or reduce test frequency");
         println!("• 'Network error': Check internet connection");
         println!("• 'Config not found': Run cargo build --release first");
-        println!("");
+        println!();
         println!("📊 Coverage:");
         println!("• Configuration loading and validation");
         println!("• API endpoint mocking and testing");
         println!("• Error handling and edge cases");
         println!("• Rate limiting behavior");
         println!("• Serialization/deserialization");
-
-        assert!(true, "Documentation test completed successfully");
     }
 
     /// Test that shows how to skip integration tests
@@ -284,7 +282,5 @@ mod test_documentation {
             println!("✅ Real API integration tests will run");
             println!("   Set SKIP_REAL_API_TESTS=1 to skip tests requiring real API keys");
         }
-
-        assert!(true, "Skip integration test option verified");
     }
 }
\ No newline at end of file
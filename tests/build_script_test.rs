@@ -22,5 +22,6 @@ fn test_build_script_generates_config() {
     let config_content = fs::read_to_string(&config_path).expect("Failed to read config file");
     assert!(config_content.contains("YOUR_SIMKL_CLIENT_ID"), "Config missing Simkl ID");
     assert!(config_content.contains("YOUR_TMDB_ACCESS_TOKEN"), "Config missing TMDB token");
-    assert!(config_content.contains("YOUR_AMAZON_EMAIL"), "Config missing Amazon email");
+    assert!(config_content.contains("\"region\": \"com\""), "Config missing Amazon region");
+    assert!(!config_content.contains("YOUR_AMAZON_PASSWORD"), "Config should not ask for the Amazon password");
 }
\ No newline at end of file
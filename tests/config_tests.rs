@@ -11,6 +11,7 @@ pub struct TestAppConfig {
     tmdb: TestTmdbConfig,
     tvdb: TestTvdbConfig,
     mal: TestMalConfig,
+    #[serde(default)]
     amazon: TestAmazonConfig,
     output: TestOutputConfig,
 }
@@ -43,12 +44,12 @@ struct TestMalConfig {
     client_secret: String,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize, Validate)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize, Validate)]
+#[serde(default)]
 struct TestAmazonConfig {
-    #[validate(email(message = "Invalid email format (optional for manual login)"))]
     email: String,
-    #[validate(length(min = 1, message = "Password cannot be empty (optional for manual login)"))]
     password: String,
+    region: String,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Validate)]
@@ -91,21 +92,22 @@ impl TestConfigManager {
             return PathBuf::from(env_path);
         }
 
-        // Default behavior: look in the target directory
+        // Otherwise the one generated by the build in the target directory, never the
+        // installed config the exporter itself uses
         let exe_path = env::current_exe().expect("Failed to get current exe path");
         let exe_dir = exe_path.parent().expect("Failed to get exe directory");
 
         // For tests, we need to look in the target directory, not the deps subdirectory
         if exe_dir.ends_with("deps") {
-            // If we're in deps, go up two levels to target/release or target/debug
-            exe_dir.parent().unwrap().parent().unwrap().join("config.json")
+            // If we're in deps, go up one level to target/release or target/debug
+            exe_dir.parent().unwrap().join("config.json")
         } else {
             exe_dir.join("config.json")
         }
     }
 
     fn get_or_init() -> &'static TestConfigManager {
-        TEST_CONFIG_MANAGER.get_or_init(|| TestConfigManager::new())
+        TEST_CONFIG_MANAGER.get_or_init(TestConfigManager::new)
     }
 }
 
@@ -168,7 +170,6 @@ mod tests {
                 // Verify required fields are present
                 assert!(!config.simkl.client_id.is_empty(), "Simkl client ID should not be empty");
                 assert!(!config.tmdb.access_token.is_empty(), "TMDB access token should not be empty");
-                assert!(!config.amazon.email.is_empty(), "Amazon email should not be empty");
 
                 // Check if we have real API keys or test placeholders
                 let has_real_keys = has_real_api_keys(&config);
@@ -225,9 +226,6 @@ mod tests {
         println!("  Required providers (Simkl + TMDB): {}", if has_real_keys { "✅ Configured" } else { "⚠️  Using placeholders" });
         println!("  TVDB (optional): {}", if tvdb_real { "✅ Configured" } else { "⚠️  Using placeholders" });
         println!("  MyAnimeList (optional): {}", if mal_real { "✅ Configured" } else { "⚠️  Using placeholders" });
-
-        // This test just reports status, doesn't fail
-        assert!(true, "API key detection completed");
     }
 
     /// Test configuration file existence
@@ -260,7 +258,7 @@ mod integration_tests {
         assert!(!config.simkl.client_id.is_empty(), "Simkl client ID should not be empty");
         assert!(!config.simkl.client_secret.is_empty(), "Simkl client secret should not be empty");
         assert!(!config.tmdb.access_token.is_empty(), "TMDB access token should not be empty");
-        assert!(!config.amazon.email.is_empty(), "Amazon email should not be empty");
+        assert!(config.amazon.password.is_empty() || !config.amazon.email.is_empty(), "Amazon password set without an email");
 
         // Test configuration validation
         match config.validate() {
@@ -327,19 +325,19 @@ mod documentation_tests {
         println!("📖 Configuration Test Setup Guide:");
         println!("=================================");
         println!("1. Build the project: cargo build --release");
-        println!("2. Edit target/release/config.json with real API keys");
-        println!("3. Run tests: cargo test --release");
-        println!("4. For integration tests, ensure API keys are valid");
-        println!("");
+        println!("2. Run it once; config.json moves to the platform config directory");
+        println!("   (e.g. ~/.config/primevideo-to-simkl/config.json on Linux)");
+        println!("3. Edit that config.json with real API keys");
+        println!("4. Run tests: cargo test --release");
+        println!("5. For integration tests, ensure API keys are valid");
+        println!();
         println!("Environment variables (optional):");
         println!("  CONFIG_PATH=/path/to/config.json  - Use custom config location");
         println!("  SKIP_INTEGRATION_TESTS=1          - Skip API integration tests");
-        println!("");
+        println!();
         println!("Test categories:");
         println!("  cargo test config_tests           - Configuration loading tests");
         println!("  cargo test integration_tests      - API integration tests");
         println!("  cargo test --release              - All tests with release config");
-
-        assert!(true, "Documentation test completed");
     }
 }
\ No newline at end of file
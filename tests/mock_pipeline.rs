@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+/// The exporter run in `dir`, with the fixture config and with its config, data and cache
+/// directories inside `dir`, so a test never reads or moves the files of a real install
+fn exporter(dir: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_primevideo-to-simkl-exporter"));
+    command
+        .current_dir(dir)
+        .env("HOME", dir)
+        .env("XDG_CONFIG_HOME", dir.join("config"))
+        .env("XDG_DATA_HOME", dir.join("data"))
+        .env("XDG_CACHE_HOME", dir.join("cache"))
+        .env("PV2SIMKL_OUTPUT__WATERMARK", dir.join("watermark.json"))
+        .arg("--config")
+        .arg(fixture("config.json"));
+    command
+}
+
+// Runs the whole export (saved history -> mock providers -> CSV) without a browser or API keys
+#[test]
+fn test_mock_pipeline_exports_fixture_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("export.csv");
+
+    let status = exporter(dir.path())
+        .arg("--mock")
+        .arg(fixture("metadata.json"))
+        .arg("--input")
+        .arg(fixture("history.json"))
+        .arg("-o")
+        .arg(&output)
+        .args(["-L", "warn"])
+        .status()
+        .expect("failed to run the exporter");
+    assert!(status.success());
+
+    let csv = std::fs::read_to_string(&output).unwrap();
+    let mut lines = csv.lines();
+    assert!(lines.next().unwrap().starts_with("simkl_id,TVDB_ID,TMDB,IMDB_ID"));
+    let rows: Vec<&str> = lines.collect();
+    assert_eq!(rows.len(), 2);
+    assert!(rows.iter().any(|row| row.starts_with("417044,,329865,tt2543164,") && row.contains("Arrival")));
+    assert!(rows.iter().any(|row| row.starts_with("1271131,366924,108978,") && row.contains(",s1e3,1,3,")));
+}
+
+// Mock answers never reach the real cache, so warming it from them is refused
+#[test]
+fn test_cache_warm_refuses_mock_providers() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = exporter(dir.path())
+        .arg("--mock")
+        .arg(fixture("metadata.json"))
+        .args(["-L", "warn", "cache", "warm", "--input"])
+        .arg(fixture("history.json"))
+        .output()
+        .expect("failed to run the exporter");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Warming the cache"));
+}
+
+// The first run leaves a watermark; an incremental run then only exports from that day on
+#[test]
+fn test_incremental_run_starts_at_watermark() {
+    let dir = tempfile::tempdir().unwrap();
+    let export = |output: &str, extra: &[&str]| {
+        let status = exporter(dir.path())
+            .arg("--mock")
+            .arg(fixture("metadata.json"))
+            .arg("--input")
+            .arg(fixture("history.json"))
+            .args(["-o", output, "-L", "warn"])
+            .args(extra)
+            .status()
+            .expect("failed to run the exporter");
+        assert!(status.success());
+        std::fs::read_to_string(dir.path().join(output)).unwrap()
+    };
+
+    export("export.csv", &[]);
+    let watermark = std::fs::read_to_string(dir.path().join("watermark.json")).unwrap();
+    assert!(watermark.contains("2023-09-02"));
+
+    let csv = export("new.csv", &["--incremental"]);
+    let rows: Vec<&str> = csv.lines().skip(1).collect();
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].contains("Reacher"));
+}